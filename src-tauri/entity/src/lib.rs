@@ -75,9 +75,28 @@ pub enum FileStatus {
     Failed,
 }
 
+/// Android 公共目录（通用文件集合，可保存任意类型的文件）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PublicBaseDir {
+    #[default]
+    Download,
+    Documents,
+}
+
+impl PublicBaseDir {
+    /// 目录显示名
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Self::Download => "Download",
+            Self::Documents => "Documents",
+        }
+    }
+}
+
 /// 保存位置（跨平台）
 ///
-/// 桌面端使用文件系统绝对路径，Android 端使用公共目录子目录名。
+/// 桌面端使用文件系统绝对路径，Android 端使用公共目录子目录名或用户选择的 SAF 目录。
 /// 数据库中以 JSON 形式存储在 `save_path` 列，通过 `FromJsonQueryResult` 自动序列化/反序列化。
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -85,5 +104,20 @@ pub enum SaveLocation {
     /// 桌面端：文件系统绝对路径
     Path { path: String },
     /// Android 端：公共目录子目录（如 `"SwarmDrop"` → `Download/SwarmDrop`）
-    AndroidPublicDir { subdir: String },
+    ///
+    /// `base_dir` 为公共目录（缺省为 Download，兼容旧记录）。
+    /// `by_media_type` 为 true 时按 MIME 类型分流：图片 → `Pictures/{subdir}`，
+    /// 视频 → `Movies/{subdir}`，音频 → `Music/{subdir}`，其他仍在 `{base_dir}/{subdir}`。
+    AndroidPublicDir {
+        #[serde(default, rename = "baseDir")]
+        base_dir: PublicBaseDir,
+        subdir: String,
+        #[serde(default, rename = "byMediaType")]
+        by_media_type: bool,
+    },
+    /// Android 端：用户通过系统目录选择器选定的目录（序列化的 `FileUri`，SAF tree URI）
+    AndroidSafDir {
+        #[serde(rename = "dirUri")]
+        dir_uri: serde_json::Value,
+    },
 }
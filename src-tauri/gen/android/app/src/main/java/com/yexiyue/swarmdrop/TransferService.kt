@@ -0,0 +1,105 @@
+package com.yexiyue.swarmdrop
+
+import android.app.NotificationChannel
+import android.app.NotificationManager
+import android.app.PendingIntent
+import android.app.Service
+import android.content.Intent
+import android.content.pm.ServiceInfo
+import android.os.Build
+import android.os.IBinder
+import androidx.core.app.NotificationCompat
+import androidx.core.app.ServiceCompat
+
+/**
+ * 传输期间的前台服务
+ *
+ * 只负责保持进程存活并展示常驻通知（总体进度 + 取消按钮），传输本身仍在 Rust 中进行。
+ */
+class TransferService : Service() {
+
+    override fun onBind(intent: Intent?): IBinder? = null
+
+    override fun onStartCommand(intent: Intent?, flags: Int, startId: Int): Int {
+        when (intent?.action) {
+            ACTION_CANCEL -> {
+                TransferServicePlugin.instance?.get()?.onCancelRequested()
+                return START_NOT_STICKY
+            }
+            ACTION_UPDATE -> showNotification(
+                intent.getIntExtra(EXTRA_ACTIVE_SESSIONS, 0),
+                intent.getLongExtra(EXTRA_TOTAL_BYTES, 0),
+                intent.getLongExtra(EXTRA_TRANSFERRED_BYTES, 0),
+            )
+            else -> showNotification(0, 0, 0)
+        }
+        // 进程被杀后传输会话已不存在，无需重建服务
+        return START_NOT_STICKY
+    }
+
+    override fun onDestroy() {
+        ServiceCompat.stopForeground(this, ServiceCompat.STOP_FOREGROUND_REMOVE)
+        super.onDestroy()
+    }
+
+    private fun showNotification(activeSessions: Int, totalBytes: Long, transferredBytes: Long) {
+        ensureChannel()
+
+        val percent = if (totalBytes > 0) (transferredBytes * 100 / totalBytes).toInt() else 0
+        val title = if (activeSessions > 1) "正在传输 $activeSessions 个任务" else "正在传输文件"
+
+        val openIntent = PendingIntent.getActivity(
+            this,
+            0,
+            Intent(this, MainActivity::class.java).addFlags(Intent.FLAG_ACTIVITY_SINGLE_TOP),
+            PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+        )
+        val cancelIntent = PendingIntent.getService(
+            this,
+            1,
+            Intent(this, TransferService::class.java).setAction(ACTION_CANCEL),
+            PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+        )
+
+        val notification = NotificationCompat.Builder(this, CHANNEL_ID)
+            .setSmallIcon(R.mipmap.ic_launcher)
+            .setContentTitle(title)
+            .setContentText("$percent%")
+            .setProgress(100, percent, totalBytes <= 0)
+            .setOngoing(true)
+            .setOnlyAlertOnce(true)
+            .setContentIntent(openIntent)
+            .addAction(0, "取消", cancelIntent)
+            .build()
+
+        val type = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q) {
+            ServiceInfo.FOREGROUND_SERVICE_TYPE_DATA_SYNC
+        } else {
+            0
+        }
+        ServiceCompat.startForeground(this, NOTIFICATION_ID, notification, type)
+    }
+
+    private fun ensureChannel() {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.O) return
+        val manager = getSystemService(NotificationManager::class.java)
+        if (manager.getNotificationChannel(CHANNEL_ID) == null) {
+            manager.createNotificationChannel(
+                NotificationChannel(CHANNEL_ID, "文件传输", NotificationManager.IMPORTANCE_LOW)
+            )
+        }
+    }
+
+    companion object {
+        const val ACTION_START = "com.yexiyue.swarmdrop.transfer.START"
+        const val ACTION_UPDATE = "com.yexiyue.swarmdrop.transfer.UPDATE"
+        const val ACTION_CANCEL = "com.yexiyue.swarmdrop.transfer.CANCEL"
+
+        const val EXTRA_ACTIVE_SESSIONS = "activeSessions"
+        const val EXTRA_TOTAL_BYTES = "totalBytes"
+        const val EXTRA_TRANSFERRED_BYTES = "transferredBytes"
+
+        private const val CHANNEL_ID = "transfer"
+        private const val NOTIFICATION_ID = 1001
+    }
+}
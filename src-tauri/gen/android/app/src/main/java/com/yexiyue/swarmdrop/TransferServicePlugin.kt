@@ -0,0 +1,81 @@
+package com.yexiyue.swarmdrop
+
+import android.content.Intent
+import android.util.Log
+import androidx.core.content.ContextCompat
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.lang.ref.WeakReference
+
+@InvokeArg
+class TransferProgressArgs {
+    var activeSessions: Int = 0
+    var totalBytes: Long = 0
+    var transferredBytes: Long = 0
+}
+
+/**
+ * 传输前台服务插件
+ *
+ * Rust 端在第一个传输会话开始时启动服务、最后一个结束时停止，期间定时刷新进度。
+ * 通知上的"取消"按钮通过 `cancel-transfers` 事件交给前端取消全部传输。
+ */
+@TauriPlugin
+class TransferServicePlugin(private val activity: android.app.Activity) : Plugin(activity) {
+
+    override fun load(webView: android.webkit.WebView) {
+        instance = WeakReference(this)
+    }
+
+    @Command
+    fun startTransferService(invoke: Invoke) {
+        sendToService(TransferService.ACTION_START)
+        invoke.resolve()
+    }
+
+    @Command
+    fun updateTransferProgress(invoke: Invoke) {
+        val args = invoke.parseArgs(TransferProgressArgs::class.java)
+        sendToService(TransferService.ACTION_UPDATE) {
+            putExtra(TransferService.EXTRA_ACTIVE_SESSIONS, args.activeSessions)
+            putExtra(TransferService.EXTRA_TOTAL_BYTES, args.totalBytes)
+            putExtra(TransferService.EXTRA_TRANSFERRED_BYTES, args.transferredBytes)
+        }
+        invoke.resolve()
+    }
+
+    @Command
+    fun stopTransferService(invoke: Invoke) {
+        activity.stopService(Intent(activity, TransferService::class.java))
+        invoke.resolve()
+    }
+
+    private fun sendToService(action: String, extras: Intent.() -> Unit = {}) {
+        val intent = Intent(activity, TransferService::class.java).apply {
+            this.action = action
+            extras()
+        }
+        try {
+            ContextCompat.startForegroundService(activity, intent)
+        } catch (e: Exception) {
+            // Android 12+ 禁止从后台启动前台服务，此时只能放弃保活
+            Log.w(TAG, "启动传输前台服务失败", e)
+        }
+    }
+
+    /** 通知上的"取消"按钮被点击 */
+    fun onCancelRequested() {
+        trigger("cancel-transfers", JSObject())
+    }
+
+    companion object {
+        private const val TAG = "TransferServicePlugin"
+
+        var instance: WeakReference<TransferServicePlugin>? = null
+            private set
+    }
+}
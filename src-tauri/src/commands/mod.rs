@@ -4,12 +4,28 @@
 //! 所有业务逻辑委托给 [`network`](crate::network)、
 //! [`device`](crate::device) 和 [`pairing`](crate::pairing) 模块。
 
-use crate::device::{DeviceFilter, DeviceListResult, PairedDeviceInfo};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::device::{
+    ConnectionType, DeviceDetail, DeviceListResult, DeviceQuery, PairedDeviceInfo, PeerDiagnostics,
+};
+use crate::diagnostics::log_buffer::LOG_BUFFER;
+use crate::diagnostics::log_level::LogLevelHandle;
+use crate::diagnostics::log_stream::LOG_STREAM;
+use crate::network::blocklist::{BlockList, BLOCKED_PEERS_FILE};
+use crate::network::config::{
+    AddrValidation, ListenConfig, NetworkOptions, NetworkTimeouts, PeerSources,
+};
+use crate::network::power_save::PowerSave;
+use crate::network::traffic::{TrafficSnapshot, TrafficStats, TRAFFIC_STATS_FILE};
 use crate::network::{NetManager, NetManagerState, NetworkStatus};
+use crate::pairing::manager::PairedReconnectResult;
 use crate::protocol::{AppRequest, AppResponse};
-use crate::AppError;
-use swarm_p2p_core::libp2p::{identity::Keypair, PeerId};
-use tauri::{AppHandle, Manager, State};
+use crate::settings::SettingsStore;
+use crate::{events, AppError};
+use swarm_p2p_core::libp2p::{identity::Keypair, Multiaddr, PeerId};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::Mutex;
 use tracing::{info, warn};
 
@@ -27,6 +43,7 @@ macro_rules! with_manager {
 mod identity;
 mod mcp;
 mod pairing;
+mod settings;
 mod transfer;
 
 // glob re-export：Tauri 的 #[tauri::command] 宏会生成 __cmd__* 隐藏符号，
@@ -34,51 +51,99 @@ mod transfer;
 pub use identity::*;
 pub use mcp::*;
 pub use pairing::*;
+pub use settings::*;
 pub use transfer::*;
 
 #[tauri::command]
+#[expect(clippy::too_many_arguments, reason = "启动配置由前端逐项传入")]
 pub async fn start(
     app: AppHandle,
     keypair: State<'_, Keypair>,
     paired_devices: Vec<PairedDeviceInfo>,
     custom_bootstrap_nodes: Option<Vec<String>>,
+    use_default_bootstrap: Option<bool>,
+    custom_relays: Option<Vec<String>>,
+    timeouts: Option<NetworkTimeouts>,
+    listen_config: Option<ListenConfig>,
+    options: Option<NetworkOptions>,
 ) -> crate::AppResult<()> {
+    let timeouts = timeouts.unwrap_or_default();
+    timeouts.validate().map_err(AppError::Network)?;
+    let options = options.unwrap_or_default();
+    let peers = PeerSources::resolve(
+        use_default_bootstrap.unwrap_or(true),
+        &custom_bootstrap_nodes.unwrap_or_default(),
+        &custom_relays.unwrap_or_default(),
+    )?;
+
+    // 重复调用 start 时先停止旧节点的后台任务（bootstrap 重试等），避免任务堆积
+    if let Some(state) = app.try_state::<NetManagerState>() {
+        if let Some(old) = state.lock().await.take() {
+            old.cancel_background_tasks();
+        }
+    }
+
+    // 指定端口被占用时回退到随机端口，并通知前端
+    let (listen_addrs, fallbacks) = listen_config.unwrap_or_default().resolve();
+    for fallback in &fallbacks {
+        let _ = app.emit(events::LISTEN_PORT_FALLBACK, fallback);
+    }
+
     let agent_version = crate::device::OsInfo::default().to_agent_version();
     let config = crate::network::config::create_node_config(
         agent_version,
-        &custom_bootstrap_nodes.unwrap_or_default(),
+        &peers,
+        &timeouts,
+        listen_addrs,
+        &options,
     );
 
     let (client, receiver) =
         swarm_p2p_core::start::<AppRequest, AppResponse>((*keypair).clone(), config)
             .map_err(|e| AppError::Network(e.to_string()))?;
 
+    // 流量统计跨重启累计
+    let traffic = TrafficStats::load(app.path().app_local_data_dir()?.join(TRAFFIC_STATS_FILE));
+    // 屏蔽列表与已配对设备一起加载
+    let blocklist = BlockList::load(app.path().app_local_data_dir()?.join(BLOCKED_PEERS_FILE));
+
+    // 传输相关设置在启动时读取，修改后重启节点生效
+    let settings = app
+        .try_state::<SettingsStore>()
+        .map(|s| s.get())
+        .unwrap_or_default();
+
     let peer_id = PeerId::from_public_key(&keypair.public());
     let net_manager = NetManager::new(
         client.clone(),
         peer_id,
         paired_devices,
-    );
+        &timeouts,
+        options,
+        &peers,
+        Arc::new(traffic),
+        &settings,
+    )
+    .with_blocklist(blocklist)
+    .with_power_save(app.state::<PowerSave>().inner().clone());
 
-    // 宣布上线（bootstrap 前发布，尽早让对方发现）
-    if let Err(e) = net_manager.pairing().announce_online().await {
-        warn!("Failed to announce online: {}", e);
-    }
+    // 多传输并发时定时推送总体进度
+    net_manager.spawn_overall_progress(app.clone());
+
+    // 定时刷新 DHT 在线记录
+    net_manager.spawn_online_refresh();
 
     // 获取事件循环需要的共享引用（在存入 state 之前）
     let shared = net_manager.shared_refs();
 
-    // DHT bootstrap → 完成后检查已配对设备是否在线
-    let bootstrap_client = client.clone();
-    let pairing_for_startup = shared.pairing.clone();
-    tokio::spawn(async move {
-        match bootstrap_client.bootstrap().await {
-            Ok(result) => info!("DHT bootstrap completed: {:?}", result),
-            Err(e) => warn!("DHT bootstrap failed: {}", e),
-        }
-        // bootstrap 完成后，查询已配对设备的在线记录并注册地址
-        pairing_for_startup.check_paired_online().await;
-    });
+    // DHT bootstrap（失败时退避重试）→ 完成后宣布上线，并检查已配对设备是否在线
+    net_manager.spawn_bootstrap(app.clone(), timeouts.bootstrap_timeout());
+
+    // 之后定期检查仍离线的已配对设备
+    net_manager.spawn_paired_check(app.clone(), timeouts.paired_check_interval());
+
+    // 定期清理长期未见的未配对 peer
+    net_manager.spawn_peer_prune(app.clone());
 
     // 存入 Tauri state
     if let Some(state) = app.try_state::<NetManagerState>() {
@@ -93,12 +158,22 @@ pub async fn start(
     Ok(())
 }
 
+/// 校验自定义引导 / 中继节点地址，返回有效地址数、规范化后的地址与无效地址及原因
+///
+/// `start` 遇到无效地址会直接失败，设置页应在保存前调用本命令提示用户。
+#[tauri::command]
+pub fn validate_bootstrap_nodes(addrs: Vec<String>) -> AddrValidation {
+    crate::network::config::validate_peer_addrs(&addrs)
+}
+
 #[tauri::command]
 pub async fn shutdown(app: AppHandle) -> crate::AppResult<()> {
     if let Some(state) = app.try_state::<NetManagerState>() {
         let mut guard = state.lock().await;
         if let Some(manager) = guard.as_ref() {
-            if let Err(e) = manager.pairing().announce_offline().await {
+            // 先标记关闭，宣布下线期间到达的请求直接拒绝
+            manager.begin_shutdown();
+            if let Err(e) = manager.announce_offline().await {
                 warn!("Failed to announce offline: {}", e);
             }
             // 取消所有后台任务（超时清理等）
@@ -113,11 +188,11 @@ pub async fn shutdown(app: AppHandle) -> crate::AppResult<()> {
 #[tauri::command]
 pub async fn list_devices(
     net: State<'_, NetManagerState>,
-    filter: Option<DeviceFilter>,
+    query: Option<DeviceQuery>,
 ) -> crate::AppResult<DeviceListResult> {
     let guard = net.lock().await;
     let manager = guard.as_ref().ok_or(AppError::NodeNotStarted)?;
-    let devices = manager.devices().get_devices(filter.unwrap_or_default());
+    let devices = manager.devices().get_devices(&query.unwrap_or_default());
     let total = devices.len();
     Ok(DeviceListResult { devices, total })
 }
@@ -133,6 +208,179 @@ pub async fn get_network_status(
     }
 }
 
+/// 获取按连接类型累计的流量统计
+#[tauri::command]
+pub async fn get_traffic_stats(
+    net: State<'_, NetManagerState>,
+) -> crate::AppResult<TrafficSnapshot> {
+    with_manager!(net, |m| Ok(m.traffic().snapshot()))
+}
+
+/// 清零流量统计
+#[tauri::command]
+pub async fn reset_traffic_stats(net: State<'_, NetManagerState>) -> crate::AppResult<()> {
+    with_manager!(net, |m| {
+        m.traffic().reset();
+        Ok(())
+    })
+}
+
+/// 导出诊断包（zip）到用户选择的路径，返回该路径供前端打开所在目录
+///
+/// 节点未启动时同样可以导出（网络部分为空）。
+#[tauri::command]
+pub async fn export_diagnostics(app: AppHandle, path: PathBuf) -> crate::AppResult<PathBuf> {
+    let files = match app.try_state::<NetManagerState>() {
+        Some(state) => {
+            let guard = state.lock().await;
+            crate::diagnostics::collect(guard.as_ref()).await?
+        }
+        None => crate::diagnostics::collect(None).await?,
+    };
+
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || crate::diagnostics::write_bundle(&target, &files))
+        .await??;
+    Ok(path)
+}
+
+/// 当前网络 / 设备 / 传输状态的诊断快照（JSON，PeerId 已脱敏，不含密钥）
+///
+/// 内容与诊断包一致：网络状态、设备列表、引导节点连接状态、配对概况、活跃传输和最近日志。
+#[tauri::command]
+pub async fn get_diagnostics_snapshot(app: AppHandle) -> crate::AppResult<serde_json::Value> {
+    let snapshot = match app.try_state::<NetManagerState>() {
+        Some(state) => {
+            let guard = state.lock().await;
+            crate::diagnostics::snapshot(guard.as_ref()).await
+        }
+        None => crate::diagnostics::snapshot(None).await,
+    };
+    crate::diagnostics::snapshot_json(&snapshot)
+}
+
+/// 获取最近的日志（从旧到新）
+///
+/// `level_filter` 为最详细的级别（如 `"warn"` 只返回 WARN 和 ERROR），不传则返回全部级别。
+#[tauri::command]
+pub fn get_recent_logs(
+    limit: Option<usize>,
+    level_filter: Option<String>,
+) -> crate::AppResult<Vec<String>> {
+    let max_level = level_filter
+        .map(|level| {
+            level
+                .parse::<tracing::Level>()
+                .map_err(|_| AppError::Config(format!("无效的日志级别: {level}")))
+        })
+        .transpose()?;
+    Ok(LOG_BUFFER.recent(limit, max_level))
+}
+
+/// 清空日志缓冲区
+#[tauri::command]
+pub fn clear_logs() {
+    LOG_BUFFER.clear();
+}
+
+/// 获取当前的日志过滤规则（语法同 `RUST_LOG`）
+#[tauri::command]
+pub fn get_log_level(log_level: State<'_, LogLevelHandle>) -> crate::AppResult<String> {
+    log_level.current()
+}
+
+/// 运行时切换日志过滤规则，如 `debug` 或 `swarmdrop=trace,libp2p=info`，无效规则返回错误
+#[tauri::command]
+pub fn set_log_level(log_level: State<'_, LogLevelHandle>, filter: String) -> crate::AppResult<()> {
+    log_level.set(&filter)
+}
+
+/// 获取推送到前端（`log-line` 事件）的最低日志级别
+#[tauri::command]
+pub fn get_log_stream_level() -> String {
+    LOG_STREAM.level().to_string().to_lowercase()
+}
+
+/// 设置推送到前端的最低日志级别（`error` / `warn` / `info` / `debug` / `trace`），`off` 关闭推送
+#[tauri::command]
+pub fn set_log_stream_level(level: String) -> crate::AppResult<()> {
+    LOG_STREAM.set_level(&level)
+}
+
+/// 省电模式是否开启
+#[tauri::command]
+pub fn get_power_save_mode(power_save: State<'_, PowerSave>) -> bool {
+    power_save.is_enabled()
+}
+
+/// 开启或关闭省电模式：暂停 / 恢复在线记录刷新与已配对设备在线检查
+///
+/// 开启期间之后才上线的设备可能找不到本机，发来的 Offer 会丢失（见 [`PowerSave`]）。
+/// 开始传输时自动关闭。节点未启动时同样可以设置，下次 `start` 生效。
+#[tauri::command]
+pub fn set_power_save_mode(power_save: State<'_, PowerSave>, enabled: bool) {
+    if power_save.set(enabled) {
+        info!("省电模式已{}", if enabled { "开启" } else { "关闭" });
+    }
+}
+
+/// 手动拨号指定节点（排查连通性用），可附带 multiaddr，成功时返回连接类型
+#[tauri::command]
+pub async fn dial_peer(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+    addrs: Option<Vec<Multiaddr>>,
+) -> crate::AppResult<Option<ConnectionType>> {
+    with_manager!(net, |m| m
+        .dial_peer(&app, peer_id, addrs.unwrap_or_default())
+        .await)
+}
+
+/// 立即重新检查所有离线的已配对设备（DHT 查询在线记录并拨号），返回查到 / 拨通的设备
+#[tauri::command]
+pub async fn reconnect_paired_devices(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+) -> crate::AppResult<PairedReconnectResult> {
+    with_manager!(net, |m| m.reconnect_paired(&app, None).await)
+}
+
+/// 立即重新检查指定的已配对设备
+#[tauri::command]
+pub async fn reconnect_device(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> crate::AppResult<PairedReconnectResult> {
+    with_manager!(net, |m| m.reconnect_paired(&app, Some(peer_id)).await)
+}
+
+/// 获取单个设备的详细信息，未知设备返回 `DeviceNotFound`
+#[tauri::command]
+pub async fn get_device(
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> crate::AppResult<DeviceDetail> {
+    with_manager!(net, |m| {
+        let mut detail = m
+            .devices()
+            .get_peer_detail(&peer_id)
+            .ok_or_else(|| AppError::DeviceNotFound(peer_id.to_string()))?;
+        detail.has_active_transfer = m.transfer().has_session_with(&peer_id);
+        Ok(detail)
+    })
+}
+
+/// 获取指定节点的打洞统计与当前连接类型（排查传输慢的原因）
+#[tauri::command]
+pub async fn get_peer_diagnostics(
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> crate::AppResult<PeerDiagnostics> {
+    with_manager!(net, |m| Ok(m.devices().peer_diagnostics(&peer_id)))
+}
+
 /// Android APK 下载安装（仅 Android 平台可用）
 #[tauri::command]
 pub async fn install_update(app: AppHandle, url: String, is_force: bool) -> crate::AppResult<()> {
@@ -1,8 +1,9 @@
+use crate::device::PairedDeviceInfo;
 use crate::events;
 use crate::network::NetManagerState;
-use crate::pairing::code::{PairingCodeInfo, ShareCodeRecord};
+use crate::pairing::code::{CodeFormat, PairingCodeInfo, ShareCodeRecord};
 use crate::protocol::{PairingMethod, PairingResponse};
-use crate::AppResult;
+use crate::{AppError, AppResult};
 use serde::{Deserialize, Serialize};
 use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
 use tauri::{AppHandle, Emitter, State};
@@ -15,13 +16,51 @@ pub struct DeviceInfo {
     pub code_record: ShareCodeRecord,
 }
 
-/// 生成配对码
+/// 生成配对码，`format` 缺省为 6 位数字
 #[tauri::command]
 pub async fn generate_pairing_code(
     net: State<'_, NetManagerState>,
     expires_in_secs: Option<u64>,
+    format: Option<CodeFormat>,
 ) -> AppResult<PairingCodeInfo> {
-    with_manager!(net, |m| m.pairing().generate_code(expires_in_secs.unwrap_or(300)).await)
+    with_manager!(net, |m| m
+        .pairing()
+        .generate_code(expires_in_secs.unwrap_or(300), format.unwrap_or_default())
+        .await)
+}
+
+/// 当前配对码的二维码载荷（`swarmdrop://pair?...`），需先调用 `generate_pairing_code`
+#[tauri::command]
+pub async fn generate_pairing_payload(net: State<'_, NetManagerState>) -> AppResult<String> {
+    with_manager!(net, |m| m.pairing().pairing_payload().await)
+}
+
+/// 扫码配对：解析二维码载荷并直接发起配对（地址失效时回退到 DHT 查询）
+///
+/// 配对成功后自动添加到已配对设备，并 emit `paired-device-added` 事件通知前端。
+#[tauri::command]
+pub async fn pair_from_payload(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    payload: String,
+) -> AppResult<PairingResponse> {
+    let (response, paired_info) =
+        with_manager!(net, |m| m.pairing().pair_from_payload(&payload).await)?;
+
+    if let Some(info) = paired_info {
+        let _ = app.emit(events::PAIRED_DEVICE_ADDED, &info);
+    }
+
+    Ok(response)
+}
+
+/// 撤销当前配对码（配对码页面的取消按钮），移除其 DHT 记录
+#[tauri::command]
+pub async fn revoke_pairing_code(net: State<'_, NetManagerState>) -> AppResult<()> {
+    with_manager!(net, |m| {
+        m.pairing().revoke_code().await;
+        Ok(())
+    })
 }
 
 /// 通过配对码查询对端设备信息
@@ -58,7 +97,36 @@ pub async fn request_pairing(
     Ok(response)
 }
 
-/// 取消与指定设备的配对（同步更新运行时状态）
+/// 向局域网内已发现的设备直接发起配对
+///
+/// 使用 mDNS 发现的局域网地址直连，以 `PairingMethod::Direct` 配对，不查询 DHT，
+/// 两端均无互联网时也可配对。对端当前未在局域网中被发现时直接返回错误。
+#[tauri::command]
+pub async fn request_pairing_lan(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> AppResult<PairingResponse> {
+    let (response, paired_info) = with_manager!(net, |m| {
+        let addrs = m.devices().lan_addrs(&peer_id);
+        if addrs.is_empty() {
+            return Err(AppError::Network(format!(
+                "设备 {peer_id} 当前不在局域网中，无法直接配对"
+            )));
+        }
+        m.pairing()
+            .request_pairing(peer_id, PairingMethod::Direct, Some(addrs))
+            .await
+    })?;
+
+    if let Some(info) = paired_info {
+        let _ = app.emit(events::PAIRED_DEVICE_ADDED, &info);
+    }
+
+    Ok(response)
+}
+
+/// 取消与指定设备的配对（同步更新运行时状态，对端在线时通知其同样移除）
 #[tauri::command]
 pub async fn remove_paired_device(
     net: State<'_, NetManagerState>,
@@ -67,11 +135,51 @@ pub async fn remove_paired_device(
     let guard = net.lock().await;
     // 节点未运行时静默成功（前端仍会更新 Stronghold）
     if let Some(manager) = guard.as_ref() {
-        manager.pairing().remove_paired_device(&peer_id);
+        manager.unpair(peer_id);
     }
     Ok(())
 }
 
+/// 设置已配对设备的昵称（去除首尾空白，超长截断；为空时清除昵称）
+///
+/// 返回更新后的设备信息，同时推送 `paired-device-updated`（前端据此同步 Stronghold）。
+#[tauri::command]
+pub async fn set_device_nickname(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+    nickname: Option<String>,
+) -> AppResult<PairedDeviceInfo> {
+    with_manager!(net, |m| m.set_device_nickname(&app, peer_id, nickname.as_deref()))
+}
+
+/// 屏蔽节点：之后其配对请求与传输请求都被静默拒绝
+///
+/// 已配对时同时取消配对并刷新设备列表，返回 true（前端需同步移除 Stronghold 中的设备）。
+#[tauri::command]
+pub async fn block_peer(
+    app: AppHandle,
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> AppResult<bool> {
+    with_manager!(net, |m| Ok(m.block_peer(&app, peer_id)))
+}
+
+/// 解除屏蔽
+#[tauri::command]
+pub async fn unblock_peer(net: State<'_, NetManagerState>, peer_id: PeerId) -> AppResult<()> {
+    with_manager!(net, |m| {
+        m.blocklist().unblock(&peer_id);
+        Ok(())
+    })
+}
+
+/// 被屏蔽的节点列表
+#[tauri::command]
+pub async fn list_blocked(net: State<'_, NetManagerState>) -> AppResult<Vec<PeerId>> {
+    with_manager!(net, |m| Ok(m.blocklist().list()))
+}
+
 /// 处理收到的配对请求（接受/拒绝）
 ///
 /// 接受配对后自动添加到已配对设备，并 emit `paired-device-added` 事件通知前端。
@@ -95,3 +203,42 @@ pub async fn respond_pairing_request(
 
     Ok(())
 }
+
+/// 配对验证码核对状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingVerification {
+    /// 用户是否已核对
+    pub verified: bool,
+    /// 验证码（已核对或此前配对的设备为 None）
+    pub code: Option<String>,
+}
+
+/// 查询已配对设备的验证码，供两端用户比对
+#[tauri::command]
+pub async fn get_pairing_verification(
+    net: State<'_, NetManagerState>,
+    peer_id: PeerId,
+) -> AppResult<PairingVerification> {
+    let info = with_manager!(net, |m| m.pairing().get_verification(&peer_id))?;
+    Ok(PairingVerification {
+        verified: info.verified,
+        code: info.verification_code,
+    })
+}
+
+/// 确认两端验证码一致，标记配对为已核对并通知对端
+#[tauri::command]
+pub async fn confirm_pairing(net: State<'_, NetManagerState>, peer_id: PeerId) -> AppResult<()> {
+    with_manager!(net, |m| m.pairing().confirm_pairing(peer_id).await)?;
+    Ok(())
+}
+
+/// 验证码不一致，中止配对：移除该设备并通知对端同样移除
+#[tauri::command]
+pub async fn abort_pairing(net: State<'_, NetManagerState>, peer_id: PeerId) -> AppResult<()> {
+    with_manager!(net, |m| {
+        m.pairing().abort_pairing(peer_id).await;
+        Ok(())
+    })
+}
@@ -0,0 +1,20 @@
+//! 后端设置命令
+
+use tauri::State;
+
+use crate::settings::{AppSettings, SettingsStore};
+
+/// 读取后端持久化的设置
+#[tauri::command]
+pub async fn get_settings(settings: State<'_, SettingsStore>) -> crate::AppResult<AppSettings> {
+    Ok(settings.get())
+}
+
+/// 保存设置（默认保存目录不存在时创建，不可写时返回错误）
+#[tauri::command]
+pub async fn set_settings(
+    store: State<'_, SettingsStore>,
+    settings: AppSettings,
+) -> crate::AppResult<()> {
+    store.set(settings)
+}
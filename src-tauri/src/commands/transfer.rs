@@ -2,16 +2,26 @@
 //!
 //! 薄层命令入口，所有业务逻辑委托给 [`transfer`](crate::transfer) 模块。
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::Serialize;
 use tauri::ipc::Channel;
-use tauri::State;
+use tauri::{Manager, State};
 use uuid::Uuid;
 
-use crate::file_source::{EnumeratedFile, FileSource};
+use crate::file_source::{
+    path_ops, EnumeratedFile, FileSource, HashVerification, ScanContext, ScanFilter, ScanProgress,
+    SymlinkPolicy,
+};
+use crate::network::power_save::PowerSave;
 use crate::network::NetManagerState;
-use crate::transfer::offer::{PrepareProgress, StartSendResult, TransferManager};
+use crate::settings::SettingsStore;
+use crate::transfer::estimate::TransferEstimate;
+use crate::transfer::offer::{
+    PeerSendResult, PrepareProgress, PreparedTransfer, StartSendResult, TransferManager,
+};
 use sea_orm::EntityTrait;
 
 // ============ scan_sources ============
@@ -26,44 +36,106 @@ pub struct ScannedSourceResult {
     pub files: Vec<EnumeratedFile>,
     /// 此来源的总大小
     pub total_size: u64,
+    /// 默认保存目录位于此目录内（本机收发时接收的文件会写入来源目录，前端据此提醒用户）
+    pub contains_save_dir: bool,
 }
 
 /// 扫描文件来源：遍历目录、收集元数据，不计算 hash
 ///
 /// 用于用户选择文件/文件夹后在 UI 上展示文件树。
 /// 每个 FileSource 返回一个 ScannedSourceResult，包含扁平化的文件列表。
+/// `symlink_policy` 控制目录中符号链接的处理方式，默认跳过。
+/// `include` / `exclude` 为目录遍历的 glob 过滤规则（见 [`ScanFilter`]），默认不过滤。
+/// 接收中的 `.part` 临时文件始终跳过；默认保存目录位于所选目录内时在结果中标记。
+/// 通过 `on_progress` Channel 上报累计的文件数和字节数；
+/// 节点运行时可用 `scan_id` 调用 `cancel_prepare` 取消扫描。
 #[tauri::command]
 pub async fn scan_sources(
     app: tauri::AppHandle,
     sources: Vec<FileSource>,
+    symlink_policy: Option<SymlinkPolicy>,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    scan_id: Option<Uuid>,
+    on_progress: Channel<ScanProgress>,
 ) -> crate::AppResult<Vec<ScannedSourceResult>> {
+    let filter = ScanFilter::new(
+        include.as_deref().unwrap_or_default(),
+        exclude.as_deref().unwrap_or_default(),
+    )?;
+    // 扫描不依赖 P2P 节点，节点未启动时扫描不可取消
+    let transfer = match app.try_state::<NetManagerState>() {
+        Some(net) => get_transfer(&net).await.ok(),
+        None => None,
+    };
+    let guard = match (&transfer, scan_id) {
+        (Some(transfer), Some(id)) => Some(transfer.register_cancellable(id)?),
+        _ => None,
+    };
+    let cancel = guard
+        .as_ref()
+        .map(|g| g.token().clone())
+        .unwrap_or_default();
+    let scan = Arc::new(
+        ScanContext::new(cancel, move |p| {
+            let _ = on_progress.send(p);
+        })
+        .with_filter(filter),
+    );
+
+    let symlink_policy = symlink_policy.unwrap_or_default();
+    let save_dir = app
+        .try_state::<SettingsStore>()
+        .and_then(|s| s.effective_save_location(&app))
+        .and_then(|location| match location {
+            entity::SaveLocation::Path { path } => Some(PathBuf::from(path)),
+            _ => None,
+        });
     let mut results = Vec::new();
 
     for source in sources {
+        scan.check_cancelled()?;
         let meta = source.metadata(&app).await?;
 
         if meta.is_dir {
-            let entries = source.enumerate_dir(&meta.name, &app).await?;
+            let contains_save_dir = match (&source, &save_dir) {
+                (FileSource::Path { path }, Some(save_dir)) => path_ops::is_within(save_dir, path),
+                _ => false,
+            };
+            if contains_save_dir {
+                tracing::warn!(
+                    "默认保存目录位于所选目录内，本机接收的文件会写入该目录: {}",
+                    meta.name
+                );
+            }
+            let entries = source
+                .enumerate_dir(&meta.name, symlink_policy, &scan, &app)
+                .await?;
             let total_size: u64 = entries.iter().map(|e| e.size).sum();
             results.push(ScannedSourceResult {
                 is_directory: true,
                 files: entries,
                 total_size,
+                contains_save_dir,
             });
         } else {
+            scan.record(meta.size);
             results.push(ScannedSourceResult {
                 is_directory: false,
                 total_size: meta.size,
+                contains_save_dir: false,
                 files: vec![EnumeratedFile {
                     name: meta.name.clone(),
                     relative_path: meta.name,
                     source,
                     size: meta.size,
+                    is_directory: false,
                 }],
             });
         }
     }
 
+    scan.finish();
     Ok(results)
 }
 
@@ -93,17 +165,24 @@ pub struct PreparedTransferResult {
 ///
 /// 接收 `scan_sources` 返回的 `EnumeratedFile` 列表（前端可能已过滤掉用户移除的文件）。
 /// 不再做目录遍历，只计算 hash。通过 `on_progress` Channel 实时上报进度。
+/// `prepared_id` 由前端生成，准备期间可用它调用 `cancel_prepare`。
 #[tauri::command]
 pub async fn prepare_send(
     app: tauri::AppHandle,
     net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
     files: Vec<EnumeratedFile>,
     on_progress: Channel<PrepareProgress>,
 ) -> crate::AppResult<PreparedTransferResult> {
     let transfer = get_transfer(&net).await?;
-    let prepared = transfer.prepare(files, &app, on_progress).await?;
+    let prepared = transfer
+        .prepare(prepared_id, files, &app, on_progress)
+        .await?;
+    Ok(prepared_result(&prepared))
+}
 
-    Ok(PreparedTransferResult {
+fn prepared_result(prepared: &PreparedTransfer) -> PreparedTransferResult {
+    PreparedTransferResult {
         prepared_id: prepared.prepared_id,
         total_size: prepared.total_size,
         files: prepared
@@ -117,11 +196,53 @@ pub async fn prepare_send(
                 is_directory: false,
             })
             .collect(),
-    })
+    }
+}
+
+/// 重新准备：只对上次准备之后大小或修改时间变化的文件重新计算校验和
+///
+/// 已不存在的文件会从结果中移除，其余文件的 fileId 不变。
+/// 进度通过 `on_progress` 上报（只统计需要重新计算的文件），期间可用 `cancel_prepare` 取消。
+#[tauri::command]
+pub async fn refresh_prepared(
+    app: tauri::AppHandle,
+    net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
+    on_progress: Channel<PrepareProgress>,
+) -> crate::AppResult<PreparedTransferResult> {
+    let transfer = get_transfer(&net).await?;
+    let prepared = transfer
+        .refresh_prepared(prepared_id, &app, on_progress)
+        .await?;
+    Ok(prepared_result(&prepared))
+}
+
+/// 丢弃已准备的传输（发送后仍会保留以便再次发送），返回是否存在
+#[tauri::command]
+pub async fn discard_prepared(
+    net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
+) -> crate::AppResult<bool> {
+    let transfer = get_transfer(&net).await?;
+    Ok(transfer.discard_prepared(&prepared_id))
+}
+
+/// 取消进行中的 prepare_send / scan_sources，返回是否找到对应任务（已完成或不存在时返回 false）
+#[tauri::command]
+pub async fn cancel_prepare(
+    net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
+) -> crate::AppResult<bool> {
+    let transfer = get_transfer(&net).await?;
+    Ok(transfer.cancel_prepare(&prepared_id))
 }
 
 /// 开始发送：构造 Offer，发送到目标 peer（非阻塞，通过事件通知结果）
+///
+/// `lan_plaintext` 为用户的"局域网不加密"设置，省略时始终加密。
+/// `prefer_direct_timeout_ms` 为大文件仅有中继连接时等待打洞直连的毫秒数，省略时不等待。
 #[tauri::command]
+#[expect(clippy::too_many_arguments, reason = "发送选项由前端逐项传入")]
 pub async fn start_send(
     app: tauri::AppHandle,
     net: State<'_, NetManagerState>,
@@ -129,22 +250,89 @@ pub async fn start_send(
     peer_id: String,
     peer_name: String,
     selected_file_ids: Vec<u32>,
+    lan_plaintext: Option<bool>,
+    prefer_direct_timeout_ms: Option<u64>,
 ) -> crate::AppResult<StartSendResult> {
     let transfer = get_transfer(&net).await?;
-    transfer.send_offer(&prepared_id, &peer_id, &peer_name, &selected_file_ids, app)
+    exit_power_save(&app);
+    transfer.send_offer(
+        &prepared_id,
+        &peer_id,
+        &peer_name,
+        &selected_file_ids,
+        lan_plaintext.unwrap_or(false),
+        prefer_direct_timeout_ms.map(Duration::from_millis),
+        app,
+    )
+}
+
+/// 同时发送给多个 peer：每个 peer 独立会话，返回逐个 peer 的结果
+#[tauri::command]
+pub async fn start_send_multi(
+    app: tauri::AppHandle,
+    net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
+    peer_ids: Vec<String>,
+    selected_file_ids: Vec<u32>,
+    lan_plaintext: Option<bool>,
+    prefer_direct_timeout_ms: Option<u64>,
+) -> crate::AppResult<Vec<PeerSendResult>> {
+    let transfer = get_transfer(&net).await?;
+    exit_power_save(&app);
+    transfer.send_offer_multi(
+        &prepared_id,
+        &peer_ids,
+        &selected_file_ids,
+        lan_plaintext.unwrap_or(false),
+        prefer_direct_timeout_ms.map(Duration::from_millis),
+        app,
+    )
+}
+
+/// 估算发送给指定 peer 的耗时（按当前连接类型与 RTT，不传输数据）
+#[tauri::command]
+pub async fn estimate_transfer(
+    net: State<'_, NetManagerState>,
+    prepared_id: Uuid,
+    peer_id: String,
+) -> crate::AppResult<TransferEstimate> {
+    let transfer = get_transfer(&net).await?;
+    transfer.estimate(&prepared_id, &peer_id)
 }
 
-/// 确认接收：生成密钥，回复 OfferResult，启动后台拉取
+/// 确认接收：协商会话密钥，回复 OfferResult，启动后台拉取
+///
+/// `save_location` 为空时使用设置中的默认保存位置（见 [`SettingsStore`]）；
+/// `save_path_template` 为空时使用设置中的保存路径模板，空字符串表示不使用模板。
+/// `accepted_file_ids` 为 None 时接收全部文件，否则只接收其中的文件；
+/// 传入空列表（且 Offer 不含目录）时返回「未选择任何文件」错误，Offer 保留以便重新选择。
 #[tauri::command]
+#[expect(clippy::too_many_arguments, reason = "接收选项由前端逐项传入")]
 pub async fn accept_receive(
     app: tauri::AppHandle,
     net: State<'_, NetManagerState>,
+    settings: State<'_, SettingsStore>,
     session_id: Uuid,
-    save_location: entity::SaveLocation,
+    accepted_file_ids: Option<Vec<u32>>,
+    save_location: Option<entity::SaveLocation>,
+    save_path_template: Option<String>,
+    lan_plaintext: Option<bool>,
+    prefer_direct_timeout_ms: Option<u64>,
 ) -> crate::AppResult<()> {
+    let save_location = settings.resolve_save_location(save_location, &app)?;
+    let save_path_template = settings.resolve_save_path_template(save_path_template)?;
     let transfer = get_transfer(&net).await?;
+    exit_power_save(&app);
     transfer
-        .accept_and_start_receive(&session_id, save_location, app)
+        .accept_and_start_receive(
+            &session_id,
+            accepted_file_ids.as_deref(),
+            save_location,
+            save_path_template,
+            lan_plaintext.unwrap_or(false),
+            prefer_direct_timeout_ms.map(Duration::from_millis),
+            app,
+        )
         .await
 }
 
@@ -168,6 +356,22 @@ pub async fn cancel_send(
     transfer.cancel_send(&session_id).await
 }
 
+/// 获取尚未处理的入站 Offer（前端重载后恢复待确认的接收请求）
+#[tauri::command]
+pub async fn get_pending_offers(
+    app: tauri::AppHandle,
+    net: State<'_, NetManagerState>,
+    settings: State<'_, SettingsStore>,
+) -> crate::AppResult<Vec<crate::transfer::offer::TransferOfferEvent>> {
+    let transfer = get_transfer(&net).await?;
+    let default_save_location = settings.effective_save_location(&app);
+    Ok(transfer
+        .get_pending_offers()
+        .into_iter()
+        .map(|offer| offer.with_default_save_location(default_save_location.clone()))
+        .collect())
+}
+
 /// 取消接收
 #[tauri::command]
 pub async fn cancel_receive(
@@ -270,6 +474,7 @@ pub async fn resume_transfer(
     session_id: Uuid,
 ) -> crate::AppResult<ResumeTransferResult> {
     let transfer = get_transfer(&net).await?;
+    exit_power_save(&app);
 
     // 从 DB 读取会话方向
     let session = entity::TransferSession::find_by_id(session_id)
@@ -311,16 +516,34 @@ pub async fn resume_transfer(
     })
 }
 
+/// 重新校验已接收文件的完整性（BLAKE3），无需重新下载
+///
+/// `expected_checksum` 通常来自传输历史中的文件记录，返回是否一致及实际 hash。
+#[tauri::command]
+pub async fn verify_file(
+    app: tauri::AppHandle,
+    source: FileSource,
+    expected_checksum: String,
+) -> crate::AppResult<HashVerification> {
+    source.verify_hash(&expected_checksum, Some(&app)).await
+}
+
 /// 解析 Android 公共目录的 content:// URI（仅 Android 平台）
 ///
-/// 前端用于调用 `AndroidFs.showViewDirDialog(uri)` 打开保存目录。
+/// 前端用于调用 `AndroidFs.showViewDirDialog(uri)` 打开保存目录，`base_dir` 缺省为 Download。
 #[cfg(target_os = "android")]
 #[tauri::command]
 pub async fn resolve_android_dir_uri(
     app: tauri::AppHandle,
     subdir: String,
+    base_dir: Option<entity::PublicBaseDir>,
 ) -> crate::AppResult<Option<serde_json::Value>> {
-    let uri = crate::file_sink::android_ops::resolve_save_dir_uri(&subdir, &app).await;
+    let uri = crate::file_sink::android_ops::resolve_save_dir_uri(
+        base_dir.unwrap_or_default(),
+        &subdir,
+        &app,
+    )
+    .await;
     Ok(uri.and_then(|u| serde_json::to_value(&u).ok()))
 }
 
@@ -329,12 +552,46 @@ pub async fn resolve_android_dir_uri(
 #[tauri::command]
 pub async fn resolve_android_dir_uri(
     _subdir: String,
+    _base_dir: Option<entity::PublicBaseDir>,
 ) -> crate::AppResult<Option<serde_json::Value>> {
     Ok(None)
 }
 
+/// 在系统文件管理器中显示并选中文件（桌面端）
+///
+/// Windows 使用 Explorer `/select`、macOS 使用 Finder `-R`（由 opener 插件实现），
+/// Linux 文件管理器不支持选中时退回打开所在目录。
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn reveal_path(path: String) -> crate::AppResult<()> {
+    let path = std::path::PathBuf::from(path);
+    std::fs::metadata(&path)?;
+
+    if let Err(e) = tauri_plugin_opener::reveal_item_in_dir(&path) {
+        tracing::warn!("无法选中文件，改为打开所在目录: {}", e);
+        let dir = path.parent().unwrap_or(&path);
+        tauri_plugin_opener::open_path(dir, None::<&str>)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Android stub（文件通过 content:// URI 打开，见前端 `openTransferResult`）
+#[cfg(target_os = "android")]
+#[tauri::command]
+pub async fn reveal_path(_path: String) -> crate::AppResult<()> {
+    Err(crate::AppError::Config("仅桌面端支持".into()))
+}
+
 // ============ 辅助函数 ============
 
+/// 开始传输时关闭省电模式，恢复在线记录刷新与已配对设备在线检查
+fn exit_power_save(app: &tauri::AppHandle) {
+    if app.state::<PowerSave>().set(false) {
+        tracing::info!("开始传输，已关闭省电模式");
+    }
+}
+
 /// 从 Tauri State 中获取 TransferManager（短暂持锁后立即释放）
 async fn get_transfer(net: &NetManagerState) -> crate::AppResult<Arc<TransferManager>> {
     let guard = net.lock().await;
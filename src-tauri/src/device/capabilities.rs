@@ -0,0 +1,119 @@
+//! 节点能力声明
+//!
+//! 客户端在 identify 的 agent_version 中附带 `caps=` 字段（逗号分隔的能力标识），
+//! 对端在发送 Offer 前据此选择双方都支持的特性，而不是靠猜测或等待协商失败。
+//! 未知标识直接忽略；不带 `caps=` 的旧版本客户端视为不支持任何可选特性。
+
+use serde::Serialize;
+
+/// 断点续传（ResumeRequest / ResumeOffer）
+const CAP_RESUME: &str = "resume";
+/// X25519 密钥交换（Offer / OfferResult 携带临时公钥）
+const CAP_KEY_EXCHANGE: &str = "x25519";
+/// 断点续传重新协商密钥（ResumeRequest / ResumeOffer 携带临时公钥，不再直接发送密钥）
+const CAP_RESUME_KEY_EXCHANGE: &str = "resume-x25519";
+/// Offer 节点身份签名
+const CAP_OFFER_SIGNATURE: &str = "offer-sig";
+/// 局域网明文传输协商
+const CAP_LAN_PLAINTEXT: &str = "lan-plain";
+
+/// 对端支持的可选特性
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerCapabilities {
+    pub resume: bool,
+    pub key_exchange: bool,
+    pub resume_key_exchange: bool,
+    pub offer_signature: bool,
+    pub lan_plaintext: bool,
+}
+
+impl PeerCapabilities {
+    /// 本机支持的全部特性
+    pub const LOCAL: Self = Self {
+        resume: true,
+        key_exchange: true,
+        resume_key_exchange: true,
+        offer_signature: true,
+        lan_plaintext: true,
+    };
+
+    /// 编码为 `caps=` 字段的值，如 `resume,x25519,resume-x25519,offer-sig,lan-plain`
+    pub fn to_token(&self) -> String {
+        [
+            (self.resume, CAP_RESUME),
+            (self.key_exchange, CAP_KEY_EXCHANGE),
+            (self.resume_key_exchange, CAP_RESUME_KEY_EXCHANGE),
+            (self.offer_signature, CAP_OFFER_SIGNATURE),
+            (self.lan_plaintext, CAP_LAN_PLAINTEXT),
+        ]
+        .into_iter()
+        .filter_map(|(supported, cap)| supported.then_some(cap))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+
+    /// 解析 `caps=` 字段的值，未知标识忽略
+    pub fn from_token(token: &str) -> Self {
+        let mut caps = Self::default();
+        for cap in token.split(',').map(str::trim) {
+            match cap {
+                CAP_RESUME => caps.resume = true,
+                CAP_KEY_EXCHANGE => caps.key_exchange = true,
+                CAP_RESUME_KEY_EXCHANGE => caps.resume_key_exchange = true,
+                CAP_OFFER_SIGNATURE => caps.offer_signature = true,
+                CAP_LAN_PLAINTEXT => caps.lan_plaintext = true,
+                _ => {}
+            }
+        }
+        caps
+    }
+
+    /// 从 agent_version 中取出 `caps=` 字段，旧版本客户端没有该字段时返回 None
+    pub fn from_agent_version(agent_version: &str) -> Option<Self> {
+        // host= 之后是主机名，不参与字段解析（见 OsInfo::from_agent_version）
+        let fields = agent_version
+            .split_once("; host=")
+            .map_or(agent_version, |(fields, _)| fields);
+        fields
+            .split("; ")
+            .find_map(|part| part.strip_prefix("caps="))
+            .map(Self::from_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_round_trip() {
+        let token = PeerCapabilities::LOCAL.to_token();
+        assert_eq!(token, "resume,x25519,resume-x25519,offer-sig,lan-plain");
+        let parsed = PeerCapabilities::from_token(&token);
+        assert_eq!(parsed, PeerCapabilities::LOCAL);
+        assert_eq!(PeerCapabilities::default().to_token(), "");
+    }
+
+    #[test]
+    fn test_from_agent_version() {
+        // 未知能力忽略
+        let caps = PeerCapabilities::from_agent_version(
+            "swarmdrop/9.0.0; os=linux; caps=zstd,resume,aesgcm; host=box",
+        )
+        .unwrap();
+        assert_eq!(
+            caps,
+            PeerCapabilities {
+                resume: true,
+                ..Default::default()
+            }
+        );
+
+        // 旧版本不带 caps=；主机名中的 caps= 不会被误认
+        assert_eq!(
+            PeerCapabilities::from_agent_version("swarmdrop/0.1.0; os=linux; host=a; caps=x25519"),
+            None
+        );
+    }
+}
@@ -1,11 +1,19 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
 use swarm_p2p_core::NodeEvent;
 
-use super::utils::infer_connection_type;
-use super::{ConnectionType, Device, DeviceStatus, OsInfo, PairedDeviceInfo};
+use super::utils::{
+    connection_quality, disambiguate_display_names, infer_connection_type, is_lan_addr,
+    is_public_direct_addr, sort_lan_first,
+};
+use super::{
+    ConnectionQuality, ConnectionType, Device, DeviceDetail, DeviceStatus, OsInfo,
+    PairedDeviceInfo, PeerCapabilities, PeerDiagnostics, RTT_HISTORY_LEN,
+};
 use crate::protocol::AppRequest;
 
 /// 运行时 Peer 信息（DashMap 中的值）
@@ -14,13 +22,16 @@ pub(super) struct PeerInfo {
     pub peer_id: PeerId,
     pub addrs: Vec<Multiaddr>,
     pub agent_version: Option<String>,
+    /// 从 agent_version 解析出的能力声明（旧版本客户端或尚未 identify 时为 None）
+    pub capabilities: Option<PeerCapabilities>,
     pub rtt_ms: Option<u64>,
+    /// 最近的 ping RTT（从旧到新，断开后保留）
+    pub rtt_history: VecDeque<u64>,
     pub is_connected: bool,
     /// DCUtR 打洞是否成功（比地址推断更准确）
     pub hole_punched: bool,
-    /// 发现时间戳，暂未使用但后续可用于超时清理
-    #[expect(dead_code)]
-    pub discovered_at: i64,
+    /// 最近一次发现或有事件涉及该 peer 的时间（毫秒），用于清理长期未见的 peer
+    pub seen_at: i64,
     pub connected_at: Option<i64>,
 }
 
@@ -31,16 +42,21 @@ impl PeerInfo {
             peer_id,
             addrs,
             agent_version: None,
+            capabilities: None,
             rtt_ms: None,
+            rtt_history: VecDeque::new(),
             is_connected: false,
             hole_punched: false,
-            discovered_at: chrono::Utc::now().timestamp_millis(),
+            seen_at: chrono::Utc::now().timestamp_millis(),
             connected_at: None,
         }
     }
 }
 
 /// 设备过滤器
+///
+/// 简单过滤器序列化为字符串（`"all"` / `"connected"` / `"paired"`），
+/// 组合条件为 `{ "filtered": { ... } }`。
 #[derive(Debug, Clone, serde::Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum DeviceFilter {
@@ -48,6 +64,74 @@ pub enum DeviceFilter {
     All,
     Connected,
     Paired,
+    /// 组合条件，省略的字段不参与过滤；候选集包含已发现的设备和全部已配对设备
+    #[serde(rename_all = "camelCase")]
+    Filtered {
+        /// 是否已配对
+        paired: Option<bool>,
+        /// 是否已连接
+        connected: Option<bool>,
+        /// 平台（如 `android`、`windows`，不区分大小写）
+        platform: Option<String>,
+        /// 主机名或昵称子串匹配（不区分大小写）
+        query: Option<String>,
+    },
+}
+
+/// 设备排序方式
+#[derive(Debug, Clone, Copy, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeviceSort {
+    /// 延迟升序（无延迟数据的排最后）
+    Latency,
+    /// 主机名升序（不区分大小写）
+    Name,
+    /// 最近连接时间降序（从未连接的排最后）
+    LastConnected,
+}
+
+/// 设备查询条件
+///
+/// 所有字段均可省略，`{ filter: "paired" }` 与旧的 `DeviceFilter` 参数等价。
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeviceQuery {
+    pub filter: DeviceFilter,
+    /// 只返回在线设备（可与任意 filter 组合，例如在线的已配对设备）
+    pub online_only: bool,
+    /// 排序方式，None 时不保证顺序
+    pub sort: Option<DeviceSort>,
+    /// 主机名子串匹配（不区分大小写）
+    pub search: Option<String>,
+}
+
+impl From<DeviceFilter> for DeviceQuery {
+    fn from(filter: DeviceFilter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+}
+
+/// 下线后在此时间内重新上线视为连接抖动（仍推送事件，但不再提醒用户）
+const PRESENCE_FLAP_WINDOW: Duration = Duration::from_secs(10);
+
+/// 默认过期窗口：未连接、未配对的 peer 超过该时长未见即从设备列表中清理
+pub const DEFAULT_STALE_PEER_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// 已配对设备的在线状态变化（事件循环据此推送 `paired-device-online` / `offline`）
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresenceChange {
+    Online {
+        peer_id: PeerId,
+        connection: Option<ConnectionType>,
+        /// 刚下线不久又重新连接（连接抖动），前端与系统通知不应再次提醒
+        flapping: bool,
+    },
+    Offline {
+        peer_id: PeerId,
+    },
 }
 
 /// 设备管理器
@@ -60,6 +144,14 @@ pub struct DeviceManager {
     peers: DashMap<PeerId, PeerInfo>,
     /// 与 PairingManager 共享的已配对设备（只读）
     paired_devices: Arc<DashMap<PeerId, PairedDeviceInfo>>,
+    /// 已报告上线的已配对设备（连接事件与 check_paired_online 可能先后报告同一次上线，据此去重）
+    online_paired: DashSet<PeerId>,
+    /// 已配对设备最近一次下线的时间（识别连接抖动）
+    last_offline: DashMap<PeerId, Instant>,
+    /// 打洞统计（断开连接后保留，供排查传输慢的原因）
+    diagnostics: DashMap<PeerId, PeerDiagnostics>,
+    /// 未连接、未配对的 peer 超过该时长未见即视为过期
+    stale_after: Duration,
 }
 
 impl DeviceManager {
@@ -68,16 +160,30 @@ impl DeviceManager {
         Self {
             peers: DashMap::new(),
             paired_devices,
+            online_paired: DashSet::new(),
+            last_offline: DashMap::new(),
+            diagnostics: DashMap::new(),
+            stale_after: DEFAULT_STALE_PEER_WINDOW,
         }
     }
 
+    /// 设置过期窗口（默认 [`DEFAULT_STALE_PEER_WINDOW`]）
+    pub fn with_stale_peer_window(mut self, window: Duration) -> Self {
+        self.stale_after = window;
+        self
+    }
+
     /// 处理 NodeEvent，更新 peer 状态
-    pub fn handle_event(&self, event: &NodeEvent<AppRequest>) {
+    ///
+    /// 已配对设备连接 / 断开时返回对应的在线状态变化。
+    pub fn handle_event(&self, event: &NodeEvent<AppRequest>) -> Option<PresenceChange> {
+        let now = chrono::Utc::now().timestamp_millis();
         match event {
             NodeEvent::PeersDiscovered { peers } => {
                 for (peer_id, addr) in peers {
                     match self.peers.get_mut(peer_id) {
                         Some(mut entry) => {
+                            entry.seen_at = now;
                             if !entry.addrs.contains(addr) {
                                 entry.addrs.push(addr.clone());
                             }
@@ -93,9 +199,9 @@ impl DeviceManager {
             }
 
             NodeEvent::PeerConnected { peer_id } => {
-                let now = chrono::Utc::now().timestamp_millis();
                 match self.peers.get_mut(peer_id) {
                     Some(mut entry) => {
+                        entry.seen_at = now;
                         entry.is_connected = true;
                         entry.connected_at = Some(now);
                     }
@@ -106,14 +212,17 @@ impl DeviceManager {
                         self.peers.insert(*peer_id, info);
                     }
                 }
+                return self.mark_paired_online(peer_id);
             }
 
             NodeEvent::PeerDisconnected { peer_id } => {
                 if let Some(mut entry) = self.peers.get_mut(peer_id) {
+                    entry.seen_at = now;
                     entry.is_connected = false;
                     entry.rtt_ms = None;
                     entry.hole_punched = false;
                 }
+                return self.mark_paired_offline(peer_id);
             }
 
             NodeEvent::IdentifyReceived {
@@ -122,34 +231,99 @@ impl DeviceManager {
                 ..
             } => {
                 if let Some(mut entry) = self.peers.get_mut(peer_id) {
+                    entry.seen_at = now;
+                    entry.capabilities = PeerCapabilities::from_agent_version(agent_version);
                     entry.agent_version = Some(agent_version.clone());
                 }
             }
 
             NodeEvent::PingSuccess { peer_id, rtt_ms } => {
                 if let Some(mut entry) = self.peers.get_mut(peer_id) {
+                    entry.seen_at = now;
                     entry.rtt_ms = Some(*rtt_ms);
+                    if entry.rtt_history.len() == RTT_HISTORY_LEN {
+                        entry.rtt_history.pop_front();
+                    }
+                    entry.rtt_history.push_back(*rtt_ms);
                 }
             }
 
             NodeEvent::HolePunchSucceeded { peer_id } => {
                 if let Some(mut entry) = self.peers.get_mut(peer_id) {
+                    entry.seen_at = now;
                     entry.hole_punched = true;
                 }
+                self.diagnostics
+                    .entry(*peer_id)
+                    .or_default()
+                    .hole_punch_attempts += 1;
+            }
+
+            NodeEvent::HolePunchFailed { peer_id, error } => {
+                if let Some(mut entry) = self.peers.get_mut(peer_id) {
+                    entry.seen_at = now;
+                }
+                let mut entry = self.diagnostics.entry(*peer_id).or_default();
+                entry.hole_punch_attempts += 1;
+                entry.hole_punch_failures += 1;
+                entry.last_hole_punch_error = Some(error.to_string());
             }
 
             // 其他事件忽略
             _ => {}
         }
+        None
+    }
+
+    /// 已配对设备已连接且尚未报告上线时，返回上线变化（重复调用只报告一次）
+    pub fn mark_paired_online(&self, peer_id: &PeerId) -> Option<PresenceChange> {
+        if !self.paired_devices.contains_key(peer_id) || !self.is_connected(peer_id) {
+            return None;
+        }
+        self.online_paired
+            .insert(*peer_id)
+            .then(|| PresenceChange::Online {
+                peer_id: *peer_id,
+                connection: self.connection_type(peer_id),
+                flapping: self
+                    .last_offline
+                    .get(peer_id)
+                    .is_some_and(|t| t.elapsed() < PRESENCE_FLAP_WINDOW),
+            })
+    }
+
+    /// 已报告上线的已配对设备断开时，返回下线变化
+    fn mark_paired_offline(&self, peer_id: &PeerId) -> Option<PresenceChange> {
+        let peer_id = self.online_paired.remove(peer_id)?;
+        self.last_offline.insert(peer_id, Instant::now());
+        Some(PresenceChange::Offline { peer_id })
+    }
+
+    /// 查询单个已配对设备（在线状态事件携带完整设备信息）
+    pub fn get_paired_device(&self, peer_id: &PeerId) -> Option<Device> {
+        self.get_devices(&DeviceFilter::Paired.into())
+            .into_iter()
+            .find(|d| d.peer_id == *peer_id)
     }
 
-    /// 统一查询设备列表
-    pub fn get_devices(&self, filter: DeviceFilter) -> Vec<Device> {
-        match filter {
+    /// 已配对设备的名称（昵称优先，通知文案用）
+    pub fn paired_name(&self, peer_id: &PeerId) -> Option<String> {
+        self.paired_devices
+            .get(peer_id)
+            .map(|d| d.name().to_owned())
+    }
+
+    /// 统一查询设备列表：按 filter 取候选集，再依次做在线过滤、主机名匹配和排序
+    ///
+    /// 已过期但尚未被 [`prune_stale`](Self::prune_stale) 清理的 peer 不会出现在结果中。
+    pub fn get_devices(&self, query: &DeviceQuery) -> Vec<Device> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut devices: Vec<Device> = match &query.filter {
             DeviceFilter::All | DeviceFilter::Connected => {
-                let connected_only = matches!(filter, DeviceFilter::Connected);
+                let connected_only = matches!(query.filter, DeviceFilter::Connected);
                 self.peers
                     .iter()
+                    .filter(|entry| !self.is_stale(entry.value(), now))
                     .filter(|entry| {
                         let peer = entry.value();
                         // 只返回 SwarmDrop 客户端（过滤掉引导/中继等基础设施节点）
@@ -168,24 +342,74 @@ impl DeviceManager {
                 .map(|entry| {
                     let info = entry.value();
                     let peer_info = self.peers.get(&info.peer_id);
-                    let (status, connection, latency) = match peer_info.as_deref() {
+                    let (status, connection, latency, quality) = match peer_info.as_deref() {
                         Some(p) if p.is_connected => {
                             connection_info(&p.addrs, p.rtt_ms, p.hole_punched)
                         }
-                        _ => (DeviceStatus::Offline, None, None),
+                        _ => (DeviceStatus::Offline, None, None, None),
                     };
 
                     Device {
                         peer_id: info.peer_id,
+                        display_name: info.name().to_owned(),
+                        nickname: info.nickname.clone(),
                         os_info: info.os_info.clone(),
                         status,
                         connection,
                         latency,
+                        quality,
+                        last_connected_at: peer_info.and_then(|p| p.connected_at),
+                        last_seen_at: info.last_seen_at,
                         is_paired: true,
                     }
                 })
                 .collect(),
+            // 已配对设备取自 Paired（包含从未发现的），其余取自 All
+            DeviceFilter::Filtered { paired, .. } => {
+                let mut devices = Vec::new();
+                if *paired != Some(false) {
+                    devices = self.get_devices(&DeviceFilter::Paired.into());
+                }
+                if *paired != Some(true) {
+                    devices.extend(
+                        self.get_devices(&DeviceFilter::All.into())
+                            .into_iter()
+                            .filter(|d| !d.is_paired),
+                    );
+                }
+                devices
+            }
+        };
+        // 在完整候选集上消歧，避免过滤 / 搜索改变展示名
+        disambiguate_display_names(&mut devices);
+
+        if let DeviceFilter::Filtered {
+            connected,
+            platform,
+            query: keyword,
+            ..
+        } = &query.filter
+        {
+            let keyword = normalize_keyword(keyword.as_deref());
+            devices.retain(|d| {
+                connected.is_none_or(|c| matches!(d.status, DeviceStatus::Online) == c)
+                    && platform
+                        .as_deref()
+                        .is_none_or(|p| d.os_info.platform.eq_ignore_ascii_case(p.trim()))
+                    && keyword.as_deref().is_none_or(|k| matches_keyword(d, k))
+            });
+        }
+
+        if query.online_only {
+            devices.retain(|d| matches!(d.status, DeviceStatus::Online));
+        }
+        if let Some(keyword) = normalize_keyword(query.search.as_deref()) {
+            devices.retain(|d| matches_keyword(d, &keyword));
+        }
+        if let Some(sort) = query.sort {
+            sort_devices(&mut devices, sort);
         }
+        devices
     }
 
     /// 将 PeerInfo 转换为 Device
@@ -196,22 +420,120 @@ impl DeviceManager {
             .and_then(OsInfo::from_agent_version)
             .unwrap_or_else(|| OsInfo::unknown_from_peer_id(&peer.peer_id));
 
-        let (status, connection, latency) = if peer.is_connected {
+        let (status, connection, latency, quality) = if peer.is_connected {
             connection_info(&peer.addrs, peer.rtt_ms, peer.hole_punched)
         } else {
-            (DeviceStatus::Offline, None, None)
+            (DeviceStatus::Offline, None, None, None)
         };
 
+        let paired = self.paired_devices.get(&peer.peer_id);
+        let nickname = paired.as_ref().and_then(|p| p.nickname.clone());
+        let last_seen_at = paired.as_ref().and_then(|p| p.last_seen_at);
         Device {
             peer_id: peer.peer_id,
+            display_name: nickname.clone().unwrap_or_else(|| os_info.hostname.clone()),
+            nickname,
             os_info,
             status,
             connection,
             latency,
-            is_paired: self.paired_devices.contains_key(&peer.peer_id),
+            quality,
+            last_connected_at: peer.connected_at,
+            last_seen_at,
+            is_paired: paired.is_some(),
         }
     }
 
+    /// 当前与指定 peer 的连接类型（未连接时为 None）
+    pub fn connection_type(&self, peer_id: &PeerId) -> Option<ConnectionType> {
+        self.peers
+            .get(peer_id)
+            .filter(|p| p.is_connected)
+            .and_then(|p| connection_info(&p.addrs, p.rtt_ms, p.hole_punched).1)
+    }
+
+    /// 指定 peer 通过 identify 上报的设备信息（尚未 identify 时为 None）
+    pub fn os_info(&self, peer_id: &PeerId) -> Option<OsInfo> {
+        self.peers.get(peer_id).and_then(|p| {
+            p.agent_version
+                .as_deref()
+                .and_then(OsInfo::from_agent_version)
+        })
+    }
+
+    /// 指定 peer 声明的能力（旧版本客户端或尚未 identify 时为 None）
+    pub fn capabilities(&self, peer_id: &PeerId) -> Option<PeerCapabilities> {
+        self.peers.get(peer_id).and_then(|p| p.capabilities)
+    }
+
+    /// 与指定 peer 最近一次测得的 RTT（毫秒，未连接时为 None）
+    pub fn rtt_ms(&self, peer_id: &PeerId) -> Option<u64> {
+        self.peers
+            .get(peer_id)
+            .filter(|p| p.is_connected)
+            .and_then(|p| p.rtt_ms)
+    }
+
+    /// 单个设备的详细信息，既未被发现（或已过期）也未配对时返回 None
+    ///
+    /// `has_active_transfer` 由调用方根据传输会话填写，这里始终为 false。
+    pub fn get_peer_detail(&self, peer_id: &PeerId) -> Option<DeviceDetail> {
+        // 经由列表查询取得，保证展示名的消歧结果与设备列表一致
+        let device = self
+            .get_devices(&DeviceFilter::All.into())
+            .into_iter()
+            .find(|d| d.peer_id == *peer_id)
+            .or_else(|| self.get_paired_device(peer_id))?;
+        let peer = self.peers.get(peer_id);
+        let peer = peer.as_deref();
+        Some(DeviceDetail {
+            device,
+            addrs: peer.map(|p| p.addrs.clone()).unwrap_or_default(),
+            agent_version: peer.and_then(|p| p.agent_version.clone()),
+            connected_at: peer.filter(|p| p.is_connected).and_then(|p| p.connected_at),
+            hole_punched: peer.is_some_and(|p| p.hole_punched),
+            rtt_history: peer
+                .map(|p| p.rtt_history.iter().copied().collect())
+                .unwrap_or_default(),
+            has_active_transfer: false,
+        })
+    }
+
+    /// 指定 peer 的打洞统计与当前连接类型
+    pub fn peer_diagnostics(&self, peer_id: &PeerId) -> PeerDiagnostics {
+        let mut diagnostics = self
+            .diagnostics
+            .get(peer_id)
+            .map(|d| d.clone())
+            .unwrap_or_default();
+        diagnostics.connection = self.connection_type(peer_id);
+        diagnostics
+    }
+
+    /// 指定 peer 已知的局域网地址（私有 / 链路本地地址，按发现顺序）
+    pub fn lan_addrs(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        let Some(peer) = self.peers.get(peer_id) else {
+            return Vec::new();
+        };
+        let mut addrs = peer.addrs.clone();
+        sort_lan_first(&mut addrs);
+        addrs.into_iter().take_while(is_lan_addr).collect()
+    }
+
+    /// 指定 peer 已知的公网直连地址（中继节点据此拼接 circuit 地址）
+    pub fn public_addrs(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.peers
+            .get(peer_id)
+            .map(|p| {
+                p.addrs
+                    .iter()
+                    .filter(|a| is_public_direct_addr(a))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// 检查指定 peer 是否处于连接状态
     pub fn is_connected(&self, peer_id: &PeerId) -> bool {
         self.peers
@@ -233,43 +555,481 @@ impl DeviceManager {
             .count()
     }
 
-    /// 已发现的 SwarmDrop 客户端数量
+    /// 已发现的 SwarmDrop 客户端数量（不含已过期的 peer）
     pub fn discovered_count(&self) -> usize {
+        let now = chrono::Utc::now().timestamp_millis();
         self.peers
             .iter()
             .filter(|e| {
-                e.value()
-                    .agent_version
-                    .as_deref()
-                    .is_some_and(OsInfo::is_swarmdrop_agent)
+                !self.is_stale(e.value(), now)
+                    && e.value()
+                        .agent_version
+                        .as_deref()
+                        .is_some_and(OsInfo::is_swarmdrop_agent)
             })
             .count()
     }
 
-    /// 是否有已连接的引导/中继节点（agent_version 以 swarm-bootstrap/ 开头）
-    pub fn has_connected_bootstrap_peer(&self) -> bool {
-        self.peers.iter().any(|e| {
-            let p = e.value();
-            p.is_connected
-                && p.agent_version
-                    .as_deref()
-                    .is_some_and(OsInfo::is_bootstrap_agent)
-        })
+    /// 当前连接的引导/中继节点（agent_version 以 swarm-bootstrap/ 开头）
+    ///
+    /// 同时连接多个时取最早建立连接的一个，保证结果稳定。
+    pub fn connected_bootstrap_peer(&self) -> Option<PeerId> {
+        self.peers
+            .iter()
+            .filter(|e| {
+                let p = e.value();
+                p.is_connected
+                    && p.agent_version
+                        .as_deref()
+                        .is_some_and(OsInfo::is_bootstrap_agent)
+            })
+            .min_by_key(|e| (e.value().connected_at, e.value().peer_id))
+            .map(|e| *e.key())
+    }
+
+    /// 清理过期的 peer，返回清理的数量
+    pub fn prune_stale(&self) -> usize {
+        self.prune_stale_at(chrono::Utc::now().timestamp_millis())
     }
+
+    fn prune_stale_at(&self, now: i64) -> usize {
+        let mut pruned = Vec::new();
+        self.peers.retain(|peer_id, peer| {
+            let stale = self.is_stale(peer, now);
+            if stale {
+                pruned.push(*peer_id);
+            }
+            !stale
+        });
+        for peer_id in &pruned {
+            self.diagnostics.remove(peer_id);
+        }
+        pruned.len()
+    }
+
+    /// 未连接、未配对且超过过期窗口未见的 peer 视为过期（已配对设备永不过期）
+    fn is_stale(&self, peer: &PeerInfo, now: i64) -> bool {
+        !peer.is_connected
+            && !self.paired_devices.contains_key(&peer.peer_id)
+            && now.saturating_sub(peer.seen_at) >= self.stale_after.as_millis() as i64
+    }
+}
+
+/// 去掉首尾空白并转为小写，空关键字视为未指定
+fn normalize_keyword(keyword: Option<&str>) -> Option<String> {
+    keyword
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(str::to_lowercase)
 }
 
-/// 根据连接状态提取 (DeviceStatus, ConnectionType, latency)
+/// 主机名或昵称包含关键字（`keyword` 已转为小写）
+fn matches_keyword(device: &Device, keyword: &str) -> bool {
+    device.os_info.hostname.to_lowercase().contains(keyword)
+        || device
+            .nickname
+            .as_ref()
+            .is_some_and(|n| n.to_lowercase().contains(keyword))
+}
+
+/// 按指定方式排序设备列表（主机名作为次要排序键，保证结果稳定）
+fn sort_devices(devices: &mut [Device], sort: DeviceSort) {
+    // 按用户看到的展示名（昵称优先）排序
+    let name_key = |d: &Device| d.display_name.to_lowercase();
+    match sort {
+        DeviceSort::Latency => devices.sort_by(|a, b| {
+            // None 排在最后
+            (a.latency.is_none(), a.latency, name_key(a))
+                .cmp(&(b.latency.is_none(), b.latency, name_key(b)))
+        }),
+        DeviceSort::Name => devices.sort_by_key(name_key),
+        DeviceSort::LastConnected => devices.sort_by(|a, b| {
+            b.last_connected_at
+                .cmp(&a.last_connected_at)
+                .then_with(|| name_key(a).cmp(&name_key(b)))
+        }),
+    }
+}
+
+/// 根据连接状态提取 (DeviceStatus, ConnectionType, latency, ConnectionQuality)
 ///
 /// `hole_punched` 为 true 时直接判定为 DCUtR，比地址推断更准确。
 fn connection_info(
     addrs: &[swarm_p2p_core::libp2p::Multiaddr],
     rtt_ms: Option<u64>,
     hole_punched: bool,
-) -> (DeviceStatus, Option<ConnectionType>, Option<u64>) {
+) -> (
+    DeviceStatus,
+    Option<ConnectionType>,
+    Option<u64>,
+    Option<ConnectionQuality>,
+) {
     let connection = if hole_punched {
         Some(ConnectionType::Dcutr)
     } else {
         infer_connection_type(addrs)
     };
-    (DeviceStatus::Online, connection, rtt_ms)
+    let quality = connection_quality(connection.as_ref(), rtt_ms);
+    (DeviceStatus::Online, connection, rtt_ms, Some(quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_info(hostname: &str) -> OsInfo {
+        OsInfo {
+            hostname: hostname.into(),
+            os: "linux".into(),
+            platform: "linux".into(),
+            arch: "x86_64".into(),
+        }
+    }
+
+    fn peer(hostname: &str, rtt_ms: Option<u64>, connected_at: Option<i64>) -> PeerInfo {
+        PeerInfo {
+            peer_id: PeerId::random(),
+            addrs: vec!["/ip4/192.168.1.2/tcp/4001".parse().unwrap()],
+            agent_version: Some(os_info(hostname).to_agent_version()),
+            capabilities: Some(PeerCapabilities::LOCAL),
+            rtt_ms,
+            rtt_history: rtt_ms.into_iter().collect(),
+            is_connected: rtt_ms.is_some(),
+            hole_punched: false,
+            seen_at: chrono::Utc::now().timestamp_millis(),
+            connected_at,
+        }
+    }
+
+    /// 固定的设备集合：alpha(在线, 80ms) / Bravo(在线, 20ms) / charlie(离线) / 引导节点
+    fn fixture() -> DeviceManager {
+        let paired = Arc::new(DashMap::new());
+        let manager = DeviceManager::new(paired.clone());
+
+        let alpha = peer("alpha", Some(80), Some(3_000));
+        let bravo = peer("Bravo", Some(20), Some(1_000));
+        let charlie = peer("charlie", None, Some(2_000));
+        let mut bootstrap = peer("bootstrap", Some(5), Some(4_000));
+        bootstrap.agent_version = Some(format!("{}0.1.0", OsInfo::BOOTSTRAP_AGENT_PREFIX));
+
+        paired.insert(
+            charlie.peer_id,
+            PairedDeviceInfo {
+                peer_id: charlie.peer_id,
+                os_info: os_info("charlie"),
+                paired_at: 0,
+                verified: true,
+                verification_code: None,
+                nickname: None,
+                last_seen_at: Some(1_500),
+            },
+        );
+        for p in [alpha, bravo, charlie, bootstrap] {
+            manager.peers.insert(p.peer_id, p);
+        }
+        manager
+    }
+
+    fn hostnames(devices: &[Device]) -> Vec<&str> {
+        devices
+            .iter()
+            .map(|d| d.os_info.hostname.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_filters_still_work() {
+        let manager = fixture();
+        assert_eq!(manager.get_devices(&DeviceFilter::All.into()).len(), 3);
+        assert_eq!(
+            manager.get_devices(&DeviceFilter::Connected.into()).len(),
+            2
+        );
+
+        let paired = manager.get_devices(&DeviceFilter::Paired.into());
+        assert_eq!(hostnames(&paired), vec!["charlie"]);
+        assert_eq!(paired[0].last_connected_at, Some(2_000));
+        assert_eq!(paired[0].last_seen_at, Some(1_500));
+    }
+
+    #[test]
+    fn test_sort_devices() {
+        let manager = fixture();
+        let query = |sort| DeviceQuery {
+            sort: Some(sort),
+            ..Default::default()
+        };
+
+        let by_latency = manager.get_devices(&query(DeviceSort::Latency));
+        assert_eq!(hostnames(&by_latency), vec!["Bravo", "alpha", "charlie"]);
+
+        let by_name = manager.get_devices(&query(DeviceSort::Name));
+        assert_eq!(hostnames(&by_name), vec!["alpha", "Bravo", "charlie"]);
+
+        let by_last_connected = manager.get_devices(&query(DeviceSort::LastConnected));
+        assert_eq!(
+            hostnames(&by_last_connected),
+            vec!["alpha", "charlie", "Bravo"]
+        );
+    }
+
+    #[test]
+    fn test_online_only_and_search() {
+        let manager = fixture();
+
+        let online_paired = manager.get_devices(&DeviceQuery {
+            filter: DeviceFilter::Paired,
+            online_only: true,
+            ..Default::default()
+        });
+        assert!(online_paired.is_empty());
+
+        let searched = manager.get_devices(&DeviceQuery {
+            search: Some(" BRA ".into()),
+            ..Default::default()
+        });
+        assert_eq!(hostnames(&searched), vec!["Bravo"]);
+    }
+
+    #[test]
+    fn test_filtered() {
+        let manager = fixture();
+        let mut delta = peer("delta", Some(30), Some(5_000));
+        delta.agent_version = Some(
+            OsInfo {
+                platform: "android".into(),
+                ..os_info("delta")
+            }
+            .to_agent_version(),
+        );
+        manager.peers.insert(delta.peer_id, delta);
+
+        let filtered = |paired, connected, platform: Option<&str>, query: Option<&str>| {
+            let filter = DeviceFilter::Filtered {
+                paired,
+                connected,
+                platform: platform.map(Into::into),
+                query: query.map(Into::into),
+            };
+            let mut names: Vec<String> = manager
+                .get_devices(&filter.into())
+                .into_iter()
+                .map(|d| d.os_info.hostname)
+                .collect();
+            names.sort();
+            names
+        };
+
+        // 不指定任何条件：已发现的设备 + 已配对设备
+        assert_eq!(
+            filtered(None, None, None, None),
+            ["Bravo", "alpha", "charlie", "delta"]
+        );
+        assert_eq!(filtered(Some(true), None, None, None), ["charlie"]);
+        assert_eq!(
+            filtered(Some(false), None, None, None),
+            ["Bravo", "alpha", "delta"]
+        );
+        assert_eq!(
+            filtered(None, Some(true), None, None),
+            ["Bravo", "alpha", "delta"]
+        );
+        assert_eq!(filtered(None, Some(false), None, None), ["charlie"]);
+        assert_eq!(filtered(None, None, Some("Android"), None), ["delta"]);
+        assert_eq!(filtered(None, None, None, Some(" ALP ")), ["alpha"]);
+
+        // 组合条件
+        assert_eq!(
+            filtered(Some(false), Some(true), Some("linux"), None),
+            ["Bravo", "alpha"]
+        );
+        assert_eq!(
+            filtered(Some(true), Some(true), None, None),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            filtered(Some(false), None, Some("linux"), Some("bra")),
+            ["Bravo"]
+        );
+    }
+
+    #[test]
+    fn test_filter_serde_compat() {
+        let query: DeviceQuery = serde_json::from_str(r#"{ "filter": "all" }"#).unwrap();
+        assert!(matches!(query.filter, DeviceFilter::All));
+
+        let query: DeviceQuery = serde_json::from_str(
+            r#"{ "filter": { "filtered": { "paired": true, "platform": "android" } } }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            query.filter,
+            DeviceFilter::Filtered {
+                paired: Some(true),
+                connected: None,
+                platform: Some(ref p),
+                query: None,
+            } if p == "android"
+        ));
+    }
+
+    #[test]
+    fn test_connected_bootstrap_peer() {
+        let manager = fixture();
+        let bootstrap = manager.connected_bootstrap_peer().unwrap();
+        assert!(manager
+            .peers
+            .get(&bootstrap)
+            .and_then(|p| p.agent_version.clone())
+            .is_some_and(|v| OsInfo::is_bootstrap_agent(&v)));
+
+        manager.handle_event(&NodeEvent::PeerDisconnected { peer_id: bootstrap });
+        assert_eq!(manager.connected_bootstrap_peer(), None);
+
+        manager.handle_event(&NodeEvent::PeerConnected { peer_id: bootstrap });
+        assert_eq!(manager.connected_bootstrap_peer(), Some(bootstrap));
+    }
+
+    #[test]
+    fn test_paired_presence_changes() {
+        let manager = fixture();
+        let charlie = *manager.paired_devices.iter().next().unwrap().key();
+
+        let change = manager.handle_event(&NodeEvent::PeerConnected { peer_id: charlie });
+        assert!(
+            matches!(change, Some(PresenceChange::Online { peer_id, .. }) if peer_id == charlie)
+        );
+        // check_paired_online 拨号成功后再次报告：已上线，不重复推送
+        assert_eq!(manager.mark_paired_online(&charlie), None);
+
+        let change = manager.handle_event(&NodeEvent::PeerDisconnected { peer_id: charlie });
+        assert_eq!(change, Some(PresenceChange::Offline { peer_id: charlie }));
+        assert_eq!(
+            manager.handle_event(&NodeEvent::PeerDisconnected { peer_id: charlie }),
+            None
+        );
+
+        // 刚下线又重连：仍报告上线，但标记为抖动
+        let change = manager.handle_event(&NodeEvent::PeerConnected { peer_id: charlie });
+        assert!(matches!(
+            change,
+            Some(PresenceChange::Online { flapping: true, .. })
+        ));
+        assert!(manager
+            .get_paired_device(&charlie)
+            .is_some_and(|d| d.is_paired));
+
+        // 未配对设备不产生在线状态变化
+        assert_eq!(
+            manager.handle_event(&NodeEvent::PeerConnected {
+                peer_id: PeerId::random()
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prune_stale_peers() {
+        let manager = fixture();
+        let charlie = *manager.paired_devices.iter().next().unwrap().key();
+        manager.peers.get_mut(&charlie).unwrap().seen_at = 0;
+
+        let mut delta = peer("delta", None, None);
+        delta.seen_at = 0;
+        let delta_id = delta.peer_id;
+        manager.peers.insert(delta_id, delta);
+        manager.diagnostics.entry(delta_id).or_default();
+
+        // 过期但尚未清理的 peer 已不计入查询结果
+        assert_eq!(manager.discovered_count(), 3);
+        assert!(!hostnames(&manager.get_devices(&DeviceFilter::All.into())).contains(&"delta"));
+
+        // 已配对（charlie）与已连接的 peer 不会被清理
+        assert_eq!(manager.prune_stale(), 1);
+        assert!(!manager.peers.contains_key(&delta_id));
+        assert!(!manager.diagnostics.contains_key(&delta_id));
+        assert!(manager.peers.contains_key(&charlie));
+        assert_eq!(manager.discovered_count(), 3);
+
+        // 涉及 peer 的事件刷新时间戳
+        let mut echo = peer("echo", None, None);
+        echo.seen_at = 0;
+        let echo_id = echo.peer_id;
+        manager.peers.insert(echo_id, echo);
+        manager.handle_event(&NodeEvent::PingSuccess {
+            peer_id: echo_id,
+            rtt_ms: 10,
+        });
+        assert_eq!(manager.prune_stale(), 0);
+
+        let seen_at = manager.peers.get(&echo_id).unwrap().seen_at;
+        let window = DEFAULT_STALE_PEER_WINDOW.as_millis() as i64;
+        assert_eq!(manager.prune_stale_at(seen_at + window - 1), 0);
+        assert_eq!(manager.prune_stale_at(seen_at + window), 1);
+    }
+
+    #[test]
+    fn test_get_peer_detail() {
+        let manager = fixture();
+        assert!(manager.get_peer_detail(&PeerId::random()).is_none());
+
+        let alpha = manager
+            .get_devices(&DeviceFilter::All.into())
+            .into_iter()
+            .find(|d| d.os_info.hostname == "alpha")
+            .unwrap()
+            .peer_id;
+        for rtt_ms in 0..RTT_HISTORY_LEN as u64 + 5 {
+            manager.handle_event(&NodeEvent::PingSuccess {
+                peer_id: alpha,
+                rtt_ms,
+            });
+        }
+        let detail = manager.get_peer_detail(&alpha).unwrap();
+        assert_eq!(detail.device.os_info.hostname, "alpha");
+        assert_eq!(detail.connected_at, Some(3_000));
+        assert_eq!(detail.addrs.len(), 1);
+        assert!(detail.agent_version.is_some());
+        assert_eq!(detail.rtt_history.len(), RTT_HISTORY_LEN);
+        assert_eq!(detail.rtt_history.first(), Some(&5));
+        assert_eq!(
+            detail.rtt_history.last(),
+            Some(&(RTT_HISTORY_LEN as u64 + 4))
+        );
+
+        // 已配对但离线：仍可查询，不返回当前连接时间
+        let charlie = *manager.paired_devices.iter().next().unwrap().key();
+        let detail = manager.get_peer_detail(&charlie).unwrap();
+        assert!(detail.device.is_paired);
+        assert_eq!(detail.connected_at, None);
+        assert_eq!(detail.device.last_connected_at, Some(2_000));
+    }
+
+    #[test]
+    fn test_hole_punch_diagnostics() {
+        let manager = fixture();
+        let peer_id = PeerId::random();
+        assert_eq!(
+            manager.peer_diagnostics(&peer_id),
+            PeerDiagnostics::default()
+        );
+
+        for error in ["timeout", "no addresses"] {
+            manager.handle_event(&NodeEvent::HolePunchFailed {
+                peer_id,
+                error: error.into(),
+            });
+        }
+        manager.handle_event(&NodeEvent::HolePunchSucceeded { peer_id });
+
+        let diagnostics = manager.peer_diagnostics(&peer_id);
+        assert_eq!(diagnostics.hole_punch_attempts, 3);
+        assert_eq!(diagnostics.hole_punch_failures, 2);
+        assert_eq!(
+            diagnostics.last_hole_punch_error.as_deref(),
+            Some("no addresses")
+        );
+        // 未连接：无连接类型，统计保留
+        assert_eq!(diagnostics.connection, None);
+    }
 }
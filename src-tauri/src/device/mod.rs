@@ -3,19 +3,21 @@
 //! 管理本机 OS 信息、运行时 peer 发现和已配对设备状态。
 //! [`DeviceManager`] 维护 peer 列表并提供统一的设备查询接口。
 
+mod capabilities;
 pub mod manager;
 mod utils;
 
-pub use manager::{DeviceFilter, DeviceManager};
+pub use capabilities::PeerCapabilities;
+pub use manager::{DeviceFilter, DeviceManager, DeviceQuery, DeviceSort, PresenceChange};
 
 use serde::{Deserialize, Serialize};
-use swarm_p2p_core::libp2p::PeerId;
+use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
 
 /// 设备操作系统信息
 ///
 /// 用于本机信息采集、agent_version 编码/解码，
 /// 以及作为 [`Device`]、[`PairedDeviceInfo`] 等类型的嵌入字段。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OsInfo {
     pub hostname: String,
     pub os: String,
@@ -51,13 +53,17 @@ impl OsInfo {
         agent_version.starts_with(Self::BOOTSTRAP_AGENT_PREFIX)
     }
 
+    /// 编码为 agent_version（`host=` 固定为最后一个字段，见 [`Self::from_agent_version`]）
+    ///
+    /// 同时附带本机能力声明 `caps=`（见 [`PeerCapabilities`]）。
     pub fn to_agent_version(&self) -> String {
         format!(
-            "swarmdrop/{}; os={}; platform={}; arch={}; host={}",
+            "swarmdrop/{}; os={}; platform={}; arch={}; caps={}; host={}",
             env!("CARGO_PKG_VERSION"),
             self.os,
             self.platform,
             self.arch,
+            PeerCapabilities::LOCAL.to_token(),
             self.hostname
         )
     }
@@ -76,29 +82,40 @@ impl OsInfo {
     /// 从 agent_version 字符串反解析出 OsInfo
     ///
     /// 格式: `swarmdrop/{ver}; os={os}; platform={platform}; arch={arch}; host={hostname}`
+    ///
+    /// - `host=` 是最后一个字段，其后的全部内容都是主机名（主机名本身可能包含 `"; "`）
+    /// - 其余字段顺序任意，未知的 `key=value` 忽略（兼容后续版本新增字段）
+    /// - 缺失的字段填 `"unknown"`；一个已知字段都没有时返回 None
     pub fn from_agent_version(agent_version: &str) -> Option<Self> {
+        let (fields, hostname) = match agent_version.strip_prefix("host=") {
+            Some(hostname) => ("", Some(hostname)),
+            None => match agent_version.split_once("; host=") {
+                Some((fields, hostname)) => (fields, Some(hostname)),
+                None => (agent_version, None),
+            },
+        };
+
         let mut os = None;
         let mut platform = None;
         let mut arch = None;
-        let mut hostname = None;
-
-        for part in agent_version.split("; ") {
-            if let Some(v) = part.strip_prefix("os=") {
-                os = Some(v.to_string());
-            } else if let Some(v) = part.strip_prefix("platform=") {
-                platform = Some(v.to_string());
-            } else if let Some(v) = part.strip_prefix("arch=") {
-                arch = Some(v.to_string());
-            } else if let Some(v) = part.strip_prefix("host=") {
-                hostname = Some(v.to_string());
+        for (key, value) in fields.split("; ").filter_map(|part| part.split_once('=')) {
+            match key {
+                "os" => os = Some(value),
+                "platform" => platform = Some(value),
+                "arch" => arch = Some(value),
+                _ => {}
             }
         }
 
+        if hostname.is_none() && os.is_none() && platform.is_none() && arch.is_none() {
+            return None;
+        }
+        let or_unknown = |v: Option<&str>| v.unwrap_or("unknown").to_string();
         Some(Self {
-            hostname: hostname?,
-            os: os?,
-            platform: platform?,
-            arch: arch?,
+            hostname: or_unknown(hostname),
+            os: or_unknown(os),
+            platform: or_unknown(platform),
+            arch: or_unknown(arch),
         })
     }
 }
@@ -111,6 +128,56 @@ pub struct PairedDeviceInfo {
     #[serde(flatten)]
     pub os_info: OsInfo,
     pub paired_at: i64,
+    /// 用户是否已核对配对验证码（此前配对的设备没有验证码，视为已核对）
+    #[serde(default = "default_verified")]
+    pub verified: bool,
+    /// 配对验证码（未核对时保留，供界面提示用户比对）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_code: Option<String>,
+    /// 用户设置的昵称（见 [`normalize_nickname`]）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// 最近一次确认对方在线的时间戳（毫秒），精度见 [`LAST_SEEN_GRANULARITY_MS`]
+    ///
+    /// 连接建立或断开、收到对方请求、查到对方新的 DHT 在线记录时更新。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<i64>,
+}
+
+/// `last_seen_at` 的更新粒度：与记录值相差不足一分钟时不更新，避免频繁推送事件
+pub const LAST_SEEN_GRANULARITY_MS: i64 = 60_000;
+
+impl PairedDeviceInfo {
+    /// 展示用名称：有昵称时用昵称，否则为主机名
+    pub fn name(&self) -> &str {
+        self.nickname.as_deref().unwrap_or(&self.os_info.hostname)
+    }
+
+    /// 记录一次在线时间（毫秒），比记录值晚至少 [`LAST_SEEN_GRANULARITY_MS`] 时更新并返回 true
+    pub fn touch_last_seen(&mut self, at: i64) -> bool {
+        if self
+            .last_seen_at
+            .is_some_and(|last| at - last < LAST_SEEN_GRANULARITY_MS)
+        {
+            return false;
+        }
+        self.last_seen_at = Some(at);
+        true
+    }
+}
+
+fn default_verified() -> bool {
+    true
+}
+
+/// 昵称最大字符数
+pub const MAX_NICKNAME_CHARS: usize = 32;
+
+/// 规范化用户输入的昵称：去除首尾空白，超长截断，为空时返回 None（清除昵称）
+pub fn normalize_nickname(nickname: &str) -> Option<String> {
+    let nickname: String = nickname.trim().chars().take(MAX_NICKNAME_CHARS).collect();
+    let nickname = nickname.trim_end();
+    (!nickname.is_empty()).then(|| nickname.to_owned())
 }
 
 /// 设备状态
@@ -130,6 +197,16 @@ pub enum ConnectionType {
     Relay,
 }
 
+/// 连接质量（由 RTT 与连接类型推导，供前端显示信号格数）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionQuality {
+    Excellent,
+    Good,
+    Fair,
+    Poor,
+}
+
 /// 统一的设备输出类型
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -137,12 +214,47 @@ pub struct Device {
     pub peer_id: PeerId,
     #[serde(flatten)]
     pub os_info: OsInfo,
+    /// 展示名：昵称优先，否则为主机名；重名时追加 PeerId 短后缀，如 `Pixel (…a3f2)`
+    pub display_name: String,
+    /// 已配对设备的昵称
+    pub nickname: Option<String>,
     pub status: DeviceStatus,
     pub connection: Option<ConnectionType>,
     pub latency: Option<u64>,
+    /// 连接质量（离线时为 None）
+    pub quality: Option<ConnectionQuality>,
+    /// 最近一次建立连接的时间戳（毫秒），从未连接时为 None
+    pub last_connected_at: Option<i64>,
+    /// 已配对设备最近一次在线的时间戳（毫秒，跨重启保留，见 [`PairedDeviceInfo::last_seen_at`]）
+    pub last_seen_at: Option<i64>,
     pub is_paired: bool,
 }
 
+/// 单个设备的详细信息（`get_device` 命令返回）
+///
+/// 在 [`Device`] 的基础上附带运行时的连接细节，设备详情页无需拉取整个设备列表。
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceDetail {
+    #[serde(flatten)]
+    pub device: Device,
+    /// 已知的全部地址（按发现顺序，仅已配对且从未发现时为空）
+    pub addrs: Vec<Multiaddr>,
+    /// identify 上报的原始 agent_version（尚未 identify 时为 None）
+    pub agent_version: Option<String>,
+    /// 当前连接建立的时间戳（毫秒，未连接时为 None）
+    pub connected_at: Option<i64>,
+    /// DCUtR 打洞是否成功
+    pub hole_punched: bool,
+    /// 最近的 ping RTT（毫秒，从旧到新，最多 [`RTT_HISTORY_LEN`] 条）
+    pub rtt_history: Vec<u64>,
+    /// 是否有与该设备进行中的传输会话
+    pub has_active_transfer: bool,
+}
+
+/// 每个 peer 保留的 ping RTT 条数
+pub const RTT_HISTORY_LEN: usize = 20;
+
 /// 设备列表查询结果
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -150,3 +262,115 @@ pub struct DeviceListResult {
     pub devices: Vec<Device>,
     pub total: usize,
 }
+
+/// 单个 peer 的 NAT 穿透诊断信息（`get_peer_diagnostics` 命令返回）
+///
+/// 打洞失败时连接仍走中继，传输会明显变慢，据此向用户解释原因。
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerDiagnostics {
+    /// 当前连接类型（未连接时为 None）
+    pub connection: Option<ConnectionType>,
+    /// DCUtR 打洞尝试次数（成功 + 失败）
+    pub hole_punch_attempts: u32,
+    /// 打洞失败次数
+    pub hole_punch_failures: u32,
+    /// 最近一次打洞失败的错误信息
+    pub last_hole_punch_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_info(hostname: &str) -> OsInfo {
+        OsInfo {
+            hostname: hostname.into(),
+            os: "linux".into(),
+            platform: "linux".into(),
+            arch: "x86_64".into(),
+        }
+    }
+
+    /// 解析结果：[hostname, os, platform, arch]
+    fn parse(agent_version: &str) -> [String; 4] {
+        let info = OsInfo::from_agent_version(agent_version).unwrap();
+        [info.hostname, info.os, info.platform, info.arch]
+    }
+
+    #[test]
+    fn test_agent_version_round_trip() {
+        let info = os_info("my; weird host");
+        let parsed = OsInfo::from_agent_version(&info.to_agent_version()).unwrap();
+        assert_eq!(parsed.hostname, "my; weird host");
+        assert_eq!(parsed.arch, "x86_64");
+    }
+
+    #[test]
+    fn test_agent_version_reordered_and_extra_fields() {
+        let reordered =
+            "swarmdrop/9.0.0; arch=aarch64; caps=zstd; os=android; platform=android; host=Pixel";
+        assert_eq!(parse(reordered), ["Pixel", "android", "android", "aarch64"]);
+        // 缺失字段填 unknown，不再整体解析失败
+        let partial = "swarmdrop/0.1.0; os=macos; host=MacBook";
+        assert_eq!(parse(partial), ["MacBook", "macos", "unknown", "unknown"]);
+        // 主机名中的 "; " 与 "=" 原样保留
+        let semicolon = "swarmdrop/0.1.0; os=windows; host=a; b=c";
+        assert_eq!(parse(semicolon)[0], "a; b=c");
+    }
+
+    #[test]
+    fn test_normalize_nickname() {
+        assert_eq!(
+            normalize_nickname("  书房台式机 ").as_deref(),
+            Some("书房台式机")
+        );
+        assert_eq!(normalize_nickname(" \t "), None);
+        assert_eq!(normalize_nickname(""), None);
+
+        // 按字符截断，截断后末尾的空白同样去除
+        let long = format!("{} {}", "a".repeat(MAX_NICKNAME_CHARS - 1), "b".repeat(10));
+        assert_eq!(
+            normalize_nickname(&long).unwrap(),
+            "a".repeat(MAX_NICKNAME_CHARS - 1)
+        );
+        let wide = "机".repeat(MAX_NICKNAME_CHARS + 5);
+        assert_eq!(
+            normalize_nickname(&wide).unwrap().chars().count(),
+            MAX_NICKNAME_CHARS
+        );
+    }
+
+    #[test]
+    fn test_touch_last_seen() {
+        let mut info = PairedDeviceInfo {
+            peer_id: PeerId::random(),
+            os_info: os_info("desk"),
+            paired_at: 0,
+            verified: true,
+            verification_code: None,
+            nickname: None,
+            last_seen_at: None,
+        };
+        assert!(info.touch_last_seen(1_000));
+        // 一分钟内的变化与更早的时间不更新
+        assert!(!info.touch_last_seen(1_000 + LAST_SEEN_GRANULARITY_MS - 1));
+        assert!(!info.touch_last_seen(0));
+        assert_eq!(info.last_seen_at, Some(1_000));
+        assert!(info.touch_last_seen(1_000 + LAST_SEEN_GRANULARITY_MS));
+
+        // 跨重启：随 PairedDeviceInfo 序列化，旧记录缺省为 None
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["lastSeenAt"], 1_000 + LAST_SEEN_GRANULARITY_MS);
+        let mut legacy = json.clone();
+        legacy.as_object_mut().unwrap().remove("lastSeenAt");
+        let legacy: PairedDeviceInfo = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.last_seen_at, None);
+    }
+
+    #[test]
+    fn test_agent_version_unrecognized() {
+        assert!(OsInfo::from_agent_version("rust-libp2p/0.53.0").is_none());
+        assert!(OsInfo::from_agent_version("").is_none());
+    }
+}
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+
 use swarm_p2p_core::libp2p::{multiaddr::Protocol, Multiaddr};
 
-use super::ConnectionType;
+use super::{ConnectionQuality, ConnectionType, Device};
+
+/// 重名消歧时使用的 PeerId 后缀长度
+const PEER_ID_SUFFIX_LEN: usize = 4;
 
 /// 基于 Multiaddr 分析推断连接类型
 ///
@@ -40,6 +45,101 @@ pub fn infer_connection_type(addrs: &[Multiaddr]) -> Option<ConnectionType> {
     }
 }
 
+/// 由 RTT 与连接类型计算连接质量
+///
+/// 规则:
+/// 1. 按 RTT 分档: ≤50ms Excellent, ≤150ms Good, ≤300ms Fair, 其余 Poor
+/// 2. 只有局域网直连可达 Excellent，其余连接最高 Good
+/// 3. 中继连接带宽受限，最高 Fair
+/// 4. 尚无 RTT 样本时按连接类型给出保守估计
+pub fn connection_quality(
+    connection: Option<&ConnectionType>,
+    rtt_ms: Option<u64>,
+) -> ConnectionQuality {
+    let Some(rtt) = rtt_ms else {
+        return match connection {
+            Some(ConnectionType::Lan | ConnectionType::Dcutr) => ConnectionQuality::Good,
+            _ => ConnectionQuality::Fair,
+        };
+    };
+
+    let by_rtt = match rtt {
+        0..=50 => ConnectionQuality::Excellent,
+        51..=150 => ConnectionQuality::Good,
+        151..=300 => ConnectionQuality::Fair,
+        _ => ConnectionQuality::Poor,
+    };
+    let cap = match connection {
+        Some(ConnectionType::Lan) => ConnectionQuality::Excellent,
+        Some(ConnectionType::Relay) => ConnectionQuality::Fair,
+        Some(ConnectionType::Dcutr) | None => ConnectionQuality::Good,
+    };
+    worse(by_rtt, cap)
+}
+
+/// 取两者中较差的质量
+fn worse(a: ConnectionQuality, b: ConnectionQuality) -> ConnectionQuality {
+    fn rank(q: ConnectionQuality) -> u8 {
+        match q {
+            ConnectionQuality::Excellent => 3,
+            ConnectionQuality::Good => 2,
+            ConnectionQuality::Fair => 1,
+            ConnectionQuality::Poor => 0,
+        }
+    }
+    if rank(a) <= rank(b) {
+        a
+    } else {
+        b
+    }
+}
+
+/// 设备的基础展示名：昵称优先，否则为主机名
+fn base_name(device: &Device) -> &str {
+    device
+        .nickname
+        .as_deref()
+        .unwrap_or(&device.os_info.hostname)
+}
+
+/// 基础展示名（昵称或主机名）重复的设备在展示名后追加 PeerId 短后缀
+///
+/// 只修改 `display_name`，不改动 `os_info.hostname`；
+/// 后缀仅取决于 PeerId，与列表顺序无关，结果稳定。
+pub fn disambiguate_display_names(devices: &mut [Device]) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for d in devices.iter() {
+        *counts.entry(base_name(d)).or_default() += 1;
+    }
+    let duplicated: Vec<bool> = devices.iter().map(|d| counts[base_name(d)] > 1).collect();
+
+    for (device, duplicated) in devices.iter_mut().zip(duplicated) {
+        let name = base_name(device);
+        device.display_name = if duplicated {
+            let id = device.peer_id.to_string();
+            let suffix = &id[id.len().saturating_sub(PEER_ID_SUFFIX_LEN)..];
+            format!("{name} (…{suffix})")
+        } else {
+            name.to_owned()
+        };
+    }
+}
+
+/// 是否为局域网直连地址（私有 / 回环 / 链路本地 IP，且不经中继）
+pub fn is_lan_addr(addr: &Multiaddr) -> bool {
+    !has_p2p_circuit(addr) && has_private_ip(addr)
+}
+
+/// 是否为公网直连地址（非私有 IP，且不经中继）
+pub fn is_public_direct_addr(addr: &Multiaddr) -> bool {
+    !has_p2p_circuit(addr) && !has_private_ip(addr)
+}
+
+/// 将局域网地址排到最前（稳定排序，同类地址保持原有顺序）
+pub fn sort_lan_first(addrs: &mut [Multiaddr]) {
+    addrs.sort_by_key(|addr| !is_lan_addr(addr));
+}
+
 fn has_p2p_circuit(addr: &Multiaddr) -> bool {
     addr.iter().any(|p| matches!(p, Protocol::P2pCircuit))
 }
@@ -55,3 +155,154 @@ fn has_public_ip(addr: &Multiaddr) -> bool {
         matches!(p, Protocol::Ip4(ip) if !ip.is_private() && !ip.is_loopback() && !ip.is_link_local() && !ip.is_unspecified())
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use swarm_p2p_core::libp2p::PeerId;
+
+    use super::*;
+    use crate::device::{DeviceStatus, OsInfo};
+
+    #[test]
+    fn test_connection_quality_table() {
+        use ConnectionQuality::*;
+        use ConnectionType::*;
+
+        let cases = [
+            // (连接类型, RTT, 期望质量)
+            (Some(Lan), Some(5), Excellent),
+            (Some(Lan), Some(50), Excellent),
+            (Some(Lan), Some(120), Good),
+            (Some(Lan), Some(250), Fair),
+            (Some(Lan), Some(800), Poor),
+            (Some(Dcutr), Some(20), Good),
+            (Some(Dcutr), Some(200), Fair),
+            (Some(Dcutr), Some(500), Poor),
+            (Some(Relay), Some(20), Fair),
+            (Some(Relay), Some(200), Fair),
+            (Some(Relay), Some(600), Poor),
+            (None, Some(30), Good),
+            (Some(Lan), None, Good),
+            (Some(Dcutr), None, Good),
+            (Some(Relay), None, Fair),
+            (None, None, Fair),
+        ];
+
+        for (connection, rtt_ms, expected) in cases {
+            assert_eq!(
+                connection_quality(connection.as_ref(), rtt_ms),
+                expected,
+                "connection={connection:?}, rtt_ms={rtt_ms:?}"
+            );
+        }
+    }
+
+    fn device(hostname: &str) -> Device {
+        Device {
+            peer_id: PeerId::random(),
+            os_info: OsInfo {
+                hostname: hostname.into(),
+                os: "android".into(),
+                platform: "android".into(),
+                arch: "aarch64".into(),
+            },
+            display_name: String::new(),
+            nickname: None,
+            status: DeviceStatus::Online,
+            connection: None,
+            latency: None,
+            quality: None,
+            last_connected_at: None,
+            is_paired: false,
+        }
+    }
+
+    #[test]
+    fn test_disambiguate_display_names() {
+        let mut devices = vec![
+            device("Pixel"),
+            device("MacBook"),
+            device("Pixel"),
+            device("localhost"),
+            device("localhost"),
+            device("localhost"),
+        ];
+        disambiguate_display_names(&mut devices);
+
+        assert_eq!(devices[1].display_name, "MacBook");
+        for d in devices.iter().filter(|d| d.os_info.hostname != "MacBook") {
+            let id = d.peer_id.to_string();
+            let expected = format!("{} (…{})", d.os_info.hostname, &id[id.len() - 4..]);
+            assert_eq!(d.display_name, expected);
+        }
+        // hostname 本身不被修改
+        assert_eq!(devices[0].os_info.hostname, "Pixel");
+
+        // 与顺序无关：反转后每台设备的展示名不变
+        let before: HashMap<_, _> = devices
+            .iter()
+            .map(|d| (d.peer_id, d.display_name.clone()))
+            .collect();
+        devices.reverse();
+        disambiguate_display_names(&mut devices);
+        for d in &devices {
+            assert_eq!(before[&d.peer_id], d.display_name);
+        }
+    }
+
+    #[test]
+    fn test_display_name_prefers_nickname() {
+        let mut devices = vec![
+            device("DESKTOP-7F3K2"),
+            device("DESKTOP-7F3K2"),
+            device("Pixel"),
+        ];
+        devices[0].nickname = Some("书房".into());
+        devices[2].nickname = Some("书房".into());
+        disambiguate_display_names(&mut devices);
+
+        // 设置昵称后主机名不再重复；昵称重复时同样追加后缀
+        assert_eq!(devices[1].display_name, "DESKTOP-7F3K2");
+        for d in [&devices[0], &devices[2]] {
+            let id = d.peer_id.to_string();
+            assert_eq!(d.display_name, format!("书房 (…{})", &id[id.len() - 4..]));
+        }
+    }
+
+    #[test]
+    fn test_sort_lan_first() {
+        let relay: Multiaddr = "/ip4/10.0.0.1/tcp/4001/p2p-circuit".parse().unwrap();
+        let public: Multiaddr = "/ip4/8.8.8.8/tcp/4001".parse().unwrap();
+        let lan: Multiaddr = "/ip4/192.168.1.10/tcp/4001".parse().unwrap();
+        let link_local: Multiaddr = "/ip4/169.254.3.4/udp/4001/quic-v1".parse().unwrap();
+
+        let mut addrs = vec![
+            relay.clone(),
+            public.clone(),
+            lan.clone(),
+            link_local.clone(),
+        ];
+        sort_lan_first(&mut addrs);
+        assert_eq!(addrs, vec![lan, link_local, relay.clone(), public]);
+        // 经中继的私有地址不算局域网直连
+        assert!(!is_lan_addr(&relay));
+    }
+
+    #[test]
+    fn test_infer_connection_type() {
+        let lan: Multiaddr = "/ip4/192.168.1.10/tcp/4001".parse().unwrap();
+        let public: Multiaddr = "/ip4/8.8.8.8/tcp/4001".parse().unwrap();
+        let relay: Multiaddr = "/ip4/8.8.8.8/tcp/4001/p2p-circuit".parse().unwrap();
+
+        assert_eq!(infer_connection_type(&[]), None);
+        assert_eq!(
+            infer_connection_type(&[relay.clone(), lan]),
+            Some(ConnectionType::Lan)
+        );
+        assert_eq!(
+            infer_connection_type(&[relay.clone(), public]),
+            Some(ConnectionType::Dcutr)
+        );
+        assert_eq!(infer_connection_type(&[relay]), Some(ConnectionType::Relay));
+    }
+}
@@ -0,0 +1,183 @@
+//! 日志环形缓冲区
+//!
+//! 作为 `tracing_subscriber::fmt` 的 writer，保留最近 [`LOG_BUFFER_LINES`] 行日志，
+//! 供应用内日志页（`get_recent_logs`）和诊断包的 `logs.txt` 使用。
+//! Android release 构建看不到 stdout，这是查看运行日志的唯一途径。
+//! 超出容量时丢弃最旧的行，内存占用有上限。
+//!
+//! 缓冲区是全局静态变量而不是 Tauri state：`init_tracing` 在 Builder 创建之前执行。
+//! 锁只在写入 / 复制行时短暂持有，期间不会输出日志，因此命令中记录日志不会死锁。
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// 保留的日志行数
+pub const LOG_BUFFER_LINES: usize = 2000;
+
+/// 全局日志缓冲区（`init_tracing` 中注册为 fmt layer 的 writer）
+pub static LOG_BUFFER: LogBuffer = LogBuffer::new(LOG_BUFFER_LINES);
+
+/// 单行日志（保留级别用于过滤）
+struct LogLine {
+    level: Level,
+    text: String,
+}
+
+/// 固定容量的日志行缓冲区
+pub struct LogBuffer {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    /// 创建单条日志的 writer（fmt layer 每个事件调用一次）
+    pub fn writer(&self, level: Level) -> LogWriter<'_> {
+        LogWriter {
+            buffer: self,
+            level,
+            pending: Vec::new(),
+        }
+    }
+
+    /// 当前缓存的全部日志行（从旧到新）
+    pub fn lines(&self) -> Vec<String> {
+        self.recent(None, None)
+    }
+
+    /// 最近的日志行（从旧到新）
+    ///
+    /// - `limit`：最多返回的行数（取最新的），None 表示全部
+    /// - `max_level`：只保留不比该级别更详细的行，如 `WARN` 只返回 WARN 和 ERROR
+    pub fn recent(&self, limit: Option<usize>, max_level: Option<Level>) -> Vec<String> {
+        let Ok(lines) = self.lines.lock() else {
+            return Vec::new();
+        };
+        let mut recent: Vec<String> = lines
+            .iter()
+            .rev()
+            .filter(|l| max_level.is_none_or(|max| l.level <= max))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|l| l.text.clone())
+            .collect();
+        recent.reverse();
+        recent
+    }
+
+    /// 清空缓冲区
+    pub fn clear(&self) {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.clear();
+        }
+    }
+
+    fn push(&self, level: Level, text: &str) {
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            if lines.len() == self.capacity {
+                lines.pop_front();
+            }
+            lines.push_back(LogLine {
+                level,
+                text: line.to_owned(),
+            });
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for &'static LogBuffer {
+    type Writer = LogWriter<'static>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.writer(Level::INFO)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.writer(*meta.level())
+    }
+}
+
+/// 收集一条日志的全部输出，drop 时写入缓冲区
+pub struct LogWriter<'a> {
+    buffer: &'a LogBuffer,
+    level: Level,
+    pending: Vec<u8>,
+}
+
+impl Write for LogWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter<'_> {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            self.buffer.push(self.level, &String::from_utf8_lossy(&self.pending));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_most_recent_lines() {
+        let buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            let mut writer = buffer.writer(Level::INFO);
+            writeln!(writer, "line {i}").unwrap();
+        }
+        {
+            let mut writer = buffer.writer(Level::INFO);
+            writer.write_all(b"multi a\nmulti b\n").unwrap();
+        }
+
+        assert_eq!(buffer.lines(), vec!["line 4", "multi a", "multi b"]);
+    }
+
+    #[test]
+    fn test_recent_filters_by_level_and_limit() {
+        let buffer = LogBuffer::new(10);
+        for (level, text) in [
+            (Level::DEBUG, "debug 1"),
+            (Level::WARN, "warn 1"),
+            (Level::INFO, "info 1"),
+            (Level::ERROR, "error 1"),
+            (Level::WARN, "warn 2"),
+        ] {
+            writeln!(buffer.writer(level), "{text}").unwrap();
+        }
+
+        assert_eq!(
+            buffer.recent(None, Some(Level::WARN)),
+            vec!["warn 1", "error 1", "warn 2"]
+        );
+        assert_eq!(
+            buffer.recent(Some(2), Some(Level::INFO)),
+            vec!["error 1", "warn 2"]
+        );
+        assert_eq!(buffer.recent(Some(1), None), vec!["warn 2"]);
+
+        buffer.clear();
+        assert!(buffer.lines().is_empty());
+    }
+}
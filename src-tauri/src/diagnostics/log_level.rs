@@ -0,0 +1,70 @@
+//! 运行时调整日志级别
+//!
+//! `init_tracing` 把 [`EnvFilter`] 包在 `reload::Layer` 中并作为全局过滤器，
+//! 对 stdout 和日志缓冲区等所有 layer 同时生效。句柄注册为 Tauri state，
+//! 用户无需设置环境变量（Android 上几乎做不到）即可临时打开 debug 日志排查问题。
+
+use tracing::info;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::{AppError, AppResult};
+
+/// 未设置 `RUST_LOG` 时的默认过滤规则
+pub const DEFAULT_LOG_FILTER: &str = "swarmdrop=debug,swarm_p2p_core=debug";
+
+/// 日志过滤器的重载句柄（Tauri state）
+pub struct LogLevelHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogLevelHandle {
+    pub fn new(handle: reload::Handle<EnvFilter, Registry>) -> Self {
+        Self(handle)
+    }
+
+    /// 当前生效的过滤规则
+    pub fn current(&self) -> AppResult<String> {
+        self.0
+            .with_current(|filter| filter.to_string())
+            .map_err(|e| AppError::Config(format!("读取日志级别失败: {e}")))
+    }
+
+    /// 替换过滤规则，语法同 `RUST_LOG`（如 `debug`、`swarmdrop=trace,libp2p=info`）
+    pub fn set(&self, directives: &str) -> AppResult<()> {
+        let filter = parse_filter(directives)?;
+        self.0
+            .reload(filter)
+            .map_err(|e| AppError::Config(format!("设置日志级别失败: {e}")))?;
+        info!("日志级别已切换为: {}", directives.trim());
+        Ok(())
+    }
+}
+
+/// 校验并解析过滤规则
+fn parse_filter(directives: &str) -> AppResult<EnvFilter> {
+    let directives = directives.trim();
+    if directives.is_empty() {
+        return Err(AppError::Config("日志级别不能为空".into()));
+    }
+    EnvFilter::try_new(directives)
+        .map_err(|e| AppError::Config(format!("无效的日志级别 {directives:?}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_get_log_level() {
+        let (_layer, handle) = reload::Layer::<EnvFilter, Registry>::new(EnvFilter::new("info"));
+        let handle = LogLevelHandle::new(handle);
+        assert_eq!(handle.current().unwrap(), "info");
+
+        handle.set(" swarmdrop=trace ").unwrap();
+        assert_eq!(handle.current().unwrap(), "swarmdrop=trace");
+
+        // 无效规则不会替换当前过滤器
+        for invalid in ["", "swarmdrop=verbose"] {
+            assert!(matches!(handle.set(invalid), Err(AppError::Config(_))));
+        }
+        assert_eq!(handle.current().unwrap(), "swarmdrop=trace");
+    }
+}
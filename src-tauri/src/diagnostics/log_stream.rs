@@ -0,0 +1,246 @@
+//! 实时日志推送
+//!
+//! 作为 `tracing` layer 把达到最低级别（默认 WARN）的日志以 `log-line` 事件推送给前端，
+//! 应用内诊断面板无需连接调试器或抓取 logcat 即可实时查看。
+//!
+//! 与 [`LOG_BUFFER`](super::log_buffer::LOG_BUFFER) 一样是全局静态变量：`init_tracing`
+//! 在 Builder 创建之前执行，`AppHandle` 在 setup 中通过 [`LogStream::attach`] 补上，
+//! 此前的日志只进入缓冲区。每秒最多推送 [`MAX_LINES_PER_SEC`] 行，超出的行丢弃并计数，
+//! 在下一条推送中通过 `dropped` 告知前端，避免日志风暴拖垮 IPC。
+
+use std::cell::Cell;
+use std::fmt::{Debug, Write as _};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::events;
+use crate::{AppError, AppResult};
+
+/// 每个限流窗口内最多推送的行数
+const MAX_LINES_PER_SEC: u32 = 50;
+
+/// 限流窗口
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// 全局日志推送 layer（`init_tracing` 中注册）
+pub static LOG_STREAM: LogStream = LogStream::new(LevelFilter::WARN);
+
+thread_local! {
+    /// 当前线程正在推送日志（emit 内部产生的日志不再推送，避免递归）
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// `log-line` 事件 payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLinePayload {
+    /// 小写级别名，如 `warn`
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// 自上一条推送以来因限流丢弃的行数
+    pub dropped: u64,
+}
+
+/// 推送给前端的日志 layer
+pub struct LogStream {
+    app: OnceLock<AppHandle>,
+    min_level: AtomicU8,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl LogStream {
+    pub const fn new(min_level: LevelFilter) -> Self {
+        Self {
+            app: OnceLock::new(),
+            min_level: AtomicU8::new(level_to_u8(min_level)),
+            limiter: Mutex::new(RateLimiter::new(MAX_LINES_PER_SEC)),
+        }
+    }
+
+    /// 设置推送目标（setup 中调用一次，重复调用忽略）
+    pub fn attach(&self, app: AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    /// 当前推送的最低级别
+    pub fn level(&self) -> LevelFilter {
+        level_from_u8(self.min_level.load(Ordering::Relaxed))
+    }
+
+    /// 设置推送的最低级别：`error` / `warn` / `info` / `debug` / `trace`，`off` 关闭推送
+    ///
+    /// 只能在全局过滤器（`set_log_level`）放行的范围内生效。
+    pub fn set_level(&self, level: &str) -> AppResult<()> {
+        let filter = level
+            .trim()
+            .parse::<LevelFilter>()
+            .map_err(|_| AppError::Config(format!("无效的日志级别: {level}")))?;
+        self.min_level.store(level_to_u8(filter), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn publish(&self, event: &Event<'_>) {
+        let meta = event.metadata();
+        if *meta.level() > self.level() {
+            return;
+        }
+        let Some(app) = self.app.get() else {
+            return;
+        };
+        if EMITTING.with(|emitting| emitting.replace(true)) {
+            return;
+        }
+
+        let admitted = self
+            .limiter
+            .lock()
+            .ok()
+            .and_then(|mut limiter| limiter.admit(Instant::now()));
+        if let Some(dropped) = admitted {
+            let mut visitor = MessageVisitor::default();
+            event.record(&mut visitor);
+            let payload = LogLinePayload {
+                level: meta.level().as_str().to_lowercase(),
+                target: meta.target().to_owned(),
+                message: visitor.finish(),
+                dropped,
+            };
+            let _ = app.emit(events::LOG_LINE, &payload);
+        }
+
+        EMITTING.with(|emitting| emitting.set(false));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for &'static LogStream {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        self.publish(event);
+    }
+}
+
+/// 固定窗口限流
+struct RateLimiter {
+    max_per_window: u32,
+    window_start: Option<Instant>,
+    count: u32,
+    dropped: u64,
+}
+
+impl RateLimiter {
+    const fn new(max_per_window: u32) -> Self {
+        Self {
+            max_per_window,
+            window_start: None,
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// 放行时返回此前累计丢弃的行数（并清零），超出窗口配额时返回 None
+    fn admit(&mut self, now: Instant) -> Option<u64> {
+        if self
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= RATE_WINDOW)
+        {
+            self.window_start = Some(now);
+            self.count = 0;
+        }
+        if self.count >= self.max_per_window {
+            self.dropped += 1;
+            return None;
+        }
+        self.count += 1;
+        Some(std::mem::take(&mut self.dropped))
+    }
+}
+
+/// 提取 `message` 字段，其余字段以 `key=value` 追加在后面
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        self.message + &self.fields
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+const fn level_to_u8(filter: LevelFilter) -> u8 {
+    match filter.into_level() {
+        None => 0,
+        Some(Level::ERROR) => 1,
+        Some(Level::WARN) => 2,
+        Some(Level::INFO) => 3,
+        Some(Level::DEBUG) => 4,
+        Some(Level::TRACE) => 5,
+    }
+}
+
+fn level_from_u8(value: u8) -> LevelFilter {
+    match value {
+        0 => LevelFilter::OFF,
+        1 => LevelFilter::ERROR,
+        2 => LevelFilter::WARN,
+        3 => LevelFilter::INFO,
+        4 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2);
+        let start = Instant::now();
+
+        assert_eq!(limiter.admit(start), Some(0));
+        assert_eq!(limiter.admit(start), Some(0));
+        assert_eq!(limiter.admit(start), None);
+        assert_eq!(limiter.admit(start + Duration::from_millis(500)), None);
+
+        // 新窗口放行，并带上之前丢弃的行数
+        assert_eq!(limiter.admit(start + RATE_WINDOW), Some(2));
+        assert_eq!(limiter.admit(start + RATE_WINDOW), Some(0));
+    }
+
+    #[test]
+    fn test_set_level() {
+        let stream = LogStream::new(LevelFilter::WARN);
+        assert_eq!(stream.level(), LevelFilter::WARN);
+
+        stream.set_level(" debug ").unwrap();
+        assert_eq!(stream.level(), LevelFilter::DEBUG);
+        stream.set_level("off").unwrap();
+        assert_eq!(stream.level(), LevelFilter::OFF);
+
+        assert!(matches!(
+            stream.set_level("verbose"),
+            Err(AppError::Config(_))
+        ));
+        assert_eq!(stream.level(), LevelFilter::OFF);
+    }
+}
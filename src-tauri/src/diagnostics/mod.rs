@@ -0,0 +1,245 @@
+//! 诊断包导出
+//!
+//! 把排查问题所需的运行时信息打包为 zip：网络状态、设备列表、引导节点连接状态、
+//! 配对概况、活跃传输、版本 / 系统信息、生效的网络开关和最近的日志。
+//! 同一份 [`Snapshot`] 也可直接以 JSON 返回给前端（`get_diagnostics_snapshot`）。
+//!
+//! 诊断包会被用户附在公开的问题反馈中，因此：
+//! - PeerId 只保留末尾几位（[`redact_peer_ids`]）
+//! - 不包含密钥、Stronghold 内容、配对码和传输密钥（只收集下列快照，不读取任何凭据）
+
+pub mod log_buffer;
+pub mod log_level;
+pub mod log_stream;
+
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+use swarm_p2p_core::libp2p::PeerId;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::device::{Device, DeviceFilter, OsInfo};
+use crate::network::{BootstrapStatus, NetManager, NetworkStatus};
+use crate::transfer::offer::ActiveSessionInfo;
+use crate::AppResult;
+
+use log_buffer::LOG_BUFFER;
+
+/// PeerId 脱敏后保留的末尾字符数
+const PEER_ID_VISIBLE_SUFFIX: usize = 6;
+
+/// 版本与系统信息（不含主机名）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AppInfo {
+    version: &'static str,
+    os: String,
+    platform: String,
+    arch: String,
+}
+
+/// 配对概况（不含配对码与验证码）
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PairingSummary {
+    paired_devices: usize,
+    /// 尚未核对验证码的已配对设备
+    unverified_devices: usize,
+    /// 是否有未过期的配对码
+    active_code: bool,
+}
+
+/// 诊断快照（节点未启动时网络相关部分为空）
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    app_info: AppInfo,
+    network_status: NetworkStatus,
+    devices: Vec<Device>,
+    bootstrap_peers: Vec<BootstrapStatus>,
+    pairing: PairingSummary,
+    transfers: Vec<ActiveSessionInfo>,
+    logs: Vec<String>,
+}
+
+/// 诊断包中的单个文件
+pub struct BundleFile {
+    pub name: &'static str,
+    pub content: String,
+}
+
+/// 从各管理器收集诊断快照（只读）
+pub async fn snapshot(manager: Option<&NetManager>) -> Snapshot {
+    let os = OsInfo::default();
+    let app_info = AppInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        os: os.os,
+        platform: os.platform,
+        arch: os.arch,
+    };
+
+    let Some(m) = manager else {
+        return Snapshot {
+            app_info,
+            network_status: NetworkStatus::default(),
+            devices: Vec::new(),
+            bootstrap_peers: Vec::new(),
+            pairing: PairingSummary::default(),
+            transfers: Vec::new(),
+            logs: LOG_BUFFER.lines(),
+        };
+    };
+
+    let paired = m.pairing().get_paired_devices();
+    Snapshot {
+        app_info,
+        network_status: m.get_network_status(),
+        devices: m.devices().get_devices(&DeviceFilter::All.into()),
+        bootstrap_peers: m.bootstrap_statuses(),
+        pairing: PairingSummary {
+            paired_devices: paired.len(),
+            unverified_devices: paired.iter().filter(|d| !d.verified).count(),
+            active_code: m.pairing().has_active_code(),
+        },
+        transfers: m.transfer().active_sessions().await,
+        logs: LOG_BUFFER.lines(),
+    }
+}
+
+/// 快照转为脱敏后的 JSON
+pub fn snapshot_json(snapshot: &Snapshot) -> AppResult<serde_json::Value> {
+    let text = redact_peer_ids(&serde_json::to_string(snapshot)?);
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// 收集诊断包的各个文件
+pub async fn collect(manager: Option<&NetManager>) -> AppResult<Vec<BundleFile>> {
+    let snapshot = snapshot(manager).await;
+    Ok(vec![
+        json_file("app_info.json", &snapshot.app_info)?,
+        json_file("network_options.json", &snapshot.network_status.options)?,
+        json_file("network_status.json", &snapshot.network_status)?,
+        json_file("devices.json", &snapshot.devices)?,
+        json_file("bootstrap_peers.json", &snapshot.bootstrap_peers)?,
+        json_file("pairing.json", &snapshot.pairing)?,
+        json_file("transfers.json", &snapshot.transfers)?,
+        BundleFile {
+            name: "logs.txt",
+            content: redact_peer_ids(&snapshot.logs.join("\n")),
+        },
+    ])
+}
+
+/// 序列化为格式化 JSON 并脱敏
+fn json_file(name: &'static str, value: &impl Serialize) -> AppResult<BundleFile> {
+    Ok(BundleFile {
+        name,
+        content: redact_peer_ids(&serde_json::to_string_pretty(value)?),
+    })
+}
+
+/// 写入 zip 文件
+pub fn write_bundle(path: &Path, files: &[BundleFile]) -> AppResult<()> {
+    let mut zip = ZipWriter::new(std::fs::File::create(path)?);
+    for file in files {
+        zip.start_file(file.name, SimpleFileOptions::default())
+            .map_err(std::io::Error::other)?;
+        zip.write_all(file.content.as_bytes())?;
+    }
+    zip.finish().map_err(std::io::Error::other)?;
+    Ok(())
+}
+
+/// 把文本中所有 PeerId 替换为 `…<末尾几位>`
+///
+/// PeerId 是 base58 字符串，按字母数字连续段切分后逐段尝试解析，
+/// JSON、multiaddr（`/p2p/<id>`）和日志中的 PeerId 都能覆盖。
+pub fn redact_peer_ids(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut token = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            token.push(c);
+            continue;
+        }
+        push_token(&mut out, &token);
+        token.clear();
+        out.push(c);
+    }
+    push_token(&mut out, &token);
+    out
+}
+
+fn push_token(out: &mut String, token: &str) {
+    if token.len() > PEER_ID_VISIBLE_SUFFIX && token.parse::<PeerId>().is_ok() {
+        out.push('…');
+        out.push_str(&token[token.len() - PEER_ID_VISIBLE_SUFFIX..]);
+    } else {
+        out.push_str(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_redact_peer_ids() {
+        let peer = PeerId::random().to_string();
+        let suffix = &peer[peer.len() - PEER_ID_VISIBLE_SUFFIX..];
+        let text = format!(
+            r#"{{"peerId":"{peer}","addr":"/ip4/1.2.3.4/tcp/4001/p2p/{peer}"}} dial {peer}: ok"#
+        );
+
+        let redacted = redact_peer_ids(&text);
+        assert!(!redacted.contains(&peer));
+        assert_eq!(redacted.matches(&format!("…{suffix}")).count(), 3);
+        assert!(redacted.contains("/ip4/1.2.3.4/tcp/4001/p2p/…"));
+        assert_eq!(redact_peer_ids("port 4001 tcp"), "port 4001 tcp");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_without_node() {
+        let value = snapshot_json(&snapshot(None).await).unwrap();
+        assert_eq!(value["networkStatus"]["status"], "stopped");
+        assert_eq!(value["bootstrapPeers"], serde_json::json!([]));
+        assert_eq!(value["pairing"]["activeCode"], false);
+        assert!(value["logs"].is_array());
+    }
+
+    #[test]
+    fn test_write_bundle() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_diagnostics");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bundle.zip");
+
+        let files = vec![
+            BundleFile {
+                name: "a.json",
+                content: "{}".into(),
+            },
+            BundleFile {
+                name: "logs.txt",
+                content: "line 1\nline 2".into(),
+            },
+        ];
+        write_bundle(&path, &files).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut logs = String::new();
+        archive
+            .by_name("logs.txt")
+            .unwrap()
+            .read_to_string(&mut logs)
+            .unwrap();
+        assert_eq!(logs, "line 1\nline 2");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -48,6 +48,10 @@ pub enum AppError {
     #[error("无效的配对码")]
     InvalidCode,
 
+    /// 配对二维码载荷格式错误
+    #[error("无效的配对二维码: {0}")]
+    InvalidPairingPayload(String),
+
     /// tokio 任务错误
     #[error("Task join error: {0}")]
     TaskJoin(#[from] tokio::task::JoinError),
@@ -59,9 +63,117 @@ pub enum AppError {
     /// 数据库错误
     #[error("Database error: {0}")]
     Database(#[from] sea_orm::DbErr),
+
+    /// 配置错误（用户提供的地址、参数无效）
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// 操作被用户取消
+    #[error("操作已取消")]
+    Cancelled,
+
+    /// 对端发来的分块请求无效（会话不属于对端、文件不存在或分块越界）
+    #[error("Invalid chunk request: {0}")]
+    InvalidChunkRequest(String),
+
+    /// 分块解密失败（密钥不一致或数据被篡改，重试无法恢复）
+    #[error("密钥不匹配或数据被篡改: {0}")]
+    Crypto(String),
+
+    /// 密钥协商失败（对端公钥无效、派生失败或对端未提供密钥材料）
+    #[error("密钥协商失败: {0}")]
+    KeyExchange(String),
+
+    /// 设备不存在（既未被发现也未配对）
+    #[error("设备不存在: {0}")]
+    DeviceNotFound(String),
+
+    /// 配对码或配对目标属于本机
+    #[error("不能与本机配对（本机 PeerId: {0}）")]
+    SelfPairing(String),
+}
+
+// ============ 错误码 ============
+
+/// `Network` 错误的细分：(消息特征, 错误码, 是否可重试)，按顺序匹配第一个
+///
+/// 这些错误目前以字符串构造，特征取自各处的错误消息；修改消息时需同步更新此表（有测试覆盖）。
+const NETWORK_CODES: &[(&str, &str, bool)] = &[
+    ("中继已关闭", "network/relay-disabled", false),
+    ("不在局域网中", "pairing/peer-not-on-lan", false),
+    ("DHT 暂不可用", "network/dht-unavailable", true),
+    ("超时", "network/timeout", true),
+    ("意外的响应类型", "network/unexpected-response", false),
+];
+
+/// `Transfer` 错误的细分，规则同 [`NETWORK_CODES`]
+const TRANSFER_CODES: &[(&str, &str, bool)] = &[
+    ("文件校验失败", "transfer/checksum-mismatch", true),
+    ("传输已取消", "transfer/cancelled", false),
+    ("连接中断", "transfer/connection-lost", true),
+    ("连接已断开", "transfer/connection-lost", true),
+    ("分块重试耗尽", "transfer/retries-exhausted", true),
+    ("会话不存在", "transfer/session-not-found", false),
+    ("节点正在关闭", "transfer/peer-shutting-down", true),
+    ("拒绝了存储权限", "transfer/storage-permission-denied", false),
+    ("未选择任何文件", "transfer/no-files", false),
+    ("文件列表为空", "transfer/no-files", false),
+    ("不能向本机发送", "transfer/self-target", false),
+];
+
+/// 在细分表中查找，未命中时返回默认错误码
+fn lookup(
+    table: &[(&str, &'static str, bool)],
+    msg: &str,
+    default: (&'static str, bool),
+) -> (&'static str, bool) {
+    table
+        .iter()
+        .find(|(pattern, ..)| msg.contains(pattern))
+        .map_or(default, |&(_, code, retryable)| (code, retryable))
+}
+
+impl AppError {
+    /// 稳定的机器可读错误码（前端据此决定展示方式，不要匹配 message）
+    pub fn code(&self) -> &'static str {
+        self.classify().0
+    }
+
+    /// 相同操作重试是否可能成功（网络抖动、校验失败等）
+    pub fn retryable(&self) -> bool {
+        self.classify().1
+    }
+
+    fn classify(&self) -> (&'static str, bool) {
+        match self {
+            AppError::Io(_) => ("io/failed", false),
+            AppError::Serialization(_) => ("internal/serialization", false),
+            AppError::Tauri(_) => ("internal/tauri", false),
+            AppError::P2p(_) => ("network/p2p", true),
+            AppError::Network(msg) => lookup(NETWORK_CODES, msg, ("network/failed", true)),
+            AppError::Identity(_) => ("identity/invalid", false),
+            AppError::NodeNotStarted => ("node/not-started", false),
+            AppError::ExpiredCode => ("pairing/expired-code", false),
+            AppError::InvalidCode => ("pairing/invalid-code", false),
+            AppError::InvalidPairingPayload(_) => ("pairing/invalid-payload", false),
+            AppError::TaskJoin(_) => ("internal/task-join", false),
+            AppError::Transfer(msg) => lookup(TRANSFER_CODES, msg, ("transfer/failed", false)),
+            AppError::Database(_) => ("database/failed", false),
+            AppError::Config(_) => ("config/invalid", false),
+            AppError::Cancelled => ("operation/cancelled", false),
+            AppError::InvalidChunkRequest(_) => ("transfer/invalid-chunk-request", false),
+            AppError::Crypto(_) => ("transfer/decrypt-failed", false),
+            AppError::KeyExchange(_) => ("transfer/key-exchange-failed", false),
+            AppError::DeviceNotFound(_) => ("device/not-found", false),
+            AppError::SelfPairing(_) => ("pairing/self", false),
+        }
+    }
 }
 
 /// 传递给前端的序列化错误格式
+///
+/// `{ kind, code, message, retryable }`：`kind` 为变体名，`code` 为稳定错误码，
+/// `message` 仅用于展示。
 impl Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -69,7 +181,7 @@ impl Serialize for AppError {
     {
         use serde::ser::SerializeStruct;
 
-        let mut state = serializer.serialize_struct("AppError", 2)?;
+        let mut state = serializer.serialize_struct("AppError", 4)?;
 
         let (kind, message) = match self {
             AppError::Io(e) => ("Io", e.to_string()),
@@ -81,13 +193,23 @@ impl Serialize for AppError {
             AppError::NodeNotStarted => ("NodeNotStarted", self.to_string()),
             AppError::ExpiredCode => ("ExpiredCode", self.to_string()),
             AppError::InvalidCode => ("InvalidCode", self.to_string()),
+            AppError::InvalidPairingPayload(_) => ("InvalidPairingPayload", self.to_string()),
             AppError::TaskJoin(e) => ("TaskJoin", e.to_string()),
             AppError::Transfer(msg) => ("Transfer", msg.clone()),
             AppError::Database(e) => ("Database", e.to_string()),
+            AppError::Config(msg) => ("Config", msg.clone()),
+            AppError::Cancelled => ("Cancelled", self.to_string()),
+            AppError::InvalidChunkRequest(msg) => ("InvalidChunkRequest", msg.clone()),
+            AppError::Crypto(_) => ("Crypto", self.to_string()),
+            AppError::KeyExchange(_) => ("KeyExchange", self.to_string()),
+            AppError::DeviceNotFound(_) => ("DeviceNotFound", self.to_string()),
+            AppError::SelfPairing(_) => ("SelfPairing", self.to_string()),
         };
 
         state.serialize_field("kind", kind)?;
+        state.serialize_field("code", self.code())?;
         state.serialize_field("message", &message)?;
+        state.serialize_field("retryable", &self.retryable())?;
         state.end()
     }
 }
@@ -96,3 +218,142 @@ impl Serialize for AppError {
 
 /// Result 类型别名
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialized(err: AppError) -> serde_json::Value {
+        serde_json::to_value(&err).unwrap()
+    }
+
+    #[test]
+    fn test_serialize_shape() {
+        assert_eq!(
+            serialized(AppError::ExpiredCode),
+            serde_json::json!({
+                "kind": "ExpiredCode",
+                "code": "pairing/expired-code",
+                "message": "配对码已过期",
+                "retryable": false,
+            })
+        );
+    }
+
+    /// 错误码是前端契约，重构时不能悄悄改变
+    #[test]
+    fn test_variant_codes() {
+        let cases = [
+            (AppError::NodeNotStarted, "node/not-started", false),
+            (AppError::ExpiredCode, "pairing/expired-code", false),
+            (AppError::InvalidCode, "pairing/invalid-code", false),
+            (
+                AppError::InvalidPairingPayload("x".into()),
+                "pairing/invalid-payload",
+                false,
+            ),
+            (AppError::Cancelled, "operation/cancelled", false),
+            (AppError::Config("x".into()), "config/invalid", false),
+            (
+                AppError::InvalidChunkRequest("x".into()),
+                "transfer/invalid-chunk-request",
+                false,
+            ),
+            (
+                AppError::Crypto("x".into()),
+                "transfer/decrypt-failed",
+                false,
+            ),
+            (
+                AppError::KeyExchange("x".into()),
+                "transfer/key-exchange-failed",
+                false,
+            ),
+            (AppError::Io(std::io::Error::other("x")), "io/failed", false),
+            (
+                AppError::DeviceNotFound("x".into()),
+                "device/not-found",
+                false,
+            ),
+            (AppError::SelfPairing("x".into()), "pairing/self", false),
+        ];
+        for (err, code, retryable) in cases {
+            assert_eq!(err.code(), code, "{err:?}");
+            assert_eq!(err.retryable(), retryable, "{err:?}");
+        }
+    }
+
+    /// 使用各处实际构造的错误消息，确保细分表与消息保持一致
+    #[test]
+    fn test_stringly_sub_codes() {
+        let cases = [
+            (
+                AppError::Transfer("文件校验失败: /tmp/a.bin".into()),
+                "transfer/checksum-mismatch",
+                true,
+            ),
+            (
+                AppError::Transfer("连接中断，30 秒内未能重连发送方".into()),
+                "transfer/connection-lost",
+                true,
+            ),
+            (
+                AppError::Transfer("传输已取消".into()),
+                "transfer/cancelled",
+                false,
+            ),
+            (
+                AppError::Transfer("发送会话不存在: 1".into()),
+                "transfer/session-not-found",
+                false,
+            ),
+            (
+                AppError::Transfer("发送方报告错误: 节点正在关闭".into()),
+                "transfer/peer-shutting-down",
+                true,
+            ),
+            (
+                AppError::Transfer("不能向本机发送文件（本机 PeerId: 12D3）".into()),
+                "transfer/self-target",
+                false,
+            ),
+            (
+                AppError::Transfer("加密失败: x".into()),
+                "transfer/failed",
+                false,
+            ),
+            (
+                AppError::Network("连接 12D3 超时（10s）".into()),
+                "network/timeout",
+                true,
+            ),
+            (
+                AppError::Network(
+                    "中继已关闭（仅局域网模式），无法连接不在同一局域网的设备: 连接 12D3 超时（10s）"
+                        .into(),
+                ),
+                "network/relay-disabled",
+                false,
+            ),
+            (
+                AppError::Network("设备 12D3 当前不在局域网中，无法直接配对".into()),
+                "pairing/peer-not-on-lan",
+                false,
+            ),
+            (
+                AppError::Network("尚未连接到引导节点，DHT 暂不可用，请检查网络后重试".into()),
+                "network/dht-unavailable",
+                true,
+            ),
+            (
+                AppError::Network("connection closed".into()),
+                "network/failed",
+                true,
+            ),
+        ];
+        for (err, code, retryable) in cases {
+            assert_eq!(err.code(), code, "{err:?}");
+            assert_eq!(err.retryable(), retryable, "{err:?}");
+        }
+    }
+}
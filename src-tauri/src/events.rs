@@ -5,14 +5,25 @@
 // === 网络状态 ===
 pub const NETWORK_STATUS_CHANGED: &str = "network-status-changed";
 pub const DEVICES_CHANGED: &str = "devices-changed";
+pub const LISTEN_PORT_FALLBACK: &str = "listen-port-fallback";
+pub const HOLE_PUNCH_FAILED: &str = "hole-punch-failed";
 
 // === 配对 ===
 pub const PAIRING_REQUEST_RECEIVED: &str = "pairing-request-received";
 pub const PAIRED_DEVICE_ADDED: &str = "paired-device-added";
+pub const PAIRED_DEVICE_ONLINE: &str = "paired-device-online";
+pub const PAIRED_DEVICE_OFFLINE: &str = "paired-device-offline";
+pub const PAIRED_DEVICE_REMOVED: &str = "paired-device-removed";
+pub const PAIRED_DEVICE_UPDATED: &str = "paired-device-updated";
+pub const PAIRING_CONFIRMATION_RECEIVED: &str = "pairing-confirmation-received";
+pub const PAIRING_BRUTEFORCE_DETECTED: &str = "pairing-bruteforce-detected";
 
 // === 传输 ===
 pub const TRANSFER_OFFER: &str = "transfer-offer";
 pub const TRANSFER_PROGRESS: &str = "transfer-progress";
+pub const TRANSFER_VERIFYING: &str = "transfer-verifying";
+pub const OVERALL_PROGRESS: &str = "overall-progress";
+pub const TRANSFER_RECONNECTING: &str = "transfer-reconnecting";
 pub const TRANSFER_COMPLETE: &str = "transfer-complete";
 pub const TRANSFER_FAILED: &str = "transfer-failed";
 pub const TRANSFER_ACCEPTED: &str = "transfer-accepted";
@@ -20,3 +31,7 @@ pub const TRANSFER_REJECTED: &str = "transfer-rejected";
 pub const TRANSFER_PAUSED: &str = "transfer-paused";
 pub const TRANSFER_RESUMED: &str = "transfer-resumed";
 pub const TRANSFER_DB_ERROR: &str = "transfer-db-error";
+pub const SAVE_LOCATION_FALLBACK: &str = "save-location-fallback";
+
+// === 诊断 ===
+pub const LOG_LINE: &str = "log-line";
@@ -1,17 +1,25 @@
 //! Android 端文件写入操作
 //!
-//! 通过 `tauri-plugin-android-fs` 的 PublicStorage API 将接收的文件保存到公共 Download 目录。
+//! 通过 `tauri-plugin-android-fs` 的 PublicStorage API 将接收的文件保存到公共目录（默认 Download，可选 Documents），
+//! 开启按媒体类型分流时图片 / 视频 / 音频分别进入 Pictures / Movies / Music，便于相册等应用索引。
 //! 利用 pending 机制（Android 10+）：文件在写入期间对其他应用不可见，校验通过后才公开。
 //!
+//! 用户也可以通过系统目录选择器指定保存目录（SAF tree URI，可在 SD 卡上）。
+//! SAF 没有 pending 机制，改为写入 `<name>.part` 文件，校验通过后重命名为最终文件名。
+//!
 //! 分块写入已由 `PartFile::write_chunk()` 统一处理（跨平台 pwrite），
 //! 此模块仅负责创建文件（含缓存句柄）、校验最终化和清理。
 
 use std::path::PathBuf;
 
-use tauri_plugin_android_fs::{AndroidFsExt, FileAccessMode, FileUri, PublicGeneralPurposeDir};
-use tracing::warn;
+use entity::PublicBaseDir;
+use tauri_plugin_android_fs::{
+    AndroidFsExt, FileAccessMode, FileUri, PublicAudioDir, PublicDir, PublicGeneralPurposeDir,
+    PublicImageDir, PublicVideoDir,
+};
+use tracing::{info, warn};
 
-use crate::file_sink::PartFile;
+use crate::file_sink::{hash_with_progress, PartFile};
 use crate::{AppError, AppResult};
 
 /// 请求写入权限（Android 9 及以下需要）
@@ -32,15 +40,40 @@ pub async fn ensure_permission(app: &tauri::AppHandle) -> AppResult<()> {
     Ok(())
 }
 
+/// 用户选择的公共目录对应的通用文件集合
+fn general_purpose_dir(base_dir: PublicBaseDir) -> PublicGeneralPurposeDir {
+    match base_dir {
+        PublicBaseDir::Download => PublicGeneralPurposeDir::Download,
+        PublicBaseDir::Documents => PublicGeneralPurposeDir::Documents,
+    }
+}
+
+/// 按 MIME 类型选择 MediaStore 集合：图片 → Pictures，视频 → Movies，音频 → Music，
+/// 其他 → `base_dir`
+fn public_dir_for(base_dir: PublicBaseDir, mime: Option<&str>) -> PublicDir {
+    match mime.and_then(|m| m.split('/').next()) {
+        Some("image") => PublicImageDir::Pictures.into(),
+        Some("video") => PublicVideoDir::Movies.into(),
+        Some("audio") => PublicAudioDir::Music.into(),
+        _ => general_purpose_dir(base_dir).into(),
+    }
+}
+
 /// 创建文件（pending 状态）并返回带缓存句柄的 PartFile
 ///
-/// 使用 `create_new_file_with_pending` 在 Download/{subdir}/ 下创建文件，
-/// 文件在 pending 状态下对其他应用不可见。
+/// 使用 `create_new_file_with_pending` 在 {公共目录}/{subdir}/ 下创建文件，
+/// 公共目录由 `mime` 决定（None 时为 `base_dir`），文件在 pending 状态下对其他应用不可见。
 /// 打开文件句柄并缓存，后续 `PartFile::write_chunk()` 直接使用 pwrite 写入。
+///
+/// 目标目录已有同名文件时 MediaStore 会自动重命名（如 `a (1).txt`），
+/// 此时回读实际文件名，`PartFile.final_path` 记录的是重命名后的相对路径。
 pub async fn create_part_file(
+    base_dir: PublicBaseDir,
     subdir: &str,
     relative_path: &str,
     file_size: u64,
+    preallocate: bool,
+    mime: Option<&str>,
     app: &tauri::AppHandle,
 ) -> AppResult<PartFile> {
     let full_relative = format!("{subdir}/{relative_path}");
@@ -50,7 +83,7 @@ pub async fn create_part_file(
         .public_storage()
         .create_new_file_with_pending(
             None, // 使用主存储卷
-            PublicGeneralPurposeDir::Download,
+            public_dir_for(base_dir, mime),
             &full_relative,
             None, // 从扩展名推断 MIME 类型
         )
@@ -61,6 +94,93 @@ pub async fn create_part_file(
             ))
         })?;
 
+    let actual_path = actual_relative_path(&file_uri, relative_path, app).await;
+    open_part_file(file_uri, &actual_path, file_size, preallocate, app).await
+}
+
+/// 回读系统实际创建的文件名，与请求的不同（同名冲突被自动重命名）时返回替换文件名后的相对路径
+async fn actual_relative_path(
+    file_uri: &FileUri,
+    relative_path: &str,
+    app: &tauri::AppHandle,
+) -> String {
+    let actual_name = match app.android_fs_async().get_name(file_uri).await {
+        Ok(name) => name,
+        Err(e) => {
+            warn!("读取实际文件名失败，沿用原文件名: {relative_path}, {e}");
+            return relative_path.to_owned();
+        }
+    };
+    let (parent, requested_name) = match relative_path.rsplit_once('/') {
+        Some((parent, name)) => (Some(parent), name),
+        None => (None, relative_path),
+    };
+    if actual_name == requested_name {
+        return relative_path.to_owned();
+    }
+
+    info!("目标目录已存在同名文件，系统已自动重命名: {requested_name} → {actual_name}");
+    match parent {
+        Some(parent) => format!("{parent}/{actual_name}"),
+        None => actual_name,
+    }
+}
+
+/// 在用户选择的 SAF 目录下创建 `.part` 文件并返回带缓存句柄的 PartFile
+///
+/// `relative_path` 中的父目录会自动创建；校验通过后由 [`verify_and_finalize`] 重命名为最终文件名。
+pub async fn create_saf_part_file(
+    dir_uri: &FileUri,
+    relative_path: &str,
+    file_size: u64,
+    preallocate: bool,
+    app: &tauri::AppHandle,
+) -> AppResult<PartFile> {
+    let file_uri = app
+        .android_fs_async()
+        .create_new_file(dir_uri, format!("{relative_path}.part"), None)
+        .await
+        .map_err(|e| {
+            AppError::Transfer(format!(
+                "Android 在所选目录创建文件失败: {relative_path}, {e}"
+            ))
+        })?;
+
+    let final_name = relative_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(relative_path)
+        .to_owned();
+    let part_file = open_part_file(file_uri, relative_path, file_size, preallocate, app).await?;
+    Ok(part_file.with_saf_final_name(final_name))
+}
+
+/// 持久化用户所选目录的访问权限（应用重启后仍可写入）
+///
+/// 权限已被撤销或目录不存在时返回 false。
+pub async fn persist_dir_permission(dir_uri: &FileUri, app: &tauri::AppHandle) -> bool {
+    match app
+        .android_fs_async()
+        .file_picker()
+        .persist_uri_permission(dir_uri)
+        .await
+    {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("持久化保存目录权限失败: {}, {e}", dir_uri.uri);
+            false
+        }
+    }
+}
+
+/// 打开已创建的文件、缓存写入句柄并预分配大小
+async fn open_part_file(
+    file_uri: FileUri,
+    relative_path: &str,
+    file_size: u64,
+    preallocate: bool,
+    app: &tauri::AppHandle,
+) -> AppResult<PartFile> {
     // 打开文件并缓存句柄（用于后续 pwrite 写入分块）
     let file = app
         .android_fs_async()
@@ -72,20 +192,17 @@ pub async fn create_part_file(
             ))
         })?;
 
-    // 预分配文件大小：提前检查磁盘空间，避免传输到一半才失败
-    if file_size > 0 {
+    // 预分配文件大小：提前检查磁盘空间，避免传输到一半才失败。
+    // SD 卡常见的 FAT32 / exFAT 上大文件可能预分配失败，此时不预分配继续写入（文件随写入扩展）
+    if preallocate && file_size > 0 {
         let f = file.try_clone().map_err(|e| {
             AppError::Transfer(format!(
                 "Android clone 文件句柄失败: {relative_path}, {e}"
             ))
         })?;
-        tokio::task::spawn_blocking(move || f.set_len(file_size))
-            .await?
-            .map_err(|e: std::io::Error| {
-                AppError::Transfer(format!(
-                    "Android 预分配文件大小失败: {relative_path}, {e}"
-                ))
-            })?;
+        if let Err(e) = tokio::task::spawn_blocking(move || f.set_len(file_size)).await? {
+            warn!("Android 预分配文件大小失败，改为写入时扩展: {relative_path}, {e}");
+        }
     }
 
     Ok(PartFile::new_android(
@@ -98,14 +215,16 @@ pub async fn create_part_file(
 
 /// 校验 BLAKE3 并最终化文件
 ///
-/// 1. 以只读模式打开文件，流式计算 BLAKE3 hash
-/// 2. 校验通过：`set_pending(false)` 使文件可见 + `scan()` 刷新 MediaStore
+/// 1. 以只读模式打开文件，按块流式计算 BLAKE3 hash，每读取一块调用 `on_progress(已校验字节数)`
+/// 2. 校验通过：`set_pending(false)` 使文件可见 + `scan()` 刷新 MediaStore；
+///    SAF 目录中的 `.part` 文件重命名为最终文件名
 /// 3. 校验失败：`remove_file()` 删除文件
 ///
 /// 调用前需确保写入句柄已关闭（`PartFile::close_write_handle()`）。
 pub async fn verify_and_finalize(
     part_file: &PartFile,
     expected_checksum: &str,
+    on_progress: impl Fn(u64) + Send + 'static,
     app: &tauri::AppHandle,
 ) -> AppResult<PathBuf> {
     let file_uri = part_file
@@ -122,11 +241,8 @@ pub async fn verify_and_finalize(
 
     let expected = expected_checksum.to_owned();
     let checksum_ok = tokio::task::spawn_blocking(move || {
-        let mut hasher = blake3::Hasher::new();
-        hasher
-            .update_reader(&mut file)
+        let actual_hex = hash_with_progress(&mut file, on_progress)
             .map_err(|e| AppError::Transfer(format!("Android 校验读取失败: {e}")))?;
-        let actual_hex = hasher.finalize().to_hex().to_string();
         Ok::<bool, AppError>(actual_hex == expected)
     })
     .await??;
@@ -140,6 +256,17 @@ pub async fn verify_and_finalize(
         )));
     }
 
+    // SAF 目录：去掉 .part 后缀即完成
+    if let Some(final_name) = &part_file.saf_final_name {
+        app.android_fs_async()
+            .rename(file_uri, final_name)
+            .await
+            .map_err(|e| {
+                AppError::Transfer(format!("Android 重命名文件失败: {final_name}, {e}"))
+            })?;
+        return Ok(part_file.final_path.clone());
+    }
+
     // 校验通过：取消 pending 状态，使文件对其他应用可见
     app.android_fs_async()
         .public_storage()
@@ -172,14 +299,18 @@ pub async fn cleanup_part_file(part_file: &PartFile, app: &tauri::AppHandle) {
 
 /// 获取保存目录的 FileUri
 ///
-/// 通过 `resolve_initial_location` 获取 `Download/{subdir}` 目录的标准 content URI。
+/// 通过 `resolve_initial_location` 获取 `{base_dir}/{subdir}` 目录的标准 content URI。
 /// 前端可直接用于 `showViewDirDialog`。
-pub async fn resolve_save_dir_uri(subdir: &str, app: &tauri::AppHandle) -> Option<FileUri> {
+pub async fn resolve_save_dir_uri(
+    base_dir: PublicBaseDir,
+    subdir: &str,
+    app: &tauri::AppHandle,
+) -> Option<FileUri> {
     app.android_fs_async()
         .public_storage()
         .resolve_initial_location(
             None, // 主存储卷
-            PublicGeneralPurposeDir::Download,
+            general_purpose_dir(base_dir),
             subdir,
             false, // 目录已由文件写入时自动创建
         )
@@ -1,7 +1,8 @@
 //! 文件写入抽象模块（接收端）
 //!
 //! 与 `file_source`（读取来源）对称，`file_sink` 统一处理接收端文件的写入、
-//! 校验和最终化。桌面端直接写入本地路径，Android 端通过 SAF/MediaStore 写入公共目录。
+//! 校验和最终化。桌面端直接写入本地路径，Android 端通过 MediaStore 写入公共目录，
+//! 或通过 SAF 写入用户选择的目录。
 //!
 //! ## 核心设计
 //!
@@ -10,6 +11,7 @@
 //! - **`FileSink`**：负责创建 `PartFile`（工厂），权限检查等。
 
 pub mod path_ops;
+pub mod template;
 
 #[cfg(target_os = "android")]
 pub mod android_ops;
@@ -25,18 +27,66 @@ use tauri_plugin_android_fs::FileUri;
 use crate::file_source::CHUNK_SIZE;
 use crate::{AppError, AppResult};
 
+/// 按 [`CHUNK_SIZE`] 顺序读取并计算 BLAKE3 hash（hex），每读取一块调用 `on_progress(已读字节数)`
+pub(crate) fn hash_with_progress(
+    reader: &mut impl std::io::Read,
+    on_progress: impl Fn(u64),
+) -> std::io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut total_read: u64 = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        total_read += n as u64;
+        on_progress(total_read);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Android 公共 Download 目录下的默认子目录（用户所选目录不可用时回退到这里）
+pub const DEFAULT_PUBLIC_SUBDIR: &str = "SwarmDrop";
+
 /// 文件写入目标
 ///
-/// 桌面端仅编译 `Path` 分支；Android 端同时支持两者。
+/// 桌面端仅编译 `Path` 分支；Android 端同时支持全部分支。
 pub enum FileSink {
     /// 桌面端：直接写到本地目录
     Path { save_dir: PathBuf },
 
     /// Android：保存到公共目录（SAF/MediaStore）
     ///
-    /// `subdir` 为 Download 目录下的子目录名（如 "SwarmDrop"）。
+    /// `base_dir` 为公共目录（Download / Documents），`subdir` 为其下的子目录名（如 "SwarmDrop"）；
+    /// `by_media_type` 为 true 时图片 / 视频 / 音频分别保存到 Pictures / Movies / Music，
+    /// 其他文件仍保存到 `base_dir`。
+    #[cfg(target_os = "android")]
+    AndroidPublicDir {
+        base_dir: entity::PublicBaseDir,
+        subdir: String,
+        by_media_type: bool,
+    },
+
+    /// Android：保存到用户通过系统目录选择器选定的目录（SAF tree URI）
+    ///
+    /// 可以是 SD 卡或任意文件夹，访问权限在接受传输时持久化。
     #[cfg(target_os = "android")]
-    AndroidPublicDir { subdir: String },
+    AndroidSafDir { dir_uri: FileUri },
+}
+
+/// 断点续传时单个文件在接收端的状态
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResumeState {
+    /// 已校验并最终化（附最终路径），无需再拉取
+    Finalized(PathBuf),
+    /// `.part` 文件存在且大小正确，已有的 bitmap 可以沿用
+    PartValid,
+    /// 没有可续传的 `.part` 文件（不存在、大小不符或平台不支持断点续传）
+    Missing,
 }
 
 /// .part 临时文件
@@ -47,7 +97,8 @@ pub enum FileSink {
 pub struct PartFile {
     /// .part 临时文件路径（桌面端使用）
     pub part_path: PathBuf,
-    /// 最终文件路径（桌面端使用，去掉 .part 后缀）
+    /// 最终文件路径（桌面端为去掉 .part 后缀的绝对路径；Android 端为实际保存的相对路径，
+    /// 同名冲突被系统自动重命名时与请求的路径不同）
     pub final_path: PathBuf,
     /// 文件大小
     pub size: u64,
@@ -56,6 +107,9 @@ pub struct PartFile {
     /// Android 文件 URI（仅 Android 端使用）
     #[cfg(target_os = "android")]
     pub file_uri: Option<FileUri>,
+    /// SAF 目录中的最终文件名（SAF 没有 pending 机制，写入 `.part` 文件，校验通过后重命名）
+    #[cfg(target_os = "android")]
+    pub saf_final_name: Option<String>,
 }
 
 impl PartFile {
@@ -73,6 +127,8 @@ impl PartFile {
             write_handle: StdMutex::new(Some(Arc::new(write_handle))),
             #[cfg(target_os = "android")]
             file_uri: None,
+            #[cfg(target_os = "android")]
+            saf_final_name: None,
         }
     }
 
@@ -90,23 +146,15 @@ impl PartFile {
             size,
             write_handle: StdMutex::new(Some(Arc::new(write_handle))),
             file_uri: Some(file_uri),
+            saf_final_name: None,
         }
     }
 
-    /// 创建不含写入句柄的 PartFile（仅用于 `build_part_file`）
-    pub(crate) fn new_without_handle(
-        part_path: PathBuf,
-        final_path: PathBuf,
-        size: u64,
-    ) -> Self {
-        Self {
-            part_path,
-            final_path,
-            size,
-            write_handle: StdMutex::new(None),
-            #[cfg(target_os = "android")]
-            file_uri: None,
-        }
+    /// 标记为 SAF 目录中的 `.part` 文件，最终化时重命名为 `final_name`
+    #[cfg(target_os = "android")]
+    pub(crate) fn with_saf_final_name(mut self, final_name: String) -> Self {
+        self.saf_final_name = Some(final_name);
+        self
     }
 
     /// 转换为可序列化的 FileUri JSON Value（用于完成事件）
@@ -124,7 +172,8 @@ impl PartFile {
     ///
     /// 内部通过 `spawn_blocking` + 定位写入（pwrite/seek_write）实现，
     /// 不修改文件偏移量，多个分块可安全并发写入同一文件。
-    pub async fn write_chunk(&self, chunk_index: u32, data: &[u8]) -> AppResult<()> {
+    /// 接收缓冲区的所有权直接移入阻塞任务，避免每个分块多拷贝一次。
+    pub async fn write_chunk(&self, chunk_index: u32, data: Vec<u8>) -> AppResult<()> {
         let handle = {
             let guard = self.write_handle.lock().unwrap();
             guard
@@ -134,7 +183,6 @@ impl PartFile {
         };
 
         let offset = chunk_index as u64 * CHUNK_SIZE as u64;
-        let data = data.to_vec();
 
         tokio::task::spawn_blocking(move || write_all_at(&handle, &data, offset))
             .await?
@@ -153,33 +201,48 @@ impl PartFile {
     /// 校验 BLAKE3 并最终化文件
     ///
     /// 1. 关闭写入句柄
-    /// 2. 流式计算 BLAKE3 校验和
-    /// 3. 校验通过：桌面端重命名 .part → 最终路径；Android 端 set_pending(false) + scan
+    /// 2. 按块流式计算 BLAKE3 校验和，每读取一块调用 `on_progress(已校验字节数)`
+    /// 3. 校验通过：桌面端重命名 .part → 最终路径；Android 公共目录 set_pending(false) + scan，
+    ///    SAF 目录重命名 .part → 最终文件名
     /// 4. 校验失败：删除临时文件
     pub async fn verify_and_finalize(
         &self,
         expected_checksum: &str,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        on_progress: impl Fn(u64) + Send + 'static,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
     ) -> AppResult<PathBuf> {
         self.close_write_handle();
 
         #[cfg(target_os = "android")]
         if self.file_uri.is_some() {
-            return android_ops::verify_and_finalize(self, expected_checksum, app).await;
+            let app = crate::file_source::require_app(app)?;
+            return android_ops::verify_and_finalize(self, expected_checksum, on_progress, app)
+                .await;
         }
 
-        path_ops::verify_and_finalize(self, expected_checksum).await
+        path_ops::verify_and_finalize(self, expected_checksum, on_progress).await
+    }
+
+    /// 已保存文件的 URI（序列化的 `FileUri`，仅 Android 端有值，供前端打开文件）
+    pub fn saved_uri(&self) -> Option<serde_json::Value> {
+        #[cfg(target_os = "android")]
+        if let Some(file_uri) = &self.file_uri {
+            return serde_json::to_value(file_uri).ok();
+        }
+        None
     }
 
     /// 清理临时文件（静默忽略错误）
     ///
     /// 传输取消或失败时调用，删除未最终化的临时文件。
-    pub async fn cleanup(&self, #[allow(unused_variables)] app: &tauri::AppHandle) {
+    pub async fn cleanup(&self, #[allow(unused_variables)] app: Option<&tauri::AppHandle>) {
         self.close_write_handle();
 
         #[cfg(target_os = "android")]
         if self.file_uri.is_some() {
-            android_ops::cleanup_part_file(self, app).await;
+            if let Some(app) = app {
+                android_ops::cleanup_part_file(self, app).await;
+            }
             return;
         }
 
@@ -199,6 +262,14 @@ impl fmt::Debug for PartFile {
     }
 }
 
+/// .part 临时文件的文件名后缀
+pub const PART_SUFFIX: &str = ".part";
+
+/// 文件名是否为接收中的 .part 临时文件
+pub fn is_part_file(name: &str) -> bool {
+    name.ends_with(PART_SUFFIX)
+}
+
 /// 根据最终路径计算 .part 临时文件路径
 ///
 /// 规则：在原扩展名后追加 `.part`，如 `readme.md` → `readme.md.part`；
@@ -245,19 +316,52 @@ impl FileSink {
     /// 创建 .part 临时文件
     ///
     /// 返回带有缓存写入句柄的 `PartFile`，后续分块写入直接调用 `part_file.write_chunk()`。
+    /// `mime` 仅用于 Android 公共目录按媒体类型分流。
+    ///
+    /// `preallocate` 为 true 时按文件大小预分配，预分配失败（FAT32 / exFAT 上的大文件等）
+    /// 只记录日志；为 false 时不预分配，文件随分块写入扩展。
     pub async fn create_part_file(
         &self,
         relative_path: &str,
         file_size: u64,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        preallocate: bool,
+        #[allow(unused_variables)] mime: Option<&str>,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
     ) -> AppResult<PartFile> {
         match self {
             Self::Path { save_dir } => {
-                path_ops::create_part_file(save_dir, relative_path, file_size).await
+                path_ops::create_part_file(save_dir, relative_path, file_size, preallocate).await
             }
             #[cfg(target_os = "android")]
-            Self::AndroidPublicDir { subdir } => {
-                android_ops::create_part_file(subdir, relative_path, file_size, app).await
+            Self::AndroidPublicDir {
+                base_dir,
+                subdir,
+                by_media_type,
+            } => {
+                let app = crate::file_source::require_app(app)?;
+                let mime = mime.filter(|_| *by_media_type);
+                android_ops::create_part_file(
+                    *base_dir,
+                    subdir,
+                    relative_path,
+                    file_size,
+                    preallocate,
+                    mime,
+                    app,
+                )
+                .await
+            }
+            #[cfg(target_os = "android")]
+            Self::AndroidSafDir { dir_uri } => {
+                let app = crate::file_source::require_app(app)?;
+                android_ops::create_saf_part_file(
+                    dir_uri,
+                    relative_path,
+                    file_size,
+                    preallocate,
+                    app,
+                )
+                .await
             }
         }
     }
@@ -270,32 +374,80 @@ impl FileSink {
         &self,
         relative_path: &str,
         file_size: u64,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        preallocate: bool,
+        #[allow(unused_variables)] mime: Option<&str>,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
     ) -> AppResult<PartFile> {
         match self {
             Self::Path { save_dir } => {
-                path_ops::open_or_create_part_file(save_dir, relative_path, file_size).await
+                path_ops::open_or_create_part_file(save_dir, relative_path, file_size, preallocate)
+                    .await
+            }
+            #[cfg(target_os = "android")]
+            Self::AndroidPublicDir {
+                base_dir,
+                subdir,
+                by_media_type,
+            } => {
+                let app = crate::file_source::require_app(app)?;
+                let mime = mime.filter(|_| *by_media_type);
+                android_ops::create_part_file(
+                    *base_dir,
+                    subdir,
+                    relative_path,
+                    file_size,
+                    preallocate,
+                    mime,
+                    app,
+                )
+                .await
             }
             #[cfg(target_os = "android")]
-            Self::AndroidPublicDir { subdir } => {
-                android_ops::create_part_file(subdir, relative_path, file_size, app).await
+            Self::AndroidSafDir { dir_uri } => {
+                let app = crate::file_source::require_app(app)?;
+                android_ops::create_saf_part_file(
+                    dir_uri,
+                    relative_path,
+                    file_size,
+                    preallocate,
+                    app,
+                )
+                .await
             }
         }
     }
 
-    /// 构建 PartFile（不创建实际文件，不含写入句柄）
+    /// 检查断点续传时文件的状态
+    ///
+    /// Android 端暂不支持断点续传，始终返回 [`ResumeState::Missing`]。
+    pub async fn resume_state(&self, relative_path: &str, size: u64) -> ResumeState {
+        match self {
+            Self::Path { save_dir } => path_ops::resume_state(save_dir, relative_path, size).await,
+            #[cfg(target_os = "android")]
+            Self::AndroidPublicDir { .. } | Self::AndroidSafDir { .. } => ResumeState::Missing,
+        }
+    }
+
+    /// 创建空目录（重建发送方的空目录）
     ///
-    /// 用于桌面端清理场景：已知 relative_path 但不需要创建文件。
-    pub fn build_part_file(&self, relative_path: &str, size: u64) -> PartFile {
+    /// 桌面端和 SAF 目录下递归创建；Android 公共目录无法表示空目录，跳过并记录日志。
+    pub async fn create_dir(&self, relative_path: &str) -> AppResult<()> {
         match self {
             Self::Path { save_dir } => {
-                let final_path = save_dir.join(relative_path);
-                let part_path = compute_part_path(&final_path);
-                PartFile::new_without_handle(part_path, final_path, size)
+                tokio::fs::create_dir_all(save_dir.join(relative_path)).await?;
+                Ok(())
             }
             #[cfg(target_os = "android")]
             Self::AndroidPublicDir { .. } => {
-                PartFile::new_without_handle(PathBuf::new(), PathBuf::new(), size)
+                tracing::info!("Android 公共目录不支持空目录，跳过: {}", relative_path);
+                Ok(())
+            }
+            #[cfg(target_os = "android")]
+            Self::AndroidSafDir { .. } => {
+                // SAF 目录需要 AppHandle，由 create_part_file 创建文件时自动创建父目录；
+                // 这里只能跳过空目录
+                tracing::info!("SAF 目录暂不重建空目录，跳过: {}", relative_path);
+                Ok(())
             }
         }
     }
@@ -307,8 +459,18 @@ impl FileSink {
                 path: save_dir.to_string_lossy().into_owned(),
             },
             #[cfg(target_os = "android")]
-            Self::AndroidPublicDir { subdir } => entity::SaveLocation::AndroidPublicDir {
+            Self::AndroidPublicDir {
+                base_dir,
+                subdir,
+                by_media_type,
+            } => entity::SaveLocation::AndroidPublicDir {
+                base_dir: *base_dir,
                 subdir: subdir.clone(),
+                by_media_type: *by_media_type,
+            },
+            #[cfg(target_os = "android")]
+            Self::AndroidSafDir { dir_uri } => entity::SaveLocation::AndroidSafDir {
+                dir_uri: serde_json::to_value(dir_uri).unwrap_or_default(),
             },
         }
     }
@@ -318,7 +480,9 @@ impl FileSink {
         match self {
             Self::Path { save_dir } => save_dir.to_string_lossy(),
             #[cfg(target_os = "android")]
-            Self::AndroidPublicDir { .. } => Cow::Borrowed("Download"),
+            Self::AndroidPublicDir { base_dir, .. } => Cow::Borrowed(base_dir.display_name()),
+            #[cfg(target_os = "android")]
+            Self::AndroidSafDir { dir_uri } => Cow::Borrowed(&dir_uri.uri),
         }
     }
 
@@ -328,12 +492,42 @@ impl FileSink {
     /// Android 端检查并请求 `WRITE_EXTERNAL_STORAGE` 权限（Android 9 及以下需要）。
     pub async fn ensure_permission(
         &self,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
     ) -> AppResult<()> {
         match self {
             Self::Path { .. } => Ok(()),
             #[cfg(target_os = "android")]
-            Self::AndroidPublicDir { .. } => android_ops::ensure_permission(app).await,
+            Self::AndroidPublicDir { .. } => {
+                android_ops::ensure_permission(crate::file_source::require_app(app)?).await
+            }
+            // SAF 目录的权限由用户在选择目录时授予，见 resolve_access
+            #[cfg(target_os = "android")]
+            Self::AndroidSafDir { .. } => Ok(()),
+        }
+    }
+
+    /// 确认保存目录仍可访问
+    ///
+    /// 用户所选的 SAF 目录在此持久化访问权限；权限已被撤销（目录被删除、在系统设置中收回）时
+    /// 回退到公共 `Download/SwarmDrop` 目录，并返回回退原因供调用方提示用户。
+    pub async fn resolve_access(
+        self,
+        #[allow(unused_variables)] app: &tauri::AppHandle,
+    ) -> (Self, Option<String>) {
+        #[cfg(target_os = "android")]
+        if let Self::AndroidSafDir { dir_uri } = &self {
+            if !android_ops::persist_dir_permission(dir_uri, app).await {
+                let sink = Self::AndroidPublicDir {
+                    base_dir: entity::PublicBaseDir::Download,
+                    subdir: DEFAULT_PUBLIC_SUBDIR.into(),
+                    by_media_type: false,
+                };
+                let reason = format!(
+                    "所选保存目录的访问权限已失效，已改为保存到 Download/{DEFAULT_PUBLIC_SUBDIR}"
+                );
+                return (sink, Some(reason));
+            }
         }
+        (self, None)
     }
 }
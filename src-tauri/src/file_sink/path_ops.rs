@@ -5,7 +5,9 @@
 
 use std::path::{Path, PathBuf};
 
-use crate::file_sink::{compute_part_path, PartFile};
+use tracing::warn;
+
+use crate::file_sink::{compute_part_path, hash_with_progress, PartFile, ResumeState};
 use crate::{AppError, AppResult};
 
 /// 创建 .part 临时文件：创建目录 → 创建文件 → 预分配大小 → 缓存写入句柄
@@ -13,10 +15,11 @@ pub(crate) async fn create_part_file(
     save_dir: &Path,
     relative_path: &str,
     file_size: u64,
+    preallocate: bool,
 ) -> AppResult<PartFile> {
     let (part_path, final_path) = resolve_paths(save_dir, relative_path).await?;
 
-    let f = create_new_part(&part_path, file_size).await?;
+    let f = create_new_part(&part_path, file_size, preallocate).await?;
     let write_handle = f.into_std().await;
 
     Ok(PartFile::new_path(part_path, final_path, file_size, write_handle))
@@ -27,10 +30,13 @@ pub(crate) async fn create_part_file(
 /// 检查 .part 文件是否存在且大小匹配：
 /// - 匹配：以读写模式打开（不截断），保留已有数据
 /// - 不匹配或不存在：创建新文件并预分配大小
+///
+/// 未预分配的 `.part` 文件在最后一块写入前小于文件大小，续传时视为不匹配，该文件从头接收。
 pub(crate) async fn open_or_create_part_file(
     save_dir: &Path,
     relative_path: &str,
     file_size: u64,
+    preallocate: bool,
 ) -> AppResult<PartFile> {
     let (part_path, final_path) = resolve_paths(save_dir, relative_path).await?;
 
@@ -48,13 +54,31 @@ pub(crate) async fn open_or_create_part_file(
             .open(&part_path)
             .await?
     } else {
-        create_new_part(&part_path, file_size).await?
+        create_new_part(&part_path, file_size, preallocate).await?
     };
 
     let write_handle = f.into_std().await;
     Ok(PartFile::new_path(part_path, final_path, file_size, write_handle))
 }
 
+/// 检查断点续传时文件的状态：最终文件已存在 → 已完成；否则检查 .part 是否存在且大小正确
+pub(crate) async fn resume_state(save_dir: &Path, relative_path: &str, size: u64) -> ResumeState {
+    let final_path = save_dir.join(relative_path);
+    if tokio::fs::try_exists(&final_path).await.unwrap_or(false) {
+        return ResumeState::Finalized(final_path);
+    }
+
+    let part_ok = tokio::fs::metadata(compute_part_path(&final_path))
+        .await
+        .map(|m| m.len() == size)
+        .unwrap_or(false);
+    if part_ok {
+        ResumeState::PartValid
+    } else {
+        ResumeState::Missing
+    }
+}
+
 /// 解析最终路径和 .part 路径，并确保父目录存在
 async fn resolve_paths(
     save_dir: &Path,
@@ -71,10 +95,23 @@ async fn resolve_paths(
 }
 
 /// 创建新的 .part 文件并预分配大小
-async fn create_new_part(part_path: &Path, file_size: u64) -> AppResult<tokio::fs::File> {
+///
+/// 预分配失败（如 FAT32 上超过 4 GB 的文件）时记录日志后继续：分块以定位写入落盘，
+/// 文件随写入自动扩展，只是磁盘空间不足要到写入时才会发现。
+async fn create_new_part(
+    part_path: &Path,
+    file_size: u64,
+    preallocate: bool,
+) -> AppResult<tokio::fs::File> {
     let f = tokio::fs::File::create(part_path).await?;
-    if file_size > 0 {
-        f.set_len(file_size).await?;
+    if preallocate && file_size > 0 {
+        if let Err(e) = f.set_len(file_size).await {
+            warn!(
+                "预分配文件大小失败，改为写入时扩展: {}, {}",
+                part_path.display(),
+                e
+            );
+        }
     }
     Ok(f)
 }
@@ -86,12 +123,15 @@ async fn create_new_part(part_path: &Path, file_size: u64) -> AppResult<tokio::f
 pub(crate) async fn verify_and_finalize(
     part_file: &PartFile,
     expected_checksum: &str,
+    on_progress: impl Fn(u64) + Send + 'static,
 ) -> AppResult<PathBuf> {
     let part_path = part_file.part_path.clone();
     let expected = expected_checksum.to_owned();
 
-    let checksum_ok =
-        tokio::task::spawn_blocking(move || verify_checksum_sync(&part_path, &expected)).await??;
+    let checksum_ok = tokio::task::spawn_blocking(move || {
+        verify_checksum_sync(&part_path, &expected, on_progress)
+    })
+    .await??;
 
     if !checksum_ok {
         let _ = tokio::fs::remove_file(&part_file.part_path).await;
@@ -107,11 +147,13 @@ pub(crate) async fn verify_and_finalize(
 
 // ============ 同步内部实现 ============
 
-fn verify_checksum_sync(path: &Path, expected_hex: &str) -> AppResult<bool> {
+fn verify_checksum_sync(
+    path: &Path,
+    expected_hex: &str,
+    on_progress: impl Fn(u64),
+) -> AppResult<bool> {
     let mut file = std::fs::File::open(path)?;
-    let mut hasher = blake3::Hasher::new();
-    hasher.update_reader(&mut file)?;
-    let actual_hex = hasher.finalize().to_hex().to_string();
+    let actual_hex = hash_with_progress(&mut file, on_progress)?;
     Ok(actual_hex == expected_hex)
 }
 
@@ -125,7 +167,9 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "hello.txt", 1024).await.unwrap();
+        let part = create_part_file(&dir, "hello.txt", 1024, true)
+            .await
+            .unwrap();
         assert!(part.part_path.exists());
         assert_eq!(part.final_path, dir.join("hello.txt"));
         assert_eq!(part.part_path, dir.join("hello.txt.part"));
@@ -140,7 +184,7 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "docs/readme.md", 512)
+        let part = create_part_file(&dir, "docs/readme.md", 512, true)
             .await
             .unwrap();
         assert!(part.part_path.exists());
@@ -156,7 +200,7 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "Makefile", 256).await.unwrap();
+        let part = create_part_file(&dir, "Makefile", 256, true).await.unwrap();
         assert_eq!(part.part_path, dir.join("Makefile.part"));
 
         let _ = std::fs::remove_dir_all(&dir);
@@ -168,7 +212,7 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "empty.txt", 0).await.unwrap();
+        let part = create_part_file(&dir, "empty.txt", 0, true).await.unwrap();
         assert!(part.part_path.exists());
         assert_eq!(std::fs::metadata(&part.part_path).unwrap().len(), 0);
 
@@ -181,10 +225,12 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "data.bin", 1024).await.unwrap();
+        let part = create_part_file(&dir, "data.bin", 1024, true)
+            .await
+            .unwrap();
 
         let data = vec![0xABu8; 512];
-        part.write_chunk(0, &data).await.unwrap();
+        part.write_chunk(0, data.clone()).await.unwrap();
 
         // 关闭句柄后读取验证
         part.close_write_handle();
@@ -203,13 +249,16 @@ mod tests {
 
         let chunk_size = crate::file_source::CHUNK_SIZE;
         let file_size = chunk_size as u64 * 2;
-        let part = create_part_file(&dir, "multi.bin", file_size).await.unwrap();
+        let part = create_part_file(&dir, "multi.bin", file_size, true).await.unwrap();
 
         let data0 = vec![0xAAu8; chunk_size];
         let data1 = vec![0xBBu8; chunk_size];
 
         // 并发写入两个分块
-        let (r0, r1) = tokio::join!(part.write_chunk(0, &data0), part.write_chunk(1, &data1));
+        let (r0, r1) = tokio::join!(
+            part.write_chunk(0, data0.clone()),
+            part.write_chunk(1, data1.clone())
+        );
         r0.unwrap();
         r1.unwrap();
 
@@ -222,13 +271,78 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
     }
 
+    /// 8 路并发写满 `total` 字节（与接收端的并发数一致），返回耗时
+    ///
+    /// `copy` 为 true 时模拟旧接口：以 `&[u8]` 传入，写入前 `to_vec()` 复制一份。
+    async fn timed_writes(dir: &Path, name: &str, total: u64, copy: bool) -> std::time::Duration {
+        const WRITERS: u32 = 8;
+
+        let chunk_size = crate::file_source::CHUNK_SIZE;
+        let total_chunks = (total / chunk_size as u64) as u32;
+        let part = create_part_file(dir, name, total, true).await.unwrap();
+        let part = std::sync::Arc::new(part);
+
+        let started = std::time::Instant::now();
+        let tasks: Vec<_> = (0..WRITERS)
+            .map(|w| {
+                let part = part.clone();
+                tokio::spawn(async move {
+                    for chunk_index in (w..total_chunks).step_by(WRITERS as usize) {
+                        // 模拟解密输出：每个分块一个新缓冲区
+                        let data = vec![(chunk_index % 251) as u8; chunk_size];
+                        let data = if copy { data.as_slice().to_vec() } else { data };
+                        part.write_chunk(chunk_index, data).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let elapsed = started.elapsed();
+        part.close_write_handle();
+        elapsed
+    }
+
+    /// 分块写入吞吐（1 GiB）：同一次运行中对比复制缓冲区与直接移交所有权
+    ///
+    /// 耗时较长，默认忽略；手动运行：
+    /// `cargo test --release bench_write_chunk_throughput -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_write_chunk_throughput() {
+        const TOTAL: u64 = 1 << 30;
+
+        let dir = std::env::temp_dir().join("swarmdrop_bench_sink_write");
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::create_dir_all(&dir);
+
+        for (label, copy) in [
+            ("复制缓冲区 (&[u8] + to_vec)", true),
+            ("移交所有权 (Vec<u8>)", false),
+        ] {
+            let elapsed = timed_writes(&dir, &format!("bench_{copy}.bin"), TOTAL, copy).await;
+            println!(
+                "{label}: 写入 {} MiB 用时 {:?}，{:.1} MiB/s",
+                TOTAL >> 20,
+                elapsed,
+                (TOTAL >> 20) as f64 / elapsed.as_secs_f64()
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[tokio::test]
     async fn test_verify_and_finalize_success() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
         let dir = std::env::temp_dir().join("swarmdrop_test_sink_verify_ok");
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "test.txt", 0).await.unwrap();
+        let part = create_part_file(&dir, "test.txt", 0, true).await.unwrap();
         part.close_write_handle();
         std::fs::write(&part.part_path, b"hello swarmdrop").unwrap();
 
@@ -239,7 +353,14 @@ mod tests {
             hasher.finalize().to_hex().to_string()
         };
 
-        let final_path = verify_and_finalize(&part, &hash).await.unwrap();
+        let hashed = Arc::new(AtomicU64::new(0));
+        let recorder = hashed.clone();
+        let final_path = verify_and_finalize(&part, &hash, move |n| {
+            recorder.store(n, Ordering::Relaxed);
+        })
+        .await
+        .unwrap();
+        assert_eq!(hashed.load(Ordering::Relaxed), 15);
         assert!(final_path.exists());
         assert!(!part.part_path.exists());
         assert_eq!(std::fs::read_to_string(&final_path).unwrap(), "hello swarmdrop");
@@ -253,24 +374,47 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "test.txt", 0).await.unwrap();
+        let part = create_part_file(&dir, "test.txt", 0, true).await.unwrap();
         part.close_write_handle();
         std::fs::write(&part.part_path, b"hello").unwrap();
 
-        let result = verify_and_finalize(&part, "wrong_hash").await;
+        let result = verify_and_finalize(&part, "wrong_hash", |_| {}).await;
         assert!(result.is_err());
         assert!(!part.part_path.exists()); // .part 应被删除
 
         let _ = std::fs::remove_dir_all(&dir);
     }
 
+    #[tokio::test]
+    async fn test_resume_state() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_sink_resume_state");
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::create_dir_all(&dir);
+
+        assert_eq!(resume_state(&dir, "a.bin", 100).await, ResumeState::Missing);
+
+        let part = create_part_file(&dir, "a.bin", 100, true).await.unwrap();
+        part.close_write_handle();
+        assert_eq!(resume_state(&dir, "a.bin", 100).await, ResumeState::PartValid);
+        // 大小不符的 .part 不能沿用 bitmap
+        assert_eq!(resume_state(&dir, "a.bin", 200).await, ResumeState::Missing);
+
+        std::fs::rename(&part.part_path, &part.final_path).unwrap();
+        assert_eq!(
+            resume_state(&dir, "a.bin", 100).await,
+            ResumeState::Finalized(dir.join("a.bin"))
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[tokio::test]
     async fn test_cleanup_part_file() {
         let dir = std::env::temp_dir().join("swarmdrop_test_sink_cleanup");
         let _ = std::fs::remove_dir_all(&dir);
         let _ = std::fs::create_dir_all(&dir);
 
-        let part = create_part_file(&dir, "temp.bin", 100).await.unwrap();
+        let part = create_part_file(&dir, "temp.bin", 100, true).await.unwrap();
         assert!(part.part_path.exists());
 
         // 关闭句柄后删除
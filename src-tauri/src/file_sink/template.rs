@@ -0,0 +1,216 @@
+//! 保存路径模板
+//!
+//! 接收时按模板整理文件，例如 `Received/{deviceName}/{date}/{filename}`，
+//! 渲染结果作为文件在保存位置下的相对路径。支持的占位符：
+//!
+//! - `{deviceName}`：发送方设备名（昵称优先）
+//! - `{peerId}`：发送方 PeerId
+//! - `{date}`：接受传输时的本地日期（`YYYY-MM-DD`）
+//! - `{filename}`：文件在传输中的相对路径，必须单独作为最后一段
+//!
+//! 设备名和相对路径都由对端控制，替换后逐段经过 [`sanitize_segment`] 处理，
+//! 渲染结果不会包含 `..`、绝对路径或路径分隔符注入，始终位于保存位置之内。
+
+use crate::{AppError, AppResult};
+
+/// 文件相对路径占位符
+const FILENAME_PLACEHOLDER: &str = "{filename}";
+
+/// 目录段中可用的占位符
+const PLACEHOLDERS: [&str; 3] = ["{deviceName}", "{peerId}", "{date}"];
+
+/// 清理单个路径段：路径分隔符、Windows 保留字符和控制字符替换为 `_`，
+/// 去掉首尾空白与末尾的点；结果为空、`.` 或 `..` 时返回 `_`
+pub fn sanitize_segment(segment: &str) -> String {
+    let replaced: String = segment
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = replaced.trim().trim_end_matches('.');
+    if trimmed.is_empty() {
+        "_".to_owned()
+    } else {
+        trimmed.to_owned()
+    }
+}
+
+/// 清理对端传来的相对路径：逐段 [`sanitize_segment`]，空段与 `.` 段丢弃
+pub fn sanitize_relative_path(path: &str) -> String {
+    let segments: Vec<String> = path
+        .split(['/', '\\'])
+        .filter(|s| !s.is_empty() && *s != ".")
+        .map(sanitize_segment)
+        .collect();
+    if segments.is_empty() {
+        "_".to_owned()
+    } else {
+        segments.join("/")
+    }
+}
+
+/// 渲染模板所需的变量
+#[derive(Debug, Clone)]
+pub struct SavePathVars {
+    pub device_name: String,
+    pub peer_id: String,
+    pub date: String,
+}
+
+impl SavePathVars {
+    /// 以当前本地日期创建
+    pub fn today(device_name: impl Into<String>, peer_id: impl ToString) -> Self {
+        Self {
+            device_name: device_name.into(),
+            peer_id: peer_id.to_string(),
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// 解析后的保存路径模板
+///
+/// 默认值等同于 `{filename}`：直接保存到所选目录，相对路径同样经过清理。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavePathTemplate {
+    /// `{filename}` 之前的目录段（保留占位符，渲染时替换）
+    dirs: Vec<String>,
+}
+
+impl SavePathTemplate {
+    /// 解析模板，格式不合法时返回 [`AppError::Config`]
+    ///
+    /// `{filename}` 必须恰好出现一次且单独作为最后一段；目录段只能使用已知占位符。
+    pub fn parse(template: &str) -> AppResult<Self> {
+        let mut segments: Vec<&str> = template
+            .trim()
+            .split(['/', '\\'])
+            .filter(|s| !s.is_empty())
+            .collect();
+        if segments.pop() != Some(FILENAME_PLACEHOLDER) {
+            return Err(invalid(template, "必须以 {filename} 作为最后一段"));
+        }
+        for segment in &segments {
+            let mut rest = PLACEHOLDERS
+                .iter()
+                .fold((*segment).to_owned(), |s, p| s.replace(p, ""));
+            if rest.contains(FILENAME_PLACEHOLDER) {
+                return Err(invalid(template, "{filename} 只能出现一次"));
+            }
+            if let Some(start) = rest.find('{') {
+                rest.drain(..start);
+                let end = rest.find('}').map_or(rest.len(), |i| i + 1);
+                return Err(invalid(template, &format!("未知占位符 {}", &rest[..end])));
+            }
+        }
+        Ok(Self {
+            dirs: segments.into_iter().map(str::to_owned).collect(),
+        })
+    }
+
+    /// 渲染文件（或空目录）在保存位置下的相对路径
+    pub fn render(&self, vars: &SavePathVars, relative_path: &str) -> String {
+        let mut path: Vec<String> = self
+            .dirs
+            .iter()
+            .map(|segment| {
+                sanitize_segment(
+                    &segment
+                        .replace("{deviceName}", &vars.device_name)
+                        .replace("{peerId}", &vars.peer_id)
+                        .replace("{date}", &vars.date),
+                )
+            })
+            .collect();
+        path.push(sanitize_relative_path(relative_path));
+        path.join("/")
+    }
+}
+
+fn invalid(template: &str, reason: &str) -> AppError {
+    AppError::Config(format!("无效的保存路径模板 {template}: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Component, Path};
+
+    use super::*;
+
+    fn vars(device_name: &str) -> SavePathVars {
+        SavePathVars {
+            device_name: device_name.into(),
+            peer_id: "12D3KooWPeer".into(),
+            date: "2026-10-16".into(),
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = SavePathTemplate::parse("Received/{deviceName}/{date}/{filename}").unwrap();
+        assert_eq!(
+            template.render(&vars("alice-mac"), "photos/a.jpg"),
+            "Received/alice-mac/2026-10-16/photos/a.jpg"
+        );
+
+        let template = SavePathTemplate::parse("{deviceName} ({peerId})/{filename}").unwrap();
+        assert_eq!(
+            template.render(&vars("Bob"), "b.txt"),
+            "Bob (12D3KooWPeer)/b.txt"
+        );
+
+        // 只有 {filename} 时与直接保存一致
+        let template = SavePathTemplate::parse("{filename}").unwrap();
+        assert_eq!(template.render(&vars("Bob"), "dir/c.txt"), "dir/c.txt");
+
+        for bad in [
+            "Received/{deviceName}",
+            "{filename}/{date}",
+            "{filename}-{date}/{filename}",
+            "{device}/{filename}",
+        ] {
+            assert!(
+                matches!(SavePathTemplate::parse(bad), Err(AppError::Config(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_malicious_values_stay_inside_root() {
+        let template = SavePathTemplate::parse("/Received/{deviceName}/../{filename}").unwrap();
+        for (device_name, relative_path) in [
+            ("..", "../../etc/passwd"),
+            ("../../..", "a/../../b.txt"),
+            ("/etc", "/abs/c.txt"),
+            ("C:\\Windows", "..\\..\\d.txt"),
+            ("evil\0name", ""),
+        ] {
+            let rendered = template.render(&vars(device_name), relative_path);
+            let path = Path::new(&rendered);
+            assert!(
+                path.components().all(|c| matches!(c, Component::Normal(_))),
+                "{device_name} / {relative_path} => {rendered}"
+            );
+            assert!(rendered.starts_with("Received/"), "{rendered}");
+
+            // 未设置模板时同样不会跳出保存位置
+            let rendered = SavePathTemplate::default().render(&vars(device_name), relative_path);
+            assert!(
+                Path::new(&rendered)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_))),
+                "{relative_path} => {rendered}"
+            );
+        }
+        assert_eq!(
+            SavePathTemplate::parse("{filename}").unwrap(),
+            SavePathTemplate::default()
+        );
+        assert_eq!(sanitize_segment(" .. "), "_");
+        assert_eq!(sanitize_segment("a:b?"), "a_b_");
+    }
+}
@@ -7,8 +7,9 @@
 //! 重 I/O（文件读取、哈希计算）先 async 获取文件句柄，再 spawn_blocking 执行。
 
 use tauri_plugin_android_fs::{AndroidFsExt, Entry, FileUri};
+use tokio_util::sync::CancellationToken;
 
-use crate::file_source::{EnumeratedFile, FileSource, FileSourceMetadata, CHUNK_SIZE};
+use crate::file_source::{EnumeratedFile, FileSource, FileSourceMetadata, ScanContext, CHUNK_SIZE};
 use crate::{AppError, AppResult};
 
 /// 读取文件的指定分块
@@ -73,6 +74,7 @@ pub async fn compute_hash(file_uri: &FileUri, app: &tauri::AppHandle) -> AppResu
 pub async fn compute_hash_with_progress(
     file_uri: &FileUri,
     app: &tauri::AppHandle,
+    cancel: CancellationToken,
     on_progress: impl Fn(u64) + Send + 'static,
 ) -> AppResult<String> {
     let mut file = app
@@ -89,6 +91,9 @@ pub async fn compute_hash_with_progress(
         let mut total_read: u64 = 0;
 
         loop {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
             let n = file.read(&mut buf)?;
             if n == 0 {
                 break;
@@ -114,32 +119,47 @@ pub async fn metadata(file_uri: &FileUri, app: &tauri::AppHandle) -> AppResult<F
         .map_err(|e| AppError::Transfer(format!("Android 获取元数据失败: {e}")))?;
 
     match entry {
-        Entry::File { name, len, .. } => Ok(FileSourceMetadata {
+        Entry::File {
+            name,
+            len,
+            last_modified,
+            ..
+        } => Ok(FileSourceMetadata {
             name,
             size: len,
             is_dir: false,
+            modified: Some(last_modified),
         }),
-        Entry::Dir { name, .. } => Ok(FileSourceMetadata {
+        Entry::Dir {
+            name,
+            last_modified,
+            ..
+        } => Ok(FileSourceMetadata {
             name,
             size: 0,
             is_dir: true,
+            modified: Some(last_modified),
         }),
     }
 }
 
-/// 递归遍历目录，返回所有文件的扁平化列表
+/// 递归遍历目录，返回所有文件（及空目录）的扁平化列表
 ///
 /// 使用栈式迭代避免 async 递归。每层 `read_dir` 是轻量 JNI 调用，直接 await。
+/// 被 exclude 命中的目录不入栈，不会读取其内容。
 pub async fn enumerate_dir(
     file_uri: &FileUri,
     parent_relative_path: &str,
+    scan: &ScanContext,
     app: &tauri::AppHandle,
 ) -> AppResult<Vec<EnumeratedFile>> {
+    let filter = scan.filter();
     let mut files = Vec::new();
     let mut stack: Vec<(FileUri, String)> =
         vec![(file_uri.clone(), parent_relative_path.to_owned())];
 
     while let Some((uri, parent_path)) = stack.pop() {
+        scan.check_cancelled()?;
         let entries: Vec<Entry> = app
             .android_fs_async()
             .read_dir(&uri)
@@ -147,6 +167,27 @@ pub async fn enumerate_dir(
             .map_err(|e| AppError::Transfer(format!("Android 读取目录失败: {e}")))?
             .collect();
 
+        // 空目录作为条目返回，供接收方重建（设置了 include 时省略）
+        if entries.is_empty() && !parent_path.is_empty() {
+            if filter.has_include() {
+                continue;
+            }
+            let name = parent_path
+                .rsplit('/')
+                .next()
+                .unwrap_or(&parent_path)
+                .to_owned();
+            scan.record(0);
+            files.push(EnumeratedFile {
+                name,
+                relative_path: parent_path,
+                source: FileSource::AndroidUri(uri),
+                size: 0,
+                is_directory: true,
+            });
+            continue;
+        }
+
         for entry in entries {
             match entry {
                 Entry::File {
@@ -157,12 +198,20 @@ pub async fn enumerate_dir(
                     } else {
                         format!("{}/{}", parent_path, name)
                     };
-
+                    if filter.is_excluded(&relative_path, &name, false)
+                        || !filter.is_included(&relative_path, &name)
+                        || crate::file_sink::is_part_file(&name)
+                    {
+                        continue;
+                    }
+
+                    scan.record(len);
                     files.push(EnumeratedFile {
                         name,
                         relative_path,
                         source: FileSource::AndroidUri(uri),
                         size: len,
+                        is_directory: false,
                     });
                 }
                 Entry::Dir { uri, name, .. } => {
@@ -171,6 +220,9 @@ pub async fn enumerate_dir(
                     } else {
                         format!("{}/{}", parent_path, name)
                     };
+                    if filter.is_excluded(&sub_path, &name, true) {
+                        continue;
+                    }
                     stack.push((uri, sub_path));
                 }
             }
@@ -0,0 +1,106 @@
+//! 目录扫描的 include/exclude glob 过滤
+//!
+//! 模式按条目在传输中的相对路径（含所选目录名，`/` 分隔）匹配，同时也匹配条目名，
+//! 因此 `.git`、`**/node_modules/**`、`*.log` 都能按直觉生效（`*` 可跨越 `/`）。
+//! - exclude：命中的文件跳过；命中的目录直接剪枝，不再进入
+//! - include：非空时只保留命中的文件，空目录条目一并省略；目录总会进入，不受 include 影响
+//!
+//! 所选来源本身（扫描的根目录）不参与过滤。
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::{AppError, AppResult};
+
+/// include/exclude 过滤规则，默认不过滤
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl ScanFilter {
+    /// 编译 glob 模式（空白模式忽略），模式无效时返回 [`AppError::Config`]
+    pub fn new(include: &[String], exclude: &[String]) -> AppResult<Self> {
+        Ok(Self {
+            include: build_set(include)?,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// 条目是否被排除（目录被排除时不再进入）
+    pub fn is_excluded(&self, relative_path: &str, name: &str, is_dir: bool) -> bool {
+        let Some(exclude) = &self.exclude else {
+            return false;
+        };
+        // `dir/**` 形式的模式需要带尾部 `/` 才能命中目录本身
+        exclude.is_match(relative_path)
+            || exclude.is_match(name)
+            || (is_dir && exclude.is_match(format!("{relative_path}/")))
+    }
+
+    /// 文件是否保留（未设置 include 时总是保留）
+    pub fn is_included(&self, relative_path: &str, name: &str) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative_path) || include.is_match(name))
+    }
+
+    /// 是否设置了 include（此时省略空目录条目）
+    pub fn has_include(&self) -> bool {
+        self.include.is_some()
+    }
+}
+
+fn build_set(patterns: &[String]) -> AppResult<Option<GlobSet>> {
+    let mut builder = GlobSetBuilder::new();
+    let mut empty = true;
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let glob = Glob::new(pattern)
+            .map_err(|e| AppError::Config(format!("无效的 glob 模式 {pattern}: {e}")))?;
+        builder.add(glob);
+        empty = false;
+    }
+    if empty {
+        return Ok(None);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| AppError::Config(format!("编译 glob 模式失败: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_filter_rules() {
+        let filter = ScanFilter::new(
+            &patterns(&["*.rs", " "]),
+            &patterns(&["**/node_modules/**", ".git", "target"]),
+        )
+        .unwrap();
+        assert!(filter.is_excluded("proj/node_modules", "node_modules", true));
+        assert!(filter.is_excluded("proj/web/node_modules/a.js", "a.js", false));
+        assert!(filter.is_excluded("proj/.git", ".git", true));
+        assert!(filter.is_excluded("proj/target", "target", true));
+        assert!(!filter.is_excluded("proj/src", "src", true));
+
+        assert!(filter.is_included("proj/src/main.rs", "main.rs"));
+        assert!(!filter.is_included("proj/README.md", "README.md"));
+
+        let none = ScanFilter::new(&[], &patterns(&[""])).unwrap();
+        assert!(!none.has_include());
+        assert!(!none.is_excluded("proj/node_modules", "node_modules", true));
+        assert!(none.is_included("proj/README.md", "README.md"));
+
+        assert!(matches!(
+            ScanFilter::new(&patterns(&["a[b"]), &[]),
+            Err(AppError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! 内存数据操作
+//!
+//! 数据已在内存中，分块读取直接切片；hash 计算可能较慢，仍放到 `spawn_blocking` 中执行。
+
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::file_source::{FileSourceMetadata, CHUNK_SIZE};
+use crate::{AppError, AppResult};
+
+/// 读取指定分块（以缓冲区实际长度为准校验范围）
+pub fn read_chunk(data: &[u8], chunk_index: u32) -> AppResult<Vec<u8>> {
+    // 空数据：返回空分块
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let offset = chunk_index as usize * CHUNK_SIZE;
+    if offset >= data.len() {
+        return Err(AppError::Transfer(format!(
+            "chunk_index 超出范围: offset={offset}, file_size={}",
+            data.len()
+        )));
+    }
+
+    let end = (offset + CHUNK_SIZE).min(data.len());
+    Ok(data[offset..end].to_vec())
+}
+
+/// 计算 BLAKE3 hash（hex 编码）
+pub async fn compute_hash(data: &Arc<Vec<u8>>) -> AppResult<String> {
+    let data = data.clone();
+    let hash = tokio::task::spawn_blocking(move || blake3::hash(&data)).await?;
+    Ok(hash.to_hex().to_string())
+}
+
+/// 按 chunk 计算 BLAKE3 hash，每处理一个 chunk 调用 `on_progress(已处理字节数)`
+pub async fn compute_hash_with_progress(
+    data: &Arc<Vec<u8>>,
+    cancel: CancellationToken,
+    on_progress: impl Fn(u64) + Send + 'static,
+) -> AppResult<String> {
+    let data = data.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = blake3::Hasher::new();
+        let mut total: u64 = 0;
+        for chunk in data.chunks(CHUNK_SIZE) {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            hasher.update(chunk);
+            total += chunk.len() as u64;
+            on_progress(total);
+        }
+        Ok(hasher.finalize().to_hex().to_string())
+    })
+    .await?
+}
+
+/// 元数据：名称由调用方指定，没有修改时间
+pub fn metadata(data: &[u8], name: &str) -> FileSourceMetadata {
+    FileSourceMetadata {
+        name: name.to_owned(),
+        size: data.len() as u64,
+        is_dir: false,
+        modified: None,
+    }
+}
@@ -1,30 +1,41 @@
 //! 文件来源抽象模块
 //!
-//! 统一处理标准路径和 Android content:// URI 两种文件来源。
+//! 统一处理标准路径、Android content:// URI 与内存数据三种文件来源。
 //! 通过条件编译隔离平台代码，桌面端不编译 Android 相关逻辑。
 
+pub mod filter;
+pub mod memory_ops;
 pub mod path_ops;
 
 #[cfg(target_os = "android")]
 pub mod android_ops;
 
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
 #[cfg(target_os = "android")]
 use tauri_plugin_android_fs::FileUri;
 
-use crate::AppResult;
+pub use filter::ScanFilter;
+
+use crate::{AppError, AppResult};
 
 /// 分块大小：256 KB
 pub const CHUNK_SIZE: usize = 256 * 1024;
 
-/// 文件来源：标准路径 或 Android content:// URI
+/// 扫描进度最短上报间隔
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 文件来源：标准路径、Android content:// URI 或内存数据
 ///
-/// 桌面端仅编译 `Path` 分支；Android 端同时支持 `Path` 和 `AndroidUri`。
+/// 桌面端编译 `Path` 与 `Memory` 分支；Android 端额外支持 `AndroidUri`。
 /// 前端通过 Tauri IPC 传入时，`AndroidUri` 分支的字段与
 /// `tauri-plugin-android-fs-api` 的 `AndroidFsUri` 类型序列化格式一致。
+/// 发往对端的只有 [`FileInfo`](crate::protocol::FileInfo)，`FileSource` 本身不会出现在协议中。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum FileSource {
@@ -35,6 +46,13 @@ pub enum FileSource {
     /// 直接复用 `tauri-plugin-android-fs` 的 `FileUri` 类型
     #[cfg(target_os = "android")]
     AndroidUri(FileUri),
+
+    /// 内存中的数据（剪贴板内容、应用生成的数据等），以 `name` 作为文件名发送
+    ///
+    /// 仅在进程内构造，不参与序列化：前端无法传入，序列化时返回错误，
+    /// 也无法持久化，因此这类传输中断后不能断点续传。
+    #[serde(skip)]
+    Memory { data: Arc<Vec<u8>>, name: String },
 }
 
 /// 文件元数据
@@ -46,12 +64,51 @@ pub struct FileSourceMetadata {
     pub size: u64,
     /// 是否为目录
     pub is_dir: bool,
+    /// 最后修改时间（平台无法提供时为 None）
+    pub modified: Option<SystemTime>,
+}
+
+/// 文件校验结果（`verify_file` 命令返回）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashVerification {
+    /// 是否与期望的 checksum 一致
+    pub matches: bool,
+    /// 实际计算出的 BLAKE3 hash（hex）
+    pub checksum: String,
+}
+
+impl HashVerification {
+    /// 比较实际 hash 与期望值（hex 不区分大小写）
+    pub fn new(checksum: String, expected_hex: &str) -> Self {
+        Self {
+            matches: checksum.eq_ignore_ascii_case(expected_hex.trim()),
+            checksum,
+        }
+    }
+}
+
+/// 目录遍历时对符号链接的处理方式
+///
+/// 跟随符号链接可能进入巨大或循环的目录树，或把目录外的文件带进来，默认跳过。
+/// Android SAF URI 没有符号链接，该选项仅对标准路径生效。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// 忽略所有符号链接
+    #[default]
+    Skip,
+    /// 跟随符号链接（检测到循环时跳过该链接）
+    Follow,
+    /// 指向文件的符号链接按普通文件发送，不进入指向目录的链接
+    IncludeAsFile,
 }
 
 /// 目录遍历后的扁平化文件条目
 ///
 /// 同时用于 `scan_sources` 命令返回和 `prepare_send` 命令输入，
 /// 因此同时派生 Serialize + Deserialize。
+/// 空目录也会作为条目返回（`is_directory = true`，`size = 0`），以便接收方重建。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EnumeratedFile {
@@ -63,24 +120,114 @@ pub struct EnumeratedFile {
     pub source: FileSource,
     /// 文件大小
     pub size: u64,
+    /// 是否为空目录条目
+    #[serde(default)]
+    pub is_directory: bool,
+}
+
+/// scan_sources 进度事件（通过 Tauri Channel 实时推送给前端）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    /// 已发现的文件数（含空目录条目）
+    pub files_found: u64,
+    /// 已统计的字节数
+    pub bytes_found: u64,
+}
+
+type ScanProgressFn = dyn Fn(ScanProgress) + Send + Sync;
+
+/// 目录扫描上下文：取消检查 + 节流的进度上报 + include/exclude 过滤
+///
+/// 一次 scan_sources 的所有来源共用同一个上下文，进度为累计值。
+pub struct ScanContext {
+    cancel: CancellationToken,
+    on_progress: Box<ScanProgressFn>,
+    filter: ScanFilter,
+    /// 累计进度与上次上报时间
+    state: Mutex<(ScanProgress, Option<Instant>)>,
+}
+
+impl Default for ScanContext {
+    /// 不可取消、不上报进度（MCP 等内部调用）
+    fn default() -> Self {
+        Self::new(CancellationToken::new(), |_| {})
+    }
+}
+
+impl ScanContext {
+    pub fn new(
+        cancel: CancellationToken,
+        on_progress: impl Fn(ScanProgress) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            cancel,
+            on_progress: Box::new(on_progress),
+            filter: ScanFilter::default(),
+            state: Mutex::new((ScanProgress::default(), None)),
+        }
+    }
+
+    /// 设置目录遍历时的过滤规则（默认不过滤）
+    pub fn with_filter(mut self, filter: ScanFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn filter(&self) -> &ScanFilter {
+        &self.filter
+    }
+
+    /// 已取消时返回 [`AppError::Cancelled`]
+    pub fn check_cancelled(&self) -> AppResult<()> {
+        if self.cancel.is_cancelled() {
+            Err(AppError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 记录一个条目，距上次上报超过 [`SCAN_PROGRESS_INTERVAL`] 时推送进度
+    pub fn record(&self, size: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let (progress, last_emit) = &mut *state;
+        progress.files_found += 1;
+        progress.bytes_found += size;
+        if last_emit.is_none_or(|t| t.elapsed() >= SCAN_PROGRESS_INTERVAL) {
+            *last_emit = Some(Instant::now());
+            (self.on_progress)(*progress);
+        }
+    }
+
+    /// 推送最终进度
+    pub fn finish(&self) {
+        if let Ok(state) = self.state.lock() {
+            (self.on_progress)(state.0);
+        }
+    }
 }
 
 impl FileSource {
     /// 读取文件的指定分块
     ///
     /// `file_size` 用于验证 chunk_index 范围和计算最后一块的读取量。
+    /// `app` 仅 Android content URI 需要，桌面路径可传 `None`。
     pub async fn read_chunk(
         &self,
         file_size: u64,
         chunk_index: u32,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
     ) -> AppResult<Vec<u8>> {
         match self {
             Self::Path { path } => path_ops::read_chunk(path, file_size, chunk_index).await,
             #[cfg(target_os = "android")]
             Self::AndroidUri(file_uri) => {
-                android_ops::read_chunk(file_uri, file_size, chunk_index, app).await
+                android_ops::read_chunk(file_uri, file_size, chunk_index, require_app(app)?)
+                    .await
             }
+            Self::Memory { data, .. } => memory_ops::read_chunk(data, chunk_index),
         }
     }
 
@@ -93,22 +240,58 @@ impl FileSource {
             Self::Path { path } => path_ops::compute_hash(path).await,
             #[cfg(target_os = "android")]
             Self::AndroidUri(file_uri) => android_ops::compute_hash(file_uri, app).await,
+            Self::Memory { data, .. } => memory_ops::compute_hash(data).await,
         }
     }
 
     /// 流式计算 BLAKE3 hash，每读取一个 chunk 调用 `on_progress(当前文件已读字节数)`
+    ///
+    /// `app` 仅 Android content URI 需要，桌面路径可传 `None`。
+    /// 每个 chunk 之间检查 `cancel`，取消后返回 [`AppError::Cancelled`](crate::AppError::Cancelled)。
     pub async fn compute_hash_with_progress(
         &self,
-        #[allow(unused_variables)] app: &tauri::AppHandle,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
+        cancel: CancellationToken,
         on_progress: impl Fn(u64) + Send + 'static,
     ) -> AppResult<String> {
         match self {
             Self::Path { path } => {
-                path_ops::compute_hash_with_progress(path, on_progress).await
+                path_ops::compute_hash_with_progress(path, cancel, on_progress).await
             }
             #[cfg(target_os = "android")]
             Self::AndroidUri(file_uri) => {
-                android_ops::compute_hash_with_progress(file_uri, app, on_progress).await
+                android_ops::compute_hash_with_progress(
+                    file_uri,
+                    require_app(app)?,
+                    cancel,
+                    on_progress,
+                )
+                .await
+            }
+            Self::Memory { data, .. } => {
+                memory_ops::compute_hash_with_progress(data, cancel, on_progress).await
+            }
+        }
+    }
+
+    /// 重新计算 BLAKE3 hash 并与期望的 checksum 比较
+    ///
+    /// `app` 仅 Android content URI 需要，桌面路径可传 `None`。
+    pub async fn verify_hash(
+        &self,
+        expected_hex: &str,
+        #[allow(unused_variables)] app: Option<&tauri::AppHandle>,
+    ) -> AppResult<HashVerification> {
+        match self {
+            Self::Path { path } => path_ops::verify_hash(path, expected_hex).await,
+            #[cfg(target_os = "android")]
+            Self::AndroidUri(file_uri) => {
+                let checksum = android_ops::compute_hash(file_uri, require_app(app)?).await?;
+                Ok(HashVerification::new(checksum, expected_hex))
+            }
+            Self::Memory { data, .. } => {
+                let checksum = memory_ops::compute_hash(data).await?;
+                Ok(HashVerification::new(checksum, expected_hex))
             }
         }
     }
@@ -122,23 +305,33 @@ impl FileSource {
             Self::Path { path } => path_ops::metadata(path).await,
             #[cfg(target_os = "android")]
             Self::AndroidUri(file_uri) => android_ops::metadata(file_uri, app).await,
+            Self::Memory { data, name } => Ok(memory_ops::metadata(data, name)),
         }
     }
 
     /// 递归遍历目录，返回所有文件的扁平化列表
     ///
     /// `parent_relative_path` 是当前目录在传输中的相对路径前缀。
+    /// 每发现一个条目向 `scan` 记录一次，取消后返回 [`AppError::Cancelled`]。
+    /// 内存数据不是目录，返回错误。
     pub async fn enumerate_dir(
         &self,
         parent_relative_path: &str,
+        symlinks: SymlinkPolicy,
+        scan: &Arc<ScanContext>,
         #[allow(unused_variables)] app: &tauri::AppHandle,
     ) -> AppResult<Vec<EnumeratedFile>> {
         match self {
-            Self::Path { path } => path_ops::enumerate_dir(path, parent_relative_path).await,
+            Self::Path { path } => {
+                path_ops::enumerate_dir(path, parent_relative_path, symlinks, scan.clone()).await
+            }
             #[cfg(target_os = "android")]
             Self::AndroidUri(file_uri) => {
-                android_ops::enumerate_dir(file_uri, parent_relative_path, app).await
+                android_ops::enumerate_dir(file_uri, parent_relative_path, scan, app).await
             }
+            Self::Memory { name, .. } => Err(AppError::Transfer(format!(
+                "内存数据不是目录，无法遍历: {name}"
+            ))),
         }
     }
 }
@@ -151,6 +344,12 @@ pub fn calc_total_chunks(file_size: u64) -> u32 {
     file_size.div_ceil(CHUNK_SIZE as u64) as u32
 }
 
+/// Android 文件操作必须持有 AppHandle，缺失时返回错误
+#[cfg(target_os = "android")]
+pub(crate) fn require_app(app: Option<&tauri::AppHandle>) -> AppResult<&tauri::AppHandle> {
+    app.ok_or_else(|| crate::AppError::Transfer("Android 文件操作缺少 AppHandle".into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,4 +362,65 @@ mod tests {
         assert_eq!(calc_total_chunks(CHUNK_SIZE as u64 + 1), 2);
         assert_eq!(calc_total_chunks(CHUNK_SIZE as u64 * 10), 10);
     }
+
+    #[tokio::test]
+    async fn test_verify_hash_detects_tampering() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_verify_file");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("received.bin");
+        let mut data = vec![0x5au8; CHUNK_SIZE + 100];
+        std::fs::write(&path, &data).unwrap();
+
+        let source = FileSource::Path { path: path.clone() };
+        let expected = blake3::hash(&data).to_hex().to_string();
+        let ok = source
+            .verify_hash(&expected.to_uppercase(), None)
+            .await
+            .unwrap();
+        assert!(ok.matches);
+        assert_eq!(ok.checksum, expected);
+
+        // 篡改一个字节后校验失败
+        data[CHUNK_SIZE] ^= 1;
+        std::fs::write(&path, &data).unwrap();
+        let tampered = source.verify_hash(&expected, None).await.unwrap();
+        assert!(!tampered.matches);
+        assert_ne!(tampered.checksum, expected);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_memory_source() {
+        let mut data = vec![0x3cu8; CHUNK_SIZE * 2 + 10];
+        data[CHUNK_SIZE] = 0xff;
+        let source = FileSource::Memory {
+            data: Arc::new(data.clone()),
+            name: "clipboard.txt".into(),
+        };
+        let size = data.len() as u64;
+
+        let last = source.read_chunk(size, 2, None).await.unwrap();
+        assert_eq!(last, data[CHUNK_SIZE * 2..]);
+        let middle = source.read_chunk(size, 1, None).await.unwrap();
+        assert_eq!(middle, data[CHUNK_SIZE..CHUNK_SIZE * 2]);
+        assert!(source.read_chunk(size, 3, None).await.is_err());
+
+        let expected = blake3::hash(&data).to_hex().to_string();
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let checksum = source
+            .compute_hash_with_progress(None, CancellationToken::new(), {
+                let progress = progress.clone();
+                move |read| progress.lock().unwrap().push(read)
+            })
+            .await
+            .unwrap();
+        assert_eq!(checksum, expected);
+        assert_eq!(progress.lock().unwrap().last(), Some(&size));
+        assert!(source.verify_hash(&expected, None).await.unwrap().matches);
+
+        // 本地专用，不能序列化
+        assert!(serde_json::to_string(&source).is_err());
+    }
 }
@@ -3,8 +3,15 @@
 //! 所有阻塞 I/O 操作通过 `tokio::task::spawn_blocking` 包装为异步。
 
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::file_source::{EnumeratedFile, FileSource, FileSourceMetadata, CHUNK_SIZE};
+use tokio_util::sync::CancellationToken;
+
+use crate::file_sink::is_part_file;
+use crate::file_source::{
+    EnumeratedFile, FileSource, FileSourceMetadata, HashVerification, ScanContext, SymlinkPolicy,
+    CHUNK_SIZE,
+};
 use crate::{AppError, AppResult};
 
 // ============ FileSource 分派方法 ============
@@ -24,11 +31,14 @@ pub async fn compute_hash(path: &Path) -> AppResult<String> {
 /// 流式计算 BLAKE3 hash，每读取一个 chunk 调用 `on_progress(已读字节数)`
 pub async fn compute_hash_with_progress(
     path: &Path,
+    cancel: CancellationToken,
     on_progress: impl Fn(u64) + Send + 'static,
 ) -> AppResult<String> {
     let path = path.to_path_buf();
-    tokio::task::spawn_blocking(move || compute_hash_sync_with_progress(&path, on_progress))
-        .await?
+    tokio::task::spawn_blocking(move || {
+        compute_hash_sync_with_progress(&path, &cancel, on_progress)
+    })
+    .await?
 }
 
 /// 获取文件或目录的元数据
@@ -43,17 +53,20 @@ pub async fn metadata(path: &Path) -> AppResult<FileSourceMetadata> {
         name,
         size: if meta.is_file() { meta.len() } else { 0 },
         is_dir: meta.is_dir(),
+        modified: meta.modified().ok(),
     })
 }
 
-/// 递归遍历目录，返回所有文件的扁平化列表
+/// 递归遍历目录，返回所有文件（及空目录）的扁平化列表
 pub async fn enumerate_dir(
     path: &Path,
     parent_relative_path: &str,
+    symlinks: SymlinkPolicy,
+    scan: Arc<ScanContext>,
 ) -> AppResult<Vec<EnumeratedFile>> {
     let path = path.to_path_buf();
     let parent = parent_relative_path.to_owned();
-    tokio::task::spawn_blocking(move || enumerate_dir_sync(&path, &parent)).await?
+    tokio::task::spawn_blocking(move || enumerate_dir_sync(&path, &parent, symlinks, &scan)).await?
 }
 
 // ============ 接收方使用的独立方法 ============
@@ -64,13 +77,13 @@ pub async fn write_chunk(path: &Path, offset: u64, data: Vec<u8>) -> AppResult<(
     tokio::task::spawn_blocking(move || write_chunk_sync(&path, offset, &data)).await?
 }
 
-/// 校验文件的 BLAKE3 checksum
-pub async fn verify_hash(path: &Path, expected_hex: &str) -> AppResult<bool> {
+/// 校验文件的 BLAKE3 checksum，同时返回实际计算出的 hash
+pub async fn verify_hash(path: &Path, expected_hex: &str) -> AppResult<HashVerification> {
     let path = path.to_path_buf();
     let expected = expected_hex.to_owned();
     tokio::task::spawn_blocking(move || {
         let actual = compute_hash_sync(&path)?;
-        Ok(actual == expected)
+        Ok(HashVerification::new(actual, &expected))
     })
     .await?
 }
@@ -113,6 +126,7 @@ fn compute_hash_sync(path: &Path) -> AppResult<String> {
 
 fn compute_hash_sync_with_progress(
     path: &Path,
+    cancel: &CancellationToken,
     on_progress: impl Fn(u64),
 ) -> AppResult<String> {
     use std::io::Read;
@@ -123,6 +137,9 @@ fn compute_hash_sync_with_progress(
     let mut total_read: u64 = 0;
 
     loop {
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
         let n = file.read(&mut buf)?;
         if n == 0 {
             break;
@@ -135,18 +152,52 @@ fn compute_hash_sync_with_progress(
     Ok(hasher.finalize().to_hex().to_string())
 }
 
-fn enumerate_dir_sync(path: &Path, parent_relative_path: &str) -> AppResult<Vec<EnumeratedFile>> {
-    use path_slash::PathExt as _;
+/// 遍历目录
+///
+/// `Follow` 时由 walkdir 检测循环：指回祖先目录的链接会产生错误条目，随其他错误一并跳过。
+/// 被 exclude 命中的目录通过 `filter_entry` 剪枝，不会进入。
+fn enumerate_dir_sync(
+    path: &Path,
+    parent_relative_path: &str,
+    symlinks: SymlinkPolicy,
+    scan: &ScanContext,
+) -> AppResult<Vec<EnumeratedFile>> {
     use walkdir::WalkDir;
 
     let mut files = Vec::new();
+    let filter = scan.filter();
 
     for entry in WalkDir::new(path)
-        .follow_links(true)
+        .follow_links(symlinks == SymlinkPolicy::Follow)
         .into_iter()
+        .filter_entry(|e| {
+            // 所选目录本身不参与过滤
+            e.depth() == 0
+                || !filter.is_excluded(
+                    &relative_path_of(path, e.path(), parent_relative_path),
+                    &e.file_name().to_string_lossy(),
+                    e.file_type().is_dir(),
+                )
+        })
         .filter_map(|e| e.ok())
     {
-        if entry.file_type().is_dir() {
+        scan.check_cancelled()?;
+
+        // 未跟随的符号链接：IncludeAsFile 时仅保留指向文件的链接（按目标文件大小）
+        let link_size = if entry.path_is_symlink() && symlinks != SymlinkPolicy::Follow {
+            match std::fs::metadata(entry.path()) {
+                Ok(meta) if symlinks == SymlinkPolicy::IncludeAsFile && meta.is_file() => {
+                    Some(meta.len())
+                }
+                _ => continue,
+            }
+        } else {
+            None
+        };
+
+        let is_directory = entry.file_type().is_dir();
+        // 非空目录会由其中的文件隐式创建，只需保留空目录；设置了 include 时不保留空目录
+        if is_directory && (filter.has_include() || !is_empty_dir(entry.path())) {
             continue;
         }
 
@@ -156,15 +207,24 @@ fn enumerate_dir_sync(path: &Path, parent_relative_path: &str) -> AppResult<Vec<
             .map(|n| n.to_string_lossy().into_owned())
             .unwrap_or_default();
 
-        let sub_path =
-            pathdiff::diff_paths(entry_path, path).unwrap_or_else(|| entry_path.to_path_buf());
-        let relative_path = if parent_relative_path.is_empty() {
-            sub_path.to_slash_lossy().into_owned()
+        let relative_path = relative_path_of(path, entry_path, parent_relative_path);
+        if relative_path.is_empty() {
+            continue;
+        }
+        if !is_directory && !filter.is_included(&relative_path, &name) {
+            continue;
+        }
+        // 接收中的 .part 临时文件（保存目录位于所选目录内时，遍历期间可能不断出现）
+        if !is_directory && is_part_file(&name) {
+            continue;
+        }
+
+        let size = if is_directory {
+            0
         } else {
-            format!("{}/{}", parent_relative_path, sub_path.to_slash_lossy())
+            link_size.unwrap_or_else(|| entry.metadata().map(|m| m.len()).unwrap_or(0))
         };
-
-        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        scan.record(size);
 
         files.push(EnumeratedFile {
             name,
@@ -173,12 +233,41 @@ fn enumerate_dir_sync(path: &Path, parent_relative_path: &str) -> AppResult<Vec<
                 path: entry_path.to_path_buf(),
             },
             size,
+            is_directory,
         });
     }
 
     Ok(files)
 }
 
+/// 条目在传输中的相对路径：`parent_relative_path` + 相对于遍历根目录的路径（`/` 分隔）
+fn relative_path_of(root: &Path, entry_path: &Path, parent_relative_path: &str) -> String {
+    use path_slash::PathExt as _;
+
+    let sub_path =
+        pathdiff::diff_paths(entry_path, root).unwrap_or_else(|| entry_path.to_path_buf());
+    let sub_path = sub_path.to_slash_lossy();
+    match (parent_relative_path.is_empty(), sub_path.is_empty()) {
+        (true, _) => sub_path.into_owned(),
+        // 根目录本身
+        (false, true) => parent_relative_path.to_owned(),
+        (false, false) => format!("{}/{}", parent_relative_path, sub_path),
+    }
+}
+
+/// `inner` 是否为 `dir` 本身或位于其中（尽量按规范化路径比较，避免符号链接与 `..` 干扰）
+pub fn is_within(inner: &Path, dir: &Path) -> bool {
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    canonical(inner).starts_with(canonical(dir))
+}
+
+/// 判断目录是否为空（读取失败视为非空，避免误报）
+fn is_empty_dir(path: &Path) -> bool {
+    std::fs::read_dir(path)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false)
+}
+
 fn write_chunk_sync(path: &Path, offset: u64, data: &[u8]) -> AppResult<()> {
     use std::io::{Seek, SeekFrom, Write};
 
@@ -244,9 +333,14 @@ mod tests {
         assert!(!hash.is_empty());
 
         // verify_hash 应该匹配
-        assert!(verify_hash(&file_path, &hash).await.unwrap());
+        assert!(verify_hash(&file_path, &hash).await.unwrap().matches);
         // 错误的 hash 不匹配
-        assert!(!verify_hash(&file_path, "0000000000000000").await.unwrap());
+        assert!(
+            !verify_hash(&file_path, "0000000000000000")
+                .await
+                .unwrap()
+                .matches
+        );
 
         let _ = std::fs::remove_dir_all(&dir);
     }
@@ -287,7 +381,9 @@ mod tests {
         std::fs::write(dir.join("a.txt"), "aaa").unwrap();
         std::fs::write(sub.join("b.txt"), "bbb").unwrap();
 
-        let files = enumerate_dir(&dir, "root").await.unwrap();
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::default(), Arc::default())
+            .await
+            .unwrap();
         assert_eq!(files.len(), 2);
 
         let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
@@ -302,6 +398,224 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
     }
 
+    #[tokio::test]
+    async fn test_enumerate_dir_with_empty_entries() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_enum_empty");
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::create_dir_all(dir.join("empty_dir"));
+        let _ = std::fs::create_dir_all(dir.join("full_dir"));
+        std::fs::write(dir.join("full_dir/zero.bin"), "").unwrap();
+
+        let entries = enumerate_dir(&dir, "root", SymlinkPolicy::default(), Arc::default())
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let empty_dir = entries.iter().find(|e| e.is_directory).unwrap();
+        assert_eq!(empty_dir.relative_path, "root/empty_dir");
+        assert_eq!(empty_dir.size, 0);
+
+        // 零字节文件照常作为文件返回，非空目录不单独出现
+        let zero = entries.iter().find(|e| !e.is_directory).unwrap();
+        assert_eq!(zero.relative_path, "root/full_dir/zero.bin");
+        assert_eq!(zero.size, 0);
+
+        // 根目录本身为空时返回根目录条目
+        let root_only = enumerate_dir(
+            &dir.join("empty_dir"),
+            "empty_dir",
+            SymlinkPolicy::Skip,
+            Arc::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(root_only.len(), 1);
+        assert!(root_only[0].is_directory);
+        assert_eq!(root_only[0].relative_path, "empty_dir");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_enumerate_dir_symlink_outside_tree() {
+        use std::os::unix::fs::symlink;
+
+        let base = std::env::temp_dir().join("swarmdrop_test_enum_symlink");
+        let _ = std::fs::remove_dir_all(&base);
+        let dir = base.join("tree");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(dir.join("a.txt"), "aaa").unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        symlink(&outside, dir.join("outside_dir")).unwrap();
+        symlink(outside.join("secret.txt"), dir.join("secret_link.txt")).unwrap();
+
+        // 默认跳过：目录外的文件不会被带进来
+        let skipped = enumerate_dir(&dir, "root", SymlinkPolicy::default(), Arc::default())
+            .await
+            .unwrap();
+        let paths: Vec<&str> = skipped.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["root/a.txt"]);
+
+        // 按文件包含：只保留指向文件的链接，大小取目标文件
+        let as_file = enumerate_dir(&dir, "root", SymlinkPolicy::IncludeAsFile, Arc::default())
+            .await
+            .unwrap();
+        assert_eq!(as_file.len(), 2);
+        let link = as_file
+            .iter()
+            .find(|f| f.relative_path == "root/secret_link.txt")
+            .unwrap();
+        assert_eq!(link.size, 6);
+        assert!(!link.is_directory);
+
+        let followed = enumerate_dir(&dir, "root", SymlinkPolicy::Follow, Arc::default())
+            .await
+            .unwrap();
+        assert!(followed
+            .iter()
+            .any(|f| f.relative_path == "root/outside_dir/secret.txt"));
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_enumerate_dir_symlink_cycle() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join("swarmdrop_test_enum_cycle");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/b.txt"), "bbb").unwrap();
+        symlink(&dir, dir.join("sub/loop")).unwrap();
+
+        // 跟随链接时遇到循环也能正常结束，循环链接本身被跳过
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::Follow, Arc::default())
+            .await
+            .unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["root/sub/b.txt"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_enumerate_dir_progress_and_cancel() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_enum_progress");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for i in 0..50 {
+            std::fs::write(dir.join(format!("sub/{i}.txt")), vec![b'x'; i]).unwrap();
+        }
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let scan = Arc::new(ScanContext::new(CancellationToken::new(), move |p| {
+            recorder.lock().unwrap().push(p);
+        }));
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::Skip, scan.clone())
+            .await
+            .unwrap();
+        scan.finish();
+
+        let events = events.lock().unwrap();
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].files_found <= w[1].files_found));
+        let last = events.last().unwrap();
+        assert_eq!(last.files_found, files.len() as u64);
+        assert_eq!(last.bytes_found, (0..50).sum::<u64>());
+
+        // 已取消的扫描立即返回 Cancelled
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let scan = Arc::new(ScanContext::new(cancel, |_| {}));
+        let result = enumerate_dir(&dir, "root", SymlinkPolicy::Skip, scan).await;
+        assert!(matches!(result, Err(AppError::Cancelled)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_enumerate_dir_with_filter() {
+        use crate::file_source::ScanFilter;
+
+        let dir = std::env::temp_dir().join("swarmdrop_test_enum_filter");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("web/node_modules/pkg")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("README.md"), "readme").unwrap();
+        std::fs::write(dir.join("web/index.js"), "js").unwrap();
+        std::fs::write(dir.join("web/node_modules/pkg/lib.rs"), "lib").unwrap();
+
+        let scan_with = |include: &[&str], exclude: &[&str]| {
+            let to_vec = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let filter = ScanFilter::new(&to_vec(include), &to_vec(exclude)).unwrap();
+            Arc::new(ScanContext::default().with_filter(filter))
+        };
+        let sorted_paths = |files: Vec<EnumeratedFile>| {
+            let mut paths: Vec<String> = files.into_iter().map(|f| f.relative_path).collect();
+            paths.sort();
+            paths
+        };
+
+        // 命中的目录整棵剪枝，不进入也不计入扫描进度
+        let scan = scan_with(&[], &["**/node_modules/**"]);
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::Skip, scan.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            sorted_paths(files),
+            vec![
+                "root/README.md",
+                "root/empty",
+                "root/src/main.rs",
+                "root/web/index.js"
+            ]
+        );
+        assert_eq!(scan.state.lock().unwrap().0.files_found, 4);
+
+        // include 只保留命中的文件，空目录条目省略；被排除目录中的文件即使命中 include 也不保留
+        let scan = scan_with(&["*.rs"], &["**/node_modules/**"]);
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::Skip, scan)
+            .await
+            .unwrap();
+        assert_eq!(sorted_paths(files), vec!["root/src/main.rs"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_enumerate_dir_skips_part_files() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_enum_part");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("inbox")).unwrap();
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        std::fs::write(dir.join("inbox/video.mp4.part"), vec![0u8; 64]).unwrap();
+        std::fs::write(dir.join("inbox/Makefile.part"), "").unwrap();
+
+        let scan = Arc::new(ScanContext::default());
+        let files = enumerate_dir(&dir, "root", SymlinkPolicy::Skip, scan.clone())
+            .await
+            .unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["root/a.txt"]);
+        assert_eq!(scan.state.lock().unwrap().0.files_found, 1);
+
+        // 保存目录位于来源目录内
+        assert!(is_within(&dir.join("inbox"), &dir));
+        assert!(is_within(&dir, &dir));
+        assert!(is_within(&dir.join("inbox/../inbox"), &dir));
+        assert!(!is_within(&dir, &dir.join("inbox")));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[tokio::test]
     async fn test_write_chunk() {
         let dir = std::env::temp_dir().join("swarmdrop_test_write");
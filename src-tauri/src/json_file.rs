@@ -0,0 +1,49 @@
+//! 应用数据目录下的 JSON 文件持久化
+//!
+//! 设置、流量统计、屏蔽列表等小文件整体读写：加载时文件不存在或损坏都不阻止启动，
+//! 由调用方回退到默认值；损坏时记录日志，下次保存会覆盖。
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::AppResult;
+
+/// 读取 JSON 文件，不存在或损坏时返回 None（损坏时以 `label` 记录日志）
+pub(crate) fn load<T: DeserializeOwned>(path: &Path, label: &str) -> Option<T> {
+    let data = std::fs::read(path).ok()?;
+    serde_json::from_slice(&data)
+        .inspect_err(|e| warn!("{}文件损坏，已忽略: {}", label, e))
+        .ok()
+}
+
+/// 整体写入 JSON 文件
+pub(crate) fn save<T: Serialize + ?Sized>(path: &Path, value: &T) -> AppResult<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_json_file");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.json");
+
+        assert_eq!(load::<Vec<u32>>(&path, "测试"), None);
+
+        save(&path, &[1u32, 2, 3]).unwrap();
+        assert_eq!(load::<Vec<u32>>(&path, "测试"), Some(vec![1, 2, 3]));
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert_eq!(load::<Vec<u32>>(&path, "测试"), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -7,31 +7,50 @@ pub(crate) mod pairing;
 pub mod protocol;
 pub(crate) mod transfer;
 pub(crate) mod database;
+pub(crate) mod diagnostics;
+pub(crate) mod json_file;
 pub(crate) mod mcp;
+pub(crate) mod settings;
 pub use error::{AppError, AppResult};
 
 pub mod file_sink;
 pub mod file_source;
 mod mobile;
 
+use diagnostics::log_level::LogLevelHandle;
 use tauri::Manager;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
+
+/// 初始化日志，返回运行时调整日志级别用的句柄
+fn init_tracing() -> LogLevelHandle {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(diagnostics::log_level::DEFAULT_LOG_FILTER));
+    // 过滤器放在最内层并可重载，对下面所有 layer 生效
+    let (filter, handle) = reload::Layer::new(filter);
 
-fn init_tracing() {
     tracing_subscriber::registry()
+        .with(filter)
         .with(fmt::layer())
+        // 同时保留最近的日志，供应用内日志页和诊断包使用
         .with(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("swarmdrop=debug,swarm_p2p_core=debug")),
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(&diagnostics::log_buffer::LOG_BUFFER),
         )
+        // 推送给前端诊断面板（setup 中 attach AppHandle 后生效）
+        .with(&diagnostics::log_stream::LOG_STREAM)
         .init();
+
+    LogLevelHandle::new(handle)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    init_tracing();
+    let log_level = init_tracing();
 
     let builder = tauri::Builder::default()
+        .manage(log_level)
+        .manage(network::power_save::PowerSave::default())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_fs::init())
@@ -41,14 +60,27 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_process::init())
-        .plugin(mobile::init());
+        .plugin(mobile::init())
+        .plugin(mobile::init_transfer_service());
 
     // Android 文件选择插件
     #[cfg(target_os = "android")]
     let builder = builder.plugin(tauri_plugin_android_fs::init());
 
+    // 移动端切到后台时开启省电模式，回到前台时关闭（桌面端需要保持可被发现，不自动切换）
+    #[cfg(mobile)]
+    let builder = builder.on_window_event(|window, event| {
+        if let tauri::WindowEvent::Focused(focused) = event {
+            window
+                .state::<network::power_save::PowerSave>()
+                .set(!focused);
+        }
+    });
+
     builder
         .setup(|app| {
+            diagnostics::log_stream::LOG_STREAM.attach(app.handle().clone());
+
             // updater 在 setup 中注册，移动端不支持时容错跳过
             if let Err(e) = app
                 .handle()
@@ -69,6 +101,13 @@ pub fn run() {
 
             app.manage(db);
 
+            // 后端持久化的设置（默认保存目录等）
+            let settings_path = app
+                .path()
+                .app_local_data_dir()?
+                .join(settings::SETTINGS_FILE);
+            app.manage(settings::SettingsStore::load(settings_path));
+
             // 初始化 MCP Server 状态容器
             app.manage(mcp::server::McpServerState::default());
 
@@ -77,30 +116,69 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::start,
             commands::shutdown,
+            commands::validate_bootstrap_nodes,
             commands::generate_keypair,
             commands::register_keypair,
             commands::generate_pairing_code,
+            commands::revoke_pairing_code,
+            commands::generate_pairing_payload,
+            commands::pair_from_payload,
             commands::get_device_info,
             commands::request_pairing,
+            commands::request_pairing_lan,
             commands::respond_pairing_request,
             commands::remove_paired_device,
+            commands::set_device_nickname,
+            commands::block_peer,
+            commands::unblock_peer,
+            commands::list_blocked,
+            commands::get_pairing_verification,
+            commands::confirm_pairing,
+            commands::abort_pairing,
             commands::list_devices,
             commands::get_network_status,
+            commands::dial_peer,
+            commands::reconnect_paired_devices,
+            commands::reconnect_device,
+            commands::get_device,
+            commands::get_peer_diagnostics,
+            commands::get_traffic_stats,
+            commands::reset_traffic_stats,
+            commands::export_diagnostics,
+            commands::get_diagnostics_snapshot,
+            commands::get_recent_logs,
+            commands::clear_logs,
+            commands::get_log_level,
+            commands::set_log_level,
+            commands::get_log_stream_level,
+            commands::set_log_stream_level,
+            commands::get_power_save_mode,
+            commands::set_power_save_mode,
             commands::install_update,
+            commands::get_settings,
+            commands::set_settings,
             commands::scan_sources,
             commands::prepare_send,
+            commands::cancel_prepare,
+            commands::refresh_prepared,
+            commands::discard_prepared,
             commands::start_send,
+            commands::start_send_multi,
+            commands::estimate_transfer,
             commands::accept_receive,
             commands::reject_receive,
             commands::cancel_send,
             commands::cancel_receive,
+            commands::get_pending_offers,
             commands::get_transfer_history,
             commands::get_transfer_session,
             commands::delete_transfer_session,
             commands::clear_transfer_history,
             commands::pause_transfer,
             commands::resume_transfer,
+            commands::verify_file,
             commands::resolve_android_dir_uri,
+            commands::reveal_path,
             commands::get_mcp_status,
             commands::start_mcp_server,
             commands::stop_mcp_server,
@@ -3,6 +3,7 @@
 //! 提供 3 个 Tool：get_network_status、list_available_devices、send_files
 
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::CallToolResult;
@@ -12,8 +13,9 @@ use tauri::Manager;
 
 use super::McpHandler;
 use crate::device::{DeviceFilter, DeviceStatus};
-use crate::file_source::{EnumeratedFile, FileSource};
+use crate::file_source::{EnumeratedFile, FileSource, SymlinkPolicy};
 use crate::network::NetManagerState;
+use crate::transfer::offer::generate_id;
 
 /// 辅助：构造 MCP 错误结果（isError: true）
 fn mcp_error(msg: impl std::fmt::Display) -> Result<CallToolResult, ErrorData> {
@@ -91,7 +93,7 @@ impl McpHandler {
         get_net_manager!(self, _state, guard);
         let manager = guard.as_ref().unwrap();
 
-        let devices = manager.devices().get_devices(DeviceFilter::Paired);
+        let devices = manager.devices().get_devices(&DeviceFilter::Paired.into());
         let available: Vec<McpDevice> = devices
             .into_iter()
             .filter(|d| matches!(d.status, DeviceStatus::Online))
@@ -137,7 +139,12 @@ impl McpHandler {
                     .unwrap_or_default();
                 let source = FileSource::Path { path: path.clone() };
                 let dir_files = source
-                    .enumerate_dir(&dir_name, &self.app)
+                    .enumerate_dir(
+                        &dir_name,
+                        SymlinkPolicy::default(),
+                        &Arc::default(),
+                        &self.app,
+                    )
                     .await
                     .map_err(|e| ErrorData::internal_error(format!("遍历目录失败: {e}"), None))?;
                 entries.extend(dir_files);
@@ -151,6 +158,7 @@ impl McpHandler {
                     name,
                     source: FileSource::Path { path },
                     size: meta.len(),
+                    is_directory: false,
                 });
             }
         }
@@ -163,7 +171,7 @@ impl McpHandler {
         let on_progress = tauri::ipc::Channel::new(|_| Ok(()));
         let prepared = manager
             .transfer()
-            .prepare(entries, &self.app, on_progress)
+            .prepare(generate_id(), entries, &self.app, on_progress)
             .await
             .map_err(|e| ErrorData::internal_error(format!("准备传输失败: {e}"), None))?;
 
@@ -175,17 +183,26 @@ impl McpHandler {
         // 查询对端设备名
         let peer_name = manager
             .devices()
-            .get_devices(DeviceFilter::Paired)
+            .get_devices(&DeviceFilter::Paired.into())
             .into_iter()
             .find(|d| d.peer_id.to_string() == params.peer_id)
             .map(|d| d.os_info.hostname)
             .unwrap_or_else(|| params.peer_id.clone());
 
         // send_offer
-        let result = manager
-            .transfer_arc()
-            .send_offer(&prepared_id, &params.peer_id, &peer_name, &all_file_ids, self.app.clone())
-            .map_err(|e| ErrorData::internal_error(format!("发送 Offer 失败: {e}"), None))?;
+        let result = manager.transfer_arc().send_offer(
+            &prepared_id,
+            &params.peer_id,
+            &peer_name,
+            &all_file_ids,
+            false,
+            None,
+            self.app.clone(),
+        );
+        // 发送任务已持有所需的文件列表，MCP 不会再次发送同一批文件
+        manager.transfer().discard_prepared(&prepared_id);
+        let result =
+            result.map_err(|e| ErrorData::internal_error(format!("发送 Offer 失败: {e}"), None))?;
 
         let response = SendFilesResponse {
             session_id: result.session_id.to_string(),
@@ -1,7 +1,7 @@
 //! Android 移动端插件桥接
 //!
 //! 通过 Tauri Plugin Builder 注册 Kotlin 插件到运行时，
-//! 实际业务逻辑在 UpdaterPlugin.kt 中实现。
+//! 实际业务逻辑在 UpdaterPlugin.kt / TransferServicePlugin.kt 中实现。
 
 use tauri::{
     plugin::{Builder, TauriPlugin},
@@ -31,6 +31,54 @@ impl<R: Runtime> UpdaterPlugin<R> {
     }
 }
 
+/// Android 传输前台服务插件句柄（仅 Android 编译）
+///
+/// 作为 [`KeepAlive`](crate::transfer::keep_alive::KeepAlive) 实现交给总体进度任务，
+/// 有活跃会话期间保持前台服务，避免应用切到后台后传输被系统挂起。
+#[cfg(target_os = "android")]
+#[derive(Clone)]
+pub struct TransferServicePlugin<R: Runtime>(tauri::plugin::PluginHandle<R>);
+
+#[cfg(target_os = "android")]
+impl<R: Runtime> TransferServicePlugin<R> {
+    fn run(&self, command: &str, payload: impl serde::Serialize) {
+        let result: Result<serde_json::Value, _> = self.0.run_mobile_plugin(command, payload);
+        if let Err(e) = result {
+            tracing::warn!("前台服务调用失败 {}: {}", command, e);
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+impl<R: Runtime> crate::transfer::keep_alive::KeepAlive for TransferServicePlugin<R> {
+    fn start(&self) {
+        self.run("startTransferService", ());
+    }
+
+    fn update(&self, progress: &crate::transfer::progress::OverallProgressEvent) {
+        #[derive(serde::Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Payload {
+            active_sessions: usize,
+            total_bytes: u64,
+            transferred_bytes: u64,
+        }
+
+        self.run(
+            "updateTransferProgress",
+            Payload {
+                active_sessions: progress.active_sessions,
+                total_bytes: progress.total_bytes,
+                transferred_bytes: progress.transferred_bytes,
+            },
+        );
+    }
+
+    fn stop(&self) {
+        self.run("stopTransferService", ());
+    }
+}
+
 /// 构建 Android 更新插件
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
     Builder::new("android-updater")
@@ -50,3 +98,25 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
         })
         .build()
 }
+
+/// 构建 Android 传输前台服务插件
+///
+/// 通知上的"取消"按钮通过插件事件 `cancel-transfers` 通知前端取消全部传输。
+pub fn init_transfer_service<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("transfer-service")
+        .setup(|app, api| {
+            #[cfg(target_os = "android")]
+            {
+                use tauri::Manager;
+                let handle =
+                    api.register_android_plugin(PLUGIN_IDENTIFIER, "TransferServicePlugin")?;
+                app.manage(TransferServicePlugin(handle));
+            }
+
+            #[cfg(not(target_os = "android"))]
+            let _ = (app, api);
+
+            Ok(())
+        })
+        .build()
+}
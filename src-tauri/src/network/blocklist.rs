@@ -0,0 +1,116 @@
+//! 屏蔽列表
+//!
+//! 被屏蔽节点的配对请求与传输 Offer 在事件循环中直接以通用原因拒绝，
+//! 不通知用户、不推送事件到前端。列表以 JSON 保存在应用数据目录，
+//! `start` 时与已配对设备一起加载，重启后仍然生效。
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use swarm_p2p_core::libp2p::PeerId;
+use tracing::warn;
+
+use crate::json_file;
+
+/// 持久化文件名（位于应用本地数据目录）
+pub const BLOCKED_PEERS_FILE: &str = "blocked_peers.json";
+
+/// 被屏蔽的节点集合（由 [`NetManager`](super::NetManager) 持有，与事件循环共享）
+#[derive(Debug, Default)]
+pub struct BlockList {
+    peers: RwLock<HashSet<PeerId>>,
+    /// 持久化路径（为 None 时仅保存在内存中）
+    path: Option<PathBuf>,
+}
+
+impl BlockList {
+    /// 从文件加载，文件不存在或损坏时为空列表
+    pub fn load(path: PathBuf) -> Self {
+        let peers: Vec<PeerId> = json_file::load(&path, "屏蔽列表").unwrap_or_default();
+        Self {
+            peers: RwLock::new(peers.into_iter().collect()),
+            path: Some(path),
+        }
+    }
+
+    pub fn contains(&self, peer_id: &PeerId) -> bool {
+        self.peers.read().is_ok_and(|peers| peers.contains(peer_id))
+    }
+
+    /// 屏蔽节点并保存，返回是否为新增
+    pub fn block(&self, peer_id: PeerId) -> bool {
+        let added = self
+            .peers
+            .write()
+            .is_ok_and(|mut peers| peers.insert(peer_id));
+        if added {
+            self.flush();
+        }
+        added
+    }
+
+    /// 解除屏蔽并保存，返回是否原本在列表中
+    pub fn unblock(&self, peer_id: &PeerId) -> bool {
+        let removed = self
+            .peers
+            .write()
+            .is_ok_and(|mut peers| peers.remove(peer_id));
+        if removed {
+            self.flush();
+        }
+        removed
+    }
+
+    /// 全部被屏蔽的节点（按 PeerId 排序，保证结果稳定）
+    pub fn list(&self) -> Vec<PeerId> {
+        let mut peers: Vec<PeerId> = self
+            .peers
+            .read()
+            .map(|peers| peers.iter().copied().collect())
+            .unwrap_or_default();
+        peers.sort_by_key(|peer_id| peer_id.to_bytes());
+        peers
+    }
+
+    /// 保存到文件（失败仅记录日志）
+    fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = json_file::save(path, &self.list()) {
+            warn!("保存屏蔽列表失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persist_and_reload() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_blocklist");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(BLOCKED_PEERS_FILE);
+
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let blocklist = BlockList::load(path.clone());
+        assert!(blocklist.block(a));
+        assert!(!blocklist.block(a));
+        assert!(blocklist.block(b));
+        assert!(blocklist.unblock(&b));
+        assert!(!blocklist.unblock(&b));
+
+        let reloaded = BlockList::load(path.clone());
+        assert!(reloaded.contains(&a));
+        assert!(!reloaded.contains(&b));
+        assert_eq!(reloaded.list(), vec![a]);
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert!(BlockList::load(path).list().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
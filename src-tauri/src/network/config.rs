@@ -1,9 +1,14 @@
+use std::net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket};
 use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 use swarm_p2p_core::{
     libp2p::{multiaddr::Protocol, Multiaddr, PeerId},
     NodeConfig,
 };
 
+use crate::{AppError, AppResult};
+
 /// SwarmDrop 引导+中继节点
 ///
 /// 使用 /ip4/ 格式，所有平台通用（Android 无 DNS transport）。
@@ -12,44 +17,529 @@ const BOOTSTRAP_NODES: &[&str] = &[
     "/ip4/47.115.172.218/udp/4001/quic-v1/p2p/12D3KooWCq8xgrSap7VZZHpW7EYXw8zFmNEgru9D7cGHGW3bMASX",
 ];
 
-/// 解析 Multiaddr 字符串列表为 (PeerId, Multiaddr) 对
-fn parse_multiaddrs(addrs: &[impl AsRef<str>]) -> Vec<(PeerId, Multiaddr)> {
-    addrs
+/// 默认请求-响应超时（秒）：大分块在慢速链路上需要足够余量
+const DEFAULT_REQ_RESP_TIMEOUT_SECS: u64 = 180;
+/// 默认拨号超时（秒）：对端不可达时让配对等交互尽快失败
+const DEFAULT_DIAL_TIMEOUT_SECS: u64 = 15;
+/// 默认 DHT bootstrap 超时（秒）：引导节点不可达时不无限等待
+const DEFAULT_BOOTSTRAP_TIMEOUT_SECS: u64 = 60;
+/// 默认已配对设备在线检查间隔（秒）
+const DEFAULT_PAIRED_CHECK_INTERVAL_SECS: u64 = 180;
+/// 默认 peer 过期窗口（秒）
+const DEFAULT_STALE_PEER_WINDOW_SECS: u64 =
+    crate::device::manager::DEFAULT_STALE_PEER_WINDOW.as_secs();
+
+/// 网络超时配置（由 `start` 命令传入，省略的字段使用默认值）
+///
+/// `swarm_p2p_core` 的 request-response 只支持一个全局超时，无法按请求类型区分。
+/// 配对请求和传输 Offer 都要等待对方用户确认，同样依赖这个长超时；
+/// 交互场景真正需要"快速失败"的是对端不可达，因此由应用层对拨号单独加超时。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkTimeouts {
+    /// 单个请求等待响应的超时（秒），作用于所有请求类型
+    pub req_resp_timeout_secs: u64,
+    /// 主动拨号的超时（秒）
+    pub dial_timeout_secs: u64,
+    /// 启动时 DHT bootstrap 的超时（秒）
+    pub bootstrap_timeout_secs: u64,
+    /// 定期检查离线的已配对设备是否上线的间隔（秒）
+    pub paired_check_interval_secs: u64,
+    /// 未连接、未配对的 peer 超过该时长（秒）未见即从设备列表中清理
+    pub stale_peer_window_secs: u64,
+}
+
+impl Default for NetworkTimeouts {
+    fn default() -> Self {
+        Self {
+            req_resp_timeout_secs: DEFAULT_REQ_RESP_TIMEOUT_SECS,
+            dial_timeout_secs: DEFAULT_DIAL_TIMEOUT_SECS,
+            bootstrap_timeout_secs: DEFAULT_BOOTSTRAP_TIMEOUT_SECS,
+            paired_check_interval_secs: DEFAULT_PAIRED_CHECK_INTERVAL_SECS,
+            stale_peer_window_secs: DEFAULT_STALE_PEER_WINDOW_SECS,
+        }
+    }
+}
+
+impl NetworkTimeouts {
+    /// 校验配置，超时为 0 时返回错误信息
+    pub fn validate(&self) -> Result<(), String> {
+        if self.req_resp_timeout_secs == 0 {
+            return Err("请求超时必须大于 0".into());
+        }
+        if self.dial_timeout_secs == 0 {
+            return Err("拨号超时必须大于 0".into());
+        }
+        if self.bootstrap_timeout_secs == 0 {
+            return Err("bootstrap 超时必须大于 0".into());
+        }
+        if self.paired_check_interval_secs == 0 {
+            return Err("已配对设备检查间隔必须大于 0".into());
+        }
+        if self.stale_peer_window_secs == 0 {
+            return Err("peer 过期窗口必须大于 0".into());
+        }
+        Ok(())
+    }
+
+    pub fn req_resp_timeout(&self) -> Duration {
+        Duration::from_secs(self.req_resp_timeout_secs)
+    }
+
+    pub fn dial_timeout(&self) -> Duration {
+        Duration::from_secs(self.dial_timeout_secs)
+    }
+
+    pub fn bootstrap_timeout(&self) -> Duration {
+        Duration::from_secs(self.bootstrap_timeout_secs)
+    }
+
+    pub fn paired_check_interval(&self) -> Duration {
+        Duration::from_secs(self.paired_check_interval_secs)
+    }
+
+    pub fn stale_peer_window(&self) -> Duration {
+        Duration::from_secs(self.stale_peer_window_secs)
+    }
+}
+
+/// 网络功能开关（由 `start` 命令传入，省略的字段默认开启）
+///
+/// 受限网络中 mDNS 会刷屏报错；关闭中继即为仅局域网模式，不产生任何中继流量。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkOptions {
+    /// 局域网 mDNS 发现
+    pub mdns: bool,
+    /// 中继客户端（跨 NAT 连接的兜底）
+    pub relay_client: bool,
+    /// DCUtR 打洞（依赖中继建立初始连接）
+    pub dcutr: bool,
+    /// AutoNAT 公网可达性探测
+    pub autonat: bool,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            mdns: true,
+            relay_client: true,
+            dcutr: true,
+            autonat: true,
+        }
+    }
+}
+
+/// 监听配置（由 `start` 命令传入，省略的字段使用随机端口 / 所有网卡）
+///
+/// 企业网络防火墙需要固定端口。修改后重启节点即可生效。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ListenConfig {
+    /// TCP 监听端口（None 或 0 为随机端口）
+    pub tcp_port: Option<u16>,
+    /// QUIC（UDP）监听端口（None 或 0 为随机端口）
+    pub quic_port: Option<u16>,
+    /// 监听的网卡地址（为空时监听 0.0.0.0）
+    pub interfaces: Vec<IpAddr>,
+}
+
+/// 监听端口被占用等原因回退到随机端口时推送的警告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenFallbackEvent {
+    /// "tcp" 或 "quic"
+    pub transport: String,
+    pub interface: IpAddr,
+    /// 请求的端口
+    pub requested_port: u16,
+    /// 失败原因
+    pub reason: String,
+}
+
+impl ListenConfig {
+    /// 生成监听地址列表
+    ///
+    /// 逐个探测指定端口是否可绑定，不可用时回退到随机端口并返回对应警告；
+    /// 连随机端口都无法绑定的网卡（地址不属于本机等）直接跳过。
+    pub fn resolve(&self) -> (Vec<Multiaddr>, Vec<ListenFallbackEvent>) {
+        let interfaces = if self.interfaces.is_empty() {
+            vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)]
+        } else {
+            self.interfaces.clone()
+        };
+
+        let mut addrs = Vec::new();
+        let mut warnings = Vec::new();
+        for ip in interfaces {
+            let tcp = probe_port(ip, self.tcp_port, "tcp", |ip, port| {
+                TcpListener::bind((ip, port)).map(drop)
+            });
+            let quic = probe_port(ip, self.quic_port, "quic", |ip, port| {
+                UdpSocket::bind((ip, port)).map(drop)
+            });
+            for result in [tcp, quic] {
+                match result {
+                    Ok((addr, warning)) => {
+                        addrs.push(addr);
+                        warnings.extend(warning);
+                    }
+                    Err(warning) => warnings.push(warning),
+                }
+            }
+        }
+
+        if addrs.is_empty() {
+            // 所有指定网卡都不可用，退回默认监听
+            addrs = ListenConfig::default().resolve().0;
+        }
+        (addrs, warnings)
+    }
+}
+
+/// 探测端口可用性，返回监听地址及回退警告（网卡本身不可用时返回 Err）
+fn probe_port(
+    ip: IpAddr,
+    port: Option<u16>,
+    transport: &str,
+    bind: impl Fn(IpAddr, u16) -> std::io::Result<()>,
+) -> Result<(Multiaddr, Option<ListenFallbackEvent>), ListenFallbackEvent> {
+    let requested_port = port.unwrap_or(0);
+    let warning = |e: std::io::Error| ListenFallbackEvent {
+        transport: transport.to_string(),
+        interface: ip,
+        requested_port,
+        reason: e.to_string(),
+    };
+
+    let (port, fallback) = match bind(ip, requested_port) {
+        Ok(()) => (requested_port, None),
+        Err(e) if requested_port != 0 => {
+            tracing::warn!(
+                "{} 端口 {}:{} 不可用，改用随机端口: {}",
+                transport,
+                ip,
+                requested_port,
+                e
+            );
+            bind(ip, 0).map_err(&warning)?;
+            (0, Some(warning(e)))
+        }
+        Err(e) => return Err(warning(e)),
+    };
+    Ok((listen_addr(ip, port, transport), fallback))
+}
+
+/// 构造 TCP / QUIC 监听地址
+fn listen_addr(ip: IpAddr, port: u16, transport: &str) -> Multiaddr {
+    let mut addr = Multiaddr::from(ip);
+    if transport == "tcp" {
+        addr.push(Protocol::Tcp(port));
+    } else {
+        addr.push(Protocol::Udp(port));
+        addr.push(Protocol::QuicV1);
+    }
+    addr
+}
+
+/// 节点地址无效的原因（类型化，供前端 i18n 使用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum InvalidAddrReason {
+    /// 无法解析为 Multiaddr
+    Malformed { message: String },
+    /// 缺少 `/p2p/<PeerId>`
+    MissingPeerId,
+}
+
+impl std::fmt::Display for InvalidAddrReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed { message } => write!(f, "无法解析为 Multiaddr（{message}）"),
+            Self::MissingPeerId => f.write_str("缺少 /p2p/<PeerId>"),
+        }
+    }
+}
+
+/// 单个无效地址
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidAddr {
+    /// 用户输入的原始地址
+    pub addr: String,
+    pub reason: InvalidAddrReason,
+}
+
+/// 节点地址列表的校验结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddrValidation {
+    /// 有效地址数
+    pub valid_count: usize,
+    /// 有效地址的规范形式（去除首尾空白、按 Multiaddr 重新编码），前端可直接回填
+    pub normalized: Vec<String>,
+    pub invalid: Vec<InvalidAddr>,
+}
+
+/// 解析单个 Multiaddr（忽略首尾空白），必须包含 `/p2p/<PeerId>`
+fn parse_peer_addr(s: &str) -> Result<(PeerId, Multiaddr), InvalidAddrReason> {
+    let addr: Multiaddr = s.trim().parse().map_err(|e| InvalidAddrReason::Malformed {
+        message: format!("{e}"),
+    })?;
+    let peer_id = addr
         .iter()
-        .filter_map(|s| {
-            let addr: Multiaddr = s.as_ref().parse().ok()?;
-            let peer_id = addr.iter().find_map(|p| match p {
-                Protocol::P2p(id) => Some(id),
-                _ => None,
-            })?;
-            Some((peer_id, addr))
+        .find_map(|p| match p {
+            Protocol::P2p(id) => Some(id),
+            _ => None,
+        })
+        .ok_or(InvalidAddrReason::MissingPeerId)?;
+    Ok((peer_id, addr))
+}
+
+/// 用户输入中的非空地址（空行忽略）
+fn non_empty(addrs: &[String]) -> impl Iterator<Item = &String> {
+    addrs.iter().filter(|s| !s.trim().is_empty())
+}
+
+/// 解析用户提供的地址列表，任一地址无效时返回指明该地址的配置错误
+fn parse_custom_addrs(label: &str, addrs: &[String]) -> AppResult<Vec<(PeerId, Multiaddr)>> {
+    non_empty(addrs)
+        .map(|s| {
+            parse_peer_addr(s)
+                .map_err(|reason| AppError::Config(format!("无效的{label}地址 \"{s}\": {reason}")))
         })
         .collect()
 }
 
+/// 逐个校验用户输入的节点地址，列出无效地址及原因（不会因单个地址无效而失败）
+///
+/// 供设置页保存前提示，避免拼错的地址直到 `start` 失败才被发现。
+pub fn validate_peer_addrs(addrs: &[String]) -> AddrValidation {
+    let mut result = AddrValidation::default();
+    for s in non_empty(addrs) {
+        match parse_peer_addr(s) {
+            Ok((_, addr)) => result.normalized.push(addr.to_string()),
+            Err(reason) => result.invalid.push(InvalidAddr {
+                addr: s.clone(),
+                reason,
+            }),
+        }
+    }
+    result.valid_count = result.normalized.len();
+    result
+}
+
+/// 引导节点与中继节点来源（由 `start` 命令参数解析）
+#[derive(Debug, Clone, Default)]
+pub struct PeerSources {
+    /// 启动时连接的节点（引导节点 + 自定义中继）
+    pub bootstrap_peers: Vec<(PeerId, Multiaddr)>,
+    /// 预期提供中继预约的节点（默认节点 + 自定义中继），用于展示各自的预约状态
+    pub relay_peers: Vec<PeerId>,
+}
+
+impl PeerSources {
+    /// 合并默认节点与用户自定义的引导 / 中继节点
+    ///
+    /// 核心库会向所有已连接的引导节点申请中继预约，因此自定义中继同样作为引导节点连接。
+    /// `use_default_bootstrap` 为 false 时完全不连接内置节点，便于自建部署。
+    pub fn resolve(
+        use_default_bootstrap: bool,
+        custom_bootstrap_nodes: &[String],
+        custom_relays: &[String],
+    ) -> AppResult<Self> {
+        let mut sources = Self::default();
+
+        if use_default_bootstrap {
+            let defaults = BOOTSTRAP_NODES
+                .iter()
+                .filter_map(|s| parse_peer_addr(s).ok());
+            for (peer_id, addr) in defaults {
+                sources.add_relay(peer_id);
+                sources.bootstrap_peers.push((peer_id, addr));
+            }
+        }
+
+        let custom_peers = parse_custom_addrs("引导节点", custom_bootstrap_nodes)?;
+        if !custom_peers.is_empty() {
+            tracing::info!("Parsed {} custom bootstrap peers", custom_peers.len());
+        }
+        sources.bootstrap_peers.extend(custom_peers);
+
+        for (peer_id, addr) in parse_custom_addrs("中继", custom_relays)? {
+            sources.add_relay(peer_id);
+            if !sources.bootstrap_peers.contains(&(peer_id, addr.clone())) {
+                sources.bootstrap_peers.push((peer_id, addr));
+            }
+        }
+
+        tracing::info!(
+            "Total {} bootstrap peers, {} relay peers",
+            sources.bootstrap_peers.len(),
+            sources.relay_peers.len()
+        );
+        Ok(sources)
+    }
+
+    fn add_relay(&mut self, peer_id: PeerId) {
+        if !self.relay_peers.contains(&peer_id) {
+            self.relay_peers.push(peer_id);
+        }
+    }
+}
+
 /// 创建 P2P 节点配置
 ///
-/// `custom_bootstrap_nodes` — 用户自定义的额外引导节点地址，与默认节点合并
+/// `peers` — [`PeerSources::resolve`] 解析出的引导 / 中继节点
+/// `timeouts` — 已校验的超时配置
+/// `listen_addrs` — [`ListenConfig::resolve`] 生成的监听地址
+/// `options` — 网络功能开关
 pub fn create_node_config(
     agent_version: String,
-    custom_bootstrap_nodes: &[String],
+    peers: &PeerSources,
+    timeouts: &NetworkTimeouts,
+    listen_addrs: Vec<Multiaddr>,
+    options: &NetworkOptions,
 ) -> NodeConfig {
-    let mut bootstrap_peers = parse_multiaddrs(BOOTSTRAP_NODES);
+    NodeConfig::new("/swarmdrop/1.0.0", agent_version)
+        .with_mdns(options.mdns)
+        .with_relay_client(options.relay_client)
+        .with_dcutr(options.dcutr)
+        .with_autonat(options.autonat)
+        .with_req_resp_timeout(timeouts.req_resp_timeout())
+        .with_listen_addrs(listen_addrs)
+        .with_bootstrap_peers(peers.bootstrap_peers.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_default_and_partial() {
+        let timeouts = NetworkTimeouts::default();
+        assert!(timeouts.validate().is_ok());
+        assert_eq!(timeouts.req_resp_timeout(), Duration::from_secs(180));
 
-    // 合并自定义引导节点
-    if !custom_bootstrap_nodes.is_empty() {
-        let custom_peers = parse_multiaddrs(custom_bootstrap_nodes);
-        tracing::info!("Parsed {} custom bootstrap peers", custom_peers.len());
-        bootstrap_peers.extend(custom_peers);
+        let partial: NetworkTimeouts =
+            serde_json::from_str(r#"{ "reqRespTimeoutSecs": 600 }"#).unwrap();
+        assert_eq!(partial.req_resp_timeout_secs, 600);
+        assert_eq!(partial.dial_timeout_secs, DEFAULT_DIAL_TIMEOUT_SECS);
     }
 
-    tracing::info!("Total {} bootstrap peers", bootstrap_peers.len());
+    #[test]
+    fn test_reject_zero_timeouts() {
+        let zero_req = NetworkTimeouts {
+            req_resp_timeout_secs: 0,
+            ..Default::default()
+        };
+        assert!(zero_req.validate().is_err());
 
-    NodeConfig::new("/swarmdrop/1.0.0", agent_version)
-        .with_mdns(true)
-        .with_relay_client(true)
-        .with_dcutr(true)
-        .with_autonat(true)
-        .with_req_resp_timeout(Duration::from_secs(180))
-        .with_bootstrap_peers(bootstrap_peers)
+        let zero_dial = NetworkTimeouts {
+            dial_timeout_secs: 0,
+            ..Default::default()
+        };
+        assert!(zero_dial.validate().is_err());
+    }
+
+    #[test]
+    fn test_listen_config_default() {
+        let (addrs, warnings) = ListenConfig::default().resolve();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            addrs,
+            vec![
+                "/ip4/0.0.0.0/tcp/0".parse::<Multiaddr>().unwrap(),
+                "/ip4/0.0.0.0/udp/0/quic-v1".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listen_port_in_use_falls_back() {
+        let occupied = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = occupied.local_addr().unwrap().port();
+
+        let config = ListenConfig {
+            tcp_port: Some(port),
+            quic_port: None,
+            interfaces: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+        };
+        let (addrs, warnings) = config.resolve();
+
+        assert_eq!(
+            addrs[0],
+            "/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>().unwrap()
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].transport, "tcp");
+        assert_eq!(warnings[0].requested_port, port);
+    }
+
+    #[test]
+    fn test_network_options_partial() {
+        let options: NetworkOptions = serde_json::from_str(r#"{ "relayClient": false }"#).unwrap();
+        assert!(!options.relay_client);
+        assert!(options.mdns && options.dcutr && options.autonat);
+    }
+
+    #[test]
+    fn test_peer_sources_default_and_custom_relay() {
+        let relay = PeerId::random();
+        let relay_addr = format!("/ip4/203.0.113.7/tcp/4001/p2p/{relay}");
+
+        let with_default = PeerSources::resolve(true, &[], &[relay_addr.clone()]).unwrap();
+        assert_eq!(
+            with_default.bootstrap_peers.len(),
+            BOOTSTRAP_NODES.len() + 1
+        );
+        // 默认节点的 TCP / QUIC 地址属于同一个 PeerId
+        assert_eq!(with_default.relay_peers.len(), 2);
+        assert_eq!(with_default.relay_peers[1], relay);
+
+        let self_hosted = PeerSources::resolve(false, &[], &[relay_addr]).unwrap();
+        assert_eq!(self_hosted.relay_peers, vec![relay]);
+        assert_eq!(self_hosted.bootstrap_peers.len(), 1);
+    }
+
+    #[test]
+    fn test_reject_invalid_custom_addrs() {
+        let missing_peer = "/ip4/203.0.113.7/tcp/4001".to_string();
+        let err = PeerSources::resolve(true, &[], &[missing_peer.clone()]).unwrap_err();
+        assert!(matches!(&err, AppError::Config(msg) if msg.contains(&missing_peer)));
+
+        let garbage = "not-a-multiaddr".to_string();
+        let err = PeerSources::resolve(true, &[garbage.clone()], &[]).unwrap_err();
+        assert!(matches!(&err, AppError::Config(msg) if msg.contains(&garbage)));
+    }
+
+    #[test]
+    fn test_validate_peer_addrs() {
+        let peer = PeerId::random();
+        let addrs = vec![
+            format!("  /ip4/203.0.113.7/tcp/4001/p2p/{peer}\n"),
+            String::new(),
+            "/ip4/203.0.113.7/tcp/4001".to_string(),
+            "/ip4/not-an-ip/tcp/4001".to_string(),
+        ];
+
+        let result = validate_peer_addrs(&addrs);
+        assert_eq!(result.valid_count, 1);
+        assert_eq!(
+            result.normalized,
+            vec![format!("/ip4/203.0.113.7/tcp/4001/p2p/{peer}")]
+        );
+        assert_eq!(result.invalid.len(), 2);
+        assert_eq!(result.invalid[0].addr, addrs[2]);
+        assert_eq!(result.invalid[0].reason, InvalidAddrReason::MissingPeerId);
+        assert_eq!(result.invalid[1].addr, addrs[3]);
+        assert!(matches!(
+            result.invalid[1].reason,
+            InvalidAddrReason::Malformed { .. }
+        ));
+
+        // 空行与首尾空白不影响启动时的解析
+        let sources = PeerSources::resolve(false, &addrs[..2], &[]).unwrap();
+        assert_eq!(sources.bootstrap_peers.len(), 1);
+    }
 }
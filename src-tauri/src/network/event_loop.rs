@@ -2,17 +2,19 @@ use serde::Serialize;
 use swarm_p2p_core::{EventReceiver, NodeEvent};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_notification::NotificationExt;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use sea_orm::DatabaseConnection;
 
-use super::manager::SharedNetRefs;
-use crate::device::DeviceFilter;
+use super::manager::{insert_listen_addr, SharedNetRefs};
+use super::throttle::StatusEmitter;
+use crate::device::{ConnectionType, Device, DeviceFilter, DeviceManager, OsInfo, PresenceChange};
 use crate::events;
+use crate::pairing::manager::CodeScreening;
 use crate::protocol::{
-    AppRequest, AppResponse, OfferRejectReason, PairingRequest, ResumeRejectReason,
-    TransferRequest, TransferResponse,
+    AppRequest, AppResponse, OfferRejectReason, PairingConfirmation, PairingRefuseReason,
+    PairingRequest, PairingResponse, ResumeRejectReason, TransferRequest, TransferResponse,
 };
 use crate::transfer::progress::{TransferDbErrorEvent, TransferDirection, TransferFailedEvent, TransferPausedEvent, TransferResumedEvent, TransferResumedFileInfo};
 use swarm_p2p_core::libp2p::PeerId;
@@ -27,26 +29,61 @@ struct PairingRequestPayload {
     request: PairingRequest,
 }
 
-/// 传输 Offer 事件 payload（推送给前端）
+/// 检测到配对码暴力猜测事件 payload
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct TransferOfferPayload {
-    session_id: Uuid,
-    peer_id: String,
-    device_name: String,
-    files: Vec<TransferFilePayload>,
-    total_size: u64,
+struct PairingBruteforcePayload {
+    peer_id: PeerId,
+    /// 当前配对码累计猜错次数过多，已作废
+    code_revoked: bool,
+}
+
+/// 节点关闭期间拒绝分块请求时返回的错误（接收方据此识别为可重试）
+const SHUTTING_DOWN_MESSAGE: &str = "节点正在关闭";
+
+/// 对端配对核对结果事件 payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairingConfirmationPayload {
+    peer_id: PeerId,
+    #[serde(flatten)]
+    confirmation: PairingConfirmation,
+}
+
+/// 对端取消配对事件 payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairedDeviceRemovedPayload {
+    peer_id: PeerId,
+    hostname: String,
+}
+
+/// 已配对设备上线事件 payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairedDeviceOnlinePayload {
+    peer_id: PeerId,
+    connection: Option<ConnectionType>,
+    /// 完整设备信息（前端可直接用于提示，无需比对设备列表）
+    device: Option<Device>,
+    /// 连接抖动导致的重新上线（前端不应再次提醒）
+    flapping: bool,
+}
+
+/// 已配对设备下线事件 payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairedDeviceOfflinePayload {
+    peer_id: PeerId,
+    device: Option<Device>,
 }
 
-/// Offer 中的文件信息（前端展示用）
+/// 打洞失败事件 payload（连接仍走中继，供诊断传输慢的原因）
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct TransferFilePayload {
-    file_id: u32,
-    name: String,
-    relative_path: String,
-    size: u64,
-    is_directory: bool,
+struct HolePunchFailedPayload {
+    peer_id: PeerId,
+    error: String,
 }
 
 use std::path::PathBuf;
@@ -56,8 +93,10 @@ use sea_orm::EntityTrait;
 
 use crate::file_source::FileSource;
 use crate::protocol::FileChecksum;
+use crate::transfer::crypto::{KeyExchange, SessionKey};
 use crate::transfer::offer::{
-    build_file_infos_and_bitmaps, build_sender_resume_state, PreparedFile, TransferManager,
+    build_file_infos_and_bitmaps, build_sender_resume_state, responder_session_key, PreparedFile,
+    TransferManager,
 };
 use crate::transfer::sender::SendSession;
 
@@ -68,6 +107,7 @@ fn reject_resume(session_id: Uuid, reason: ResumeRejectReason) -> TransferRespon
         accepted: false,
         reason: Some(reason),
         key: None,
+        public_key: None,
     }
 }
 
@@ -126,14 +166,34 @@ async fn validate_resume_session(
     })
 }
 
-/// 发送方处理 ResumeRequest：验证文件校验和，重建 PreparedFile，创建 SendSession
+/// 发送方处理 ResumeRequest：协商密钥，验证文件校验和，重建 PreparedFile，创建 SendSession
+#[expect(clippy::too_many_arguments, reason = "断点续传需要完整的请求上下文")]
 async fn handle_resume_request(
     app: &AppHandle,
     session_id: Uuid,
     peer_id: PeerId,
+    local_peer_id: PeerId,
     file_checksums: &[FileChecksum],
+    public_key: Option<[u8; 32]>,
+    key_exchange_required: bool,
     transfer: &Arc<TransferManager>,
 ) -> TransferResponse {
+    // 接收方携带公钥时重新派生密钥；旧版本接收方不携带，生成新密钥直接返回（发送方不持久化密钥）
+    let derived = responder_session_key(
+        public_key,
+        key_exchange_required,
+        &session_id,
+        &local_peer_id,
+        &peer_id,
+    );
+    let (key, legacy_key, public_key) = match derived {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!("断点续传密钥协商失败: session={}, {}", session_id, e);
+            return reject_resume(session_id, ResumeRejectReason::KeyExchangeFailed);
+        }
+    };
+
     let ctx = match validate_resume_session(app, session_id, file_checksums).await {
         Ok(ctx) => ctx,
         Err(reason) => return reject_resume(session_id, reason),
@@ -164,12 +224,12 @@ async fn handle_resume_request(
             source: FileSource::Path { path },
             size: db_file.size as u64,
             checksum: db_file.checksum.clone(),
+            preview: None,
+            mime: crate::transfer::offer::guess_mime(&db_file.name),
+            modified: None,
         });
     }
 
-    // 生成密钥（发送方不持久化密钥，每次恢复重新生成）
-    let key = crate::transfer::crypto::generate_key();
-
     // 从 DB 构建 resume_state（file_id → (chunks_done, transferred_bytes)）
     let resume_state = build_sender_resume_state(&ctx.db_files);
 
@@ -179,7 +239,7 @@ async fn handle_resume_request(
         peer_id,
         prepared_files,
         &key,
-        app.clone(),
+        transfer.session_context(app),
         &resume_state,
     ));
     transfer.insert_send_session(session_id, send_session);
@@ -190,22 +250,95 @@ async fn handle_resume_request(
         session_id,
         accepted: true,
         reason: None,
-        key: Some(key),
+        key: legacy_key,
+        public_key,
     }
 }
 
-/// 接收方处理 ResumeOffer：验证文件校验和，创建 ReceiveSession，开始拉取
+/// 接收方确定断点续传的会话密钥：发送方携带公钥时以 ECDH 派生，否则使用旧版本发送方直接提供的密钥
+///
+/// 返回 `(会话密钥, 需回复的本方公钥)`。发送方声明支持续传密钥交换时不接受直接提供的密钥。
+fn resume_offer_key(
+    session_id: Uuid,
+    sender: PeerId,
+    local_peer_id: PeerId,
+    key: Option<SessionKey>,
+    public_key: Option<[u8; 32]>,
+    key_exchange_required: bool,
+) -> Result<(SessionKey, Option<[u8; 32]>), ResumeRejectReason> {
+    match (public_key, key) {
+        (Some(sender_public), _) => {
+            let exchange = KeyExchange::new();
+            let local_public = exchange.public_key();
+            exchange
+                .derive(&sender_public, &session_id, &sender, &local_peer_id)
+                .map(|key| (key, Some(local_public)))
+                .map_err(|e| {
+                    warn!("断点续传密钥协商失败: session={}, {}", session_id, e);
+                    ResumeRejectReason::KeyExchangeFailed
+                })
+        }
+        (None, Some(_)) if key_exchange_required => {
+            warn!("ResumeOffer 降级为明文密钥，已拒绝: session={}", session_id);
+            Err(ResumeRejectReason::KeyExchangeFailed)
+        }
+        (None, Some(key)) => Ok((key, None)),
+        (None, None) => {
+            warn!("ResumeOffer 未携带密钥: session={}", session_id);
+            Err(ResumeRejectReason::KeyExchangeFailed)
+        }
+    }
+}
+
+/// 接收方处理 ResumeOffer：协商密钥，验证文件校验和，回复后创建 ReceiveSession 开始拉取
+///
+/// 先回复再拉取：密钥交换时发送方收到本方公钥、派生出密钥后才创建 SendSession。
+#[expect(clippy::too_many_arguments, reason = "断点续传需要完整的请求上下文")]
 async fn handle_resume_offer(
     app: &AppHandle,
+    pending_id: u64,
     session_id: Uuid,
     peer_id: PeerId,
-    key: &[u8; 32],
+    local_peer_id: PeerId,
+    key: Option<SessionKey>,
+    public_key: Option<[u8; 32]>,
+    key_exchange_required: bool,
     file_checksums: &[FileChecksum],
     transfer: &Arc<TransferManager>,
-) -> TransferResponse {
-    let ctx = match validate_resume_session(app, session_id, file_checksums).await {
-        Ok(ctx) => ctx,
-        Err(reason) => return reject_resume_offer(session_id, reason),
+) {
+    let keys = resume_offer_key(
+        session_id,
+        peer_id,
+        local_peer_id,
+        key,
+        public_key,
+        key_exchange_required,
+    );
+    let prepared = match keys {
+        Ok(keys) => validate_resume_session(app, session_id, file_checksums)
+            .await
+            .map(|ctx| (ctx, keys)),
+        Err(reason) => Err(reason),
+    };
+    let response = match &prepared {
+        Ok((_, (_, public_key))) => TransferResponse::ResumeOfferResult {
+            session_id,
+            accepted: true,
+            reason: None,
+            public_key: *public_key,
+        },
+        Err(reason) => reject_resume_offer(session_id, reason.clone()),
+    };
+    if let Err(e) = transfer
+        .client()
+        .send_response(pending_id, AppResponse::Transfer(response))
+        .await
+    {
+        warn!("发送 ResumeOfferResult 失败: {}", e);
+        return;
+    }
+    let Ok((ctx, (key, _))) = prepared else {
+        return;
     };
 
     // 构建 FileInfo、initial_bitmaps（复用已有辅助函数）
@@ -230,13 +363,14 @@ async fn handle_resume_offer(
     let peer_id_str = ctx.session.peer_id.0.clone();
 
     // 创建 ReceiveSession 并开始拉取
+    let sink = crate::transfer::offer::resolve_file_sink(&save_location, session_id, app).await;
     transfer.start_receive_from_offer(
         session_id,
         peer_id,
         file_infos,
         total_size,
-        crate::transfer::offer::build_file_sink(&save_location),
-        key,
+        sink,
+        &key,
         app.clone(),
         initial_bitmaps,
     );
@@ -255,12 +389,6 @@ async fn handle_resume_offer(
     );
 
     info!("接受发送方断点续传: session={}", session_id);
-
-    TransferResponse::ResumeOfferResult {
-        session_id,
-        accepted: true,
-        reason: None,
-    }
 }
 
 /// 构造拒绝 ResumeOffer 的响应
@@ -269,6 +397,99 @@ fn reject_resume_offer(session_id: Uuid, reason: ResumeRejectReason) -> Transfer
         session_id,
         accepted: false,
         reason: Some(reason),
+        public_key: None,
+    }
+}
+
+/// 拒绝 Offer（不推送给前端）
+fn reject_offer(shared: &SharedNetRefs, pending_id: u64, reason: OfferRejectReason) {
+    let response = AppResponse::Transfer(TransferResponse::OfferResult {
+        accepted: false,
+        key: None,
+        reason: Some(reason),
+        encryption: true,
+        public_key: None,
+    });
+    let client = shared.client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.send_response(pending_id, response).await {
+            warn!("Failed to reject offer: {}", e);
+        }
+    });
+}
+
+/// 以通用原因拒绝配对请求（不区分原因，避免向对方泄露屏蔽 / 锁定状态）
+fn refuse_pairing(shared: &SharedNetRefs, pending_id: u64) {
+    let response = AppResponse::Pairing(PairingResponse::Refused {
+        reason: PairingRefuseReason::UserRejected,
+    });
+    let client = shared.client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.send_response(pending_id, response).await {
+            warn!("Failed to refuse pairing request: {}", e);
+        }
+    });
+}
+
+/// 确认配对类通知（核对结果、取消配对）
+fn ack_pairing(shared: &SharedNetRefs, pending_id: u64) {
+    let client = shared.client.clone();
+    tokio::spawn(async move {
+        let ack = AppResponse::Pairing(PairingResponse::Success);
+        if let Err(e) = client.send_response(pending_id, ack).await {
+            warn!("Failed to ack pairing notification: {}", e);
+        }
+    });
+}
+
+/// 连接建立 / 打洞成功后，将最新的连接类型同步给该 peer 的活跃传输会话
+async fn sync_transfer_connection(shared: &SharedNetRefs, peer_id: PeerId) {
+    if let Some(connection) = shared.devices.connection_type(&peer_id) {
+        shared.transfer.update_peer_connection(&peer_id, connection).await;
+    }
+}
+
+/// 推送已配对设备上线 / 下线事件，上线时在窗口未聚焦的情况下发送系统通知
+pub(crate) fn emit_presence_change(app: &AppHandle, devices: &DeviceManager, change: PresenceChange) {
+    match change {
+        PresenceChange::Online {
+            peer_id,
+            connection,
+            flapping,
+        } => {
+            info!(
+                "已配对设备上线: {} ({:?}, flapping={})",
+                peer_id, connection, flapping
+            );
+            if !flapping {
+                if let Some(name) = devices.paired_name(&peer_id) {
+                    notify_if_unfocused(app, "设备上线", &format!("{name} 已上线"));
+                }
+            }
+            let payload = PairedDeviceOnlinePayload {
+                peer_id,
+                connection,
+                device: devices.get_paired_device(&peer_id),
+                flapping,
+            };
+            let _ = app.emit(events::PAIRED_DEVICE_ONLINE, &payload);
+        }
+        PresenceChange::Offline { peer_id } => {
+            info!("已配对设备下线: {}", peer_id);
+            let payload = PairedDeviceOfflinePayload {
+                peer_id,
+                device: devices.get_paired_device(&peer_id),
+            };
+            let _ = app.emit(events::PAIRED_DEVICE_OFFLINE, &payload);
+        }
+    }
+}
+
+/// 记录已配对设备的在线时间，达到更新粒度时推送完整记录供前端重新保存
+fn touch_last_seen(app: &AppHandle, shared: &SharedNetRefs, peer_id: &PeerId) {
+    let now = chrono::Utc::now().timestamp_millis();
+    if let Some(info) = shared.pairing.touch_last_seen(peer_id, now) {
+        let _ = app.emit(events::PAIRED_DEVICE_UPDATED, &info);
     }
 }
 
@@ -286,6 +507,21 @@ fn notify_if_unfocused(app: &AppHandle, title: &str, body: &str) {
     }
 }
 
+/// 重新计算当前连接的引导节点，返回是否发生变化
+fn update_bootstrap_peer(shared: &SharedNetRefs, current: &mut Option<PeerId>) -> bool {
+    let latest = shared.devices.connected_bootstrap_peer();
+    if latest == *current {
+        return false;
+    }
+    match latest {
+        Some(peer_id) => info!("已连接引导节点: {}", peer_id),
+        None => warn!("与所有引导节点的连接已断开"),
+    }
+    shared.pairing.set_dht_ready(latest.is_some());
+    *current = latest;
+    true
+}
+
 /// 启动事件循环：后端消费所有 NodeEvent，通过 Tauri Event 推送高层域事件 + payload
 ///
 /// 参照 libs/core 的责任链模式——前端不接触原始 NodeEvent。
@@ -295,25 +531,38 @@ pub fn spawn_event_loop(
     shared: SharedNetRefs,
 ) {
     tokio::spawn(async move {
-        let emit_device_and_status = || {
-            let devices = shared.devices.get_devices(DeviceFilter::All);
-            let _ = app.emit(events::DEVICES_CHANGED, &devices);
-            let net_status = shared.build_network_status();
-            let _ = app.emit(events::NETWORK_STATUS_CHANGED, &net_status);
+        // 设备列表 + 网络状态推送经节流器合并，高频的 Ping / Identify 不再逐条推送
+        let status_emitter = {
+            let app = app.clone();
+            let shared = shared.clone();
+            StatusEmitter::new(move || {
+                let devices = shared.devices.get_devices(&DeviceFilter::All.into());
+                let _ = app.emit(events::DEVICES_CHANGED, &devices);
+                let net_status = shared.build_network_status();
+                let _ = app.emit(events::NETWORK_STATUS_CHANGED, &net_status);
+            })
         };
+        status_emitter.spawn_flush_task();
+        let mut bootstrap_peer = shared.devices.connected_bootstrap_peer();
 
         while let Some(event) = receiver.recv().await {
             // handle_event 对不相关的事件直接忽略，无条件调用后再消费 event
-            shared.devices.handle_event(&event);
+            if let Some(change) = shared.devices.handle_event(&event) {
+                emit_presence_change(&app, &shared.devices, change);
+            }
 
             match event {
                 // === 网络状态事件 ===
                 NodeEvent::Listening { addr } => {
-                    if let Ok(mut addrs) = shared.listen_addrs.write() {
-                        addrs.push(addr);
+                    let changed = shared
+                        .listen_addrs
+                        .write()
+                        .map(|mut addrs| insert_listen_addr(&mut addrs, addr))
+                        .unwrap_or(false);
+                    if changed {
+                        let status = shared.build_network_status();
+                        let _ = app.emit(events::NETWORK_STATUS_CHANGED, &status);
                     }
-                    let status = shared.build_network_status();
-                    let _ = app.emit(events::NETWORK_STATUS_CHANGED, &status);
                 }
                 NodeEvent::NatStatusChanged {
                     status,
@@ -325,6 +574,8 @@ pub fn spawn_event_loop(
                     if let Ok(mut pa) = shared.public_addr.write() {
                         *pa = public_addr;
                     }
+                    // 可达地址变化，立即刷新 DHT 在线记录
+                    shared.online_refresh.notify_one();
                     let net_status = shared.build_network_status();
                     let _ = app.emit(events::NETWORK_STATUS_CHANGED, &net_status);
                 }
@@ -332,26 +583,57 @@ pub fn spawn_event_loop(
                     if let Ok(mut rp) = shared.relay_peers.write() {
                         rp.insert(relay_peer_id);
                     }
+                    // 新增中继电路地址，立即刷新 DHT 在线记录
+                    shared.online_refresh.notify_one();
                     let net_status = shared.build_network_status();
                     let _ = app.emit(events::NETWORK_STATUS_CHANGED, &net_status);
                 }
 
                 // === 设备事件（handle_event 已在上方处理） ===
-                NodeEvent::PeerConnected { .. } => {
-                    emit_device_and_status();
+                NodeEvent::PeerConnected { peer_id } | NodeEvent::HolePunchSucceeded { peer_id } => {
+                    shared
+                        .traffic
+                        .connection_opened(peer_id, shared.devices.connection_type(&peer_id));
+                    touch_last_seen(&app, &shared, &peer_id);
+                    update_bootstrap_peer(&shared, &mut bootstrap_peer);
+                    status_emitter.emit_now();
+                    sync_transfer_connection(&shared, peer_id).await;
                 }
                 NodeEvent::PeerDisconnected { ref peer_id } => {
+                    shared.traffic.connection_closed(peer_id);
+                    touch_last_seen(&app, &shared, peer_id);
                     // 清理中继节点
                     if let Ok(mut rp) = shared.relay_peers.write() {
                         rp.remove(peer_id);
                     }
-                    emit_device_and_status();
+                    update_bootstrap_peer(&shared, &mut bootstrap_peer);
+                    status_emitter.emit_now();
                 }
-                NodeEvent::IdentifyReceived { .. }
-                | NodeEvent::PeersDiscovered { .. }
-                | NodeEvent::PingSuccess { .. }
-                | NodeEvent::HolePunchSucceeded { .. } => {
-                    emit_device_and_status();
+                // 引导节点需 Identify 后才能识别，连接状态变化时立即推送
+                NodeEvent::IdentifyReceived {
+                    peer_id,
+                    agent_version,
+                    ..
+                } => {
+                    // 已配对设备改了主机名等信息：更新记录，推送完整信息供前端重新保存
+                    if OsInfo::is_swarmdrop_agent(&agent_version) {
+                        let updated = OsInfo::from_agent_version(&agent_version)
+                            .and_then(|os_info| shared.pairing.refresh_os_info(&peer_id, os_info));
+                        if let Some(info) = updated {
+                            let _ = app.emit(events::PAIRED_DEVICE_UPDATED, &info);
+                        }
+                    }
+                    if update_bootstrap_peer(&shared, &mut bootstrap_peer) {
+                        status_emitter.emit_now();
+                    } else {
+                        status_emitter.mark_dirty();
+                    }
+                }
+                NodeEvent::PeersDiscovered { .. } => {
+                    status_emitter.mark_dirty();
+                }
+                NodeEvent::PingSuccess { .. } => {
+                    status_emitter.mark_stale();
                 }
 
                 // === 入站请求（缓存上下文 + 推送业务事件给前端） ===
@@ -361,16 +643,57 @@ pub fn spawn_event_loop(
                     request,
                 } => {
                     info!("Inbound request from {:?}: {:?}", peer_id, request);
+                    touch_last_seen(&app, &shared, &peer_id);
 
                     match request {
                         AppRequest::Pairing(req) => {
+                            // 被屏蔽的节点：以通用原因静默拒绝，不通知用户
+                            if shared.blocklist.contains(&peer_id) {
+                                info!("拒绝已屏蔽节点 {} 的配对请求", peer_id);
+                                refuse_pairing(&shared, pending_id);
+                                continue;
+                            }
+
+                            // 配对码错误或来源处于锁定期：直接拒绝，不推送给前端
+                            let screening =
+                                shared.pairing.screen_pairing_request(peer_id, &req.method);
+                            if screening != CodeScreening::Pass {
+                                refuse_pairing(&shared, pending_id);
+                                if let CodeScreening::WrongCode { outcome, code } = screening {
+                                    if let Some(code) = code.filter(|_| outcome.revoke_code) {
+                                        let pairing = shared.pairing.clone();
+                                        tokio::spawn(async move {
+                                            pairing.revoke_code_if_active(&code).await
+                                        });
+                                    }
+                                    if outcome.locked_out || outcome.revoke_code {
+                                        warn!(
+                                            "检测到配对码暴力猜测: peer={}, 锁定={}, 作废配对码={}",
+                                            peer_id, outcome.locked_out, outcome.revoke_code
+                                        );
+                                        let payload = PairingBruteforcePayload {
+                                            peer_id,
+                                            code_revoked: outcome.revoke_code,
+                                        };
+                                        let _ =
+                                            app.emit(events::PAIRING_BRUTEFORCE_DETECTED, &payload);
+                                    }
+                                }
+                                continue;
+                            }
+
                             shared
                                 .pairing
                                 .cache_inbound_request(peer_id, pending_id, &req);
+                            // 重新配对的设备使用已设置的昵称
+                            let requester_name = shared
+                                .devices
+                                .paired_name(&peer_id)
+                                .unwrap_or_else(|| req.os_info.hostname.clone());
                             notify_if_unfocused(
                                 &app,
                                 "配对请求",
-                                &format!("{} 请求与您配对", req.os_info.hostname),
+                                &format!("{} 请求与您配对", requester_name),
                             );
 
                             let payload = PairingRequestPayload {
@@ -381,6 +704,38 @@ pub fn spawn_event_loop(
                             let _ = app.emit(events::PAIRING_REQUEST_RECEIVED, &payload);
                         }
 
+                        AppRequest::PairingConfirmation(confirmation) => {
+                            if !shared.pairing.is_paired(&peer_id) {
+                                warn!("忽略未配对设备 {} 的配对核对消息", peer_id);
+                                continue;
+                            }
+                            let removed =
+                                shared.pairing.handle_confirmation(&peer_id, confirmation);
+                            ack_pairing(&shared, pending_id);
+                            if removed {
+                                status_emitter.mark_stale();
+                            }
+                            let payload = PairingConfirmationPayload {
+                                peer_id,
+                                confirmation,
+                            };
+                            let _ = app.emit(events::PAIRING_CONFIRMATION_RECEIVED, &payload);
+                        }
+
+                        AppRequest::Unpair => {
+                            ack_pairing(&shared, pending_id);
+                            let Some(device) = shared.pairing.remove_paired_device(&peer_id) else {
+                                continue;
+                            };
+                            info!("{} 已取消与本机的配对，已移除该设备", peer_id);
+                            status_emitter.mark_stale();
+                            let payload = PairedDeviceRemovedPayload {
+                                peer_id,
+                                hostname: device.os_info.hostname,
+                            };
+                            let _ = app.emit(events::PAIRED_DEVICE_REMOVED, &payload);
+                        }
+
                         // === 分块传输请求（ChunkRequest / Complete / Cancel） ===
                         AppRequest::Transfer(TransferRequest::ChunkRequest {
                             session_id,
@@ -389,11 +744,20 @@ pub fn spawn_event_loop(
                         }) => {
                             let session = shared.transfer.get_send_session(&session_id);
                             let client = shared.client.clone();
+                            let shutting_down = shared.is_shutting_down();
 
                             tokio::spawn(async move {
                                 let response = match session {
+                                    _ if shutting_down => {
+                                        AppResponse::Transfer(TransferResponse::ChunkError {
+                                            session_id,
+                                            file_id,
+                                            chunk_index,
+                                            error: SHUTTING_DOWN_MESSAGE.into(),
+                                        })
+                                    }
                                     Some(s) => {
-                                        match s.handle_chunk_request(file_id, chunk_index).await {
+                                        match s.handle_chunk_request(&peer_id, file_id, chunk_index).await {
                                             Ok(resp) => AppResponse::Transfer(resp),
                                             Err(e) => {
                                                 warn!("ChunkRequest 处理失败: {}", e);
@@ -423,17 +787,8 @@ pub fn spawn_event_loop(
                         }
 
                         AppRequest::Transfer(TransferRequest::Complete { session_id }) => {
-                            // 获取统计数据后清理会话
-                            let (total_bytes, elapsed_ms) = shared
-                                .transfer
-                                .get_send_session(&session_id)
-                                .map(|s| {
-                                    s.handle_complete();
-                                    (s.total_bytes_sent(), s.elapsed_ms())
-                                })
-                                .unwrap_or((0, 0));
-                            shared.transfer.remove_send_session(&session_id);
-
+                            // DB 标记完成后再由会话发射完成事件并清理，保证前端刷新历史时状态已落库
+                            let transfer = shared.transfer.clone();
                             let client = shared.client.clone();
                             let app2 = app.clone();
                             tokio::spawn(async move {
@@ -461,15 +816,8 @@ pub fn spawn_event_loop(
                                     }
                                 }
 
-                                // 发送方也发射完成事件
-                                let event = crate::transfer::progress::TransferCompleteEvent {
-                                    session_id,
-                                    direction: TransferDirection::Send,
-                                    total_bytes,
-                                    elapsed_ms,
-                                    save_location: None,
-                                };
-                                let _ = app2.emit(events::TRANSFER_COMPLETE, &event);
+                                // 发送方也发射完成事件（基于自身进度统计）
+                                transfer.complete_send_session(&session_id);
                             });
                         }
 
@@ -479,21 +827,8 @@ pub fn spawn_event_loop(
                                 session_id, reason
                             );
 
-                            // 检查是否有发送会话
-                            if let Some(s) = shared.transfer.get_send_session(&session_id) {
-                                s.handle_cancel();
-                                shared.transfer.remove_send_session(&session_id);
-                            }
-
-                            // 检查是否有接收会话
-                            if let Some(s) = shared.transfer.get_receive_session(&session_id) {
-                                shared.transfer.remove_receive_session(&session_id);
-                                // 先等待 bitmap 刷写完成，再清理 .part 文件
-                                tokio::spawn(async move {
-                                    s.cancel_and_wait().await;
-                                    s.cleanup_part_files().await;
-                                });
-                            }
+                            // 结束本方的发送 / 接收会话（发送会话自行发射失败事件）
+                            let is_sender = shared.transfer.cancel_by_peer(&session_id, &reason);
 
                             // 回复 Ack + DB 标记取消（合并为一个异步任务）
                             let client = shared.client.clone();
@@ -515,13 +850,15 @@ pub fn spawn_event_loop(
                                 }
                             });
 
-                            // 发射失败事件
-                            let event = TransferFailedEvent {
-                                session_id,
-                                direction: TransferDirection::Unknown,
-                                error: format!("对方取消: {}", reason),
-                            };
-                            let _ = app.emit(events::TRANSFER_FAILED, &event);
+                            // 发射失败事件（发送方已由 SendSession 发射）
+                            if !is_sender {
+                                let event = TransferFailedEvent::new(
+                                    session_id,
+                                    TransferDirection::Unknown,
+                                    format!("对方取消: {}", reason),
+                                );
+                                let _ = app.emit(events::TRANSFER_FAILED, &event);
+                            }
                         }
 
                         AppRequest::Transfer(TransferRequest::Pause { session_id }) => {
@@ -592,65 +929,116 @@ pub fn spawn_event_loop(
                             session_id,
                             files,
                             total_size,
+                            directories,
+                            encryption,
+                            public_key,
+                            signature,
                         }) => {
-                            // 仅接受已配对设备的 Offer
+                            // 被屏蔽的节点：以通用原因静默拒绝，不推送给前端
+                            if shared.blocklist.contains(&peer_id) {
+                                info!(
+                                    "拒绝已屏蔽节点 {} 的传输请求: session={}",
+                                    peer_id, session_id
+                                );
+                                reject_offer(&shared, pending_id, OfferRejectReason::NotPaired);
+                                continue;
+                            }
+
+                            // 节点正在关闭：不再创建新的传输
+                            if shared.is_shutting_down() {
+                                info!(
+                                    "节点正在关闭，拒绝 {} 的传输请求: session={}",
+                                    peer_id, session_id
+                                );
+                                reject_offer(&shared, pending_id, OfferRejectReason::ShuttingDown);
+                                continue;
+                            }
+
+                            // 仅接受已配对设备的 Offer（对方仍认为已配对，说明本机已取消配对）
                             if !shared.pairing.is_paired(&peer_id) {
                                 warn!("Rejecting transfer offer from unpaired peer: {}", peer_id);
-                                let response =
-                                    AppResponse::Transfer(TransferResponse::OfferResult {
-                                        accepted: false,
-                                        key: None,
-                                        reason: Some(OfferRejectReason::NotPaired),
-                                    });
-                                let client = shared.client.clone();
-                                tokio::spawn(async move {
-                                    if let Err(e) = client.send_response(pending_id, response).await
-                                    {
-                                        warn!("Failed to reject offer: {}", e);
-                                    }
-                                });
+                                reject_offer(
+                                    &shared,
+                                    pending_id,
+                                    OfferRejectReason::NoLongerPaired,
+                                );
                                 continue;
                             }
 
-                            // 获取设备名
-                            let device_name = shared
-                                .pairing
-                                .get_paired_devices()
-                                .into_iter()
-                                .find(|d| d.peer_id == peer_id)
-                                .map(|d| d.os_info.hostname)
-                                .unwrap_or_else(|| {
+                            // 校验 Offer 内容，违规直接拒绝，不推送给前端
+                            if let Err(message) =
+                                shared
+                                    .transfer
+                                    .validate_inbound_offer(&files, &directories, total_size)
+                            {
+                                warn!(
+                                    "Rejecting invalid transfer offer from {}: session={}, {}",
+                                    peer_id, session_id, message
+                                );
+                                reject_offer(
+                                    &shared,
+                                    pending_id,
+                                    OfferRejectReason::InvalidOffer { message },
+                                );
+                                continue;
+                            }
+
+                            // 携带签名时必须由发送方身份签发；对端声明支持签名时不允许缺少签名
+                            // （旧版本不签名，跳过验证）
+                            let signature_required = shared
+                                .devices
+                                .capabilities(&peer_id)
+                                .is_some_and(|caps| caps.offer_signature);
+                            let signed = crate::transfer::signing::offer_payload(
+                                &session_id,
+                                &files,
+                                &directories,
+                                total_size,
+                                public_key.as_ref(),
+                            );
+                            if let Err(message) = crate::transfer::signing::check_offer_signature(
+                                signature.as_ref(),
+                                signature_required,
+                                &peer_id,
+                                &signed,
+                            ) {
+                                error!(
+                                    "Rejecting transfer offer with invalid signature from {}: session={}, {}",
+                                    peer_id, session_id, message
+                                );
+                                reject_offer(
+                                    &shared,
+                                    pending_id,
+                                    OfferRejectReason::InvalidSignature,
+                                );
+                                continue;
+                            }
+
+                            // 获取设备名（昵称优先）
+                            let device_name =
+                                shared.devices.paired_name(&peer_id).unwrap_or_else(|| {
                                     let s = peer_id.to_string();
                                     s[s.len().saturating_sub(8)..].to_string()
                                 });
 
-                            // 缓存入站 Offer
-                            shared.transfer.cache_inbound_offer(
+                            // 缓存入站 Offer 并通知前端
+                            let payload = shared.transfer.cache_inbound_offer(
                                 pending_id,
                                 peer_id,
                                 device_name.clone(),
                                 session_id,
-                                files.clone(),
+                                files,
+                                directories,
                                 total_size,
+                                encryption,
+                                public_key,
                             );
-
-                            // 通知前端
-                            let payload = TransferOfferPayload {
-                                session_id,
-                                peer_id: peer_id.to_string(),
-                                device_name: device_name.clone(),
-                                files: files
-                                    .into_iter()
-                                    .map(|f| TransferFilePayload {
-                                        file_id: f.file_id,
-                                        name: f.name,
-                                        relative_path: f.relative_path,
-                                        size: f.size,
-                                        is_directory: false,
-                                    })
-                                    .collect(),
-                                total_size,
-                            };
+                            // 附带默认保存位置，确认对话框据此预填
+                            let default_save_location = app
+                                .try_state::<crate::settings::SettingsStore>()
+                                .and_then(|s| s.effective_save_location(&app));
+                            let payload =
+                                payload.with_default_save_location(default_save_location);
                             let _ = app.emit(events::TRANSFER_OFFER, &payload);
 
                             notify_if_unfocused(
@@ -664,6 +1052,7 @@ pub fn spawn_event_loop(
                         AppRequest::Transfer(TransferRequest::ResumeRequest {
                             session_id,
                             file_checksums,
+                            public_key,
                         }) => {
                             info!(
                                 "收到断点续传请求: session={}, files={}",
@@ -674,13 +1063,22 @@ pub fn spawn_event_loop(
                             let client = shared.client.clone();
                             let app2 = app.clone();
                             let transfer = shared.transfer.clone();
+                            let local_peer_id = shared.peer_id;
+                            // 对端声明支持续传密钥交换时不允许缺少公钥（防止降级为明文密钥）
+                            let key_exchange_required = shared
+                                .devices
+                                .capabilities(&peer_id)
+                                .is_some_and(|caps| caps.resume_key_exchange);
 
                             tokio::spawn(async move {
                                 let response = handle_resume_request(
                                     &app2,
                                     session_id,
                                     peer_id,
+                                    local_peer_id,
                                     &file_checksums,
+                                    public_key,
+                                    key_exchange_required,
                                     &transfer,
                                 )
                                 .await;
@@ -698,6 +1096,7 @@ pub fn spawn_event_loop(
                             session_id,
                             key,
                             file_checksums,
+                            public_key,
                         }) => {
                             info!(
                                 "收到发送方断点续传请求: session={}, files={}",
@@ -705,36 +1104,48 @@ pub fn spawn_event_loop(
                                 file_checksums.len()
                             );
 
-                            let client = shared.client.clone();
                             let app2 = app.clone();
                             let transfer = shared.transfer.clone();
+                            let local_peer_id = shared.peer_id;
+                            let key_exchange_required = shared
+                                .devices
+                                .capabilities(&peer_id)
+                                .is_some_and(|caps| caps.resume_key_exchange);
 
                             tokio::spawn(async move {
-                                let response = handle_resume_offer(
+                                handle_resume_offer(
                                     &app2,
+                                    pending_id,
                                     session_id,
                                     peer_id,
-                                    &key,
+                                    local_peer_id,
+                                    key,
+                                    public_key,
+                                    key_exchange_required,
                                     &file_checksums,
                                     &transfer,
                                 )
                                 .await;
-                                if let Err(e) = client
-                                    .send_response(pending_id, AppResponse::Transfer(response))
-                                    .await
-                                {
-                                    warn!("发送 ResumeOfferResult 失败: {}", e);
-                                }
                             });
                         }
                     }
                 }
 
-                // === 信息事件（仅日志，打洞失败仍走 Relay，无需通知用户） ===
+                // === 诊断事件（打洞失败仍走 Relay，统计已由 handle_event 记录） ===
                 NodeEvent::HolePunchFailed { peer_id, error } => {
                     warn!("Hole punch failed with {}: {}", peer_id, error);
+                    let payload = HolePunchFailedPayload {
+                        peer_id,
+                        error: error.to_string(),
+                    };
+                    let _ = app.emit(events::HOLE_PUNCH_FAILED, &payload);
                 }
             }
         }
+
+        info!(
+            "事件循环已退出，共推送设备 / 网络状态 {} 次",
+            status_emitter.emitted()
+        );
     });
 }
@@ -1,16 +1,28 @@
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use dashmap::DashMap;
-use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
-use tokio::sync::Mutex;
+use swarm_p2p_core::libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Mutex, Notify};
 use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
-use super::{NatStatus, NetworkStatus, NodeStatus};
-use crate::device::{DeviceManager, PairedDeviceInfo};
-use crate::pairing::manager::PairingManager;
+use super::blocklist::BlockList;
+use super::config::{NetworkOptions, NetworkTimeouts, PeerSources};
+use super::power_save::PowerSave;
+use super::traffic::TrafficStats;
+use super::{BootstrapStatus, NatStatus, NetworkStatus, NodeStatus, RelayStatus};
+use crate::device::{ConnectionType, DeviceFilter, DeviceManager, PairedDeviceInfo};
+use crate::events;
+use crate::pairing::manager::{ExternalAddrs, PairedReconnectResult, PairingManager};
 use crate::protocol::AppNetClient;
+use crate::settings::AppSettings;
+use crate::transfer::keep_alive::KeepAlive;
 use crate::transfer::offer::TransferManager;
+use crate::AppResult;
 
 /// 网络管理器
 ///
@@ -24,19 +36,43 @@ pub struct NetManager {
     transfer: Arc<TransferManager>,
     /// 全局取消令牌（shutdown 时取消所有后台任务）
     cancel_token: CancellationToken,
+    /// 在线记录刷新任务的取消令牌（全局令牌的子令牌，宣布下线前单独取消）
+    online_refresh_token: CancellationToken,
+    /// 通知在线记录刷新任务立即重新发布
+    online_refresh: Arc<Notify>,
     // 网络状态（Arc<RwLock> 供事件循环并发更新）
     listen_addrs: Arc<RwLock<Vec<Multiaddr>>>,
     nat_status: Arc<RwLock<NatStatus>>,
     public_addr: Arc<RwLock<Option<Multiaddr>>>,
     /// 当前已连接的中继节点 PeerId 集合
     relay_peers: Arc<RwLock<HashSet<PeerId>>>,
+    /// 启动时生效的网络功能开关
+    options: NetworkOptions,
+    /// 配置的中继节点（展示各自的预约状态）
+    relay_candidates: Arc<[PeerId]>,
+    /// 配置的引导节点（诊断时展示各自的连接状态）
+    bootstrap_peers: Arc<[PeerId]>,
+    /// 按连接类型累计的流量统计
+    traffic: Arc<TrafficStats>,
+    /// 被屏蔽的节点
+    blocklist: Arc<BlockList>,
+    /// 省电模式（开启时暂停在线记录刷新与已配对设备在线检查）
+    power_save: PowerSave,
+    /// shutdown 已开始（每次 start 创建新的 NetManager，标志随之重置）
+    shutting_down: Arc<AtomicBool>,
 }
 
 impl NetManager {
+    #[expect(clippy::too_many_arguments, reason = "节点启动配置逐项传入")]
     pub fn new(
         client: AppNetClient,
         peer_id: PeerId,
         paired_devices: Vec<PairedDeviceInfo>,
+        timeouts: &NetworkTimeouts,
+        options: NetworkOptions,
+        peers: &PeerSources,
+        traffic: Arc<TrafficStats>,
+        settings: &AppSettings,
     ) -> Self {
         // 创建共享的已配对设备 Map：PairingManager 读写，DeviceManager 只读
         let paired_map: Arc<DashMap<_, _>> = Arc::new(
@@ -46,18 +82,43 @@ impl NetManager {
                 .collect(),
         );
 
-        let pairing = Arc::new(PairingManager::new(
-            client.clone(),
-            peer_id,
-            paired_map.clone(),
-        ));
-        let devices = Arc::new(DeviceManager::new(paired_map));
-        let transfer = Arc::new(TransferManager::new(client.clone()));
+        let public_addr = Arc::new(RwLock::new(None));
+        let relay_peers = Arc::new(RwLock::new(HashSet::new()));
+        let devices = Arc::new(
+            DeviceManager::new(paired_map.clone())
+                .with_stale_peer_window(timeouts.stale_peer_window()),
+        );
+        let pairing = Arc::new(
+            PairingManager::new(client.clone(), peer_id, paired_map)
+                .with_dial_timeout(timeouts.dial_timeout())
+                .with_relay_enabled(options.relay_client)
+                .with_external_addrs(ExternalAddrs {
+                    public_addr: public_addr.clone(),
+                    relay_peers: relay_peers.clone(),
+                    devices: devices.clone(),
+                }),
+        );
+        let transfer = Arc::new(
+            TransferManager::new(client.clone(), peer_id, devices.clone())
+                .with_traffic_stats(traffic.clone())
+                .with_offer_limits(settings.offer_limits())
+                .with_chunk_budget(settings.chunk_budget()),
+        );
         let cancel_token = CancellationToken::new();
 
+        // 同一引导节点可能配置了多个地址，只保留一次
+        let mut bootstrap_peers: Vec<PeerId> = Vec::new();
+        for (peer_id, _) in &peers.bootstrap_peers {
+            if !bootstrap_peers.contains(peer_id) {
+                bootstrap_peers.push(*peer_id);
+            }
+        }
+
         // 启动传输资源超时清理任务
         transfer.spawn_cleanup_task(cancel_token.clone());
 
+        let online_refresh_token = cancel_token.child_token();
+
         Self {
             client,
             peer_id,
@@ -65,13 +126,34 @@ impl NetManager {
             devices,
             transfer,
             cancel_token,
+            online_refresh_token,
+            online_refresh: Arc::new(Notify::new()),
             listen_addrs: Arc::new(RwLock::new(Vec::new())),
             nat_status: Arc::new(RwLock::new(NatStatus::Unknown)),
-            public_addr: Arc::new(RwLock::new(None)),
-            relay_peers: Arc::new(RwLock::new(HashSet::new())),
+            public_addr,
+            relay_peers,
+            options,
+            relay_candidates: peers.relay_peers.clone().into(),
+            bootstrap_peers: bootstrap_peers.into(),
+            traffic,
+            blocklist: Arc::default(),
+            power_save: PowerSave::default(),
+            shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// 使用持久化的屏蔽列表（`start` 时加载）
+    pub fn with_blocklist(mut self, blocklist: BlockList) -> Self {
+        self.blocklist = Arc::new(blocklist);
+        self
+    }
+
+    /// 使用 Tauri 托管的省电模式开关（跨节点重启保留）
+    pub fn with_power_save(mut self, power_save: PowerSave) -> Self {
+        self.power_save = power_save;
+        self
+    }
+
     pub fn pairing(&self) -> &PairingManager {
         &self.pairing
     }
@@ -80,6 +162,66 @@ impl NetManager {
         &self.devices
     }
 
+    /// 当前与指定 peer 的连接路径（局域网 / 打洞直连 / 中继，未连接时为 None）
+    pub fn connection_type(&self, peer_id: &PeerId) -> Option<ConnectionType> {
+        self.devices.connection_type(peer_id)
+    }
+
+    pub fn traffic(&self) -> &TrafficStats {
+        &self.traffic
+    }
+
+    pub fn blocklist(&self) -> &BlockList {
+        &self.blocklist
+    }
+
+    /// 取消配对，返回是否原本已配对
+    ///
+    /// 对端当前已连接时在后台通知其同样移除（不等待结果）；对端离线时不通知，
+    /// 之后它发来的 Offer 会以 [`OfferRejectReason::NoLongerPaired`] 拒绝。
+    ///
+    /// [`OfferRejectReason::NoLongerPaired`]: crate::protocol::OfferRejectReason::NoLongerPaired
+    pub fn unpair(&self, peer_id: PeerId) -> bool {
+        let removed = self.pairing.remove_paired_device(&peer_id).is_some();
+        if removed && self.devices.is_connected(&peer_id) {
+            let pairing = self.pairing.clone();
+            tokio::spawn(async move { pairing.notify_unpaired(peer_id).await });
+        }
+        removed
+    }
+
+    /// 设置或清除已配对设备的昵称，推送 `paired-device-updated` 并刷新设备列表
+    pub fn set_device_nickname(
+        &self,
+        app: &AppHandle,
+        peer_id: PeerId,
+        nickname: Option<&str>,
+    ) -> AppResult<PairedDeviceInfo> {
+        let info = self.pairing.set_nickname(&peer_id, nickname)?;
+        let _ = app.emit(events::PAIRED_DEVICE_UPDATED, &info);
+        let _ = app.emit(
+            events::DEVICES_CHANGED,
+            self.devices.get_devices(&DeviceFilter::All.into()),
+        );
+        Ok(info)
+    }
+
+    /// 屏蔽节点；已配对时同时取消配对并刷新设备列表
+    ///
+    /// 返回是否移除了配对（前端据此同步更新 Stronghold）。
+    pub fn block_peer(&self, app: &AppHandle, peer_id: PeerId) -> bool {
+        self.blocklist.block(peer_id);
+        let unpaired = self.pairing.remove_paired_device(&peer_id).is_some();
+        if unpaired {
+            info!("已屏蔽并取消配对: {}", peer_id);
+            let _ = app.emit(
+                events::DEVICES_CHANGED,
+                self.devices.get_devices(&DeviceFilter::All.into()),
+            );
+        }
+        unpaired
+    }
+
     pub fn transfer(&self) -> &TransferManager {
         &self.transfer
     }
@@ -92,9 +234,224 @@ impl NetManager {
         &self.client
     }
 
-    /// 取消所有后台任务（shutdown 时调用）
+    /// 启动总体进度推送任务（随 shutdown 一并停止）
+    ///
+    /// Android 上同时驱动传输前台服务，桌面端不需要保活。
+    pub fn spawn_overall_progress(&self, app: AppHandle) {
+        #[cfg(target_os = "android")]
+        let keep_alive = {
+            use tauri::Manager;
+            app.try_state::<crate::mobile::TransferServicePlugin<tauri::Wry>>()
+                .map(|plugin| Arc::new(plugin.inner().clone()) as Arc<dyn KeepAlive>)
+        };
+        #[cfg(not(target_os = "android"))]
+        let keep_alive: Option<Arc<dyn KeepAlive>> = None;
+
+        self.transfer.spawn_overall_progress_task(
+            Arc::new(app),
+            keep_alive,
+            self.cancel_token.clone(),
+        );
+    }
+
+    /// 启动 DHT bootstrap 任务（随 shutdown 一并停止）
+    ///
+    /// 引导节点暂时不可达时按 [`bootstrap_retry_delay`] 退避重试，避免应用在无 DHT、
+    /// 无中继的降级状态下一直停留到重启。bootstrap 成功后宣布上线、检查已配对设备，
+    /// 并推送 `network-status-changed`。
+    pub fn spawn_bootstrap(&self, app: AppHandle, timeout: Duration) {
+        let shared = self.shared_refs();
+        let cancel_token = self.cancel_token.clone();
+        tokio::spawn(async move {
+            let mut attempt = 0;
+            loop {
+                let bootstrap = tokio::time::timeout(timeout, shared.client.bootstrap());
+                let succeeded = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    result = bootstrap => match result {
+                        Ok(Ok(result)) => {
+                            info!("DHT bootstrap completed: {:?}", result);
+                            true
+                        }
+                        Ok(Err(e)) => {
+                            warn!("DHT bootstrap failed: {}", e);
+                            false
+                        }
+                        Err(_) => {
+                            warn!("DHT bootstrap timed out after {:?}", timeout);
+                            false
+                        }
+                    },
+                };
+                if succeeded {
+                    // 自定义引导节点的 agent 可能无法识别，以 bootstrap 成功为准
+                    shared.pairing.set_dht_ready(true);
+                    let _ = app.emit(
+                        events::NETWORK_STATUS_CHANGED,
+                        &shared.build_network_status(),
+                    );
+                    break;
+                }
+
+                let delay = bootstrap_retry_delay(attempt);
+                attempt += 1;
+                info!("{:?} 后重试 DHT bootstrap（第 {} 次重试）", delay, attempt);
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        info!("DHT bootstrap 重试任务已停止");
+                        return;
+                    }
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            if cancel_token.is_cancelled() {
+                return;
+            }
+
+            // 宣布上线（等待 DHT 可用后发布，bootstrap 前发布必然失败）
+            if let Err(e) = shared.pairing.announce_online().await {
+                warn!("Failed to announce online: {}", e);
+            }
+            // 查询已配对设备的在线记录并注册地址
+            let result = shared
+                .pairing
+                .check_paired_online(|peer_id| shared.devices.is_connected(peer_id))
+                .await;
+            report_paired_check(&app, &shared.devices, &result);
+        });
+    }
+
+    /// 启动已配对设备在线检查任务（随 shutdown 一并停止）
+    ///
+    /// 每隔 `interval` 对离线的已配对设备执行一次 [`PairingManager::check_paired_online`]，
+    /// 解决"本机先启动、对方后上线"时对方永远不会被拨号的问题。
+    /// 没有已配对设备时逐步拉长检查间隔（最多 [`PAIRED_CHECK_MAX_BACKOFF`] 倍）。
+    /// 省电模式开启期间暂停检查，关闭后立即检查一次。
+    pub fn spawn_paired_check(&self, app: AppHandle, interval: Duration) {
+        let pairing = self.pairing.clone();
+        let devices = self.devices.clone();
+        let power_save = self.power_save.clone();
+        let cancel_token = self.cancel_token.clone();
+        tokio::spawn(async move {
+            let mut delay = interval;
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                if power_save.is_enabled() {
+                    debug!("省电模式已开启，暂停已配对设备在线检查");
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = power_save.wait_until_disabled() => {}
+                    }
+                }
+                if !pairing.has_paired_devices() {
+                    delay = (delay * 2).min(interval * PAIRED_CHECK_MAX_BACKOFF);
+                    continue;
+                }
+                delay = interval;
+
+                let result = pairing
+                    .check_paired_online(|peer_id| devices.is_connected(peer_id))
+                    .await;
+                report_paired_check(&app, &devices, &result);
+            }
+            info!("已配对设备在线检查任务已停止");
+        });
+    }
+
+    /// 启动过期 peer 清理任务（随 shutdown 一并停止）
+    ///
+    /// 每隔 [`PEER_PRUNE_INTERVAL`] 清理一次长期未见的未配对 peer，有清理时刷新设备列表。
+    pub fn spawn_peer_prune(&self, app: AppHandle) {
+        let devices = self.devices.clone();
+        let cancel_token = self.cancel_token.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = tokio::time::sleep(PEER_PRUNE_INTERVAL) => {}
+                }
+                let pruned = devices.prune_stale();
+                if pruned > 0 {
+                    debug!("已清理 {} 个过期 peer", pruned);
+                    let _ = app.emit(
+                        events::DEVICES_CHANGED,
+                        devices.get_devices(&DeviceFilter::All.into()),
+                    );
+                }
+            }
+            info!("过期 peer 清理任务已停止");
+        });
+    }
+
+    /// 启动在线记录定时刷新任务（随 shutdown 或宣布下线一并停止）
+    ///
+    /// 在线记录有效期 300 秒，只发布一次会导致其他设备找不到本机。
+    pub fn spawn_online_refresh(&self) {
+        self.pairing.spawn_online_refresh_task(
+            self.online_refresh.clone(),
+            self.power_save.clone(),
+            self.online_refresh_token.clone(),
+        );
+    }
+
+    /// 手动拨号指定节点（排查连通性用，未配对设备同样适用）
+    ///
+    /// 先把 `addrs` 注册到地址簿再拨号，失败时原样返回底层 libp2p 错误。
+    /// 拨通后推送 `devices-changed`，返回 [`DeviceManager`] 记录的连接类型
+    /// （事件循环尚未处理连接事件时为 `None`）。
+    pub async fn dial_peer(
+        &self,
+        app: &AppHandle,
+        peer_id: PeerId,
+        addrs: Vec<Multiaddr>,
+    ) -> AppResult<Option<ConnectionType>> {
+        if !addrs.is_empty() {
+            self.client.add_peer_addrs(peer_id, addrs).await?;
+        }
+        self.client.dial(peer_id).await?;
+        info!("手动拨号 {} 成功", peer_id);
+
+        report_reached_paired(app, &self.devices, &[peer_id]);
+        Ok(self.devices.connection_type(&peer_id))
+    }
+
+    /// 手动重连全部（`target` 为 None）或指定的已配对设备
+    ///
+    /// 拨通的设备与后台在线检查一样推送上线事件。
+    pub async fn reconnect_paired(
+        &self,
+        app: &AppHandle,
+        target: Option<PeerId>,
+    ) -> AppResult<PairedReconnectResult> {
+        let result = self
+            .pairing
+            .reconnect_paired(target, |peer_id| self.devices.is_connected(peer_id))
+            .await?;
+        report_paired_check(app, &self.devices, &result);
+        Ok(result)
+    }
+
+    /// 宣布下线：先停止在线记录刷新，避免下线后又被重新发布
+    pub async fn announce_offline(&self) -> AppResult<()> {
+        self.online_refresh_token.cancel();
+        self.pairing.announce_offline().await
+    }
+
+    /// 标记节点正在关闭（shutdown 开始时调用）
+    ///
+    /// 此后事件循环收到的 Offer 与分块请求直接以"节点正在关闭"拒绝，
+    /// 不再操作正在拆除的传输状态。
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Release);
+    }
+
+    /// 取消所有后台任务（shutdown 时调用），并结算、保存流量统计
     pub fn cancel_background_tasks(&self) {
         self.cancel_token.cancel();
+        self.traffic.close_all();
     }
 
     /// 获取当前网络状态快照
@@ -102,6 +459,18 @@ impl NetManager {
         self.shared_refs().build_network_status()
     }
 
+    /// 配置的引导节点及各自的连接状态（按配置顺序）
+    pub fn bootstrap_statuses(&self) -> Vec<BootstrapStatus> {
+        self.bootstrap_peers
+            .iter()
+            .map(|peer_id| BootstrapStatus {
+                peer_id: *peer_id,
+                connected: self.devices.is_connected(peer_id),
+                rtt_ms: self.devices.rtt_ms(peer_id),
+            })
+            .collect()
+    }
+
     /// 获取事件循环需要的共享引用
     pub(crate) fn shared_refs(&self) -> SharedNetRefs {
         SharedNetRefs {
@@ -114,6 +483,12 @@ impl NetManager {
             nat_status: self.nat_status.clone(),
             public_addr: self.public_addr.clone(),
             relay_peers: self.relay_peers.clone(),
+            online_refresh: self.online_refresh.clone(),
+            options: self.options,
+            relay_candidates: self.relay_candidates.clone(),
+            traffic: self.traffic.clone(),
+            blocklist: self.blocklist.clone(),
+            shutting_down: self.shutting_down.clone(),
         }
     }
 }
@@ -122,6 +497,7 @@ impl NetManager {
 ///
 /// 持有与 [`NetManager`] 相同的 Arc 引用，
 /// 供 [`spawn_event_loop`](super::spawn_event_loop) 在独立 tokio task 中更新网络状态。
+#[derive(Clone)]
 pub(crate) struct SharedNetRefs {
     pub peer_id: PeerId,
     pub client: AppNetClient,
@@ -132,9 +508,21 @@ pub(crate) struct SharedNetRefs {
     pub nat_status: Arc<RwLock<NatStatus>>,
     pub public_addr: Arc<RwLock<Option<Multiaddr>>>,
     pub relay_peers: Arc<RwLock<HashSet<PeerId>>>,
+    /// 地址集合变化时通知在线记录刷新任务立即重新发布
+    pub online_refresh: Arc<Notify>,
+    pub options: NetworkOptions,
+    pub relay_candidates: Arc<[PeerId]>,
+    pub traffic: Arc<TrafficStats>,
+    pub blocklist: Arc<BlockList>,
+    pub shutting_down: Arc<AtomicBool>,
 }
 
 impl SharedNetRefs {
+    /// 节点是否正在关闭（见 [`NetManager::begin_shutdown`]）
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Acquire)
+    }
+
     /// 构建当前网络状态快照
     pub fn build_network_status(&self) -> NetworkStatus {
         let relay_peers_list: Vec<PeerId> = self
@@ -143,6 +531,8 @@ impl SharedNetRefs {
             .map(|g| g.iter().copied().collect())
             .unwrap_or_default();
 
+        let relays = relay_statuses(&self.relay_candidates, &relay_peers_list);
+        let bootstrap_peer = self.devices.connected_bootstrap_peer();
         NetworkStatus {
             status: NodeStatus::Running,
             peer_id: Some(self.peer_id),
@@ -153,11 +543,186 @@ impl SharedNetRefs {
             discovered_peers: self.devices.discovered_count(),
             relay_ready: !relay_peers_list.is_empty(),
             relay_peers: relay_peers_list,
-            bootstrap_connected: self.devices.has_connected_bootstrap_peer(),
+            bootstrap_connected: bootstrap_peer.is_some(),
+            bootstrap_peer,
+            options: Some(self.options),
+            relays,
         }
     }
 }
 
+/// 各中继节点的预约状态：配置的中继按顺序在前，其余已预约的节点追加在后
+fn relay_statuses(candidates: &[PeerId], reserved: &[PeerId]) -> Vec<RelayStatus> {
+    let mut statuses: Vec<RelayStatus> = candidates
+        .iter()
+        .map(|peer_id| RelayStatus {
+            peer_id: *peer_id,
+            reserved: reserved.contains(peer_id),
+        })
+        .collect();
+    statuses.extend(
+        reserved
+            .iter()
+            .filter(|peer_id| !candidates.contains(peer_id))
+            .map(|peer_id| RelayStatus {
+                peer_id: *peer_id,
+                reserved: true,
+            }),
+    );
+    statuses
+}
+
+/// DHT bootstrap 前几次失败后的重试间隔
+const BOOTSTRAP_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(5),
+    Duration::from_secs(15),
+    Duration::from_secs(60),
+];
+
+/// 前几次重试仍失败后的固定重试间隔
+const BOOTSTRAP_RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 第 `attempt` 次（从 0 开始）bootstrap 失败后的等待时间
+fn bootstrap_retry_delay(attempt: usize) -> Duration {
+    BOOTSTRAP_RETRY_DELAYS
+        .get(attempt)
+        .copied()
+        .unwrap_or(BOOTSTRAP_RETRY_INTERVAL)
+}
+
+/// 没有已配对设备时，在线检查间隔最多拉长到的倍数
+const PAIRED_CHECK_MAX_BACKOFF: u32 = 4;
+
+/// 过期 peer 清理间隔
+const PEER_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 上报拨通的设备：已配对设备逐个推送上线事件，并刷新设备列表
+fn report_reached_paired(app: &AppHandle, devices: &DeviceManager, reached: &[PeerId]) {
+    if reached.is_empty() {
+        return;
+    }
+    for peer_id in reached {
+        if let Some(change) = devices.mark_paired_online(peer_id) {
+            super::emit_presence_change(app, devices, change);
+        }
+    }
+    let _ = app.emit(
+        events::DEVICES_CHANGED,
+        devices.get_devices(&DeviceFilter::All.into()),
+    );
+}
+
+/// 上报已配对设备在线检查结果：`last_seen_at` 有更新的设备推送完整记录供前端保存，
+/// 拨通的设备推送上线事件
+fn report_paired_check(app: &AppHandle, devices: &DeviceManager, result: &PairedReconnectResult) {
+    for info in &result.seen {
+        let _ = app.emit(events::PAIRED_DEVICE_UPDATED, info);
+    }
+    report_reached_paired(app, devices, &result.dialed);
+}
+
+/// 局域网拨号超时（超时后沿用现有连接，不阻塞传输）
+const LAN_DIAL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 传输开始前尽量走局域网直连
+///
+/// 两端在同一局域网、但通过互联网配对时，地址簿可能先拨通中继地址。
+/// 这里从 [`DeviceManager`] 取出对端已知的局域网地址（私有 / 链路本地优先），
+/// 重新注册到地址簿并主动拨号。拨号失败或超时则静默沿用现有连接。
+///
+/// 返回拨号后的连接类型，供进度事件展示。
+pub async fn ensure_best_connection(
+    client: &AppNetClient,
+    devices: &DeviceManager,
+    peer_id: PeerId,
+) -> Option<ConnectionType> {
+    let lan_addrs = devices.lan_addrs(&peer_id);
+    if lan_addrs.is_empty() {
+        return devices.connection_type(&peer_id);
+    }
+
+    debug!("尝试局域网直连 {}: {:?}", peer_id, lan_addrs);
+    let dial = async {
+        client.add_peer_addrs(peer_id, lan_addrs).await?;
+        client.dial(peer_id).await
+    };
+    match tokio::time::timeout(LAN_DIAL_TIMEOUT, dial).await {
+        Ok(Ok(_)) => info!("已向 {} 发起局域网直连", peer_id),
+        Ok(Err(e)) => debug!("局域网拨号失败，沿用现有连接: {}", e),
+        Err(_) => debug!("局域网拨号超时，沿用现有连接: {}", peer_id),
+    }
+
+    devices.connection_type(&peer_id)
+}
+
+/// 等待打洞直连的最长时间（前端传入的等待时间超出时截断）
+pub const MAX_PREFER_DIRECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 等待打洞直连的最小传输大小，小文件走中继也很快，不值得等待
+pub const PREFER_DIRECT_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 等待打洞直连时检查连接类型的间隔
+const DIRECT_UPGRADE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 当前仅有中继连接时，等待 DCUtR 打洞升级为直连
+///
+/// 中继带宽通常受限，大文件传输值得多等一会儿。打洞成功时事件循环会把
+/// `HolePunchSucceeded` 记录到 [`DeviceManager`]，连接类型随之变为 `Dcutr`。
+/// 超时（最长 [`MAX_PREFER_DIRECT_TIMEOUT`]）仍未升级则沿用中继连接。
+///
+/// 返回等待结束时的连接类型。
+pub async fn wait_for_direct_connection(
+    devices: &DeviceManager,
+    peer_id: PeerId,
+    timeout: Duration,
+) -> Option<ConnectionType> {
+    let deadline = tokio::time::Instant::now() + timeout.min(MAX_PREFER_DIRECT_TIMEOUT);
+    loop {
+        let connection = devices.connection_type(&peer_id);
+        if connection != Some(ConnectionType::Relay) {
+            return connection;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            info!("等待 {} 打洞直连超时，使用中继连接传输", peer_id);
+            return connection;
+        }
+        tokio::time::sleep(DIRECT_UPGRADE_POLL_INTERVAL).await;
+    }
+}
+
+/// 插入监听地址：已存在时忽略，否则按 [`listen_addr_order`] 插入到稳定位置
+///
+/// 返回是否有变化（无变化时无需推送网络状态）。
+pub(super) fn insert_listen_addr(addrs: &mut Vec<Multiaddr>, addr: Multiaddr) -> bool {
+    if addrs.contains(&addr) {
+        return false;
+    }
+    let key = listen_addr_order(&addr);
+    let index = addrs.partition_point(|a| listen_addr_order(a) <= key);
+    addrs.insert(index, addr);
+    true
+}
+
+/// 监听地址的展示顺序：回环地址最后；其余 QUIC、TCP、中继电路依次排列，同类按字典序
+fn listen_addr_order(addr: &Multiaddr) -> (bool, u8, String) {
+    let mut loopback = false;
+    let mut transport = 3;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::Ip4(ip) => loopback = ip.is_loopback(),
+            Protocol::Ip6(ip) => loopback = ip.is_loopback(),
+            Protocol::QuicV1 | Protocol::Quic => transport = transport.min(0),
+            Protocol::Tcp(_) => transport = transport.min(1),
+            Protocol::P2pCircuit => {
+                transport = 2;
+                break;
+            }
+            _ => {}
+        }
+    }
+    (loopback, transport, addr.to_string())
+}
+
 /// 读取 RwLock，中毒时返回默认值
 fn read_or<T: Clone>(lock: &RwLock<T>, default: T) -> T {
     lock.read().map(|g| g.clone()).unwrap_or(default)
@@ -165,3 +730,100 @@ fn read_or<T: Clone>(lock: &RwLock<T>, default: T) -> T {
 
 /// Tauri 状态中的网络管理器容器
 pub type NetManagerState = Mutex<Option<NetManager>>;
+
+#[cfg(test)]
+mod tests {
+    use swarm_p2p_core::NodeEvent;
+
+    use super::*;
+
+    #[test]
+    fn test_relay_statuses() {
+        let (a, b, extra) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let statuses = relay_statuses(&[a, b], &[extra, b]);
+
+        let summary: Vec<(PeerId, bool)> =
+            statuses.iter().map(|s| (s.peer_id, s.reserved)).collect();
+        assert_eq!(summary, vec![(a, false), (b, true), (extra, true)]);
+    }
+
+    #[test]
+    fn test_insert_listen_addr() {
+        let relay = PeerId::random();
+        let parse = |s: &str| -> Multiaddr { s.parse().unwrap() };
+        let loopback_quic = parse("/ip4/127.0.0.1/udp/4001/quic-v1");
+        let lan_tcp = parse("/ip4/192.168.1.5/tcp/4001");
+        let lan_quic = parse("/ip4/192.168.1.5/udp/4001/quic-v1");
+        let lan_quic_v6 = parse("/ip6/fd00::5/udp/4001/quic-v1");
+        let circuit = parse(&format!(
+            "/ip4/203.0.113.1/tcp/4001/p2p/{relay}/p2p-circuit"
+        ));
+
+        let mut addrs = Vec::new();
+        for addr in [&loopback_quic, &circuit, &lan_tcp, &lan_quic_v6, &lan_quic] {
+            assert!(insert_listen_addr(&mut addrs, addr.clone()));
+        }
+        // 监听器重启后重复上报：不重复添加
+        assert!(!insert_listen_addr(&mut addrs, lan_tcp.clone()));
+
+        assert_eq!(
+            addrs,
+            vec![lan_quic, lan_quic_v6, lan_tcp, circuit, loopback_quic]
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_retry_delay() {
+        let delays: Vec<u64> = (0..6).map(|i| bootstrap_retry_delay(i).as_secs()).collect();
+        assert_eq!(delays, vec![5, 15, 60, 300, 300, 300]);
+    }
+
+    /// 仅通过中继连接的 peer
+    fn relayed_peer(devices: &DeviceManager) -> PeerId {
+        let peer_id = PeerId::random();
+        let relay_addr: Multiaddr = format!(
+            "/ip4/203.0.113.1/tcp/4001/p2p/{}/p2p-circuit",
+            PeerId::random()
+        )
+        .parse()
+        .unwrap();
+        devices.handle_event(&NodeEvent::PeersDiscovered {
+            peers: vec![(peer_id, relay_addr)],
+        });
+        devices.handle_event(&NodeEvent::PeerConnected { peer_id });
+        peer_id
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_direct_upgrade() {
+        let devices = DeviceManager::new(Arc::new(DashMap::new()));
+        let peer_id = relayed_peer(&devices);
+        assert_eq!(
+            devices.connection_type(&peer_id),
+            Some(ConnectionType::Relay)
+        );
+
+        // 等待期间打洞成功：立即返回直连
+        let upgrade = async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            devices.handle_event(&NodeEvent::HolePunchSucceeded { peer_id });
+        };
+        let start = std::time::Instant::now();
+        let (connection, _) = tokio::join!(
+            wait_for_direct_connection(&devices, peer_id, Duration::from_secs(5)),
+            upgrade
+        );
+        assert_eq!(connection, Some(ConnectionType::Dcutr));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_direct_falls_back_to_relay() {
+        let devices = DeviceManager::new(Arc::new(DashMap::new()));
+        let peer_id = relayed_peer(&devices);
+
+        let connection =
+            wait_for_direct_connection(&devices, peer_id, Duration::from_millis(300)).await;
+        assert_eq!(connection, Some(ConnectionType::Relay));
+    }
+}
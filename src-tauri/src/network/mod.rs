@@ -6,17 +6,27 @@
 //! [`PairingManager`](crate::pairing::manager::PairingManager)，
 //! 对外提供统一的网络管理接口。
 
+pub mod blocklist;
 pub mod config;
 mod event_loop;
 mod manager;
+pub mod power_save;
+mod throttle;
+pub mod traffic;
 
 pub use event_loop::spawn_event_loop;
-pub use manager::{NetManager, NetManagerState};
+pub(crate) use event_loop::emit_presence_change;
+pub use manager::{
+    ensure_best_connection, wait_for_direct_connection, NetManager, NetManagerState,
+    PREFER_DIRECT_MIN_SIZE,
+};
 pub use swarm_p2p_core::event::NatStatus;
 
 use serde::Serialize;
 use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
 
+use config::NetworkOptions;
+
 /// 节点运行状态
 #[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -43,4 +53,29 @@ pub struct NetworkStatus {
     pub relay_peers: Vec<PeerId>,
     /// 是否至少有一个引导节点已连接
     pub bootstrap_connected: bool,
+    /// 当前连接的引导节点（诊断页展示）
+    pub bootstrap_peer: Option<PeerId>,
+    /// 生效中的网络功能开关（节点未运行时为 None）
+    pub options: Option<NetworkOptions>,
+    /// 各中继节点的预约状态（配置的中继在前）
+    pub relays: Vec<RelayStatus>,
+}
+
+/// 单个引导节点的连接状态（诊断用）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapStatus {
+    pub peer_id: PeerId,
+    pub connected: bool,
+    /// 最近一次测得的 RTT（毫秒，未连接时为 None）
+    pub rtt_ms: Option<u64>,
+}
+
+/// 单个中继节点的预约状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatus {
+    pub peer_id: PeerId,
+    /// 是否已预约成功（可经其被其他设备连接）
+    pub reserved: bool,
 }
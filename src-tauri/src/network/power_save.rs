@@ -0,0 +1,83 @@
+//! 省电模式
+//!
+//! 开启后暂停两项周期性的 DHT 活动：在线记录刷新（见
+//! [`PairingManager::spawn_online_refresh_task`](crate::pairing::manager::PairingManager::spawn_online_refresh_task)）
+//! 与已配对设备在线检查（见 [`NetManager::spawn_paired_check`](super::NetManager::spawn_paired_check)）。
+//! 已建立的连接、事件循环和进行中的传输不受影响；libp2p 的 ping 由 core 配置，同样不暂停。
+//!
+//! 代价：在线记录有效期 300 秒，暂停后过期，之后才上线的设备查不到本机地址，
+//! 发往本机的 Offer 可能丢失；本机也不会主动拨号后上线的已配对设备。
+//! 关闭省电模式时两项任务立即各执行一次。
+//!
+//! 状态由 Tauri 托管，跨节点重启保留；开始传输时自动关闭。
+//! 移动端随窗口焦点切换（切到后台开启、回到前台关闭），桌面端需要保持可被发现，只能手动开启。
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// 省电模式开关（克隆后共享同一状态）
+#[derive(Debug, Clone)]
+pub struct PowerSave {
+    enabled: Arc<watch::Sender<bool>>,
+}
+
+impl Default for PowerSave {
+    fn default() -> Self {
+        Self {
+            enabled: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl PowerSave {
+    pub fn is_enabled(&self) -> bool {
+        *self.enabled.borrow()
+    }
+
+    /// 开启或关闭，返回状态是否发生变化
+    pub fn set(&self, enabled: bool) -> bool {
+        self.enabled.send_if_modified(|current| {
+            let changed = *current != enabled;
+            *current = enabled;
+            changed
+        })
+    }
+
+    /// 等待省电模式关闭（未开启时立即返回）
+    pub async fn wait_until_disabled(&self) {
+        let mut rx = self.enabled.subscribe();
+        // Sender 由自身持有，不会被关闭
+        let _ = rx.wait_for(|enabled| !enabled).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_until_disabled() {
+        let power_save = PowerSave::default();
+        assert!(!power_save.is_enabled());
+        // 未开启时立即返回
+        power_save.wait_until_disabled().await;
+
+        assert!(power_save.set(true));
+        assert!(!power_save.set(true));
+        let waiter = tokio::spawn({
+            let power_save = power_save.clone();
+            async move { power_save.wait_until_disabled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        assert!(power_save.set(false));
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+//! 设备列表 / 网络状态事件节流
+//!
+//! 每个 peer 每隔几秒就会触发一次 `PingSuccess`，若每次都重建并推送完整设备列表，
+//! 连接多台设备时 IPC 压力很大（低端 Android 上明显卡顿）。
+//! [`StatusEmitter`] 按事件的信号强度分三档推送（payload 不变，前端无需修改）：
+//! - 连接建立 / 断开：立即推送
+//! - Identify / 发现新节点：标记为脏，最多每 [`COALESCE_INTERVAL`] 推送一次
+//! - Ping（仅 RTT 变化）：标记为过期，距上次推送超过 [`STALE_REFRESH_INTERVAL`] 才推送
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// 脏标记合并推送间隔
+pub(crate) const COALESCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 仅 RTT 变化时的刷新间隔
+pub(crate) const STALE_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+type EmitFn = dyn Fn() + Send + Sync;
+
+/// 合并推送 `devices-changed` / `network-status-changed`
+pub(crate) struct StatusEmitter {
+    emit: Box<EmitFn>,
+    /// 设备集合或连接信息有变化，下一次定时刷新时推送
+    dirty: AtomicBool,
+    /// 仅 RTT 等次要信息有变化
+    stale: AtomicBool,
+    /// 上次推送时间
+    last_emit: Mutex<Option<Instant>>,
+    /// 实际推送次数（用于衡量节流效果）
+    emitted: AtomicU64,
+}
+
+impl StatusEmitter {
+    pub fn new(emit: impl Fn() + Send + Sync + 'static) -> Arc<Self> {
+        Arc::new(Self {
+            emit: Box::new(emit),
+            dirty: AtomicBool::new(false),
+            stale: AtomicBool::new(false),
+            last_emit: Mutex::new(None),
+            emitted: AtomicU64::new(0),
+        })
+    }
+
+    /// 启动定时刷新任务，事件循环退出（StatusEmitter 被释放）后自动停止
+    pub fn spawn_flush_task(self: &Arc<Self>) {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(COALESCE_INTERVAL);
+            loop {
+                interval.tick().await;
+                match weak.upgrade() {
+                    Some(this) => this.flush_at(Instant::now()),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// 高信号事件：立即推送
+    pub fn emit_now(&self) {
+        self.emit_at(Instant::now());
+    }
+
+    /// 设备集合 / 连接信息变化：合并到下一次定时刷新
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// 仅 RTT 变化：低频刷新
+    pub fn mark_stale(&self) {
+        self.stale.store(true, Ordering::Release);
+    }
+
+    /// 实际推送次数
+    pub fn emitted(&self) -> u64 {
+        self.emitted.load(Ordering::Relaxed)
+    }
+
+    /// 定时刷新：有脏标记时推送，仅过期时按低频间隔推送
+    fn flush_at(&self, now: Instant) {
+        if self.dirty.load(Ordering::Acquire) {
+            self.emit_at(now);
+            return;
+        }
+        if !self.stale.load(Ordering::Acquire) {
+            return;
+        }
+        let due = self
+            .last_emit
+            .lock()
+            .map(|last| last.is_none_or(|t| now.duration_since(t) >= STALE_REFRESH_INTERVAL))
+            .unwrap_or(true);
+        if due {
+            self.emit_at(now);
+        }
+    }
+
+    fn emit_at(&self, now: Instant) {
+        self.dirty.store(false, Ordering::Release);
+        self.stale.store(false, Ordering::Release);
+        if let Ok(mut last) = self.last_emit.lock() {
+            *last = Some(now);
+        }
+        self.emitted.fetch_add(1, Ordering::Relaxed);
+        (self.emit)();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counting_emitter() -> (Arc<StatusEmitter>, Arc<AtomicU64>) {
+        let calls = Arc::new(AtomicU64::new(0));
+        let c = calls.clone();
+        let emitter = StatusEmitter::new(move || {
+            c.fetch_add(1, Ordering::Relaxed);
+        });
+        (emitter, calls)
+    }
+
+    #[test]
+    fn test_dirty_coalesced_into_one_emit() {
+        let (emitter, calls) = counting_emitter();
+        let start = Instant::now();
+
+        for _ in 0..20 {
+            emitter.mark_dirty();
+        }
+        emitter.flush_at(start);
+        emitter.flush_at(start + COALESCE_INTERVAL);
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_emit_now_clears_pending() {
+        let (emitter, calls) = counting_emitter();
+        let start = Instant::now();
+
+        emitter.mark_dirty();
+        emitter.mark_stale();
+        emitter.emit_now();
+        emitter.flush_at(start + COALESCE_INTERVAL);
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_ping_rate_drops_with_ten_peers() {
+        let (emitter, _) = counting_emitter();
+        let start = Instant::now();
+        let tick = Duration::from_millis(100);
+        let mut ping_events = 0u64;
+
+        // 10 个 peer 各自每 2 秒 Ping 一次（错开），模拟 60 秒
+        for step in 0..600u64 {
+            let now = start + tick * step as u32;
+            for peer in 0..10u64 {
+                if (step + peer * 2) % 20 == 0 {
+                    emitter.mark_stale();
+                    ping_events += 1;
+                }
+            }
+            if step % 5 == 0 {
+                emitter.flush_at(now);
+            }
+        }
+
+        // 节流前每个 Ping 都推送一次，节流后至少降低一个数量级
+        assert_eq!(ping_events, 300);
+        assert!(
+            emitter.emitted() * 10 <= ping_events,
+            "{}",
+            emitter.emitted()
+        );
+    }
+}
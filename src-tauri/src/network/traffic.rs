@@ -0,0 +1,259 @@
+//! 流量统计
+//!
+//! 按连接类型（局域网 / 打洞直连 / 中继）累计收发字节数与连接时长：
+//! - 传输会话完成时上报本次传输的字节数（见 [`TrafficStats::record_session`]）
+//! - 事件循环在连接建立 / 断开时记录连接时长，粗略反映各类连接的占用
+//!
+//! 统计结果以 JSON 保存在应用数据目录，重启后继续累计。中继字节数尤其重要：
+//! 那是别人服务器的带宽。
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use swarm_p2p_core::libp2p::PeerId;
+use tracing::warn;
+
+use crate::device::ConnectionType;
+use crate::json_file;
+use crate::transfer::progress::TransferDirection;
+
+/// 持久化文件名（位于应用本地数据目录）
+pub const TRAFFIC_STATS_FILE: &str = "traffic_stats.json";
+
+/// 单一连接类型的累计流量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrafficCounts {
+    /// 发送字节数
+    pub bytes_sent: u64,
+    /// 接收字节数
+    pub bytes_received: u64,
+    /// 完成的传输会话数
+    pub sessions: u32,
+    /// 累计连接时长（秒）
+    pub connected_secs: u64,
+}
+
+/// 流量统计快照（持久化格式与返回前端的格式一致）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrafficSnapshot {
+    pub lan: TrafficCounts,
+    pub dcutr: TrafficCounts,
+    pub relay: TrafficCounts,
+    /// 连接类型未知（传输结束前连接已断开等）
+    pub unknown: TrafficCounts,
+    /// 统计起始时间（毫秒时间戳，重置时更新）
+    pub since: i64,
+}
+
+impl TrafficSnapshot {
+    fn new() -> Self {
+        Self {
+            since: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        }
+    }
+
+    fn counts_mut(&mut self, connection: Option<&ConnectionType>) -> &mut TrafficCounts {
+        match connection {
+            Some(ConnectionType::Lan) => &mut self.lan,
+            Some(ConnectionType::Dcutr) => &mut self.dcutr,
+            Some(ConnectionType::Relay) => &mut self.relay,
+            None => &mut self.unknown,
+        }
+    }
+}
+
+/// 流量统计（由 [`NetManager`](super::NetManager) 持有，事件循环与传输会话共享）
+#[derive(Debug)]
+pub struct TrafficStats {
+    snapshot: Mutex<TrafficSnapshot>,
+    /// 当前连接的建立时间与连接类型（断开时累加时长）
+    open: DashMap<PeerId, (Instant, Option<ConnectionType>)>,
+    /// 持久化路径（为 None 时仅保存在内存中）
+    path: Option<PathBuf>,
+}
+
+impl Default for TrafficStats {
+    fn default() -> Self {
+        Self {
+            snapshot: Mutex::new(TrafficSnapshot::new()),
+            open: DashMap::new(),
+            path: None,
+        }
+    }
+}
+
+impl TrafficStats {
+    /// 从文件加载历史统计，文件不存在或损坏时从零开始
+    pub fn load(path: PathBuf) -> Self {
+        let snapshot = json_file::load(&path, "流量统计").unwrap_or_else(TrafficSnapshot::new);
+        Self {
+            snapshot: Mutex::new(snapshot),
+            open: DashMap::new(),
+            path: Some(path),
+        }
+    }
+
+    /// 记录一次完成的传输会话，并立即保存
+    pub fn record_session(
+        &self,
+        direction: TransferDirection,
+        bytes: u64,
+        connection: Option<&ConnectionType>,
+    ) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            let counts = snapshot.counts_mut(connection);
+            match direction {
+                TransferDirection::Send => counts.bytes_sent += bytes,
+                TransferDirection::Receive => counts.bytes_received += bytes,
+                TransferDirection::Unknown => return,
+            }
+            counts.sessions += 1;
+        }
+        self.flush();
+    }
+
+    /// 连接建立（或打洞成功升级连接类型）：结算上一段连接时长，从现在开始重新计时
+    pub fn connection_opened(&self, peer_id: PeerId, connection: Option<ConnectionType>) {
+        if let Some(previous) = self.open.insert(peer_id, (Instant::now(), connection)) {
+            self.add_connected_time(previous);
+        }
+    }
+
+    /// 连接断开：累加本段连接时长
+    pub fn connection_closed(&self, peer_id: &PeerId) {
+        if let Some((_, opened)) = self.open.remove(peer_id) {
+            self.add_connected_time(opened);
+        }
+    }
+
+    fn add_connected_time(&self, (opened_at, connection): (Instant, Option<ConnectionType>)) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.counts_mut(connection.as_ref()).connected_secs +=
+                opened_at.elapsed().as_secs();
+        }
+    }
+
+    /// 当前统计（包含仍在进行中的连接时长）
+    pub fn snapshot(&self) -> TrafficSnapshot {
+        let mut snapshot = self.snapshot.lock().map(|s| s.clone()).unwrap_or_default();
+        for entry in self.open.iter() {
+            let (opened_at, connection) = entry.value();
+            snapshot.counts_mut(connection.as_ref()).connected_secs +=
+                opened_at.elapsed().as_secs();
+        }
+        snapshot
+    }
+
+    /// 清零统计，进行中的连接从现在开始重新计时
+    pub fn reset(&self) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            *snapshot = TrafficSnapshot::new();
+        }
+        let now = Instant::now();
+        for mut entry in self.open.iter_mut() {
+            entry.value_mut().0 = now;
+        }
+        self.flush();
+    }
+
+    /// 结算所有进行中的连接并保存（节点关闭时调用）
+    pub fn close_all(&self) {
+        let peers: Vec<PeerId> = self.open.iter().map(|e| *e.key()).collect();
+        for peer_id in &peers {
+            self.connection_closed(peer_id);
+        }
+        self.flush();
+    }
+
+    /// 保存到文件（失败仅记录日志）
+    fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let Ok(snapshot) = self.snapshot.lock().map(|s| s.clone()) else {
+            return;
+        };
+        if let Err(e) = json_file::save(path, &snapshot) {
+            warn!("保存流量统计失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_record_session_by_connection_type() {
+        let stats = TrafficStats::default();
+        stats.record_session(TransferDirection::Send, 100, Some(&ConnectionType::Lan));
+        stats.record_session(TransferDirection::Receive, 40, Some(&ConnectionType::Relay));
+        stats.record_session(TransferDirection::Send, 60, Some(&ConnectionType::Relay));
+        stats.record_session(TransferDirection::Receive, 7, None);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.lan.bytes_sent, 100);
+        assert_eq!(snapshot.lan.sessions, 1);
+        assert_eq!(snapshot.relay.bytes_sent, 60);
+        assert_eq!(snapshot.relay.bytes_received, 40);
+        assert_eq!(snapshot.relay.sessions, 2);
+        assert_eq!(snapshot.unknown.bytes_received, 7);
+        assert_eq!(snapshot.dcutr, TrafficCounts::default());
+
+        stats.reset();
+        let reset = stats.snapshot();
+        assert_eq!(reset.relay, TrafficCounts::default());
+        assert!(reset.since >= snapshot.since);
+    }
+
+    #[test]
+    fn test_connection_time_follows_upgrade() {
+        let stats = TrafficStats::default();
+        let peer = PeerId::random();
+        let two_secs_ago = Instant::now() - Duration::from_secs(2);
+        stats
+            .open
+            .insert(peer, (two_secs_ago, Some(ConnectionType::Relay)));
+
+        // 打洞成功：中继段结算，之后计入 dcutr
+        stats.connection_opened(peer, Some(ConnectionType::Dcutr));
+        stats.connection_closed(&peer);
+        stats.connection_closed(&peer);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.relay.connected_secs, 2);
+        assert_eq!(snapshot.dcutr.connected_secs, 0);
+        assert!(stats.open.is_empty());
+    }
+
+    #[test]
+    fn test_persist_and_reload() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_traffic_stats");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(TRAFFIC_STATS_FILE);
+
+        let stats = TrafficStats::load(path.clone());
+        stats.record_session(TransferDirection::Send, 1024, Some(&ConnectionType::Relay));
+        let saved = stats.snapshot();
+
+        let reloaded = TrafficStats::load(path.clone());
+        assert_eq!(reloaded.snapshot(), saved);
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert_eq!(
+            TrafficStats::load(path).snapshot().relay,
+            TrafficCounts::default()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
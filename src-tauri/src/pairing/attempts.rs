@@ -0,0 +1,152 @@
+//! 配对码暴力猜测防护
+//!
+//! 远端节点可以不断发送携带猜测配对码的 PairingRequest。[`CodeAttempts`] 按来源
+//! PeerId 在滑动窗口内统计猜错次数：窗口内达到 [`MAX_FAILURES_PER_WINDOW`] 次后锁定该节点
+//! [`LOCKOUT_DURATION`]，锁定期间的请求不再校验配对码直接拒绝。
+//! 另外同一配对码累计被猜错 [`MAX_FAILURES_PER_CODE`] 次（不区分来源，防止换 PeerId 继续猜）
+//! 后作废该配对码。
+//!
+//! 时间由调用方传入，便于测试窗口计算。
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use swarm_p2p_core::libp2p::PeerId;
+
+/// 统计猜错次数的滑动窗口
+pub const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 窗口内允许的猜错次数，达到后锁定
+pub const MAX_FAILURES_PER_WINDOW: usize = 5;
+
+/// 锁定时长
+pub const LOCKOUT_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// 同一配对码累计猜错次数上限，达到后作废该配对码
+pub const MAX_FAILURES_PER_CODE: u32 = 20;
+
+/// 记录一次猜错后的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FailureOutcome {
+    /// 该节点因本次猜错刚被锁定
+    pub locked_out: bool,
+    /// 当前配对码累计猜错次数达到上限，应作废
+    pub revoke_code: bool,
+}
+
+/// 配对码猜错记录
+#[derive(Debug, Default)]
+pub struct CodeAttempts {
+    /// 各节点窗口内猜错的时间点（从旧到新）
+    failures: HashMap<PeerId, VecDeque<Instant>>,
+    /// 被锁定的节点及解锁时间
+    lockouts: HashMap<PeerId, Instant>,
+    /// 当前配对码累计猜错次数
+    code_failures: u32,
+}
+
+impl CodeAttempts {
+    /// 节点是否处于锁定期（顺带清理已过期的锁定）
+    pub fn is_locked_out(&mut self, peer_id: &PeerId, now: Instant) -> bool {
+        match self.lockouts.get(peer_id) {
+            Some(until) if now < *until => true,
+            Some(_) => {
+                self.lockouts.remove(peer_id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// 记录一次猜错
+    pub fn record_failure(&mut self, peer_id: PeerId, now: Instant) -> FailureOutcome {
+        // 清理其他节点的过期记录，避免换 PeerId 猜测时记录无限增长
+        self.failures.retain(|_, failures| {
+            failures
+                .back()
+                .is_some_and(|at| now.duration_since(*at) < FAILURE_WINDOW)
+        });
+        self.lockouts.retain(|_, until| now < *until);
+
+        let failures = self.failures.entry(peer_id).or_default();
+        while failures
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= FAILURE_WINDOW)
+        {
+            failures.pop_front();
+        }
+        failures.push_back(now);
+
+        let locked_out = failures.len() >= MAX_FAILURES_PER_WINDOW;
+        if locked_out {
+            self.failures.remove(&peer_id);
+            self.lockouts.insert(peer_id, now + LOCKOUT_DURATION);
+        }
+
+        self.code_failures += 1;
+        let revoke_code = self.code_failures >= MAX_FAILURES_PER_CODE;
+        if revoke_code {
+            self.code_failures = 0;
+        }
+
+        FailureOutcome {
+            locked_out,
+            revoke_code,
+        }
+    }
+
+    /// 生成新配对码时重置累计猜错次数（节点锁定不受影响）
+    pub fn reset_code_failures(&mut self) {
+        self.code_failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window_lockout() {
+        let mut attempts = CodeAttempts::default();
+        let peer = PeerId::random();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        // 前 4 次分散在窗口内，未锁定
+        for secs in [0, 10, 20, 30] {
+            assert!(!attempts.record_failure(peer, at(secs)).locked_out);
+        }
+        // 第 0 秒的记录滑出窗口，第 61 秒时窗口内仍只有 4 次
+        assert!(!attempts.record_failure(peer, at(61)).locked_out);
+        // 窗口内第 5 次猜错，锁定
+        assert!(attempts.record_failure(peer, at(62)).locked_out);
+
+        assert!(attempts.is_locked_out(&peer, at(62)));
+        assert!(!attempts.is_locked_out(&PeerId::random(), at(62)));
+        let unlock = at(62) + LOCKOUT_DURATION;
+        assert!(attempts.is_locked_out(&peer, unlock - Duration::from_secs(1)));
+        assert!(!attempts.is_locked_out(&peer, unlock));
+
+        // 解锁后重新计数
+        assert!(!attempts.record_failure(peer, unlock).locked_out);
+    }
+
+    #[test]
+    fn test_revoke_code_after_total_failures() {
+        let mut attempts = CodeAttempts::default();
+        let now = Instant::now();
+
+        // 每个节点只猜一次，不会触发锁定，但累计次数会作废配对码
+        for _ in 0..MAX_FAILURES_PER_CODE - 1 {
+            let outcome = attempts.record_failure(PeerId::random(), now);
+            assert_eq!(outcome, FailureOutcome::default());
+        }
+        assert!(attempts.record_failure(PeerId::random(), now).revoke_code);
+        assert!(!attempts.record_failure(PeerId::random(), now).revoke_code);
+
+        attempts.reset_code_failures();
+        for _ in 0..MAX_FAILURES_PER_CODE - 1 {
+            assert!(!attempts.record_failure(PeerId::random(), now).revoke_code);
+        }
+    }
+}
@@ -5,6 +5,75 @@ use swarm_p2p_core::libp2p::Multiaddr;
 
 const CHARSET: &[u8] = b"0123456789";
 const CODE_LENGTH: usize = 6;
+/// 单词配对码末尾数字段的位数
+const WORD_CODE_DIGITS: usize = 2;
+
+/// 单词配对码的词表（128 个常见、易拼读的英文单词，按字母序排列）
+const WORDLIST: [&str; 128] = [
+    "acorn", "amber", "anchor", "apple", "arrow", "aspen", "autumn", "badger", "bamboo", "basil",
+    "beacon", "birch", "bison", "breeze", "bridge", "brook", "cactus", "camel", "candle", "canyon",
+    "cedar", "cherry", "cider", "cloud", "clover", "cobalt", "comet", "coral", "cotton", "crane",
+    "creek", "daisy", "delta", "desert", "dolphin", "dune", "eagle", "echo", "ember", "falcon",
+    "fern", "fig", "finch", "fjord", "flint", "forest", "fox", "galaxy", "garden", "ginger",
+    "glacier", "granite", "grape", "harbor", "harvest", "hazel", "heron", "honey", "island",
+    "ivory", "jade", "jasmine", "juniper", "kettle", "kiwi", "lagoon", "lantern", "lemon", "lily",
+    "lotus", "lunar", "mango", "maple", "marble", "meadow", "melon", "mint", "moss", "nectar",
+    "nova", "oak", "ocean", "olive", "orbit", "orchid", "otter", "owl", "panda", "pearl", "pebble",
+    "pepper", "pine", "planet", "plum", "poppy", "prairie", "quartz", "rabbit", "raven", "reef",
+    "river", "robin", "saffron", "sage", "salmon", "sand", "shadow", "silver", "sparrow", "spruce",
+    "storm", "summit", "sunset", "swan", "thunder", "tiger", "topaz", "tulip", "tundra", "valley",
+    "velvet", "violet", "walnut", "willow", "winter", "wolf", "zebra", "zephyr",
+];
+
+/// 配对码格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CodeFormat {
+    /// 6 位数字，如 `472901`
+    #[default]
+    Digits6,
+    /// 两个单词加两位数字，如 `maple-otter-42`，口头传达更不容易出错
+    Words3,
+}
+
+impl CodeFormat {
+    /// 识别规范化后的配对码格式，不符合任何格式时返回 None
+    pub fn detect(code: &str) -> Option<Self> {
+        if code.len() == CODE_LENGTH && code.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(Self::Digits6);
+        }
+        let mut parts = code.split('-');
+        let (Some(first), Some(second), Some(number), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let is_number =
+            number.len() == WORD_CODE_DIGITS && number.bytes().all(|b| b.is_ascii_digit());
+        (WORDLIST.contains(&first) && WORDLIST.contains(&second) && is_number)
+            .then_some(Self::Words3)
+    }
+}
+
+/// 规范化用户输入的配对码：忽略大小写，空格 / 下划线 / 点等分隔符统一处理
+///
+/// 纯数字输入去掉分隔符（`472 901` → `472901`），
+/// 含字母的输入以 `-` 连接（`Maple Otter_42` → `maple-otter-42`）。
+pub fn normalize_code(input: &str) -> String {
+    let lower = input.to_lowercase();
+    let parts: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts
+        .iter()
+        .all(|part| part.bytes().all(|b| b.is_ascii_digit()))
+    {
+        parts.concat()
+    } else {
+        parts.join("-")
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,11 +84,17 @@ pub struct PairingCodeInfo {
 }
 
 impl PairingCodeInfo {
-    pub fn generate(expires_in_secs: u64) -> Self {
+    pub fn generate(expires_in_secs: u64, format: CodeFormat) -> Self {
         let mut rng = rand::rng();
-        let code: String = (0..CODE_LENGTH)
-            .map(|_| *CHARSET.choose(&mut rng).unwrap() as char)
-            .collect();
+        let code = match format {
+            CodeFormat::Digits6 => random_digits(&mut rng, CODE_LENGTH),
+            CodeFormat::Words3 => {
+                let first = WORDLIST.choose(&mut rng).unwrap();
+                let second = WORDLIST.choose(&mut rng).unwrap();
+                let number = random_digits(&mut rng, WORD_CODE_DIGITS);
+                format!("{first}-{second}-{number}")
+            }
+        };
         let now = chrono::Utc::now().timestamp();
         Self {
             code,
@@ -33,6 +108,12 @@ impl PairingCodeInfo {
     }
 }
 
+fn random_digits(rng: &mut impl rand::Rng, len: usize) -> String {
+    (0..len)
+        .map(|_| *CHARSET.choose(rng).unwrap() as char)
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareCodeRecord {
@@ -67,3 +148,40 @@ pub struct OnlineRecord {
     pub listen_addrs: Vec<Multiaddr>,
     pub timestamp: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_formats() {
+        let digits = PairingCodeInfo::generate(300, CodeFormat::Digits6);
+        assert_eq!(CodeFormat::detect(&digits.code), Some(CodeFormat::Digits6));
+
+        let words = PairingCodeInfo::generate(300, CodeFormat::Words3);
+        assert_eq!(CodeFormat::detect(&words.code), Some(CodeFormat::Words3));
+        // 生成的配对码已是规范形式
+        assert_eq!(normalize_code(&words.code), words.code);
+    }
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code(" 472 901 "), "472901");
+        assert_eq!(normalize_code("472-901"), "472901");
+        assert_eq!(normalize_code("Maple Otter_42"), "maple-otter-42");
+        assert_eq!(normalize_code("MAPLE--otter.42"), "maple-otter-42");
+
+        assert_eq!(
+            CodeFormat::detect("maple-otter-42"),
+            Some(CodeFormat::Words3)
+        );
+        for invalid in [
+            "47290",
+            "maple-otter",
+            "maple-unicorn-42",
+            "maple-otter-420",
+        ] {
+            assert_eq!(CodeFormat::detect(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -1,21 +1,76 @@
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
-use swarm_p2p_core::libp2p::{kad::Record, Multiaddr, PeerId};
+use tokio::sync::{watch, Notify};
+use tokio_util::sync::CancellationToken;
+use swarm_p2p_core::libp2p::{kad::Record, multiaddr::Protocol, Multiaddr, PeerId};
 
-use super::code::{OnlineRecord, PairingCodeInfo, ShareCodeRecord};
+use super::attempts::{CodeAttempts, FailureOutcome};
+use super::code::{normalize_code, CodeFormat, OnlineRecord, PairingCodeInfo, ShareCodeRecord};
 use super::dht_key;
-use crate::device::{OsInfo, PairedDeviceInfo};
+use super::payload::{PairingPayload, MAX_PAYLOAD_ADDRS};
+use super::verification::verification_code;
+use crate::device::{normalize_nickname, DeviceManager, OsInfo, PairedDeviceInfo};
+use crate::network::config::NetworkTimeouts;
+use crate::network::power_save::PowerSave;
 use crate::protocol::{
-    AppNetClient, AppRequest, AppResponse, PairingMethod, PairingRequest, PairingResponse,
+    AppNetClient, AppRequest, AppResponse, PairingConfirmation, PairingMethod, PairingRequest,
+    PairingResponse,
 };
 use crate::{AppError, AppResult};
 
+/// DHT 在线记录的有效期（秒）
+const ONLINE_RECORD_TTL_SECS: u64 = 300;
+
+/// 在线记录的定时重新发布间隔，需小于有效期以免记录过期
+const ONLINE_REFRESH_INTERVAL: Duration = Duration::from_secs(240);
+
+/// 发布 DHT 记录前等待引导节点连接的最长时间
+const DHT_READY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// DHT 记录发布的最大尝试次数（刚连上引导节点时路由表可能仍为空）
+const PUT_RECORD_ATTEMPTS: u32 = 3;
+
+/// DHT 记录发布失败后的重试间隔
+const PUT_RECORD_RETRY_DELAY: Duration = Duration::from_secs(2);
+
 /// 入站配对请求缓存（事件循环写入，handle_pairing_request 消费）
 struct PendingInbound {
     peer_id: PeerId,
     os_info: OsInfo,
+    /// 配对请求时间戳（派生验证码）
+    timestamp: i64,
+}
+
+/// 入站配对请求的预检结果（见 [`PairingManager::screen_pairing_request`]）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeScreening {
+    /// 放行，交由用户确认
+    Pass,
+    /// 配对码错误；`code` 为预检时的活跃配对码（即被猜测的配对码）
+    WrongCode {
+        outcome: FailureOutcome,
+        code: Option<String>,
+    },
+    /// 来源节点因多次猜错处于锁定期，未校验配对码
+    LockedOut,
+}
+
+/// 手动重连已配对设备的结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedReconnectResult {
+    /// 已连接、无需重连的设备
+    pub connected: Vec<PeerId>,
+    /// 查到 DHT 在线记录的设备
+    pub found: Vec<PeerId>,
+    /// 拨号成功的设备
+    pub dialed: Vec<PeerId>,
+    /// 因查到在线记录而更新了 `last_seen_at` 的设备（调用方推送给前端保存，不返回给前端）
+    #[serde(skip)]
+    pub seen: Vec<PairedDeviceInfo>,
 }
 
 /// 配对管理器
@@ -33,8 +88,28 @@ pub struct PairingManager {
     paired_devices: Arc<DashMap<PeerId, PairedDeviceInfo>>,
     /// 入站请求缓存，handle_pairing_request 时取出
     pending_inbound: DashMap<u64, PendingInbound>,
+    /// 配对码猜错记录（按来源节点锁定、按配对码累计）
+    code_attempts: Mutex<CodeAttempts>,
     /// get_device_info 查询时缓存对端 OsInfo，request_pairing 成功后使用
     discovered_peers: DashMap<PeerId, OsInfo>,
+    /// 主动拨号超时（对端不可达时尽快失败）
+    dial_timeout: Duration,
+    /// DHT 是否可用（已连接引导节点或 bootstrap 已完成），发布记录前等待其为 true
+    dht_ready: watch::Sender<bool>,
+    /// 后续发现的外部地址（发布 DHT 记录时附带）
+    external: Option<ExternalAddrs>,
+    /// 是否启用中继（关闭时拨号失败给出明确提示）
+    relay_enabled: bool,
+}
+
+/// 启动后才逐步获知的可达地址来源（与 NetManager 共享，由事件循环更新）
+pub struct ExternalAddrs {
+    /// AutoNAT 确认的公网地址
+    pub public_addr: Arc<RwLock<Option<Multiaddr>>>,
+    /// 已预约成功的中继节点
+    pub relay_peers: Arc<RwLock<HashSet<PeerId>>>,
+    /// 查询中继节点的公网地址
+    pub devices: Arc<DeviceManager>,
 }
 
 impl PairingManager {
@@ -49,33 +124,148 @@ impl PairingManager {
             active_code: Mutex::new(None),
             paired_devices,
             pending_inbound: DashMap::new(),
+            code_attempts: Mutex::new(CodeAttempts::default()),
             discovered_peers: DashMap::new(),
+            dial_timeout: NetworkTimeouts::default().dial_timeout(),
+            dht_ready: watch::Sender::new(false),
+            external: None,
+            relay_enabled: true,
+        }
+    }
+
+    /// 设置中继是否启用（仅局域网模式下关闭）
+    pub fn with_relay_enabled(mut self, relay_enabled: bool) -> Self {
+        self.relay_enabled = relay_enabled;
+        self
+    }
+
+    /// 发布 DHT 记录时附带公网地址与中继 circuit 地址
+    pub fn with_external_addrs(mut self, external: ExternalAddrs) -> Self {
+        self.external = Some(external);
+        self
+    }
+
+    /// 覆盖默认的拨号超时
+    pub fn with_dial_timeout(mut self, dial_timeout: Duration) -> Self {
+        self.dial_timeout = dial_timeout;
+        self
+    }
+
+    /// 带超时的拨号
+    ///
+    /// 中继已关闭时，失败多半是对方不在同一局域网，给出明确提示而不是笼统的超时。
+    async fn dial(&self, peer_id: PeerId) -> AppResult<()> {
+        let result = match tokio::time::timeout(self.dial_timeout, self.client.dial(peer_id)).await
+        {
+            Ok(result) => result.map_err(AppError::from),
+            Err(_) => Err(AppError::Network(format!(
+                "连接 {peer_id} 超时（{}s）",
+                self.dial_timeout.as_secs()
+            ))),
+        };
+        result.map_err(|e| {
+            if self.relay_enabled {
+                e
+            } else {
+                AppError::Network(format!(
+                    "中继已关闭（仅局域网模式），无法连接不在同一局域网的设备: {e}"
+                ))
+            }
+        })
+    }
+
+    /// 更新 DHT 可用状态（事件循环在引导节点连接变化时、启动流程在 bootstrap 完成后调用）
+    pub fn set_dht_ready(&self, ready: bool) {
+        self.dht_ready.send_if_modified(|current| {
+            let changed = *current != ready;
+            *current = ready;
+            changed
+        });
+    }
+
+    /// 等待 DHT 可用，超过 [`DHT_READY_TIMEOUT`] 仍未连接引导节点则返回错误
+    async fn wait_dht_ready(&self) -> AppResult<()> {
+        let mut ready = self.dht_ready.subscribe();
+        match tokio::time::timeout(DHT_READY_TIMEOUT, ready.wait_for(|r| *r)).await {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(AppError::Network(
+                "尚未连接到引导节点，DHT 暂不可用，请检查网络后重试".into(),
+            )),
         }
     }
 
     /// 序列化数据并发布到 DHT
+    ///
+    /// 先等待 DHT 可用，发布失败时按 [`PUT_RECORD_RETRY_DELAY`] 间隔重试，
+    /// 最多 [`PUT_RECORD_ATTEMPTS`] 次。
     async fn put_json_record(
         &self,
         key: swarm_p2p_core::libp2p::kad::RecordKey,
         data: &impl serde::Serialize,
         ttl_secs: u64,
     ) -> AppResult<()> {
-        self.client
-            .put_record(Record {
-                key,
-                value: serde_json::to_vec(data)?,
+        self.wait_dht_ready().await?;
+
+        let value = serde_json::to_vec(data)?;
+        let mut attempt = 1;
+        loop {
+            let record = Record {
+                key: key.clone(),
+                value: value.clone(),
                 publisher: Some(self.peer_id),
                 expires: Some(Instant::now() + Duration::from_secs(ttl_secs)),
-            })
-            .await?;
-        Ok(())
+            };
+            match self.client.put_record(record).await {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt < PUT_RECORD_ATTEMPTS => {
+                    tracing::debug!("发布 DHT 记录失败（第 {} 次），稍后重试: {}", attempt, e);
+                    attempt += 1;
+                    tokio::time::sleep(PUT_RECORD_RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
     }
 
     // === DHT 在线宣告 ===
 
+    /// 本节点当前的可达地址
+    ///
+    /// 在 `get_addrs`（监听地址 + swarm 外部地址）基础上补充 AutoNAT 确认的公网地址，
+    /// 以及经已预约中继节点的 circuit 地址。NAT 后的设备仅靠局域网监听地址无法被拨通。
+    async fn reachable_addrs(&self) -> AppResult<Vec<Multiaddr>> {
+        let mut addrs = self.client.get_addrs().await?;
+        let Some(external) = &self.external else {
+            return Ok(addrs);
+        };
+
+        let public_addr = external.public_addr.read().ok().and_then(|g| g.clone());
+        let relay_peers: Vec<PeerId> = external
+            .relay_peers
+            .read()
+            .map(|g| g.iter().copied().collect())
+            .unwrap_or_default();
+        let circuits = relay_peers.into_iter().flat_map(|relay| {
+            external
+                .devices
+                .public_addrs(&relay)
+                .into_iter()
+                .map(move |addr| relay_circuit_addr(addr, relay))
+        });
+
+        for addr in public_addr.into_iter().chain(circuits) {
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+        Ok(addrs)
+    }
+
     /// 宣布上线：将本节点的可达地址发布到 DHT
+    ///
+    /// 每次发布都重新获取地址（见 [`Self::reachable_addrs`]），随定时刷新逐步补全。
     pub async fn announce_online(&self) -> AppResult<()> {
-        let addrs = self.client.get_addrs().await?;
+        let addrs = self.reachable_addrs().await?;
         let record_data = OnlineRecord {
             os_info: OsInfo::default(),
             listen_addrs: addrs,
@@ -84,60 +274,136 @@ impl PairingManager {
         self.put_json_record(
             dht_key::online_key(&self.peer_id.to_bytes()),
             &record_data,
-            300,
+            ONLINE_RECORD_TTL_SECS,
         )
         .await
     }
 
-    /// 启动后检查已配对设备是否在线
+    /// 启动在线记录定时刷新任务（在 Arc<Self> 上调用，由 NetManager 创建后触发）
     ///
-    /// 在 DHT bootstrap 完成后调用。对每个已配对设备查询其在线记录，
+    /// 每 [`ONLINE_REFRESH_INTERVAL`] 重新发布一次（同时刷新地址列表，中继地址通常较晚才出现），
+    /// `trigger` 被通知时（NAT 状态变化、中继预约成功）立即发布。
+    /// 省电模式开启期间暂停发布，关闭后立即补发一次（见 [`PowerSave`]）。
+    pub fn spawn_online_refresh_task(
+        self: &Arc<Self>,
+        trigger: Arc<Notify>,
+        power_save: PowerSave,
+        cancel_token: CancellationToken,
+    ) {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            // 启动时已发布过一次，首次刷新延后一个周期
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + ONLINE_REFRESH_INTERVAL,
+                ONLINE_REFRESH_INTERVAL,
+            );
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = interval.tick() => {}
+                    _ = trigger.notified() => {
+                        // 地址集合刚变化，立即发布并重新计时
+                        interval.reset();
+                    }
+                }
+                if power_save.is_enabled() {
+                    tracing::debug!("省电模式已开启，暂停刷新在线记录");
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = power_save.wait_until_disabled() => interval.reset(),
+                    }
+                }
+                if let Err(e) = this.announce_online().await {
+                    tracing::warn!("刷新 DHT 在线记录失败: {}", e);
+                }
+            }
+            tracing::info!("在线记录刷新任务已停止");
+        });
+    }
+
+    /// 检查已配对设备是否在线
+    ///
+    /// 在 DHT bootstrap 完成后及之后定期调用。对每个已配对设备查询其在线记录，
     /// 找到则将地址注册到地址簿，使后续传输可直接 dial，无需重新配对。
-    pub async fn check_paired_online(&self) {
-        let paired = self.get_paired_devices();
+    /// `is_connected` 为 true 的设备已在线，跳过以避免无用的 DHT 查询。
+    ///
+    /// 返回检查结果，调用方据此推送上线事件与 `last_seen_at` 的更新。
+    pub async fn check_paired_online(
+        &self,
+        is_connected: impl Fn(&PeerId) -> bool,
+    ) -> PairedReconnectResult {
+        self.reconnect_paired(None, is_connected)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// 对全部（`target` 为 None）或指定的已配对设备重新执行在线检查
+    ///
+    /// 查询 DHT 在线记录 → 注册地址 → 拨号，逻辑同 [`Self::check_paired_online`]，
+    /// 供用户手动"刷新"时调用。`target` 不是已配对设备时返回错误。
+    pub async fn reconnect_paired(
+        &self,
+        target: Option<PeerId>,
+        is_connected: impl Fn(&PeerId) -> bool,
+    ) -> AppResult<PairedReconnectResult> {
+        let mut result = PairedReconnectResult::default();
+        if let Some(peer_id) = target {
+            if !self.is_paired(&peer_id) {
+                return Err(AppError::Network(format!("设备 {peer_id} 未配对")));
+            }
+        }
+        let paired: Vec<_> = self
+            .get_paired_devices()
+            .into_iter()
+            .filter(|d| target.is_none_or(|t| t == d.peer_id))
+            .filter(|d| {
+                let connected = is_connected(&d.peer_id);
+                if connected {
+                    result.connected.push(d.peer_id);
+                }
+                !connected
+            })
+            .collect();
         if paired.is_empty() {
-            return;
+            return Ok(result);
         }
 
-        tracing::info!("检查 {} 个已配对设备的在线状态", paired.len());
+        tracing::info!("检查 {} 个离线的已配对设备是否在线", paired.len());
 
         for device in paired {
-            let key = dht_key::online_key(&device.peer_id.to_bytes());
-            match self.client.get_record(key).await {
-                Ok(result) => {
-                    let record = result.record;
-                    // 跳过已过期记录
-                    if record.expires.map(|e| e < Instant::now()).unwrap_or(false) {
-                        continue;
-                    }
-                    if let Ok(online_record) =
-                        serde_json::from_slice::<OnlineRecord>(&record.value)
-                    {
-                        if online_record.listen_addrs.is_empty() {
-                            continue;
-                        }
-                        if let Err(e) = self
-                            .client
-                            .add_peer_addrs(device.peer_id, online_record.listen_addrs)
-                            .await
-                        {
-                            tracing::warn!("注册 {} 地址失败: {}", device.peer_id, e);
-                            continue;
-                        }
-                        // 主动 dial：连接成功后触发 PeerConnected 事件，
-                        // 事件循环推送 devices-changed，前端自动更新在线状态
-                        if let Err(e) = self.client.dial(device.peer_id).await {
-                            tracing::warn!("拨号 {} 失败: {}", device.peer_id, e);
-                        } else {
-                            tracing::info!("已向已配对设备 {} 发起重连", device.peer_id);
-                        }
-                    }
-                }
-                Err(_) => {
-                    // 设备离线或 DHT 查询失败，正常现象，静默忽略
-                }
+            // 设备离线或 DHT 查询失败属于正常现象，静默忽略
+            let Some(record) = lookup_online_record(&self.client, device.peer_id).await else {
+                continue;
+            };
+            // 未过期的在线记录说明对方近期在线，即使随后拨号失败也更新 last_seen_at
+            // （记录时间来自对方时钟，不晚于本机当前时间）
+            let published_at = (record.timestamp * 1000).min(chrono::Utc::now().timestamp_millis());
+            if let Some(info) = self.touch_last_seen(&device.peer_id, published_at) {
+                result.seen.push(info);
+            }
+            let listen_addrs = record.listen_addrs;
+            if listen_addrs.is_empty() {
+                continue;
+            }
+            result.found.push(device.peer_id);
+            if let Err(e) = self
+                .client
+                .add_peer_addrs(device.peer_id, listen_addrs)
+                .await
+            {
+                tracing::warn!("注册 {} 地址失败: {}", device.peer_id, e);
+                continue;
+            }
+            // 主动 dial：连接成功后触发 PeerConnected 事件，
+            // 事件循环推送 devices-changed，前端自动更新在线状态
+            if let Err(e) = self.dial(device.peer_id).await {
+                tracing::warn!("拨号 {} 失败: {}", device.peer_id, e);
+            } else {
+                tracing::info!("已向已配对设备 {} 发起重连", device.peer_id);
+                result.dialed.push(device.peer_id);
             }
         }
+        Ok(result)
     }
 
     /// 宣布下线：从 DHT 移除在线记录
@@ -150,11 +416,15 @@ impl PairingManager {
 
     // === 配对码管理 ===
 
-    pub async fn generate_code(&self, expires_in_secs: u64) -> AppResult<PairingCodeInfo> {
-        let code_info = PairingCodeInfo::generate(expires_in_secs);
+    pub async fn generate_code(
+        &self,
+        expires_in_secs: u64,
+        format: CodeFormat,
+    ) -> AppResult<PairingCodeInfo> {
+        let code_info = PairingCodeInfo::generate(expires_in_secs, format);
 
-        // 获取当前监听地址，嵌入 DHT Record，供对方 dial 时使用
-        let addrs = self.client.get_addrs().await?;
+        // 获取当前可达地址，嵌入 DHT Record，供对方 dial 时使用
+        let addrs = self.reachable_addrs().await?;
         let mut record_data = ShareCodeRecord::from(&code_info);
         record_data.listen_addrs = addrs;
 
@@ -165,19 +435,161 @@ impl PairingManager {
         )
         .await?;
 
-        // 覆盖旧码（旧 DHT 记录靠 TTL 自然过期，无需显式删除）
-        *self.active_code.lock().unwrap() = Some(code_info.clone());
+        // 覆盖旧码，并移除旧码的 DHT 记录，避免第三方在 TTL 内仍能查到本机信息
+        let previous = self.active_code.lock().unwrap().replace(code_info.clone());
+        self.code_attempts.lock().unwrap().reset_code_failures();
+        if let Some(previous) = previous.filter(|p| p.code != code_info.code) {
+            self.remove_code_record(&previous.code).await;
+        }
 
         Ok(code_info)
     }
 
+    /// 是否有未过期的配对码（诊断用，不暴露配对码本身）
+    pub fn has_active_code(&self) -> bool {
+        self.active_code
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|info| !info.is_expired())
+    }
+
+    /// 撤销当前配对码（用户取消时调用）：清除活跃配对码并移除其 DHT 记录
+    pub async fn revoke_code(&self) {
+        let previous = self.active_code.lock().unwrap().take();
+        if let Some(previous) = previous {
+            self.remove_code_record(&previous.code).await;
+        }
+    }
+
+    /// 作废被暴力猜测的配对码：仅当 `code` 仍是活跃配对码时撤销
+    ///
+    /// 作废在后台执行，期间用户可能已重新生成配对码，新码不受影响。
+    pub async fn revoke_code_if_active(&self, code: &str) {
+        let revoked = {
+            let mut active = self.active_code.lock().unwrap();
+            if active.as_ref().is_none_or(|info| info.code != code) {
+                return;
+            }
+            active.take()
+        };
+        if let Some(revoked) = revoked {
+            self.remove_code_record(&revoked.code).await;
+        }
+    }
+
+    /// 移除配对码的 DHT 记录（尽力而为，失败时记录可等 TTL 自然过期）
+    async fn remove_code_record(&self, code: &str) {
+        if let Err(e) = self
+            .client
+            .remove_record(dht_key::share_code_key(code))
+            .await
+        {
+            tracing::warn!("移除配对码 DHT 记录失败: {}", e);
+        }
+    }
+
+    /// 当前配对码的二维码载荷（附带本机 PeerId 与可达地址）
+    ///
+    /// 需先生成配对码；配对码同时发布在 DHT 上，载荷中的地址失效时扫码方可回退查询。
+    pub async fn pairing_payload(&self) -> AppResult<String> {
+        let code_info = self
+            .active_code
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(AppError::InvalidCode)?;
+        if code_info.is_expired() {
+            return Err(AppError::ExpiredCode);
+        }
+        let mut addrs = self.reachable_addrs().await?;
+        addrs.truncate(MAX_PAYLOAD_ADDRS);
+        Ok(PairingPayload {
+            code: code_info.code,
+            peer_id: self.peer_id,
+            addrs,
+        }
+        .encode())
+    }
+
+    /// 扫码配对：解析载荷后直接向载荷中的地址发起配对
+    ///
+    /// 载荷没有地址或按其地址配对失败（地址已失效）时，回退到 DHT 查询配对码，
+    /// 并确认发布者与载荷中的 PeerId 一致。
+    pub async fn pair_from_payload(
+        &self,
+        payload: &str,
+    ) -> AppResult<(PairingResponse, Option<PairedDeviceInfo>)> {
+        let payload = PairingPayload::parse(payload)?;
+        self.ensure_not_self(&payload.peer_id)?;
+        let method = PairingMethod::Code {
+            code: payload.code.clone(),
+        };
+
+        if !payload.addrs.is_empty() {
+            match self
+                .request_pairing(payload.peer_id, method.clone(), Some(payload.addrs))
+                .await
+            {
+                Ok(result) => return Ok(result),
+                Err(e) => tracing::warn!("按二维码中的地址配对失败，回退到 DHT 查询: {}", e),
+            }
+        }
+
+        // get_device_info 已将 DHT 记录中的地址注册到地址簿
+        let (publisher, _) = self.get_device_info(&payload.code).await?;
+        if publisher != payload.peer_id {
+            return Err(AppError::InvalidPairingPayload(
+                "配对码与二维码中的设备不一致".into(),
+            ));
+        }
+        self.request_pairing(publisher, method, None).await
+    }
+
     // === 配对流程 ===
 
+    /// 预检入站配对请求（事件循环收到请求时调用，早于推送给前端）
+    ///
+    /// 处于锁定期的节点一律拒绝；`Code` 模式下配对码不匹配时记录一次猜错，
+    /// 调用方直接拒绝，不打扰用户。`WrongCode` 中 `revoke_code` 为 true 时应以其中的 `code`
+    /// 调用 [`revoke_code_if_active`](Self::revoke_code_if_active) 作废被猜测的配对码。
+    pub fn screen_pairing_request(&self, peer_id: PeerId, method: &PairingMethod) -> CodeScreening {
+        let now = Instant::now();
+        let mut attempts = self.code_attempts.lock().unwrap();
+        if attempts.is_locked_out(&peer_id, now) {
+            return CodeScreening::LockedOut;
+        }
+        let PairingMethod::Code { code } = method else {
+            return CodeScreening::Pass;
+        };
+
+        let active_code = self
+            .active_code
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.code.clone());
+        if active_code.as_deref() == Some(normalize_code(code).as_str()) {
+            CodeScreening::Pass
+        } else {
+            CodeScreening::WrongCode {
+                outcome: attempts.record_failure(peer_id, now),
+                code: active_code,
+            }
+        }
+    }
+
     /// 查询配对码对应的设备信息，并缓存 OsInfo 供后续 request_pairing 使用
+    ///
+    /// 配对码先规范化（忽略大小写与分隔符），数字码与单词码均可。
     pub async fn get_device_info(&self, code: &str) -> AppResult<(PeerId, ShareCodeRecord)> {
+        let code = normalize_code(code);
+        if CodeFormat::detect(&code).is_none() {
+            return Err(AppError::InvalidCode);
+        }
         let record = self
             .client
-            .get_record(dht_key::share_code_key(code))
+            .get_record(dht_key::share_code_key(&code))
             .await?
             .record;
 
@@ -188,6 +600,7 @@ impl PairingManager {
         }
 
         let peer_id = record.publisher.ok_or(AppError::InvalidCode)?;
+        self.ensure_not_self(&peer_id)?;
         let share_record = serde_json::from_slice::<ShareCodeRecord>(&record.value)?;
 
         // 将记录中的地址注册到 Swarm 地址簿，确保后续 dial 能找到对方
@@ -204,6 +617,15 @@ impl PairingManager {
         Ok((peer_id, share_record))
     }
 
+    /// 配对目标为本机时返回 [`AppError::SelfPairing`]（例如输入了自己的配对码），
+    /// 避免向自己拨号后得到难以理解的网络错误
+    fn ensure_not_self(&self, peer_id: &PeerId) -> AppResult<()> {
+        if *peer_id == self.peer_id {
+            return Err(AppError::SelfPairing(self.peer_id.to_string()));
+        }
+        Ok(())
+    }
+
     /// 发起配对请求
     ///
     /// 返回 `(PairingResponse, Option<PairedDeviceInfo>)`：
@@ -215,12 +637,15 @@ impl PairingManager {
         method: PairingMethod,
         addrs: Option<Vec<Multiaddr>>,
     ) -> AppResult<(PairingResponse, Option<PairedDeviceInfo>)> {
+        self.ensure_not_self(&peer_id)?;
+
         if let Some(addrs) = addrs.filter(|a| !a.is_empty()) {
             self.client.add_peer_addrs(peer_id, addrs).await?;
         }
 
-        self.client.dial(peer_id).await?;
+        self.dial(peer_id).await?;
 
+        let timestamp = chrono::Utc::now().timestamp();
         let res = self
             .client
             .send_request(
@@ -228,7 +653,7 @@ impl PairingManager {
                 AppRequest::Pairing(PairingRequest {
                     os_info: OsInfo::default(),
                     method,
-                    timestamp: chrono::Utc::now().timestamp(),
+                    timestamp,
                 }),
             )
             .await?;
@@ -239,13 +664,11 @@ impl PairingManager {
                     .discovered_peers
                     .remove(&peer_id)
                     .map(|(_, info)| info)
+                    // 扫码或局域网直连配对时未查询 DHT，使用 identify 得到的设备信息
+                    .or_else(|| self.external.as_ref()?.devices.os_info(&peer_id))
                     .unwrap_or_else(|| OsInfo::unknown_from_peer_id(&peer_id));
 
-                let info = PairedDeviceInfo {
-                    peer_id,
-                    os_info,
-                    paired_at: chrono::Utc::now().timestamp_millis(),
-                };
+                let info = self.unverified_device(peer_id, os_info, timestamp);
                 self.paired_devices.insert(peer_id, info.clone());
 
                 Ok((PairingResponse::Success, Some(info)))
@@ -274,7 +697,7 @@ impl PairingManager {
             if matches!(response, PairingResponse::Success) {
                 let mut guard = self.active_code.lock().unwrap();
                 let info = guard.as_ref().ok_or(AppError::InvalidCode)?;
-                if &info.code != code {
+                if info.code != normalize_code(code) {
                     return Err(AppError::InvalidCode);
                 }
                 if info.is_expired() {
@@ -291,6 +714,11 @@ impl PairingManager {
             .send_response(pending_id, AppResponse::Pairing(response))
             .await?;
 
+        // 配对码已消耗，移除其 DHT 记录
+        if let (true, PairingMethod::Code { code }) = (accepted, method) {
+            self.remove_code_record(&normalize_code(code)).await;
+        }
+
         // 拒绝或缓存不存在 → 清理后返回 None
         let Some((_, pending)) = accepted
             .then(|| self.pending_inbound.remove(&pending_id))
@@ -301,15 +729,99 @@ impl PairingManager {
         };
 
         // 接受配对 → 构造 PairedDeviceInfo 并存储
-        let info = PairedDeviceInfo {
-            peer_id: pending.peer_id,
-            os_info: pending.os_info,
-            paired_at: chrono::Utc::now().timestamp_millis(),
-        };
+        let info = self.unverified_device(pending.peer_id, pending.os_info, pending.timestamp);
         self.paired_devices.insert(info.peer_id, info.clone());
         Ok(Some(info))
     }
 
+    /// 新配对的设备：附带验证码，等待用户核对
+    fn unverified_device(
+        &self,
+        peer_id: PeerId,
+        os_info: OsInfo,
+        timestamp: i64,
+    ) -> PairedDeviceInfo {
+        PairedDeviceInfo {
+            peer_id,
+            os_info,
+            paired_at: chrono::Utc::now().timestamp_millis(),
+            verified: false,
+            verification_code: Some(verification_code(&self.peer_id, &peer_id, timestamp)),
+            nickname: None,
+            last_seen_at: Some(chrono::Utc::now().timestamp_millis()),
+        }
+    }
+
+    // === 配对验证码核对 ===
+
+    /// 查询已配对设备的验证码与核对状态
+    pub fn get_verification(&self, peer_id: &PeerId) -> AppResult<PairedDeviceInfo> {
+        self.paired_devices
+            .get(peer_id)
+            .map(|d| d.value().clone())
+            .ok_or_else(|| AppError::Network(format!("设备 {peer_id} 未配对")))
+    }
+
+    /// 用户确认验证码一致：标记为已核对并通知对端
+    pub async fn confirm_pairing(&self, peer_id: PeerId) -> AppResult<PairedDeviceInfo> {
+        let info = {
+            let mut device = self
+                .paired_devices
+                .get_mut(&peer_id)
+                .ok_or_else(|| AppError::Network(format!("设备 {peer_id} 未配对")))?;
+            device.verified = true;
+            device.verification_code = None;
+            device.clone()
+        };
+        self.send_confirmation(peer_id, PairingConfirmation::Confirm)
+            .await;
+        Ok(info)
+    }
+
+    /// 用户发现验证码不一致：移除配对并通知对端同样移除
+    pub async fn abort_pairing(&self, peer_id: PeerId) -> Option<PairedDeviceInfo> {
+        let removed = self.remove_paired_device(&peer_id);
+        if removed.is_some() {
+            self.send_confirmation(peer_id, PairingConfirmation::Abort)
+                .await;
+        }
+        removed
+    }
+
+    /// 处理对端发来的核对结果（事件循环调用）
+    ///
+    /// 对端中止时移除该配对，返回是否已移除。
+    pub fn handle_confirmation(&self, peer_id: &PeerId, confirmation: PairingConfirmation) -> bool {
+        match confirmation {
+            PairingConfirmation::Confirm => {
+                tracing::info!("{} 已确认配对验证码", peer_id);
+                false
+            }
+            PairingConfirmation::Abort => {
+                tracing::warn!("{} 中止了配对（验证码不一致），已移除该设备", peer_id);
+                self.remove_paired_device(peer_id).is_some()
+            }
+        }
+    }
+
+    /// 通知对端核对结果（尽力而为，对端离线时仅记录日志）
+    async fn send_confirmation(&self, peer_id: PeerId, confirmation: PairingConfirmation) {
+        if let Err(e) = self
+            .client
+            .send_request(peer_id, AppRequest::PairingConfirmation(confirmation))
+            .await
+        {
+            tracing::warn!("向 {} 发送配对核对结果失败: {}", peer_id, e);
+        }
+    }
+
+    /// 通知对端本机已取消配对（尽力而为，对端离线时仅记录日志）
+    pub async fn notify_unpaired(&self, peer_id: PeerId) {
+        if let Err(e) = self.client.send_request(peer_id, AppRequest::Unpair).await {
+            tracing::warn!("向 {} 发送取消配对通知失败: {}", peer_id, e);
+        }
+    }
+
     // === 入站请求缓存 ===
 
     /// 缓存入站配对请求上下文（事件循环调用）
@@ -326,6 +838,7 @@ impl PairingManager {
             PendingInbound {
                 peer_id,
                 os_info: request.os_info.clone(),
+                timestamp: request.timestamp,
             },
         );
     }
@@ -336,6 +849,10 @@ impl PairingManager {
         self.paired_devices.contains_key(peer_id)
     }
 
+    pub fn has_paired_devices(&self) -> bool {
+        !self.paired_devices.is_empty()
+    }
+
     pub fn add_paired_device(&self, info: PairedDeviceInfo) {
         self.paired_devices.insert(info.peer_id, info);
     }
@@ -344,6 +861,44 @@ impl PairingManager {
         self.paired_devices.remove(peer_id).map(|(_, v)| v)
     }
 
+    /// 已配对设备通过 identify 上报的设备信息与记录不同（如对方改了主机名）时更新，
+    /// 返回更新后的完整记录（事件循环据此推送给前端重新保存）
+    pub fn refresh_os_info(&self, peer_id: &PeerId, os_info: OsInfo) -> Option<PairedDeviceInfo> {
+        let mut device = self.paired_devices.get_mut(peer_id)?;
+        if device.os_info == os_info {
+            return None;
+        }
+        tracing::info!(
+            "已配对设备 {} 的设备信息已变化: {} -> {}",
+            peer_id,
+            device.os_info.hostname,
+            os_info.hostname
+        );
+        device.os_info = os_info;
+        Some(device.clone())
+    }
+
+    /// 记录已配对设备的在线时间（毫秒），达到更新粒度时返回更新后的完整记录
+    /// （调用方据此推送给前端重新保存）；未配对的节点返回 None
+    pub fn touch_last_seen(&self, peer_id: &PeerId, at: i64) -> Option<PairedDeviceInfo> {
+        let mut device = self.paired_devices.get_mut(peer_id)?;
+        device.touch_last_seen(at).then(|| device.clone())
+    }
+
+    /// 设置或清除（`nickname` 为 None 或仅含空白）已配对设备的昵称，返回更新后的完整记录
+    pub fn set_nickname(
+        &self,
+        peer_id: &PeerId,
+        nickname: Option<&str>,
+    ) -> AppResult<PairedDeviceInfo> {
+        let mut device = self
+            .paired_devices
+            .get_mut(peer_id)
+            .ok_or_else(|| AppError::Network(format!("设备 {peer_id} 未配对")))?;
+        device.nickname = nickname.and_then(normalize_nickname);
+        Ok(device.clone())
+    }
+
     pub fn get_paired_devices(&self) -> Vec<PairedDeviceInfo> {
         self.paired_devices
             .iter()
@@ -351,3 +906,57 @@ impl PairingManager {
             .collect()
     }
 }
+
+/// 查询设备的 DHT 在线记录，返回其监听地址
+///
+/// 记录不存在、已过期或无法解析时返回 `None`。
+pub async fn lookup_online_addrs(client: &AppNetClient, peer_id: PeerId) -> Option<Vec<Multiaddr>> {
+    lookup_online_record(client, peer_id)
+        .await
+        .map(|r| r.listen_addrs)
+}
+
+/// 查询设备的 DHT 在线记录（不存在、已过期或无法解析时返回 `None`）
+async fn lookup_online_record(client: &AppNetClient, peer_id: PeerId) -> Option<OnlineRecord> {
+    let record = client
+        .get_record(dht_key::online_key(&peer_id.to_bytes()))
+        .await
+        .ok()?
+        .record;
+    if record.expires.is_some_and(|e| e < Instant::now()) {
+        return None;
+    }
+    serde_json::from_slice::<OnlineRecord>(&record.value).ok()
+}
+
+/// 经中继节点的 circuit 地址：`<中继地址>/p2p/<中继>/p2p-circuit`
+///
+/// 对方拨号时由 swarm 自动补上本节点的 `/p2p/<PeerId>`。
+fn relay_circuit_addr(mut relay_addr: Multiaddr, relay: PeerId) -> Multiaddr {
+    if !matches!(relay_addr.iter().last(), Some(Protocol::P2p(id)) if id == relay) {
+        relay_addr.push(Protocol::P2p(relay));
+    }
+    relay_addr.push(Protocol::P2pCircuit);
+    relay_addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_circuit_addr() {
+        let relay = PeerId::random();
+        let bare: Multiaddr = "/ip4/47.115.172.218/tcp/4001".parse().unwrap();
+        let expected: Multiaddr = format!("/ip4/47.115.172.218/tcp/4001/p2p/{relay}/p2p-circuit")
+            .parse()
+            .unwrap();
+
+        assert_eq!(relay_circuit_addr(bare.clone(), relay), expected);
+        // 地址已带中继 PeerId 时不重复追加
+        assert_eq!(
+            relay_circuit_addr(bare.with(Protocol::P2p(relay)), relay),
+            expected
+        );
+    }
+}
@@ -1,8 +1,11 @@
 //! 配对模块
 //!
-//! 管理设备配对流程：6 位配对码生成/查询、DHT 记录发布、
-//! 配对请求/响应处理。核心逻辑在 [`PairingManager`](manager::PairingManager)。
+//! 管理设备配对流程：配对码（6 位数字或单词码）生成/查询、DHT 记录发布、
+//! 配对请求/响应处理、配对码猜测防护、配对验证码核对。核心逻辑在 [`PairingManager`](manager::PairingManager)。
 
+pub mod attempts;
 pub mod code;
 pub mod dht_key;
 pub mod manager;
+pub mod payload;
+pub mod verification;
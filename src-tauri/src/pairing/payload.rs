@@ -0,0 +1,180 @@
+//! 二维码配对载荷
+//!
+//! 把配对码、本机 PeerId 与可达地址编码成一段短 URI 供对方扫码：
+//! `swarmdrop://pair?c=<配对码>&p=<PeerId>&a=<地址列表>`。
+//! 扫码方直接注册地址并发起配对，无需查询 DHT；地址失效时再回退到 DHT 查询。
+//!
+//! 载荷只包含公开信息（PeerId 与地址本就发布在 DHT 上），配对码必须与 PeerId 一同出现，
+//! 解析时缺少 PeerId 视为格式错误。
+
+use base64::prelude::*;
+use swarm_p2p_core::libp2p::{Multiaddr, PeerId};
+
+use super::code::{normalize_code, CodeFormat};
+use crate::{AppError, AppResult};
+
+/// URI 前缀
+const PAYLOAD_PREFIX: &str = "swarmdrop://pair?";
+
+/// 载荷中最多携带的地址数（控制二维码尺寸）
+pub const MAX_PAYLOAD_ADDRS: usize = 6;
+
+/// 二维码配对载荷
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairingPayload {
+    pub code: String,
+    pub peer_id: PeerId,
+    pub addrs: Vec<Multiaddr>,
+}
+
+impl PairingPayload {
+    /// 编码为 URI（地址超过 [`MAX_PAYLOAD_ADDRS`] 时截断）
+    pub fn encode(&self) -> String {
+        let mut uri = format!("{PAYLOAD_PREFIX}c={}&p={}", self.code, self.peer_id);
+        let addrs = &self.addrs[..self.addrs.len().min(MAX_PAYLOAD_ADDRS)];
+        if !addrs.is_empty() {
+            uri.push_str("&a=");
+            uri.push_str(&encode_addrs(addrs));
+        }
+        uri
+    }
+
+    /// 解析 URI，格式不合法时返回 [`AppError::InvalidPairingPayload`]；未知参数忽略
+    pub fn parse(payload: &str) -> AppResult<Self> {
+        let query = payload
+            .trim()
+            .strip_prefix(PAYLOAD_PREFIX)
+            .ok_or_else(|| invalid("不是 SwarmDrop 配对二维码"))?;
+
+        let mut code = None;
+        let mut peer_id = None;
+        let mut addrs = Vec::new();
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "c" => code = Some(value),
+                "p" => peer_id = Some(value),
+                "a" => addrs = decode_addrs(value)?,
+                _ => {}
+            }
+        }
+
+        let code = normalize_code(code.ok_or_else(|| invalid("缺少配对码"))?);
+        if CodeFormat::detect(&code).is_none() {
+            return Err(invalid("配对码格式错误"));
+        }
+        let peer_id = peer_id
+            .ok_or_else(|| invalid("缺少设备标识"))?
+            .parse::<PeerId>()
+            .map_err(|e| invalid(format!("设备标识无效: {e}")))?;
+
+        Ok(Self {
+            code,
+            peer_id,
+            addrs,
+        })
+    }
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::InvalidPairingPayload(message.into())
+}
+
+/// 地址列表编码：每个地址为 `u16 长度（大端）+ multiaddr 二进制`，整体 base64url（无填充）
+fn encode_addrs(addrs: &[Multiaddr]) -> String {
+    let mut buf = Vec::new();
+    for addr in addrs {
+        let bytes = addr.to_vec();
+        buf.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&bytes);
+    }
+    BASE64_URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn decode_addrs(encoded: &str) -> AppResult<Vec<Multiaddr>> {
+    let buf = BASE64_URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| invalid(format!("地址列表编码错误: {e}")))?;
+
+    let mut addrs = Vec::new();
+    let mut rest = buf.as_slice();
+    while !rest.is_empty() {
+        if rest.len() < 2 {
+            return Err(invalid("地址列表被截断"));
+        }
+        let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let bytes = rest
+            .get(2..2 + len)
+            .ok_or_else(|| invalid("地址列表被截断"))?;
+        let addr =
+            Multiaddr::try_from(bytes.to_vec()).map_err(|e| invalid(format!("地址无效: {e}")))?;
+        addrs.push(addr);
+        rest = &rest[2 + len..];
+    }
+    if addrs.len() > MAX_PAYLOAD_ADDRS {
+        return Err(invalid("地址数量超出上限"));
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(addr_count: usize) -> PairingPayload {
+        PairingPayload {
+            code: "042917".into(),
+            peer_id: PeerId::random(),
+            addrs: (0..addr_count)
+                .map(|i| format!("/ip4/192.168.1.{i}/tcp/4001").parse().unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = payload(2);
+        let encoded = original.encode();
+        assert!(encoded.starts_with("swarmdrop://pair?c=042917&p="));
+        assert_eq!(PairingPayload::parse(&encoded).unwrap(), original);
+
+        // 无地址时省略 a=
+        let bare = payload(0);
+        assert!(!bare.encode().contains("&a="));
+        assert_eq!(PairingPayload::parse(&bare.encode()).unwrap(), bare);
+
+        // 单词配对码
+        let words = PairingPayload {
+            code: "maple-otter-42".into(),
+            ..payload(1)
+        };
+        assert_eq!(PairingPayload::parse(&words.encode()).unwrap(), words);
+
+        // 地址过多时截断
+        let many = payload(MAX_PAYLOAD_ADDRS + 3);
+        let parsed = PairingPayload::parse(&many.encode()).unwrap();
+        assert_eq!(parsed.addrs, many.addrs[..MAX_PAYLOAD_ADDRS]);
+    }
+
+    #[test]
+    fn test_malformed_payloads() {
+        let peer_id = PeerId::random();
+        let cases = [
+            "https://example.com/pair?c=123456".to_string(),
+            // 只有配对码、没有 PeerId
+            "swarmdrop://pair?c=123456".to_string(),
+            format!("swarmdrop://pair?c=12ab56&p={peer_id}"),
+            "swarmdrop://pair?c=123456&p=not-a-peer-id".to_string(),
+            format!("swarmdrop://pair?c=123456&p={peer_id}&a=!!!"),
+            // 长度前缀超出实际数据
+            format!("swarmdrop://pair?c=123456&p={peer_id}&a=AAUE"),
+        ];
+        for case in cases {
+            let err = PairingPayload::parse(&case).unwrap_err();
+            assert!(
+                matches!(err, AppError::InvalidPairingPayload(_)),
+                "{case}: {err:?}"
+            );
+            assert_eq!(err.code(), "pairing/invalid-payload");
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! 配对验证码（SAS）
+//!
+//! 6 位配对码发布在公共 DHT 上，理论上可能被抢先查询同一配对码的攻击者冒充。
+//! 配对成功后两端各自由双方 PeerId 与配对请求时间戳派生同一个 6 位验证码，
+//! 用户在两台设备上比对一致后确认（`confirm_pairing`），不一致则中止（`abort_pairing`）。
+//! 中间人与两端分别建立的是不同的配对，双方看到的验证码不同。
+
+use swarm_p2p_core::libp2p::PeerId;
+
+/// blake3 派生密钥模式的上下文字符串（算法变化时递增版本号）
+const VERIFICATION_CONTEXT: &str = "swarmdrop 2026 pairing verification v1";
+
+/// 验证码位数对应的取值范围
+const VERIFICATION_MODULUS: u64 = 1_000_000;
+
+/// 由双方 PeerId 与配对请求时间戳派生验证码（与发起方 / 接收方顺序无关）
+pub fn verification_code(a: &PeerId, b: &PeerId, timestamp: i64) -> String {
+    let (a, b) = (a.to_bytes(), b.to_bytes());
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+
+    let mut hasher = blake3::Hasher::new_derive_key(VERIFICATION_CONTEXT);
+    for id in [&first, &second] {
+        hasher.update(&(id.len() as u32).to_be_bytes());
+        hasher.update(id);
+    }
+    hasher.update(&timestamp.to_be_bytes());
+
+    let digest = hasher.finalize();
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest.as_bytes()[..8]);
+    format!("{:06}", u64::from_be_bytes(head) % VERIFICATION_MODULUS)
+}
+
+#[cfg(test)]
+mod tests {
+    use swarm_p2p_core::libp2p::identity::Keypair;
+
+    use super::*;
+
+    /// 固定种子的 PeerId，保证断言结果确定
+    fn peer(seed: u8) -> PeerId {
+        Keypair::ed25519_from_bytes([seed; 32])
+            .unwrap()
+            .public()
+            .to_peer_id()
+    }
+
+    #[test]
+    fn test_verification_code() {
+        let (a, b, c) = (peer(1), peer(2), peer(3));
+        let code = verification_code(&a, &b, 1_700_000_000);
+
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|ch| ch.is_ascii_digit()));
+        // 两端计算结果一致
+        assert_eq!(verification_code(&b, &a, 1_700_000_000), code);
+        // 对端或时间戳不同，验证码不同
+        assert_ne!(verification_code(&a, &c, 1_700_000_000), code);
+        assert_ne!(verification_code(&a, &b, 1_700_000_001), code);
+    }
+}
@@ -6,8 +6,10 @@
 use serde::{Deserialize, Serialize};
 use swarm_p2p_core::NetClient;
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
 use crate::device::OsInfo;
+use crate::transfer::crypto::SessionKey;
 
 // ============ Pairing 协议 ============
 
@@ -41,6 +43,16 @@ pub enum PairingResponse {
     Refused { reason: PairingRefuseReason },
 }
 
+/// 配对验证码核对结果（配对成功后任一方发送给对端）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "action")]
+pub enum PairingConfirmation {
+    /// 用户确认两端验证码一致
+    Confirm,
+    /// 用户发现验证码不一致，中止配对（双方移除该配对）
+    Abort,
+}
+
 // ============ Transfer 协议 ============
 
 /// 传输文件元信息（Offer 中携带）
@@ -57,6 +69,24 @@ pub struct FileInfo {
     pub size: u64,
     /// BLAKE3 校验和（hex 编码）
     pub checksum: String,
+    /// 缩略图预览（小尺寸 JPEG，仅图片文件，尽力而为）
+    #[serde(default, with = "serde_bytes")]
+    pub preview: Option<Vec<u8>>,
+    /// MIME 类型（发送方按扩展名推断，接收方据此选择 Android 媒体目录）
+    #[serde(default)]
+    pub mime: Option<String>,
+}
+
+/// Offer 签名（发送方节点身份密钥对 Offer 规范编码的签名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfferSignature {
+    /// 发送方身份公钥（libp2p protobuf 编码）
+    #[serde(with = "serde_bytes")]
+    pub public_key: Vec<u8>,
+    /// 签名
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
 }
 
 /// 文件校验和（断点续传请求中携带）
@@ -77,6 +107,8 @@ pub enum ResumeRejectReason {
     SessionNotFound,
     /// 发送方已取消传输
     SenderCancelled,
+    /// 对端公钥无效，无法协商会话密钥（仅在对端携带公钥时出现）
+    KeyExchangeFailed,
 }
 
 /// 传输请求
@@ -88,6 +120,18 @@ pub enum TransferRequest {
         session_id: Uuid,
         files: Vec<FileInfo>,
         total_size: u64,
+        /// 需要在接收方重建的空目录（相对路径）
+        #[serde(default)]
+        directories: Vec<String>,
+        /// 是否要求加密：false 表示发送方希望在局域网直连时以明文传输（需接收方同意）
+        #[serde(default = "default_encryption")]
+        encryption: bool,
+        /// 发送方的 X25519 临时公钥（旧版本不携带，此时由接收方生成密钥并直接返回）
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
+        /// 发送方签名（旧版本不携带；携带时接收方验证失败即拒绝）
+        #[serde(default)]
+        signature: Option<OfferSignature>,
     },
     /// 接收方向发送方请求一个分块
     ChunkRequest {
@@ -109,18 +153,25 @@ pub enum TransferRequest {
         session_id: Uuid,
         /// 每个文件的校验和（用于验证源文件是否被修改）
         file_checksums: Vec<FileChecksum>,
+        /// 接收方的 X25519 临时公钥，双方据此重新派生会话密钥（旧版本不携带）
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
     },
     /// 发送方向接收方发起断点续传（发送方主动恢复）
     ResumeOffer {
         session_id: Uuid,
-        /// 发送方生成的 256-bit 对称加密密钥
+        /// 仅在接收方未声明支持密钥交换（旧版本）时使用：发送方生成的 256-bit 对称加密密钥
         #[serde(
-            serialize_with = "serialize_key",
-            deserialize_with = "deserialize_key"
+            default,
+            serialize_with = "serialize_opt_key",
+            deserialize_with = "deserialize_opt_key"
         )]
-        key: [u8; 32],
+        key: Option<SessionKey>,
         /// 每个文件的校验和（用于验证文件一致性）
         file_checksums: Vec<FileChecksum>,
+        /// 发送方的 X25519 临时公钥，双方据此重新派生会话密钥
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
     },
 }
 
@@ -128,10 +179,18 @@ pub enum TransferRequest {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum OfferRejectReason {
-    /// 发送方不在接收方的已配对设备列表中
+    /// 发送方未与接收方配对（被屏蔽的节点同样以此通用原因拒绝）
     NotPaired,
+    /// 接收方已取消与发送方的配对（发送方应同样移除该设备）
+    NoLongerPaired,
     /// 接收方用户主动拒绝
     UserDeclined,
+    /// Offer 内容不合法（文件数/大小超限、总大小不一致、文件名异常等）
+    InvalidOffer { message: String },
+    /// Offer 签名验证失败（内容可能被篡改）
+    InvalidSignature,
+    /// 接收方节点正在关闭
+    ShuttingDown,
 }
 
 /// 传输响应
@@ -141,14 +200,21 @@ pub enum TransferResponse {
     /// 接收方回复 Offer 请求
     OfferResult {
         accepted: bool,
-        /// 接受时由接收方生成的 256-bit 对称加密密钥
+        /// 仅在 Offer 未携带公钥（旧版本发送方）时使用：接收方生成的 256-bit 对称加密密钥
         #[serde(
             serialize_with = "serialize_opt_key",
             deserialize_with = "deserialize_opt_key"
         )]
-        key: Option<[u8; 32]>,
+        key: Option<SessionKey>,
         /// 拒绝时的原因（类型化）
         reason: Option<OfferRejectReason>,
+        /// 协商结果：false 表示双方同意在局域网直连时以明文传输分块
+        #[serde(default = "default_encryption")]
+        encryption: bool,
+        /// 接受时接收方的 X25519 临时公钥，双方据此派生会话密钥
+        /// （见 [`KeyExchange`](crate::transfer::crypto::KeyExchange)）
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
     },
     /// 发送方回复 ChunkRequest，返回加密后的分块数据
     Chunk {
@@ -159,6 +225,9 @@ pub enum TransferResponse {
         #[serde(with = "serde_bytes")]
         data: Vec<u8>,
         is_last: bool,
+        /// 分块是否加密（仅在协商明文且为局域网直连时为 false）
+        #[serde(default = "default_encryption")]
+        encrypted: bool,
     },
     /// 发送方确认传输完成
     Ack { session_id: Uuid },
@@ -175,12 +244,15 @@ pub enum TransferResponse {
         accepted: bool,
         /// 拒绝时的原因
         reason: Option<ResumeRejectReason>,
-        /// 接受时由接收方原始生成的对称加密密钥（发送方不持有，接收方重新提供）
+        /// 仅在请求未携带公钥（旧版本接收方）时使用：发送方重新生成的对称加密密钥
         #[serde(
             serialize_with = "serialize_opt_key",
             deserialize_with = "deserialize_opt_key"
         )]
-        key: Option<[u8; 32]>,
+        key: Option<SessionKey>,
+        /// 接受时发送方的 X25519 临时公钥
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
     },
     /// 接收方回复发送方的 ResumeOffer
     ResumeOfferResult {
@@ -188,26 +260,20 @@ pub enum TransferResponse {
         accepted: bool,
         /// 拒绝时的原因
         reason: Option<ResumeRejectReason>,
+        /// 接受时接收方的 X25519 临时公钥（ResumeOffer 未携带公钥时为空）
+        #[serde(default, with = "serde_bytes")]
+        public_key: Option<[u8; 32]>,
     },
 }
 
-/// 将 `[u8; 32]` 序列化为 bytes array（CBOR 友好）
-fn serialize_key<S: serde::Serializer>(key: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
-    serializer.serialize_bytes(&key[..])
-}
-
-/// 从 bytes 反序列化 `[u8; 32]`
-fn deserialize_key<'de, D: serde::Deserializer<'de>>(
-    deserializer: D,
-) -> Result<[u8; 32], D::Error> {
-    let v: Vec<u8> = serde_bytes::deserialize(deserializer)?;
-    v.try_into()
-        .map_err(|_| serde::de::Error::custom("expected 32 bytes for key"))
+/// 加密相关字段缺省为开启（旧版本对端不携带这些字段）
+fn default_encryption() -> bool {
+    true
 }
 
-/// 将 `Option<[u8; 32]>` 序列化为 bytes array（CBOR 友好）
+/// 将 `Option<SessionKey>` 序列化为 bytes array（CBOR 友好）
 fn serialize_opt_key<S: serde::Serializer>(
-    key: &Option<[u8; 32]>,
+    key: &Option<SessionKey>,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
     match key {
@@ -216,20 +282,22 @@ fn serialize_opt_key<S: serde::Serializer>(
     }
 }
 
-/// 从 bytes 反序列化 `Option<[u8; 32]>`
+/// 从 bytes 反序列化 `Option<SessionKey>`
 fn deserialize_opt_key<'de, D: serde::Deserializer<'de>>(
     deserializer: D,
-) -> Result<Option<[u8; 32]>, D::Error> {
+) -> Result<Option<SessionKey>, D::Error> {
     let opt: Option<Vec<u8>> = Option::deserialize(deserializer)?;
-    match opt {
-        None => Ok(None),
-        Some(v) => {
-            let arr: [u8; 32] = v
-                .try_into()
-                .map_err(|_| serde::de::Error::custom("expected 32 bytes for key"))?;
-            Ok(Some(arr))
-        }
+    opt.map(|v| key_from_bytes(Zeroizing::new(v))).transpose()
+}
+
+/// 校验长度并复制到 [`SessionKey`]（反序列化的中间缓冲区同样在释放时清零）
+fn key_from_bytes<E: serde::de::Error>(bytes: Zeroizing<Vec<u8>>) -> Result<SessionKey, E> {
+    if bytes.len() != 32 {
+        return Err(E::custom("expected 32 bytes for key"));
     }
+    let mut key = SessionKey::default();
+    key.copy_from_slice(&bytes);
+    Ok(key)
 }
 
 // ============ 顶层协议枚举 ============
@@ -238,6 +306,9 @@ fn deserialize_opt_key<'de, D: serde::Deserializer<'de>>(
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum AppRequest {
     Pairing(PairingRequest),
+    PairingConfirmation(PairingConfirmation),
+    /// 取消配对通知（对端收到后同样移除配对，回复 `PairingResponse::Success`）
+    Unpair,
     Transfer(TransferRequest),
 }
 
@@ -249,3 +320,101 @@ pub enum AppResponse {
 }
 
 pub type AppNetClient = NetClient<AppRequest, AppResponse>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_key_serializes_as_32_bytes() {
+        let key = crate::transfer::crypto::generate_key();
+        let response = TransferResponse::OfferResult {
+            accepted: true,
+            key: Some(key.clone()),
+            reason: None,
+            encryption: true,
+            public_key: None,
+        };
+
+        let value = serde_json::to_value(&response).unwrap();
+        let bytes: Vec<u8> = serde_json::from_value(value["key"].clone()).unwrap();
+        assert_eq!(bytes, key.to_vec());
+
+        let decoded: TransferResponse = serde_json::from_value(value).unwrap();
+        assert!(matches!(
+            decoded,
+            TransferResponse::OfferResult { key: Some(k), .. } if k == key
+        ));
+
+        // 长度不符的密钥被拒绝
+        let invalid = serde_json::json!({
+            "kind": "offerResult",
+            "accepted": true,
+            "key": [1, 2, 3],
+            "reason": null,
+        });
+        assert!(serde_json::from_value::<TransferResponse>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_public_key_optional_for_old_peers() {
+        // 旧版本 Offer 不携带公钥
+        let old_offer = serde_json::json!({
+            "kind": "offer",
+            "session_id": Uuid::nil(),
+            "files": [],
+            "total_size": 0,
+        });
+        let decoded: TransferRequest = serde_json::from_value(old_offer).unwrap();
+        assert!(matches!(
+            decoded,
+            TransferRequest::Offer {
+                public_key: None,
+                signature: None,
+                encryption: true,
+                ..
+            }
+        ));
+
+        let public_key = [5u8; 32];
+        let response = TransferResponse::OfferResult {
+            accepted: true,
+            key: None,
+            reason: None,
+            encryption: true,
+            public_key: Some(public_key),
+        };
+        let value = serde_json::to_value(&response).unwrap();
+        let decoded: TransferResponse = serde_json::from_value(value).unwrap();
+        assert!(matches!(
+            decoded,
+            TransferResponse::OfferResult { key: None, public_key: Some(k), .. } if k == public_key
+        ));
+    }
+
+    #[test]
+    fn test_pairing_confirmation_wire_format() {
+        let request = AppRequest::PairingConfirmation(PairingConfirmation::Abort);
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "type": "pairingConfirmation", "action": "abort" })
+        );
+        let decoded: AppRequest = serde_json::from_value(value).unwrap();
+        assert!(matches!(
+            decoded,
+            AppRequest::PairingConfirmation(PairingConfirmation::Abort)
+        ));
+    }
+
+    #[test]
+    fn test_unpair_wire_format() {
+        let value = serde_json::to_value(AppRequest::Unpair).unwrap();
+        assert_eq!(value, serde_json::json!({ "type": "unpair" }));
+        let decoded: AppRequest = serde_json::from_value(value).unwrap();
+        assert!(matches!(decoded, AppRequest::Unpair));
+
+        let reason = serde_json::to_value(OfferRejectReason::NoLongerPaired).unwrap();
+        assert_eq!(reason, serde_json::json!({ "type": "no_longer_paired" }));
+    }
+}
@@ -0,0 +1,352 @@
+//! 应用设置（后端持久化）
+//!
+//! 需要在后端生效的设置以 JSON 保存在应用本地数据目录（[`SETTINGS_FILE`]），
+//! 不依赖前端是否加载：例如 `accept_receive` 未指定保存位置时使用 `default_save_dir`。
+//! 纯界面相关的偏好（主题、语言等）仍由前端 preferences store 管理。
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use entity::SaveLocation;
+use serde::{Deserialize, Serialize};
+
+use crate::file_sink::template::SavePathTemplate;
+use crate::json_file;
+use crate::transfer::chunk_budget::ChunkBudget;
+use crate::transfer::limits::OfferLimits;
+use crate::{AppError, AppResult};
+
+/// 持久化文件名（位于应用本地数据目录）
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// 桌面端默认保存目录名（位于系统下载目录下，与前端 `getDefaultSavePath` 一致）
+#[cfg(not(target_os = "android"))]
+const DESKTOP_SAVE_DIR_NAME: &str = "SwarmDrop";
+
+/// 写入权限探测文件名（探测后立即删除）
+const WRITE_PROBE_FILE: &str = ".swarmdrop-write-test";
+
+/// 后端持久化的设置（持久化格式与返回前端的格式一致）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    /// 默认保存位置，None 时使用平台默认（桌面端 `下载/SwarmDrop`，Android 端 `Download/SwarmDrop`）
+    ///
+    /// 桌面端为目录路径；Android 端为公共目录（`androidPublicDir`）或用户选择的 SAF 目录。
+    pub default_save_dir: Option<SaveLocation>,
+    /// 保存路径模板（如 `Received/{deviceName}/{date}/{filename}`），None 时直接保存到所选目录
+    ///
+    /// 语法见 [`SavePathTemplate`]。
+    pub save_path_template: Option<String>,
+    /// 接收时不预分配文件大小
+    ///
+    /// 默认按文件大小预分配以提前发现空间不足；保存位置在 FAT32 / exFAT 等
+    /// 预分配大文件会失败或产生稀疏文件的文件系统上时开启。
+    pub skip_preallocation: bool,
+    /// 入站 Offer 的文件数上限（含空目录），None 时使用默认值（修改后重启节点生效）
+    pub max_offer_files: Option<usize>,
+    /// 入站 Offer 的总大小上限（字节），None 时使用默认值（修改后重启节点生效）
+    pub max_offer_total_size: Option<u64>,
+    /// 发送方同时读取 / 加密的分块数上限（每个占用 2 倍分块大小的内存），
+    /// None 时按可用内存确定（修改后重启节点生效）
+    pub max_in_flight_chunks: Option<usize>,
+}
+
+impl AppSettings {
+    /// 入站 Offer 限制：未设置的项使用默认值
+    pub fn offer_limits(&self) -> OfferLimits {
+        let defaults = OfferLimits::default();
+        OfferLimits {
+            max_files: self.max_offer_files.unwrap_or(defaults.max_files),
+            max_total_size: self.max_offer_total_size.unwrap_or(defaults.max_total_size),
+            ..defaults
+        }
+    }
+
+    /// 发送方在途分块内存预算：未设置时按可用内存确定
+    pub fn chunk_budget(&self) -> ChunkBudget {
+        self.max_in_flight_chunks
+            .map_or_else(ChunkBudget::from_available_memory, ChunkBudget::new)
+    }
+}
+
+/// 设置存储（Tauri state）
+#[derive(Debug, Default)]
+pub struct SettingsStore {
+    settings: Mutex<AppSettings>,
+    /// 持久化路径（为 None 时仅保存在内存中）
+    path: Option<PathBuf>,
+}
+
+impl SettingsStore {
+    /// 从文件加载设置，文件不存在或损坏时使用默认值
+    pub fn load(path: PathBuf) -> Self {
+        let settings = json_file::load(&path, "设置").unwrap_or_default();
+        Self {
+            settings: Mutex::new(settings),
+            path: Some(path),
+        }
+    }
+
+    /// 当前设置
+    pub fn get(&self) -> AppSettings {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// 校验并保存设置
+    ///
+    /// 桌面端默认保存目录不存在时创建，不可写时返回错误且不修改已保存的设置。
+    pub fn set(&self, settings: AppSettings) -> AppResult<()> {
+        if let Some(SaveLocation::Path { path }) = &settings.default_save_dir {
+            ensure_writable_dir(Path::new(path))?;
+        }
+        if let Some(template) = &settings.save_path_template {
+            SavePathTemplate::parse(template)?;
+        }
+        if settings.max_offer_files == Some(0) || settings.max_offer_total_size == Some(0) {
+            return Err(AppError::Config("Offer 限制必须大于 0".into()));
+        }
+        if settings.max_in_flight_chunks == Some(0) {
+            return Err(AppError::Config("在途分块数上限必须大于 0".into()));
+        }
+        if let Some(path) = &self.path {
+            json_file::save(path, &settings)?;
+        }
+        if let Ok(mut current) = self.settings.lock() {
+            *current = settings;
+        }
+        Ok(())
+    }
+
+    /// 生效的默认保存位置：用户设置优先，否则为平台默认
+    pub fn effective_save_location(&self, app: &tauri::AppHandle) -> Option<SaveLocation> {
+        self.get()
+            .default_save_dir
+            .or_else(|| platform_default_save_location(app))
+    }
+
+    /// 解析接收时的保存位置：前端未指定时使用默认保存位置
+    pub fn resolve_save_location(
+        &self,
+        requested: Option<SaveLocation>,
+        app: &tauri::AppHandle,
+    ) -> AppResult<SaveLocation> {
+        self.resolve_with(requested, platform_default_save_location(app))
+    }
+
+    /// 解析接收时的保存路径模板：前端指定的优先（空字符串表示不使用模板），否则使用设置中的模板
+    pub fn resolve_save_path_template(
+        &self,
+        requested: Option<String>,
+    ) -> AppResult<Option<SavePathTemplate>> {
+        requested
+            .or_else(|| self.get().save_path_template)
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| SavePathTemplate::parse(&t))
+            .transpose()
+    }
+
+    fn resolve_with(
+        &self,
+        requested: Option<SaveLocation>,
+        platform_default: Option<SaveLocation>,
+    ) -> AppResult<SaveLocation> {
+        if let Some(location) = requested {
+            return Ok(location);
+        }
+        let location = self
+            .get()
+            .default_save_dir
+            .or(platform_default)
+            .ok_or_else(|| AppError::Config("未设置默认保存目录".into()))?;
+        // 默认目录可能在设置之后被删除或改为只读，每次使用前重新确认
+        if let SaveLocation::Path { path } = &location {
+            ensure_writable_dir(Path::new(path))?;
+        }
+        Ok(location)
+    }
+}
+
+/// 平台默认保存位置
+fn platform_default_save_location(
+    #[allow(unused_variables)] app: &tauri::AppHandle,
+) -> Option<SaveLocation> {
+    #[cfg(target_os = "android")]
+    {
+        Some(SaveLocation::AndroidPublicDir {
+            base_dir: entity::PublicBaseDir::default(),
+            subdir: crate::file_sink::DEFAULT_PUBLIC_SUBDIR.into(),
+            by_media_type: false,
+        })
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        use tauri::Manager;
+
+        let dir = app.path().download_dir().ok()?.join(DESKTOP_SAVE_DIR_NAME);
+        Some(SaveLocation::Path {
+            path: dir.to_string_lossy().into_owned(),
+        })
+    }
+}
+
+/// 确认目录存在且可写，不存在时创建
+pub fn ensure_writable_dir(dir: &Path) -> AppResult<()> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| AppError::Config(format!("无法创建保存目录 {}: {e}", dir.display())))?;
+    let probe = dir.join(WRITE_PROBE_FILE);
+    std::fs::write(&probe, b"")
+        .map_err(|e| AppError::Config(format!("保存目录不可写 {}: {e}", dir.display())))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("swarmdrop_test_settings_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn path_location(dir: &Path) -> SaveLocation {
+        SaveLocation::Path {
+            path: dir.to_string_lossy().into_owned(),
+        }
+    }
+
+    #[test]
+    fn test_set_creates_dir_and_persists() {
+        let dir = test_dir("persist");
+        let file = dir.join(SETTINGS_FILE);
+        let save_dir = dir.join("nested/inbox");
+
+        let store = SettingsStore::load(file.clone());
+        assert_eq!(store.get(), AppSettings::default());
+
+        let settings = AppSettings {
+            default_save_dir: Some(path_location(&save_dir)),
+            save_path_template: Some("{deviceName}/{filename}".into()),
+            skip_preallocation: true,
+            max_offer_files: Some(100),
+            max_offer_total_size: None,
+            max_in_flight_chunks: Some(16),
+        };
+        store.set(settings.clone()).unwrap();
+        assert!(save_dir.is_dir());
+        // 未设置的 Offer 限制使用默认值
+        let limits = settings.offer_limits();
+        assert_eq!(limits.max_files, 100);
+        assert_eq!(limits.max_total_size, OfferLimits::default().max_total_size);
+        assert_eq!(settings.chunk_budget().available(), 16);
+        assert!(!save_dir.join(WRITE_PROBE_FILE).exists());
+        assert_eq!(SettingsStore::load(file).get(), settings);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_rejects_unusable_dir() {
+        let dir = test_dir("reject");
+        // 同名文件已存在，无法创建目录
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+
+        let store = SettingsStore::default();
+        let result = store.set(AppSettings {
+            default_save_dir: Some(path_location(&blocked)),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(AppError::Config(_))));
+        assert_eq!(store.get(), AppSettings::default());
+
+        let result = store.set(AppSettings {
+            save_path_template: Some("{deviceName}".into()),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(AppError::Config(_))));
+        assert_eq!(store.get(), AppSettings::default());
+
+        for invalid in [
+            AppSettings {
+                max_offer_files: Some(0),
+                ..Default::default()
+            },
+            AppSettings {
+                max_in_flight_chunks: Some(0),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(store.set(invalid), Err(AppError::Config(_))));
+            assert_eq!(store.get(), AppSettings::default());
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_default() {
+        let dir = test_dir("resolve");
+        let stored = dir.join("stored");
+        let platform = dir.join("platform");
+        let requested = path_location(&dir.join("requested"));
+
+        let store = SettingsStore::default();
+        // 前端指定的位置原样使用
+        assert_eq!(
+            store
+                .resolve_with(Some(requested.clone()), Some(path_location(&platform)))
+                .unwrap(),
+            requested
+        );
+        // 未设置默认目录：使用平台默认并创建目录
+        assert_eq!(
+            store
+                .resolve_with(None, Some(path_location(&platform)))
+                .unwrap(),
+            path_location(&platform)
+        );
+        assert!(platform.is_dir());
+        assert!(matches!(
+            store.resolve_with(None, None),
+            Err(AppError::Config(_))
+        ));
+
+        // 用户设置优先；设置后目录被删除时重新创建
+        store
+            .set(AppSettings {
+                default_save_dir: Some(path_location(&stored)),
+                save_path_template: Some("{date}/{filename}".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        std::fs::remove_dir_all(&stored).unwrap();
+        assert_eq!(
+            store
+                .resolve_with(None, Some(path_location(&platform)))
+                .unwrap(),
+            path_location(&stored)
+        );
+        assert!(stored.is_dir());
+
+        // 保存路径模板：前端指定的优先，空字符串表示不使用模板
+        assert!(store.resolve_save_path_template(None).unwrap().is_some());
+        assert_eq!(
+            store
+                .resolve_save_path_template(Some(String::new()))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            store
+                .resolve_save_path_template(Some("a/{filename}".into()))
+                .unwrap(),
+            Some(SavePathTemplate::parse("a/{filename}").unwrap())
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,148 @@
+//! 分块完成 bitmap
+//!
+//! 每个文件按 chunk_index 占一位（第 i 个 chunk 对应第 `i / 8` 字节的第 `i % 8` 位），
+//! 字节布局与 DB 中保存的 `completed_chunks` 一致，断点续传时直接恢复。
+//! 接收方以 [`AtomicBitmap`] 作为分块是否完成的唯一依据，发送方用同一结构为进度去重。
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::file_source::CHUNK_SIZE;
+
+/// 并发安全的 chunk bitmap
+///
+/// 并发分块任务各自标记完成，只有首次标记返回 true，
+/// 保证每个 chunk 只计入一次进度和 checkpoint 计数，也是断点续传的依据。
+pub(crate) struct AtomicBitmap {
+    bytes: Vec<AtomicU8>,
+}
+
+impl AtomicBitmap {
+    /// 全部未完成的 bitmap
+    pub(crate) fn new(total_chunks: u32) -> Self {
+        Self::from_bytes(&vec![0u8; total_chunks.div_ceil(8) as usize])
+    }
+
+    /// 从 DB 中保存的 bitmap 恢复
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            bytes: bytes.iter().map(|b| AtomicU8::new(*b)).collect(),
+        }
+    }
+
+    pub(crate) fn is_set(&self, chunk_index: u32) -> bool {
+        let (byte, mask) = Self::position(chunk_index);
+        self.bytes
+            .get(byte)
+            .is_some_and(|b| b.load(Ordering::Acquire) & mask != 0)
+    }
+
+    /// 标记 chunk 已完成，返回是否为首次标记（越界返回 false）
+    pub(crate) fn set(&self, chunk_index: u32) -> bool {
+        let (byte, mask) = Self::position(chunk_index);
+        self.bytes
+            .get(byte)
+            .is_some_and(|b| b.fetch_or(mask, Ordering::AcqRel) & mask == 0)
+    }
+
+    /// 当前快照（写入 DB checkpoint）
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.bytes
+            .iter()
+            .map(|b| b.load(Ordering::Acquire))
+            .collect()
+    }
+
+    fn position(chunk_index: u32) -> (usize, u8) {
+        ((chunk_index / 8) as usize, 1 << (chunk_index % 8))
+    }
+}
+
+/// 检查指定 chunk 是否已完成
+fn is_chunk_completed(bitmap: &[u8], chunk_index: u32) -> bool {
+    let byte_idx = (chunk_index / 8) as usize;
+    let bit_idx = chunk_index % 8;
+    byte_idx < bitmap.len() && (bitmap[byte_idx] & (1 << bit_idx)) != 0
+}
+
+/// 统计 bitmap 中已完成的 chunk 数（利用 popcount 加速）
+pub(crate) fn count_completed_in_bitmap(bitmap: &[u8], total_chunks: u32) -> u32 {
+    let full_bytes = (total_chunks / 8) as usize;
+    let remainder_bits = total_chunks % 8;
+
+    let mut count: u32 = bitmap.iter().take(full_bytes).map(|b| b.count_ones()).sum();
+
+    // 尾部不完整字节：仅统计有效位
+    if remainder_bits > 0 {
+        if let Some(&last_byte) = bitmap.get(full_bytes) {
+            let mask = (1u8 << remainder_bits) - 1;
+            count += (last_byte & mask).count_ones();
+        }
+    }
+
+    count
+}
+
+/// 根据 bitmap 计算已传输字节数
+pub(crate) fn bytes_from_bitmap(bitmap: &[u8], file_size: u64, total_chunks: u32) -> u64 {
+    if file_size == 0 || total_chunks == 0 {
+        return 0;
+    }
+    let chunk_size = CHUNK_SIZE as u64;
+    let last_chunk_size = match file_size % chunk_size {
+        0 => chunk_size,
+        r => r,
+    };
+
+    let full_chunk_count = count_completed_in_bitmap(bitmap, total_chunks.saturating_sub(1));
+    let last_chunk_done = is_chunk_completed(bitmap, total_chunks - 1);
+
+    full_chunk_count as u64 * chunk_size + if last_chunk_done { last_chunk_size } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitmap_helpers() {
+        let bitmap = AtomicBitmap::new(10);
+        assert!(bitmap.set(0));
+        assert!(bitmap.set(9));
+        let bm = bitmap.to_bytes();
+        assert_eq!(bm.len(), 2);
+        assert!(is_chunk_completed(&bm, 0));
+        assert!(is_chunk_completed(&bm, 9));
+        assert!(!is_chunk_completed(&bm, 1));
+        assert_eq!(count_completed_in_bitmap(&bm, 10), 2);
+
+        // 末块不足 CHUNK_SIZE 时按实际大小计算
+        let file_size = CHUNK_SIZE as u64 * 9 + 10;
+        assert_eq!(
+            bytes_from_bitmap(&bm, file_size, 10),
+            CHUNK_SIZE as u64 + 10
+        );
+    }
+
+    #[test]
+    fn test_atomic_bitmap_counts_late_retry_once() {
+        let bitmap = AtomicBitmap::from_bytes(&[0b0000_0001, 0]);
+        assert!(bitmap.is_set(0));
+        assert!(!bitmap.set(0));
+
+        // 首次请求超时后重试成功，随后首次请求的响应才晚到：同一 chunk 被两个任务同时标记
+        let newly_set = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..2).map(|_| s.spawn(|| bitmap.set(9))).collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .filter(|newly| *newly)
+                .count()
+        });
+        assert_eq!(newly_set, 1);
+        assert!(!bitmap.set(16));
+
+        let bytes = bitmap.to_bytes();
+        assert!(is_chunk_completed(&bytes, 9));
+        assert_eq!(count_completed_in_bitmap(&bytes, 10), 2);
+    }
+}
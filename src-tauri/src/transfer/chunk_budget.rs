@@ -0,0 +1,167 @@
+//! 发送方分块缓冲区的全局内存预算
+//!
+//! 每个在途的 ChunkRequest 都要分配一份明文和一份密文缓冲区（各 [`CHUNK_SIZE`]）。
+//! 同时向多个设备发送或接收方高并发拉取时，所有会话的在途请求之和没有上限，
+//! 低内存的 Android 设备上可能 OOM。[`ChunkBudget`] 在所有发送会话间共享，
+//! 读取分块前先取得一个名额，超出预算的请求排队等待；会话取消时排队的请求立即返回。
+
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::file_source::CHUNK_SIZE;
+use crate::{AppError, AppResult};
+
+/// 单个在途请求占用的内存（明文 + 密文）
+const BYTES_PER_CHUNK: u64 = 2 * CHUNK_SIZE as u64;
+
+/// 预算占可用内存的比例（1/N）
+const AVAILABLE_MEMORY_DIVISOR: u64 = 16;
+
+/// 名额下限：至少满足一个接收方的满并发拉取
+const MIN_IN_FLIGHT: usize = 8;
+
+/// 名额上限（256 MiB）
+const MAX_IN_FLIGHT: usize = 512;
+
+/// 无法获取可用内存时的名额（32 MiB）
+const DEFAULT_IN_FLIGHT: usize = 64;
+
+/// 在途分块缓冲区预算（所有发送会话共享）
+#[derive(Debug, Clone)]
+pub struct ChunkBudget {
+    semaphore: Arc<Semaphore>,
+}
+
+impl ChunkBudget {
+    /// 最多 `max_in_flight` 个分块请求同时读取 / 加密（至少为 1）
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_in_flight.max(1))),
+        }
+    }
+
+    /// 按当前可用内存确定预算
+    pub fn from_available_memory() -> Self {
+        Self::new(in_flight_for_memory(available_memory()))
+    }
+
+    /// 当前空闲名额
+    #[cfg(test)]
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    /// 等待一个名额，返回的 permit drop 时归还；`cancel` 触发时立即返回错误
+    pub async fn acquire(&self, cancel: &CancellationToken) -> AppResult<OwnedSemaphorePermit> {
+        tokio::select! {
+            _ = cancel.cancelled() => Err(AppError::Transfer("传输已取消".into())),
+            permit = self.semaphore.clone().acquire_owned() => {
+                permit.map_err(|_| AppError::Transfer("Semaphore closed".into()))
+            }
+        }
+    }
+}
+
+impl Default for ChunkBudget {
+    fn default() -> Self {
+        Self::from_available_memory()
+    }
+}
+
+/// 可用内存对应的名额数
+fn in_flight_for_memory(available: Option<u64>) -> usize {
+    match available {
+        Some(bytes) => {
+            let in_flight = bytes / AVAILABLE_MEMORY_DIVISOR / BYTES_PER_CHUNK;
+            (in_flight as usize).clamp(MIN_IN_FLIGHT, MAX_IN_FLIGHT)
+        }
+        None => DEFAULT_IN_FLIGHT,
+    }
+}
+
+/// 系统当前可用内存（字节），Linux / Android 读取 `/proc/meminfo`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_mem_available(&meminfo)
+}
+
+/// 其他平台暂不探测，使用默认预算
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn available_memory() -> Option<u64> {
+    None
+}
+
+/// 解析 `/proc/meminfo` 中的 `MemAvailable: <n> kB`
+#[cfg(any(target_os = "linux", target_os = "android", test))]
+fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_in_flight_for_memory() {
+        // 1 GiB 可用：64 MiB 预算 / 512 KiB = 128
+        assert_eq!(in_flight_for_memory(Some(1024 * MIB)), 128);
+        assert_eq!(in_flight_for_memory(Some(16 * MIB)), MIN_IN_FLIGHT);
+        assert_eq!(in_flight_for_memory(Some(64 * 1024 * MIB)), MAX_IN_FLIGHT);
+        assert_eq!(in_flight_for_memory(None), DEFAULT_IN_FLIGHT);
+    }
+
+    #[test]
+    fn test_parse_mem_available() {
+        let meminfo = "MemTotal:        3906236 kB\nMemFree:          181012 kB\nMemAvailable:    1048576 kB\n";
+        assert_eq!(parse_mem_available(meminfo), Some(1024 * MIB));
+        assert_eq!(parse_mem_available("MemTotal: 1 kB\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_and_cancels() {
+        let budget = ChunkBudget::new(1);
+        let cancel = CancellationToken::new();
+
+        let permit = budget.acquire(&cancel).await.unwrap();
+        assert_eq!(budget.available(), 0);
+
+        // 名额用尽时排队，归还后继续
+        let waiter = {
+            let budget = budget.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move { budget.acquire(&cancel).await.map(drop) })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        drop(permit);
+        waiter.await.unwrap().unwrap();
+        assert_eq!(budget.available(), 1);
+
+        // 排队中的请求在会话取消时立即返回
+        let _held = budget.acquire(&CancellationToken::new()).await.unwrap();
+        let parked = {
+            let budget = budget.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move { budget.acquire(&cancel).await.map(drop) })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(1), parked)
+            .await
+            .expect("取消后应立即返回")
+            .unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! 传输会话的外部依赖抽象
+//!
+//! `SendSession` / `ReceiveSession` 不直接持有 `AppHandle` 和 `AppNetClient`，
+//! 而是通过 [`SessionContext`] 获取：
+//! - [`EventSink`]：进度 / 完成 / 失败等事件输出（生产环境为 `AppHandle`）
+//! - [`ChunkTransport`]：向对端发送请求、断线重连（生产环境为 `AppNetClient`）
+//! - 可选的数据库连接与 `AppHandle`（Android 文件操作需要）
+//!
+//! 测试中可注入记录型 sink 和内存 transport，直接驱动真实的拉取 / 校验 / 重命名逻辑；
+//! 回环 transport 把请求交给同进程内的 `SendSession`，覆盖从读取、加密到写入、校验的完整链路。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use sea_orm::DatabaseConnection;
+use swarm_p2p_core::libp2p::PeerId;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::events;
+use crate::network::traffic::TrafficStats;
+use crate::pairing::manager::lookup_online_addrs;
+use crate::protocol::{AppNetClient, AppRequest, AppResponse};
+use crate::transfer::chunk_budget::ChunkBudget;
+use crate::transfer::progress::{
+    OverallProgressEvent, TransferCompleteEvent, TransferDbErrorEvent, TransferFailedEvent,
+    TransferProgressEvent, TransferReconnectingEvent, TransferVerifyingEvent,
+};
+use crate::AppResult;
+
+/// 可跨线程传递的 boxed future（trait object 中的异步方法使用）
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 传输事件输出
+pub trait EventSink: Send + Sync {
+    fn emit_progress(&self, event: &TransferProgressEvent);
+    fn emit_complete(&self, event: &TransferCompleteEvent);
+    fn emit_failed(&self, event: &TransferFailedEvent);
+    fn emit_db_error(&self, event: &TransferDbErrorEvent);
+    fn emit_reconnecting(&self, event: &TransferReconnectingEvent);
+    fn emit_overall_progress(&self, event: &OverallProgressEvent);
+    fn emit_verifying(&self, event: &TransferVerifyingEvent);
+}
+
+impl EventSink for AppHandle {
+    fn emit_progress(&self, event: &TransferProgressEvent) {
+        let _ = self.emit(events::TRANSFER_PROGRESS, event);
+    }
+
+    fn emit_complete(&self, event: &TransferCompleteEvent) {
+        let _ = self.emit(events::TRANSFER_COMPLETE, event);
+    }
+
+    fn emit_failed(&self, event: &TransferFailedEvent) {
+        let _ = self.emit(events::TRANSFER_FAILED, event);
+    }
+
+    fn emit_db_error(&self, event: &TransferDbErrorEvent) {
+        let _ = self.emit(events::TRANSFER_DB_ERROR, event);
+    }
+
+    fn emit_reconnecting(&self, event: &TransferReconnectingEvent) {
+        let _ = self.emit(events::TRANSFER_RECONNECTING, event);
+    }
+
+    fn emit_overall_progress(&self, event: &OverallProgressEvent) {
+        let _ = self.emit(events::OVERALL_PROGRESS, event);
+    }
+
+    fn emit_verifying(&self, event: &TransferVerifyingEvent) {
+        let _ = self.emit(events::TRANSFER_VERIFYING, event);
+    }
+}
+
+/// 会话终态计数（总体进度事件使用）与流量统计
+#[derive(Debug, Default)]
+pub struct SessionCounters {
+    pub completed: AtomicU32,
+    pub failed: AtomicU32,
+    /// 会话完成时按连接类型累计传输字节数
+    pub traffic: Arc<TrafficStats>,
+}
+
+/// 转发事件的同时统计完成 / 失败的会话数及传输流量
+struct CountingSink {
+    inner: Arc<dyn EventSink>,
+    counters: Arc<SessionCounters>,
+}
+
+impl EventSink for CountingSink {
+    fn emit_progress(&self, event: &TransferProgressEvent) {
+        self.inner.emit_progress(event);
+    }
+
+    fn emit_complete(&self, event: &TransferCompleteEvent) {
+        self.counters.completed.fetch_add(1, Ordering::Relaxed);
+        self.counters.traffic.record_session(
+            event.direction,
+            event.total_bytes,
+            event.final_connection.as_ref(),
+        );
+        self.inner.emit_complete(event);
+    }
+
+    fn emit_failed(&self, event: &TransferFailedEvent) {
+        self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        self.inner.emit_failed(event);
+    }
+
+    fn emit_db_error(&self, event: &TransferDbErrorEvent) {
+        self.inner.emit_db_error(event);
+    }
+
+    fn emit_reconnecting(&self, event: &TransferReconnectingEvent) {
+        self.inner.emit_reconnecting(event);
+    }
+
+    fn emit_overall_progress(&self, event: &OverallProgressEvent) {
+        self.inner.emit_overall_progress(event);
+    }
+
+    fn emit_verifying(&self, event: &TransferVerifyingEvent) {
+        self.inner.emit_verifying(event);
+    }
+}
+
+/// 向对端发送传输请求
+pub trait ChunkTransport: Send + Sync {
+    fn send_request(
+        &self,
+        peer_id: PeerId,
+        request: AppRequest,
+    ) -> BoxFuture<'_, AppResult<AppResponse>>;
+
+    /// 连接中断后重新拨号对端
+    fn reconnect(&self, peer_id: PeerId) -> BoxFuture<'_, AppResult<()>>;
+}
+
+impl ChunkTransport for AppNetClient {
+    fn send_request(
+        &self,
+        peer_id: PeerId,
+        request: AppRequest,
+    ) -> BoxFuture<'_, AppResult<AppResponse>> {
+        Box::pin(async move { Ok(AppNetClient::send_request(self, peer_id, request).await?) })
+    }
+
+    /// 先从 DHT 在线记录重新注册对端地址（网络切换后地址可能已变化），再发起拨号
+    fn reconnect(&self, peer_id: PeerId) -> BoxFuture<'_, AppResult<()>> {
+        Box::pin(async move {
+            if let Some(addrs) = lookup_online_addrs(self, peer_id)
+                .await
+                .filter(|a| !a.is_empty())
+            {
+                self.add_peer_addrs(peer_id, addrs).await?;
+            }
+            self.dial(peer_id).await?;
+            Ok(())
+        })
+    }
+}
+
+/// 传输会话运行所需的外部依赖
+#[derive(Clone)]
+pub struct SessionContext {
+    pub transport: Arc<dyn ChunkTransport>,
+    pub events: Arc<dyn EventSink>,
+    /// 数据库连接（为 None 时跳过持久化）
+    pub db: Option<DatabaseConnection>,
+    /// Tauri 应用句柄（仅 Android 文件操作需要）
+    pub app: Option<AppHandle>,
+    /// 发送方在途分块的全局内存预算（为 None 时不限制）
+    pub chunk_budget: Option<ChunkBudget>,
+}
+
+impl SessionContext {
+    /// 生产环境：事件经 `AppHandle` 发射，请求经 `AppNetClient` 发送
+    pub fn from_app(client: AppNetClient, app: &AppHandle) -> Self {
+        Self {
+            transport: Arc::new(client),
+            events: Arc::new(app.clone()),
+            db: app
+                .try_state::<DatabaseConnection>()
+                .map(|db| db.inner().clone()),
+            app: Some(app.clone()),
+            chunk_budget: None,
+        }
+    }
+
+    /// 与其他会话共享在途分块的内存预算
+    pub fn with_chunk_budget(mut self, budget: ChunkBudget) -> Self {
+        self.chunk_budget = Some(budget);
+        self
+    }
+
+    /// 统计该会话发射的完成 / 失败事件
+    pub fn with_counters(mut self, counters: Arc<SessionCounters>) -> Self {
+        self.events = Arc::new(CountingSink {
+            inner: self.events,
+            counters,
+        });
+        self
+    }
+
+    pub fn app(&self) -> Option<&AppHandle> {
+        self.app.as_ref()
+    }
+}
+
+/// 测试辅助：记录型 EventSink + 内存 transport + 回环 transport
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::protocol::{TransferRequest, TransferResponse};
+    use crate::transfer::sender::SendSession;
+    use crate::AppError;
+
+    /// 记录所有发射的事件，便于断言
+    #[derive(Default)]
+    pub struct RecordingSink {
+        pub progress: Mutex<Vec<TransferProgressEvent>>,
+        pub complete: Mutex<Vec<TransferCompleteEvent>>,
+        pub failed: Mutex<Vec<TransferFailedEvent>>,
+        pub reconnecting: Mutex<Vec<TransferReconnectingEvent>>,
+        pub overall: Mutex<Vec<OverallProgressEvent>>,
+        pub verifying: Mutex<Vec<TransferVerifyingEvent>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit_progress(&self, event: &TransferProgressEvent) {
+            self.progress.lock().unwrap().push(event.clone());
+        }
+
+        fn emit_complete(&self, event: &TransferCompleteEvent) {
+            self.complete.lock().unwrap().push(event.clone());
+        }
+
+        fn emit_failed(&self, event: &TransferFailedEvent) {
+            self.failed.lock().unwrap().push(event.clone());
+        }
+
+        fn emit_db_error(&self, _event: &TransferDbErrorEvent) {}
+
+        fn emit_reconnecting(&self, event: &TransferReconnectingEvent) {
+            self.reconnecting.lock().unwrap().push(event.clone());
+        }
+
+        fn emit_overall_progress(&self, event: &OverallProgressEvent) {
+            self.overall.lock().unwrap().push(event.clone());
+        }
+
+        fn emit_verifying(&self, event: &TransferVerifyingEvent) {
+            self.verifying.lock().unwrap().push(event.clone());
+        }
+    }
+
+    type Handler = dyn Fn(&AppRequest) -> AppResult<AppResponse> + Send + Sync;
+    type ReconnectHandler = dyn Fn() -> AppResult<()> + Send + Sync;
+
+    /// 内存 transport：由闭包生成响应，并记录收到的所有请求
+    pub struct MockTransport {
+        handler: Box<Handler>,
+        reconnect_handler: Box<ReconnectHandler>,
+        pub requests: Mutex<Vec<AppRequest>>,
+        /// 重连调用次数
+        pub reconnects: AtomicUsize,
+    }
+
+    impl MockTransport {
+        pub fn new(
+            handler: impl Fn(&AppRequest) -> AppResult<AppResponse> + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                handler: Box::new(handler),
+                reconnect_handler: Box::new(|| Ok(())),
+                requests: Mutex::new(Vec::new()),
+                reconnects: AtomicUsize::new(0),
+            }
+        }
+
+        /// 自定义重连结果（默认总是成功）
+        pub fn with_reconnect(
+            mut self,
+            handler: impl Fn() -> AppResult<()> + Send + Sync + 'static,
+        ) -> Self {
+            self.reconnect_handler = Box::new(handler);
+            self
+        }
+    }
+
+    impl ChunkTransport for MockTransport {
+        fn send_request(
+            &self,
+            _peer_id: PeerId,
+            request: AppRequest,
+        ) -> BoxFuture<'_, AppResult<AppResponse>> {
+            let result = (self.handler)(&request);
+            self.requests.lock().unwrap().push(request);
+            Box::pin(async move { result })
+        }
+
+        fn reconnect(&self, _peer_id: PeerId) -> BoxFuture<'_, AppResult<()>> {
+            self.reconnects.fetch_add(1, Ordering::SeqCst);
+            let result = (self.reconnect_handler)();
+            Box::pin(async move { result })
+        }
+    }
+
+    /// 回环 transport：接收方的请求直接交给同进程内真实的 [`SendSession`] 处理，
+    /// 响应方式与事件循环中的分发一致（分块读取失败返回 ChunkError，Complete / Cancel 回复 Ack）
+    pub struct LoopbackTransport {
+        sender: Arc<SendSession>,
+        /// 接收方 PeerId（发送方据此校验分块请求的来源）
+        local_peer_id: PeerId,
+    }
+
+    impl LoopbackTransport {
+        pub fn new(sender: Arc<SendSession>, local_peer_id: PeerId) -> Self {
+            Self {
+                sender,
+                local_peer_id,
+            }
+        }
+    }
+
+    impl ChunkTransport for LoopbackTransport {
+        fn send_request(
+            &self,
+            _peer_id: PeerId,
+            request: AppRequest,
+        ) -> BoxFuture<'_, AppResult<AppResponse>> {
+            Box::pin(async move {
+                let response = match request {
+                    AppRequest::Transfer(TransferRequest::ChunkRequest {
+                        session_id,
+                        file_id,
+                        chunk_index,
+                    }) => {
+                        let result = if session_id == self.sender.session_id {
+                            self.sender
+                                .handle_chunk_request(&self.local_peer_id, file_id, chunk_index)
+                                .await
+                        } else {
+                            Err(AppError::Transfer("发送会话不存在".into()))
+                        };
+                        result.unwrap_or_else(|e| TransferResponse::ChunkError {
+                            session_id,
+                            file_id,
+                            chunk_index,
+                            error: e.to_string(),
+                        })
+                    }
+                    AppRequest::Transfer(TransferRequest::Complete { session_id }) => {
+                        self.sender.handle_complete();
+                        TransferResponse::Ack { session_id }
+                    }
+                    AppRequest::Transfer(TransferRequest::Cancel { session_id, reason }) => {
+                        self.sender.handle_cancel(&reason);
+                        TransferResponse::Ack { session_id }
+                    }
+                    other => {
+                        return Err(AppError::Transfer(format!(
+                            "回环 transport 不支持的请求: {other:?}"
+                        )))
+                    }
+                };
+                Ok(AppResponse::Transfer(response))
+            })
+        }
+
+        fn reconnect(&self, _peer_id: PeerId) -> BoxFuture<'_, AppResult<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// 构造不含数据库与 AppHandle 的测试上下文
+    pub fn test_context(
+        transport: Arc<dyn ChunkTransport>,
+        events: Arc<RecordingSink>,
+    ) -> SessionContext {
+        SessionContext {
+            transport,
+            events,
+            db: None,
+            app: None,
+            chunk_budget: None,
+        }
+    }
+}
@@ -1,27 +1,67 @@
 //! 传输加密模块
 //!
 //! 使用 XChaCha20-Poly1305 对文件分块进行端到端加密。
-//! 每次传输生成独立的 256-bit 对称密钥，传输结束后销毁。
+//! 每次传输生成独立的 256-bit 对称密钥（[`SessionKey`]），传输结束后销毁：
+//! 密钥本身和加密器内部的副本在释放时都会被清零，不会残留在内存中。
+//!
+//! ## 密钥交换
+//!
+//! 会话密钥不在网络上传输：Offer 携带发送方的 X25519 临时公钥，OfferResult 携带接收方的，
+//! 双方各自用 ECDH 共享秘密经 HKDF-SHA256 派生会话密钥（见 [`KeyExchange`]），
+//! 派生时绑定 session_id 与双方 PeerId，换一个会话或换一对设备都得到不同的密钥。
+//! 旧版本对端不携带公钥，此时退回由接收方生成密钥并在 OfferResult 中返回。
+//!
+//! 断点续传同样重新交换公钥（ResumeRequest / ResumeResult、ResumeOffer / ResumeOfferResult），
+//! 为恢复后的会话派生新密钥；已落盘的数据是明文，换密钥不影响续传。
 //!
 //! ## Nonce 派生
 //!
 //! 使用 BLAKE3 `derive_key` 模式从 `(session_id, file_id, chunk_index)` 确定性派生
 //! 24 字节 nonce，支持乱序、并发和重试场景，无需同步计数器。
+//!
+//! ## 局域网明文
+//!
+//! 可信局域网内加密可能成为吞吐瓶颈，且 libp2p 的 Noise 传输层本身已加密。
+//! 双方都开启设置时可在 Offer 中协商明文（见 [`negotiate_encryption`]），
+//! 但每个分块仍按当时的连接类型决定是否加密（[`use_plaintext`]）：
+//! 只有局域网直连才发送明文，回落到中继 / 打洞连接时自动恢复加密。
+//! 密钥照常交换，文件仍做 BLAKE3 校验。
 
-use chacha20poly1305::aead::{self, Aead};
+use chacha20poly1305::aead::{self, Aead, OsRng};
 use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use swarm_p2p_core::libp2p::PeerId;
 use uuid::Uuid;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use crate::device::ConnectionType;
+use crate::{AppError, AppResult};
+
+/// HKDF info 前缀（域分离，升级派生方式时递增版本号）
+const KEY_DERIVATION_INFO: &[u8] = b"swarmdrop-transfer-key-v1";
+
+/// 会话密钥（释放时清零）
+///
+/// 从生成到写入 Offer / Resume 消息、再到传给会话构造加密器，全程以该类型传递，
+/// 中间产生的克隆同样会在释放时清零。
+pub type SessionKey = Zeroizing<[u8; 32]>;
 
 /// 传输加密器
 ///
 /// 封装 XChaCha20-Poly1305 AEAD，提供基于 `(session_id, file_id, chunk_index)`
 /// 的确定性 nonce 派生加密/解密接口。
 ///
-/// 密钥仅存于内存中，传输结束后随结构体一起销毁。
+/// 密钥仅存于内存中，传输结束后随结构体一起销毁：
+/// `XChaCha20Poly1305` 在释放时清零内部的密钥副本。
 pub struct TransferCrypto {
     cipher: XChaCha20Poly1305,
 }
 
+/// 唯一的字段在释放时清零密钥
+impl ZeroizeOnDrop for TransferCrypto {}
+
 impl TransferCrypto {
     /// 从 256-bit 密钥创建加密器
     pub fn new(key: &[u8; 32]) -> Self {
@@ -61,6 +101,70 @@ impl TransferCrypto {
     }
 }
 
+/// 一次 X25519 密钥交换（每个会话生成一次，派生后即销毁）
+///
+/// 临时私钥在释放时清零；[`Self::derive`] 消耗自身，保证同一私钥只使用一次。
+pub struct KeyExchange {
+    secret: EphemeralSecret,
+    public: PublicKey,
+}
+
+impl KeyExchange {
+    /// 生成临时密钥对（操作系统 CSPRNG）
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// 发给对端的公钥
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+
+    /// 与对端公钥计算共享秘密，派生会话密钥
+    ///
+    /// 双方传入相同的 `session_id` / `sender` / `receiver` 才能得到同一把密钥。
+    /// 对端公钥为低阶点（共享秘密全零）时拒绝，防止密钥被强制为已知值。
+    pub fn derive(
+        self,
+        peer_public: &[u8; 32],
+        session_id: &Uuid,
+        sender: &PeerId,
+        receiver: &PeerId,
+    ) -> AppResult<SessionKey> {
+        let shared = self.secret.diffie_hellman(&PublicKey::from(*peer_public));
+        if !shared.was_contributory() {
+            return Err(AppError::KeyExchange("对端公钥无效".into()));
+        }
+        derive_session_key(shared.as_bytes(), session_id, sender, receiver)
+    }
+}
+
+impl Default for KeyExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// HKDF-SHA256：salt 为 session_id，info 为 `前缀 || sender PeerId || receiver PeerId`
+fn derive_session_key(
+    shared_secret: &[u8; 32],
+    session_id: &Uuid,
+    sender: &PeerId,
+    receiver: &PeerId,
+) -> AppResult<SessionKey> {
+    let mut info = KEY_DERIVATION_INFO.to_vec();
+    info.extend_from_slice(&sender.to_bytes());
+    info.extend_from_slice(&receiver.to_bytes());
+
+    let mut key = SessionKey::default();
+    Hkdf::<Sha256>::new(Some(session_id.as_bytes()), shared_secret)
+        .expand(&info, key.as_mut_slice())
+        .map_err(|e| AppError::KeyExchange(format!("派生会话密钥失败: {e}")))?;
+    Ok(key)
+}
+
 /// 从 `(session_id, file_id, chunk_index)` 派生 24 字节 nonce
 ///
 /// 使用 BLAKE3 `derive_key` 模式：
@@ -80,10 +184,32 @@ fn derive_nonce(session_id: &Uuid, file_id: u32, chunk_index: u32) -> [u8; 24] {
     hash[..24].try_into().expect("blake3 output >= 24 bytes")
 }
 
-/// 生成随机 256-bit 加密密钥
-pub fn generate_key() -> [u8; 32] {
-    use chacha20poly1305::aead::OsRng;
-    XChaCha20Poly1305::generate_key(&mut OsRng).into()
+/// 当前分块是否以明文传输：已协商明文且当前为局域网直连
+pub fn use_plaintext(plaintext_negotiated: bool, connection: Option<&ConnectionType>) -> bool {
+    plaintext_negotiated && matches!(connection, Some(ConnectionType::Lan))
+}
+
+/// 协商是否加密（接收方回复 Offer 时调用）
+///
+/// 只有发送方请求明文、本机也允许、且当前为局域网直连时才返回 false。
+pub fn negotiate_encryption(
+    requested: bool,
+    allow_plaintext: bool,
+    connection: Option<&ConnectionType>,
+) -> bool {
+    requested || !use_plaintext(allow_plaintext, connection)
+}
+
+/// 生成随机 256-bit 加密密钥（操作系统 CSPRNG）
+///
+/// 随机字节直接写入 [`SessionKey`] 的缓冲区，不经过 `KeyInit::generate_key`
+/// 返回的中间数组，避免在栈上留下未清零的副本。
+pub fn generate_key() -> SessionKey {
+    use chacha20poly1305::aead::rand_core::RngCore;
+
+    let mut key = SessionKey::default();
+    OsRng.fill_bytes(key.as_mut_slice());
+    key
 }
 
 #[cfg(test)]
@@ -245,4 +371,100 @@ mod tests {
         assert_ne!(base, derive_nonce(&sid, 1, 0));
         assert_ne!(base, derive_nonce(&sid, 0, 1));
     }
+
+    #[test]
+    fn plaintext_only_on_negotiated_lan() {
+        assert!(use_plaintext(true, Some(&ConnectionType::Lan)));
+        assert!(!use_plaintext(false, Some(&ConnectionType::Lan)));
+        assert!(!use_plaintext(true, Some(&ConnectionType::Relay)));
+        assert!(!use_plaintext(true, Some(&ConnectionType::Dcutr)));
+        assert!(!use_plaintext(true, None));
+    }
+
+    #[test]
+    fn negotiation_requires_both_sides_and_lan() {
+        let lan = Some(&ConnectionType::Lan);
+        let relay = Some(&ConnectionType::Relay);
+        assert!(!negotiate_encryption(false, true, lan));
+        // 任一方要求加密即加密
+        assert!(negotiate_encryption(true, true, lan));
+        assert!(negotiate_encryption(false, false, lan));
+        // 非局域网连接始终加密
+        assert!(negotiate_encryption(false, true, relay));
+        assert!(negotiate_encryption(false, true, None));
+    }
+
+    #[test]
+    fn key_material_zeroized_on_drop() {
+        fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+        assert_zeroize_on_drop::<SessionKey>();
+        assert_zeroize_on_drop::<XChaCha20Poly1305>();
+        assert_zeroize_on_drop::<TransferCrypto>();
+    }
+
+    #[test]
+    fn key_exchange_derives_same_key() {
+        let sid = test_uuid();
+        let sender = PeerId::random();
+        let receiver = PeerId::random();
+
+        let sender_kx = KeyExchange::new();
+        let receiver_kx = KeyExchange::new();
+        let sender_pub = sender_kx.public_key();
+        let receiver_pub = receiver_kx.public_key();
+
+        let sender_key = sender_kx
+            .derive(&receiver_pub, &sid, &sender, &receiver)
+            .unwrap();
+        let receiver_key = receiver_kx
+            .derive(&sender_pub, &sid, &sender, &receiver)
+            .unwrap();
+        assert_eq!(*sender_key, *receiver_key);
+
+        // 派生的密钥可直接用于分块加解密
+        let ciphertext = TransferCrypto::new(&sender_key)
+            .encrypt_chunk(&sid, 0, 0, b"ecdh")
+            .unwrap();
+        assert_eq!(
+            TransferCrypto::new(&receiver_key)
+                .decrypt_chunk(&sid, 0, 0, &ciphertext)
+                .unwrap(),
+            b"ecdh"
+        );
+    }
+
+    #[test]
+    fn key_exchange_binds_session_and_peers() {
+        let shared = [9u8; 32];
+        let sender = PeerId::random();
+        let receiver = PeerId::random();
+        let derive =
+            |sid: &Uuid, a: &PeerId, b: &PeerId| *derive_session_key(&shared, sid, a, b).unwrap();
+
+        let base = derive(&test_uuid(), &sender, &receiver);
+        assert_eq!(base, derive(&test_uuid(), &sender, &receiver));
+        // 换会话、交换双方角色或换对端都得到不同的密钥
+        assert_ne!(base, derive(&test_uuid2(), &sender, &receiver));
+        assert_ne!(base, derive(&test_uuid(), &receiver, &sender));
+        assert_ne!(base, derive(&test_uuid(), &sender, &PeerId::random()));
+    }
+
+    #[test]
+    fn key_exchange_rejects_low_order_point() {
+        let result = KeyExchange::new().derive(
+            &[0u8; 32],
+            &test_uuid(),
+            &PeerId::random(),
+            &PeerId::random(),
+        );
+        assert!(matches!(result, Err(AppError::KeyExchange(_))));
+    }
+
+    #[test]
+    fn generate_key_fills_whole_buffer() {
+        let key1 = generate_key();
+        let key2 = generate_key();
+        assert_ne!(*key1, [0u8; 32]);
+        assert_ne!(*key1, *key2);
+    }
 }
@@ -0,0 +1,128 @@
+//! 发送前的耗时估算
+//!
+//! 不传输任何数据，只根据已准备的文件和对端当前的连接类型 / RTT 套用简单模型：
+//! - 吞吐取连接类型的典型带宽，与"并发窗口 / RTT"两者中的较小值
+//!   （接收方同时最多拉取 [`MAX_CONCURRENT_CHUNKS`] 个分块，高延迟链路上受此限制）
+//! - 每个文件额外计一次往返（启动拉取与校验）
+//!
+//! 结果只作为界面上的"约 2 分钟（中继）"提示，不保证准确。
+
+use serde::Serialize;
+
+use crate::device::ConnectionType;
+use crate::file_source::CHUNK_SIZE;
+use crate::transfer::receiver::MAX_CONCURRENT_CHUNKS;
+
+/// 局域网直连的典型吞吐（字节/秒）
+const LAN_THROUGHPUT: u64 = 30 * 1024 * 1024;
+
+/// 打洞直连的典型吞吐（字节/秒）
+const DCUTR_THROUGHPUT: u64 = 4 * 1024 * 1024;
+
+/// 中继连接的典型吞吐（字节/秒，公共中继通常限速）
+const RELAY_THROUGHPUT: u64 = 512 * 1024;
+
+/// 未测得 RTT 时假定的往返时间（毫秒）
+const DEFAULT_RTT_MS: u64 = 50;
+
+/// 传输耗时估算结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferEstimate {
+    /// 总字节数
+    pub total_bytes: u64,
+    /// 文件数（不含空目录）
+    pub file_count: usize,
+    /// 对端当前连接类型（未连接时为 None）
+    pub connection: Option<ConnectionType>,
+    /// 对端最近一次测得的 RTT（毫秒）
+    pub rtt_ms: Option<u64>,
+    /// 估算吞吐（字节/秒），未连接时为 None
+    pub throughput: Option<u64>,
+    /// 预计耗时（秒，向上取整），未连接时为 None
+    pub eta_secs: Option<u64>,
+}
+
+impl TransferEstimate {
+    /// 套用估算模型
+    pub fn new(
+        total_bytes: u64,
+        file_count: usize,
+        connection: Option<ConnectionType>,
+        rtt_ms: Option<u64>,
+    ) -> Self {
+        let throughput = connection
+            .as_ref()
+            .map(|c| estimate_throughput(c, rtt_ms.unwrap_or(DEFAULT_RTT_MS)));
+        let eta_secs = throughput.map(|throughput| {
+            let rtt_ms = rtt_ms.unwrap_or(DEFAULT_RTT_MS);
+            let transfer_ms = total_bytes.saturating_mul(1000).div_ceil(throughput);
+            let overhead_ms = file_count as u64 * rtt_ms;
+            (transfer_ms + overhead_ms).div_ceil(1000)
+        });
+        Self {
+            total_bytes,
+            file_count,
+            connection,
+            rtt_ms,
+            throughput,
+            eta_secs,
+        }
+    }
+}
+
+/// 连接类型的典型带宽与并发窗口限制中的较小值（字节/秒，至少为 1）
+fn estimate_throughput(connection: &ConnectionType, rtt_ms: u64) -> u64 {
+    let bandwidth = match connection {
+        ConnectionType::Lan => LAN_THROUGHPUT,
+        ConnectionType::Dcutr => DCUTR_THROUGHPUT,
+        ConnectionType::Relay => RELAY_THROUGHPUT,
+    };
+    let window = (MAX_CONCURRENT_CHUNKS * CHUNK_SIZE) as u64;
+    let window_limit = window.saturating_mul(1000) / rtt_ms.max(1);
+    bandwidth.min(window_limit).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_throughput_by_connection() {
+        // 低延迟：受连接类型带宽限制
+        assert_eq!(estimate_throughput(&ConnectionType::Lan, 1), LAN_THROUGHPUT);
+        assert_eq!(
+            estimate_throughput(&ConnectionType::Relay, 1),
+            RELAY_THROUGHPUT
+        );
+        // 高延迟：8 × 256 KB 窗口 / 0.5 s = 4 MB/s
+        assert_eq!(estimate_throughput(&ConnectionType::Lan, 500), 4 * MIB);
+        assert_eq!(
+            estimate_throughput(&ConnectionType::Dcutr, 500),
+            DCUTR_THROUGHPUT
+        );
+    }
+
+    #[test]
+    fn test_eta_arithmetic() {
+        // 60 MB 走中继：120 s 传输 + 3 个文件 × 100 ms
+        let estimate = TransferEstimate::new(60 * MIB, 3, Some(ConnectionType::Relay), Some(100));
+        assert_eq!(estimate.throughput, Some(RELAY_THROUGHPUT));
+        assert_eq!(estimate.eta_secs, Some(121));
+
+        // 局域网未测得 RTT：按默认 50 ms，300 MB / 30 MB/s = 10 s，再加 20 × 50 ms
+        let estimate = TransferEstimate::new(300 * MIB, 20, Some(ConnectionType::Lan), None);
+        assert_eq!(estimate.eta_secs, Some(11));
+
+        // 空文件也至少计入往返开销，不足 1 秒向上取整
+        let estimate = TransferEstimate::new(0, 1, Some(ConnectionType::Lan), Some(5));
+        assert_eq!(estimate.eta_secs, Some(1));
+
+        // 未连接：无法估算
+        let estimate = TransferEstimate::new(MIB, 1, None, None);
+        assert_eq!(estimate.throughput, None);
+        assert_eq!(estimate.eta_secs, None);
+    }
+}
@@ -0,0 +1,119 @@
+//! 传输保活
+//!
+//! Android 在应用切到后台后会很快挂起进程，长时间的传输会被中断。
+//! 有活跃会话时启动前台服务（常驻通知 + 总体进度），全部结束后停止。
+//!
+//! 由总体进度推送任务驱动（见 `TransferManager::spawn_overall_progress_task`），
+//! 会话插入 / 移除时会立即唤醒该任务，因此服务的启停不必等下一个推送周期。
+//! 桌面端不需要保活，不注册实现。
+
+use std::time::{Duration, Instant};
+
+use crate::transfer::progress::OverallProgressEvent;
+
+/// 通知进度的最小更新间隔（通知刷新过于频繁会被系统限流）
+pub const KEEP_ALIVE_UPDATE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// 平台保活实现（Android 前台服务）
+pub trait KeepAlive: Send + Sync {
+    /// 第一个会话开始：启动前台服务
+    fn start(&self);
+
+    /// 更新通知中的总体进度
+    fn update(&self, progress: &OverallProgressEvent);
+
+    /// 最后一个会话结束：停止前台服务
+    fn stop(&self);
+}
+
+/// 根据每轮的总体进度决定启动 / 更新 / 停止保活
+#[derive(Debug, Default)]
+pub struct KeepAliveState {
+    active: bool,
+    last_update: Option<Instant>,
+}
+
+impl KeepAliveState {
+    /// 处理一轮总体进度
+    pub fn on_progress(&mut self, keep_alive: &dyn KeepAlive, progress: &OverallProgressEvent) {
+        let now = Instant::now();
+        match (self.active, progress.active_sessions > 0) {
+            (false, true) => {
+                keep_alive.start();
+                keep_alive.update(progress);
+                self.last_update = Some(now);
+            }
+            (true, true) => {
+                let due = self
+                    .last_update
+                    .is_none_or(|t| now.duration_since(t) >= KEEP_ALIVE_UPDATE_INTERVAL);
+                if due {
+                    keep_alive.update(progress);
+                    self.last_update = Some(now);
+                }
+            }
+            (true, false) => {
+                keep_alive.stop();
+                self.last_update = None;
+            }
+            (false, false) => {}
+        }
+        self.active = progress.active_sessions > 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<&'static str>>);
+
+    impl KeepAlive for Recorder {
+        fn start(&self) {
+            self.0.lock().unwrap().push("start");
+        }
+
+        fn update(&self, _progress: &OverallProgressEvent) {
+            self.0.lock().unwrap().push("update");
+        }
+
+        fn stop(&self) {
+            self.0.lock().unwrap().push("stop");
+        }
+    }
+
+    fn progress(active_sessions: usize) -> OverallProgressEvent {
+        OverallProgressEvent {
+            active_sessions,
+            completed_sessions: 0,
+            failed_sessions: 0,
+            total_bytes: 100,
+            transferred_bytes: 10,
+            speed: 0.0,
+            eta: None,
+        }
+    }
+
+    #[test]
+    fn test_start_throttle_and_stop() {
+        let recorder = Recorder::default();
+        let mut state = KeepAliveState::default();
+
+        state.on_progress(&recorder, &progress(0));
+        state.on_progress(&recorder, &progress(1));
+        // 距上次更新不足间隔：不刷新通知
+        state.on_progress(&recorder, &progress(2));
+        state.last_update = Some(Instant::now() - KEEP_ALIVE_UPDATE_INTERVAL);
+        state.on_progress(&recorder, &progress(2));
+        state.on_progress(&recorder, &progress(0));
+        state.on_progress(&recorder, &progress(0));
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec!["start", "update", "update", "stop"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! 入站 Offer 校验
+//!
+//! 对端（即使已配对）发来的 Offer 内容不可信：文件数、总大小、文件名长度都需要设上限，
+//! 并校验 `total_size` 与各文件大小之和一致，避免缓存超大 Offer 或向前端推送巨型事件。
+//! 相对路径与空目录路径不能包含 `..`、根目录或盘符，防止写到保存位置之外。
+
+use std::path::{Component, Path};
+
+use crate::protocol::FileInfo;
+
+/// 默认最大文件数
+const DEFAULT_MAX_FILES: usize = 50_000;
+/// 默认单次传输总大小上限（1 TiB）
+const DEFAULT_MAX_TOTAL_SIZE: u64 = 1 << 40;
+/// 默认文件名最大长度（字符数）
+const DEFAULT_MAX_NAME_LEN: usize = 255;
+/// 默认相对路径最大长度（字符数）
+const DEFAULT_MAX_PATH_LEN: usize = 4096;
+
+/// 入站 Offer 限制
+#[derive(Debug, Clone)]
+pub struct OfferLimits {
+    /// 最大文件数
+    pub max_files: usize,
+    /// 总大小上限（字节）
+    pub max_total_size: u64,
+    /// 文件名最大长度（字符数）
+    pub max_name_len: usize,
+    /// 相对路径最大长度（字符数）
+    pub max_path_len: usize,
+}
+
+impl Default for OfferLimits {
+    fn default() -> Self {
+        Self {
+            max_files: DEFAULT_MAX_FILES,
+            max_total_size: DEFAULT_MAX_TOTAL_SIZE,
+            max_name_len: DEFAULT_MAX_NAME_LEN,
+            max_path_len: DEFAULT_MAX_PATH_LEN,
+        }
+    }
+}
+
+impl OfferLimits {
+    /// 校验 Offer 内容，返回描述违规原因的错误信息
+    pub fn validate(
+        &self,
+        files: &[FileInfo],
+        directories: &[String],
+        total_size: u64,
+    ) -> Result<(), String> {
+        if files.is_empty() && directories.is_empty() {
+            return Err("文件列表为空".into());
+        }
+        let entry_count = files.len() + directories.len();
+        if entry_count > self.max_files {
+            return Err(format!(
+                "文件数超出上限: {} > {}",
+                entry_count, self.max_files
+            ));
+        }
+        if total_size > self.max_total_size {
+            return Err(format!(
+                "总大小超出上限: {} > {}",
+                total_size, self.max_total_size
+            ));
+        }
+
+        let sum = files
+            .iter()
+            .try_fold(0u64, |acc, f| acc.checked_add(f.size))
+            .ok_or("文件大小之和溢出")?;
+        if sum != total_size {
+            return Err(format!("总大小不一致: 声明 {total_size}, 实际 {sum}"));
+        }
+
+        for f in files {
+            let id = format!("file_id={}", f.file_id);
+            check_len("文件名", &f.name, self.max_name_len, &id)?;
+            check_len("相对路径", &f.relative_path, self.max_path_len, &id)?;
+            check_relative("相对路径", &f.relative_path, &id)?;
+        }
+        for dir in directories {
+            check_len("目录路径", dir, self.max_path_len, "directory")?;
+            check_relative("目录路径", dir, "directory")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 检查字符串非空且不超过长度上限
+fn check_len(label: &str, value: &str, max_len: usize, id: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Err(format!("{label}为空: {id}"));
+    }
+    let len = value.chars().count();
+    if len > max_len {
+        return Err(format!("{label}过长: {id}, {len} > {max_len}"));
+    }
+    Ok(())
+}
+
+/// 检查路径为相对路径且不会跳出保存位置
+///
+/// 同时按 `/` 和 `\` 分段检查 `..`：接收方可能是 Windows，反斜杠在那里同样是分隔符。
+fn check_relative(label: &str, value: &str, id: &str) -> Result<(), String> {
+    let escapes = value.starts_with(['/', '\\'])
+        || value.split(['/', '\\']).any(|segment| segment == "..")
+        || Path::new(value)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(format!("{label}不安全: {id}, {value}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(file_id: u32, name: &str, size: u64) -> FileInfo {
+        FileInfo {
+            file_id,
+            name: name.into(),
+            relative_path: name.into(),
+            size,
+            checksum: String::new(),
+            preview: None,
+            mime: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_offer() {
+        let files = vec![file(0, "a.txt", 10), file(1, "b.txt", 20)];
+        assert!(OfferLimits::default().validate(&files, &[], 30).is_ok());
+    }
+
+    #[test]
+    fn test_reject_too_many_files() {
+        let limits = OfferLimits {
+            max_files: 2,
+            ..Default::default()
+        };
+        let files: Vec<_> = (0..3).map(|i| file(i, "a", 1)).collect();
+        assert!(limits.validate(&files, &[], 3).is_err());
+    }
+
+    #[test]
+    fn test_reject_total_size_mismatch() {
+        let files = vec![file(0, "a.txt", 10)];
+        assert!(OfferLimits::default().validate(&files, &[], 11).is_err());
+    }
+
+    #[test]
+    fn test_reject_oversized_and_overflow() {
+        let limits = OfferLimits::default();
+        let files = vec![file(0, "huge.bin", u64::MAX)];
+        assert!(limits.validate(&files, &[], u64::MAX).is_err());
+
+        let limits = OfferLimits {
+            max_total_size: u64::MAX,
+            ..Default::default()
+        };
+        let files = vec![file(0, "a", u64::MAX), file(1, "b", 1)];
+        assert!(limits.validate(&files, &[], u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_reject_bad_names() {
+        let limits = OfferLimits::default();
+        assert!(limits.validate(&[file(0, "", 1)], &[], 1).is_err());
+
+        let long_name = "x".repeat(256);
+        assert!(limits.validate(&[file(0, &long_name, 1)], &[], 1).is_err());
+        assert!(limits.validate(&[], &[String::new()], 0).is_err());
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        let limits = OfferLimits::default();
+        for bad in [
+            "../../.config/autostart",
+            "/etc/x",
+            "docs/../../x",
+            "..\\..\\x",
+            "\\\\server\\share",
+        ] {
+            let mut f = file(0, "x", 1);
+            f.relative_path = bad.into();
+            assert!(limits.validate(&[f], &[], 1).is_err(), "{bad}");
+            assert!(
+                limits.validate(&[], &[bad.to_string()], 0).is_err(),
+                "{bad}"
+            );
+        }
+        // 文件名中的点不受影响
+        let mut f = file(0, "x", 1);
+        f.relative_path = "docs/..hidden/./a..b".into();
+        assert!(limits.validate(&[f], &["docs/.git".to_string()], 1).is_ok());
+    }
+
+    #[test]
+    fn test_empty_offer() {
+        assert!(OfferLimits::default().validate(&[], &[], 0).is_err());
+        // 仅包含空目录的 Offer 合法
+        assert!(OfferLimits::default()
+            .validate(&[], &["empty".to_string()], 0)
+            .is_ok());
+    }
+}
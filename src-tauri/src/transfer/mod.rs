@@ -2,8 +2,18 @@
 //!
 //! 实现端到端加密的文件传输功能，包括文件分块、加密/解密、进度追踪等。
 
+pub mod bitmap;
+pub mod chunk_budget;
+pub mod context;
 pub mod crypto;
+pub mod estimate;
+pub mod keep_alive;
+pub mod limits;
 pub mod offer;
+pub mod preview;
 pub mod progress;
+pub mod read_ahead;
 pub mod receiver;
 pub mod sender;
+pub mod sessions;
+pub mod signing;
@@ -3,11 +3,15 @@
 //! 管理 Offer 协议（发送、接受、拒绝）和活跃传输会话（发送/接收）。
 //! 事件循环写入缓存 → 前端操作后通过 Tauri 命令消费缓存。
 
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
+use base64::prelude::*;
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use serde::Serialize;
+use swarm_p2p_core::libp2p::identity::Keypair;
 use swarm_p2p_core::libp2p::PeerId;
 use tauri::AppHandle;
 use tokio_util::sync::CancellationToken;
@@ -19,16 +23,32 @@ use tauri::Manager;
 
 use tauri::Emitter;
 
+use crate::device::{ConnectionType, DeviceManager};
+use crate::file_sink::template::{SavePathTemplate, SavePathVars};
 use crate::file_sink::FileSink;
-use crate::file_source::{EnumeratedFile, FileSource};
+use crate::file_source::{EnumeratedFile, FileSource, FileSourceMetadata};
+use crate::network::traffic::TrafficStats;
 use crate::protocol::{
     AppNetClient, AppRequest, AppResponse, FileChecksum, FileInfo, OfferRejectReason,
     ResumeRejectReason, TransferRequest, TransferResponse,
 };
-use crate::transfer::crypto::generate_key;
-use crate::transfer::progress::{TransferDbErrorEvent, TransferDirection, TransferFailedEvent};
+use crate::transfer::chunk_budget::ChunkBudget;
+use crate::transfer::context::{EventSink, SessionContext, SessionCounters};
+use crate::transfer::crypto::{
+    generate_key, negotiate_encryption, use_plaintext, KeyExchange, SessionKey,
+};
+use crate::transfer::estimate::TransferEstimate;
+use crate::transfer::keep_alive::{KeepAlive, KeepAliveState};
+use crate::transfer::limits::OfferLimits;
+use crate::transfer::preview::generate_preview;
+use crate::transfer::progress::{
+    OverallProgressEvent, ProgressSnapshot, SaveLocationFallbackEvent, TransferDbErrorEvent,
+    TransferDirection, TransferFailedEvent,
+};
 use crate::transfer::receiver::ReceiveSession;
 use crate::transfer::sender::SendSession;
+use crate::transfer::sessions::SessionRegistry;
+use crate::transfer::signing::{offer_payload, sign_offer};
 use crate::{events, AppError, AppResult};
 
 /// prepare_send 进度事件（通过 Tauri Channel 实时推送给前端）
@@ -54,10 +74,85 @@ pub struct PreparedTransfer {
     pub prepared_id: Uuid,
     /// 文件列表（含 BLAKE3 校验和）
     pub files: Vec<PreparedFile>,
+    /// 需要在接收方重建的空目录（相对路径）
+    pub directories: Vec<String>,
     /// 总大小（字节）
     pub total_size: u64,
-    /// 创建时间（用于超时清理）
-    pub created_at: Instant,
+    /// 最后使用时间（准备、刷新、发送时更新，用于闲置超时清理）
+    pub last_used_at: Instant,
+}
+
+impl PendingOffer {
+    /// 剩余有效时间（秒），超时后为 0
+    pub fn expires_in_secs(&self) -> u64 {
+        PENDING_OFFER_TIMEOUT_SECS.saturating_sub(self.created_at.elapsed().as_secs())
+    }
+
+    /// 转换为推送给前端的 Offer 事件
+    pub fn to_event(&self) -> TransferOfferEvent {
+        TransferOfferEvent {
+            session_id: self.session_id,
+            peer_id: self.peer_id.to_string(),
+            device_name: self.peer_name.clone(),
+            files: self
+                .files
+                .iter()
+                .map(|f| TransferOfferFile {
+                    file_id: f.file_id,
+                    name: f.name.clone(),
+                    relative_path: f.relative_path.clone(),
+                    size: f.size,
+                    is_directory: false,
+                    preview: f.preview.as_ref().map(|p| BASE64_STANDARD.encode(p)),
+                })
+                .collect(),
+            directories: self.directories.clone(),
+            total_size: self.total_size,
+            expires_in_secs: self.expires_in_secs(),
+            encryption: self.encryption,
+            default_save_location: None,
+        }
+    }
+}
+
+/// 传输 Offer 事件 payload（`transfer-offer` 事件与 `get_pending_offers` 共用）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOfferEvent {
+    pub session_id: Uuid,
+    pub peer_id: String,
+    pub device_name: String,
+    pub files: Vec<TransferOfferFile>,
+    /// 需要重建的空目录（相对路径）
+    pub directories: Vec<String>,
+    pub total_size: u64,
+    /// 剩余有效时间（秒），超时后 Offer 被自动清理
+    pub expires_in_secs: u64,
+    /// 发送方是否要求加密（false 表示请求局域网明文）
+    pub encryption: bool,
+    /// 未指定保存位置时接收到的位置（设置中的默认保存目录），供确认对话框预填
+    pub default_save_location: Option<entity::SaveLocation>,
+}
+
+impl TransferOfferEvent {
+    /// 附带生效的默认保存位置
+    pub fn with_default_save_location(mut self, location: Option<entity::SaveLocation>) -> Self {
+        self.default_save_location = location;
+        self
+    }
+}
+
+/// Offer 中的文件信息（前端展示用）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOfferFile {
+    pub file_id: u32,
+    pub name: String,
+    pub relative_path: String,
+    pub size: u64,
+    pub is_directory: bool,
+    /// 缩略图预览（base64 编码的 JPEG）
+    pub preview: Option<String>,
 }
 
 /// 准备好的单个文件
@@ -75,6 +170,27 @@ pub struct PreparedFile {
     pub size: u64,
     /// BLAKE3 校验和（hex）
     pub checksum: String,
+    /// 缩略图预览（JPEG）
+    pub preview: Option<Vec<u8>>,
+    /// MIME 类型（按扩展名推断）
+    pub mime: Option<String>,
+    /// 准备时的最后修改时间（refresh 时判断文件是否变化，未知时为 None）
+    pub modified: Option<SystemTime>,
+}
+
+impl PreparedFile {
+    /// Offer 中携带的文件元信息
+    pub fn to_file_info(&self) -> FileInfo {
+        FileInfo {
+            file_id: self.file_id,
+            name: self.name.clone(),
+            relative_path: self.relative_path.clone(),
+            size: self.size,
+            checksum: self.checksum.clone(),
+            preview: self.preview.clone(),
+            mime: self.mime.clone(),
+        }
+    }
 }
 
 /// 接收方缓存的入站 Offer
@@ -90,8 +206,14 @@ pub struct PendingOffer {
     pub session_id: Uuid,
     /// 文件列表
     pub files: Vec<FileInfo>,
+    /// 需要重建的空目录
+    pub directories: Vec<String>,
     /// 总大小
     pub total_size: u64,
+    /// 发送方是否要求加密（false 表示请求局域网明文）
+    pub encryption: bool,
+    /// 发送方的 X25519 临时公钥（旧版本发送方为 None）
+    pub public_key: Option<[u8; 32]>,
     /// 创建时间（用于超时清理）
     pub created_at: Instant,
 }
@@ -103,6 +225,17 @@ pub struct StartSendResult {
     pub session_id: Uuid,
 }
 
+/// `send_offer_multi` 中单个目标 peer 的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerSendResult {
+    pub peer_id: String,
+    /// 已发出 Offer 时的会话 ID（接受 / 拒绝仍通过该会话的事件通知）
+    pub session_id: Option<Uuid>,
+    /// 发出 Offer 失败的原因（不影响其他 peer）
+    pub error: Option<String>,
+}
+
 /// 对方接受 Offer 的事件 payload
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -118,6 +251,18 @@ pub struct TransferRejectedEvent {
     pub reason: Option<OfferRejectReason>,
 }
 
+/// 活跃传输会话快照（诊断包使用）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveSessionInfo {
+    pub session_id: Uuid,
+    pub direction: TransferDirection,
+    pub peer_id: PeerId,
+    pub total_bytes: u64,
+    pub transferred_bytes: u64,
+    pub speed: f64,
+}
+
 /// `initiate_resume` 的返回类型（供前端创建运行时 session）
 #[derive(Debug, Clone)]
 pub struct ResumeInfo {
@@ -137,37 +282,93 @@ pub struct ResumeFileInfo {
     pub size: i64,
 }
 
+/// 已登记的可取消任务，drop 时从 [`TransferManager`] 中注销
+pub struct CancelGuard<'a> {
+    tokens: &'a DashMap<Uuid, CancellationToken>,
+    id: Uuid,
+    token: CancellationToken,
+}
+
+impl CancelGuard<'_> {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        self.tokens.remove(&self.id);
+    }
+}
+
 /// 超时配置常量
-const PREPARED_TIMEOUT_SECS: u64 = 300; // 5 分钟
+const PREPARED_TIMEOUT_SECS: u64 = 30 * 60; // 闲置 30 分钟
 const PENDING_OFFER_TIMEOUT_SECS: u64 = 300; // 5 分钟
 const SEND_SESSION_IDLE_TIMEOUT_MS: u64 = 30 * 60 * 1000; // 30 分钟
 const CLEANUP_INTERVAL_SECS: u64 = 60; // 每 60 秒扫描一次
+const OVERALL_PROGRESS_INTERVAL_MS: u64 = 1000; // 总体进度推送间隔
 
 /// 传输管理器（原 OfferManager，扩展为管理完整传输生命周期）
 pub struct TransferManager {
     /// libp2p 网络客户端
     client: AppNetClient,
+    /// 本机 PeerId（派生会话密钥时绑定双方身份）
+    local_peer_id: PeerId,
+    /// 设备管理器（查询对端已知地址与连接类型）
+    devices: Arc<DeviceManager>,
     /// 发送方：prepare_send 的缓存（key = prepared_id）
     prepared: DashMap<Uuid, PreparedTransfer>,
+    /// 发送方：进行中的 prepare_send / scan_sources 取消令牌（key = prepared_id / scan_id）
+    preparing: DashMap<Uuid, CancellationToken>,
     /// 接收方：入站 Offer 的缓存（key = session_id）
     pending: DashMap<Uuid, PendingOffer>,
-    /// 活跃的发送会话（key = session_id）
-    send_sessions: DashMap<Uuid, Arc<SendSession>>,
-    /// 活跃的接收会话（key = session_id, Arc 包装以便回调中清理）
-    receive_sessions: Arc<DashMap<Uuid, Arc<ReceiveSession>>>,
+    /// 活跃的发送 / 接收会话
+    sessions: SessionRegistry,
+    /// 入站 Offer 限制
+    offer_limits: OfferLimits,
+    /// 所有发送会话共享的在途分块内存预算
+    chunk_budget: ChunkBudget,
+    /// 会话完成 / 失败计数（总体进度事件使用）
+    counters: Arc<SessionCounters>,
 }
 
 impl TransferManager {
-    pub fn new(client: AppNetClient) -> Self {
+    pub fn new(client: AppNetClient, local_peer_id: PeerId, devices: Arc<DeviceManager>) -> Self {
         Self {
             client,
+            local_peer_id,
+            devices,
             prepared: DashMap::new(),
+            preparing: DashMap::new(),
             pending: DashMap::new(),
-            send_sessions: DashMap::new(),
-            receive_sessions: Arc::new(DashMap::new()),
+            sessions: SessionRegistry::default(),
+            offer_limits: OfferLimits::default(),
+            chunk_budget: ChunkBudget::default(),
+            counters: Arc::new(SessionCounters::default()),
         }
     }
 
+    /// 替换入站 Offer 限制（来自设置）
+    pub fn with_offer_limits(mut self, limits: OfferLimits) -> Self {
+        self.offer_limits = limits;
+        self
+    }
+
+    /// 替换在途分块内存预算（来自设置，默认按可用内存确定）
+    pub fn with_chunk_budget(mut self, budget: ChunkBudget) -> Self {
+        self.chunk_budget = budget;
+        self
+    }
+
+    /// 会话完成时把传输字节数计入共享的流量统计
+    pub fn with_traffic_stats(mut self, traffic: Arc<TrafficStats>) -> Self {
+        self.counters = Arc::new(SessionCounters {
+            traffic,
+            ..Default::default()
+        });
+        self
+    }
+
     /// 启动后台定时清理任务（在 Arc<Self> 上调用，由 NetManager 创建后触发）
     pub fn spawn_cleanup_task(self: &Arc<Self>, cancel_token: CancellationToken) {
         let this = Arc::clone(self);
@@ -188,12 +389,127 @@ impl TransferManager {
         });
     }
 
+    /// 启动总体进度推送任务：单个定时器汇总所有活跃会话，不增加分块路径上的开销
+    ///
+    /// 提供 `keep_alive` 时（Android）同时按活跃会话数启停前台服务并刷新通知进度。
+    pub fn spawn_overall_progress_task(
+        self: &Arc<Self>,
+        events: Arc<dyn EventSink>,
+        keep_alive: Option<Arc<dyn KeepAlive>>,
+        cancel_token: CancellationToken,
+    ) {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(
+                OVERALL_PROGRESS_INTERVAL_MS,
+            ));
+            // 上一轮是否有活跃会话：全部结束后再推送一次，便于前端清空总进度
+            let mut was_active = false;
+            let mut keep_alive_state = KeepAliveState::default();
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        if let Some(keep_alive) = &keep_alive {
+                            keep_alive.stop();
+                        }
+                        info!("总体进度推送任务已停止");
+                        break;
+                    }
+                    _ = this.sessions.changed().notified() => {
+                        if let Some(keep_alive) = &keep_alive {
+                            let event = this.overall_progress().await;
+                            keep_alive_state.on_progress(keep_alive.as_ref(), &event);
+                        }
+                    }
+                    _ = interval.tick() => {
+                        let event = this.overall_progress().await;
+                        let active = event.active_sessions > 0;
+                        if active || was_active {
+                            events.emit_overall_progress(&event);
+                        }
+                        was_active = active;
+                        if let Some(keep_alive) = &keep_alive {
+                            keep_alive_state.on_progress(keep_alive.as_ref(), &event);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// 所有活跃会话的进度快照
+    pub async fn active_sessions(&self) -> Vec<ActiveSessionInfo> {
+        let info = |session_id, direction, peer_id, snapshot: ProgressSnapshot| ActiveSessionInfo {
+            session_id,
+            direction,
+            peer_id,
+            total_bytes: snapshot.total_bytes,
+            transferred_bytes: snapshot.transferred_bytes,
+            speed: snapshot.speed,
+        };
+
+        let mut sessions: Vec<_> = self
+            .sessions
+            .send_sessions()
+            .iter()
+            .map(|s| {
+                info(
+                    s.session_id,
+                    TransferDirection::Send,
+                    s.peer_id,
+                    s.progress_snapshot(),
+                )
+            })
+            .collect();
+        for s in self.sessions.receive_sessions() {
+            let snapshot = s.progress_snapshot().await;
+            sessions.push(info(
+                s.session_id,
+                TransferDirection::Receive,
+                s.peer_id,
+                snapshot,
+            ));
+        }
+        sessions
+    }
+
+    /// 当前的（发送会话数, 接收会话数）
+    pub fn sessions_count(&self) -> (usize, usize) {
+        self.sessions.count()
+    }
+
+    /// 汇总所有活跃会话的总体进度
+    async fn overall_progress(&self) -> OverallProgressEvent {
+        let mut snapshots: Vec<_> = self
+            .sessions
+            .send_sessions()
+            .iter()
+            .map(|s| s.progress_snapshot())
+            .collect();
+        // 接收会话的 tracker 是异步锁，先收集再 await，避免跨 await 持有 DashMap 引用
+        for session in self.sessions.receive_sessions() {
+            snapshots.push(session.progress_snapshot().await);
+        }
+        OverallProgressEvent::aggregate(
+            &snapshots,
+            self.counters.completed.load(Ordering::Relaxed),
+            self.counters.failed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// 构造会话上下文（附带完成 / 失败计数）
+    pub fn session_context(&self, app: &AppHandle) -> SessionContext {
+        SessionContext::from_app(self.client.clone(), app)
+            .with_counters(self.counters.clone())
+            .with_chunk_budget(self.chunk_budget.clone())
+    }
+
     /// 执行一次清理扫描
     fn run_cleanup(&self) {
         let now = Instant::now();
 
         remove_expired(&self.prepared, |v| {
-            now.duration_since(v.created_at).as_secs() > PREPARED_TIMEOUT_SECS
+            now.duration_since(v.last_used_at).as_secs() > PREPARED_TIMEOUT_SECS
         }, "prepared transfers");
 
         remove_expired(&self.pending, |v| {
@@ -201,18 +517,7 @@ impl TransferManager {
         }, "pending offers");
 
         // 清理空闲超时的 send sessions（需要额外 cancel 操作）
-        let idle_ids: Vec<Uuid> = self
-            .send_sessions
-            .iter()
-            .filter(|r| r.value().idle_ms() > SEND_SESSION_IDLE_TIMEOUT_MS)
-            .map(|r| *r.key())
-            .collect();
-        for id in &idle_ids {
-            if let Some((_, session)) = self.send_sessions.remove(id) {
-                session.cancel();
-                warn!("清理空闲超时的 send session: {}", id);
-            }
-        }
+        self.sessions.remove_idle_sends(SEND_SESSION_IDLE_TIMEOUT_MS);
     }
 
     // ============ 准备阶段 ============
@@ -223,8 +528,12 @@ impl TransferManager {
     /// 前端可能已移除部分文件（用户在 UI 中取消选择）。
     /// 此方法不做目录遍历，只对每个文件计算 hash。
     /// 通过 `on_progress` Channel 实时上报字节级进度。
+    ///
+    /// `prepared_id` 由前端预先生成，准备期间可通过 [`Self::cancel_prepare`] 取消，
+    /// 取消后返回 [`AppError::Cancelled`]，不写入缓存。
     pub async fn prepare(
         &self,
+        prepared_id: Uuid,
         entries: Vec<EnumeratedFile>,
         app: &AppHandle,
         on_progress: tauri::ipc::Channel<PrepareProgress>,
@@ -232,32 +541,198 @@ impl TransferManager {
         if entries.is_empty() {
             return Err(AppError::Transfer("文件列表为空".into()));
         }
+        if self.prepared.contains_key(&prepared_id) {
+            return Err(AppError::Transfer(format!(
+                "prepared_id 已存在: {prepared_id}"
+            )));
+        }
+
+        let guard = self.register_cancellable(prepared_id)?;
+        let prepared = self
+            .prepare_files(prepared_id, entries, app, on_progress, guard.token())
+            .await?;
+        drop(guard);
+
+        self.prepared.insert(prepared.prepared_id, prepared.clone());
+        Ok(prepared)
+    }
+
+    /// 登记可取消的准备阶段任务（prepare_send / scan_sources），返回的守卫 drop 时自动注销
+    pub fn register_cancellable(&self, id: Uuid) -> AppResult<CancelGuard<'_>> {
+        let token = CancellationToken::new();
+        match self.preparing.entry(id) {
+            Entry::Occupied(_) => {
+                return Err(AppError::Transfer(format!("任务 {id} 正在进行中")));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(token.clone());
+            }
+        }
+        Ok(CancelGuard {
+            tokens: &self.preparing,
+            id,
+            token,
+        })
+    }
+
+    /// 重新准备：只对上次准备之后大小或修改时间变化的文件重新计算 hash 与预览
+    ///
+    /// 已不存在的文件从列表中移除，其余文件保持原 fileId；修改时间未知的文件一律重新计算。
+    /// 与 [`Self::prepare`] 一样可通过 [`Self::cancel_prepare`] 取消，取消后缓存保持不变。
+    pub async fn refresh_prepared(
+        &self,
+        prepared_id: Uuid,
+        app: &AppHandle,
+        on_progress: tauri::ipc::Channel<PrepareProgress>,
+    ) -> AppResult<PreparedTransfer> {
+        let mut prepared = self
+            .prepared
+            .get(&prepared_id)
+            .map(|r| r.value().clone())
+            .ok_or_else(|| {
+                AppError::Transfer(format!("PreparedTransfer not found: {prepared_id}"))
+            })?;
+
+        let guard = self.register_cancellable(prepared_id)?;
+        let cancel = guard.token();
+
+        // 逐个比对元数据，记录需要重新 hash 的文件下标
+        let mut files = Vec::with_capacity(prepared.files.len());
+        let mut stale = Vec::new();
+        for mut file in prepared.files {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            let meta = match file.source.metadata(app).await {
+                Ok(meta) if !meta.is_dir => meta,
+                _ => {
+                    info!("文件已不存在，从待发送列表移除: {}", file.relative_path);
+                    continue;
+                }
+            };
+            if file_changed(&file, &meta) {
+                file.size = meta.size;
+                file.modified = meta.modified;
+                stale.push(files.len());
+            }
+            files.push(file);
+        }
+
+        let entries: Vec<EnumeratedFile> = stale
+            .iter()
+            .map(|&i| EnumeratedFile {
+                name: files[i].name.clone(),
+                relative_path: files[i].relative_path.clone(),
+                source: files[i].source.clone(),
+                size: files[i].size,
+                is_directory: false,
+            })
+            .collect();
+        let progress = on_progress.clone();
+        let checksums = hash_files(&entries, Some(app), hash_concurrency(), cancel, move |p| {
+            let _ = progress.send(p);
+        })
+        .await?;
+
+        for (&i, checksum) in stale.iter().zip(checksums) {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            let file = &mut files[i];
+            file.checksum = checksum;
+            file.preview = generate_preview(&file.source, &file.name, file.size, app).await;
+        }
+        drop(guard);
+
+        let total_files = entries.len() as u32;
+        let total_bytes: u64 = entries.iter().map(|e| e.size).sum();
+        let _ = on_progress.send(PrepareProgress {
+            current_file: String::new(),
+            completed_files: total_files,
+            total_files,
+            bytes_hashed: total_bytes,
+            total_bytes,
+        });
+        info!(
+            "已刷新准备任务 {}: {} 个文件，重新计算 {} 个",
+            prepared_id,
+            files.len(),
+            total_files
+        );
+
+        prepared.total_size = files.iter().map(|f| f.size).sum();
+        prepared.files = files;
+        prepared.last_used_at = Instant::now();
+
+        // 刷新期间可能已被丢弃，此时不再写回
+        let mut entry = self.prepared.get_mut(&prepared_id).ok_or_else(|| {
+            AppError::Transfer(format!("PreparedTransfer not found: {prepared_id}"))
+        })?;
+        *entry = prepared.clone();
+        Ok(prepared)
+    }
+
+    /// 丢弃已准备的传输，释放文件列表与预览占用的内存，返回是否存在
+    pub fn discard_prepared(&self, prepared_id: &Uuid) -> bool {
+        let removed = self.prepared.remove(prepared_id).is_some();
+        if removed {
+            info!("已丢弃准备任务: {}", prepared_id);
+        }
+        removed
+    }
+
+    /// 取消进行中的 prepare_send / scan_sources，返回是否找到对应任务
+    pub fn cancel_prepare(&self, prepared_id: &Uuid) -> bool {
+        match self.preparing.get(prepared_id) {
+            Some(token) => {
+                token.cancel();
+                info!("已取消准备任务: {}", prepared_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 计算 hash、生成预览，组装 [`PreparedTransfer`]（不写入缓存）
+    async fn prepare_files(
+        &self,
+        prepared_id: Uuid,
+        entries: Vec<EnumeratedFile>,
+        app: &AppHandle,
+        on_progress: tauri::ipc::Channel<PrepareProgress>,
+        cancel: &CancellationToken,
+    ) -> AppResult<PreparedTransfer> {
+        // 空目录无需 hash，单独记录
+        let (dir_entries, entries): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|e| e.is_directory);
+        let directories: Vec<String> = dir_entries.into_iter().map(|e| e.relative_path).collect();
 
         let total_files = entries.len() as u32;
         let total_bytes: u64 = entries.iter().map(|e| e.size).sum();
         let mut files = Vec::new();
-        let mut completed_bytes: u64 = 0;
-
-        for (file_id, entry) in entries.into_iter().enumerate() {
-            let file_name: std::sync::Arc<str> = entry.name.clone().into();
-            let base_bytes = completed_bytes;
-            let completed_files = file_id as u32;
-            let progress = on_progress.clone();
-
-            let checksum = entry
-                .source
-                .compute_hash_with_progress(app, move |bytes_in_file| {
-                    let _ = progress.send(PrepareProgress {
-                        current_file: file_name.to_string(),
-                        completed_files,
-                        total_files,
-                        bytes_hashed: base_bytes + bytes_in_file,
-                        total_bytes,
-                    });
-                })
-                .await?;
 
-            completed_bytes += entry.size;
+        // 修改时间在 hash 之前记录：hash 期间发生的修改会在下次 refresh 时被发现
+        let mut modified = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            let meta = entry.source.metadata(app).await.ok();
+            modified.push(meta.and_then(|m| m.modified));
+        }
+
+        let progress = on_progress.clone();
+        let checksums = hash_files(&entries, Some(app), hash_concurrency(), cancel, move |p| {
+            let _ = progress.send(p);
+        })
+        .await?;
+
+        let files_iter = entries.into_iter().zip(checksums).zip(modified);
+        for (file_id, ((entry, checksum), modified)) in files_iter.enumerate() {
+            if cancel.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            let preview = generate_preview(&entry.source, &entry.name, entry.size, app).await;
             files.push(PreparedFile {
                 file_id: file_id as u32,
                 name: entry.name,
@@ -265,6 +740,9 @@ impl TransferManager {
                 source: entry.source,
                 size: entry.size,
                 checksum,
+                preview,
+                mime: guess_mime(&entry.name),
+                modified,
             });
         }
 
@@ -277,16 +755,13 @@ impl TransferManager {
             total_bytes,
         });
 
-        let prepared = PreparedTransfer {
-            prepared_id: generate_id(),
+        Ok(PreparedTransfer {
+            prepared_id,
             files,
+            directories,
             total_size: total_bytes,
-            created_at: Instant::now(),
-        };
-
-        self.prepared.insert(prepared.prepared_id, prepared.clone());
-
-        Ok(prepared)
+            last_used_at: Instant::now(),
+        })
     }
 
     // ============ 发送方：发送 Offer + 启动传输 ============
@@ -297,18 +772,33 @@ impl TransferManager {
     /// - 接受 → 创建 SendSession + emit `transfer-accepted`
     /// - 拒绝 → emit `transfer-rejected`
     /// - 错误 → emit `transfer-failed`
+    ///
+    /// `lan_plaintext` 为 true 且当前为局域网直连时在 Offer 中请求明文传输，
+    /// 接收方同样开启该设置才会生效（见 [`negotiate_encryption`]）。
+    ///
+    /// `prefer_direct_timeout` 不为空且为大文件传输时，仅有中继连接的情况下
+    /// 先等待打洞直连一段时间再发送 Offer（见 [`Self::prefer_direct_connection`]）。
+    ///
+    /// 发送后 PreparedTransfer 仍保留，可再次发送或 [`Self::refresh_prepared`]，
+    /// 直到 [`Self::discard_prepared`] 或闲置超时被清理。
+    #[expect(clippy::too_many_arguments, reason = "发送选项由前端逐项传入")]
     pub fn send_offer(
         self: &Arc<Self>,
         prepared_id: &Uuid,
         peer_id: &str,
         peer_name: &str,
         selected_file_ids: &[u32],
+        lan_plaintext: bool,
+        prefer_direct_timeout: Option<Duration>,
         app: AppHandle,
     ) -> AppResult<StartSendResult> {
         let prepared = self
             .prepared
-            .get(prepared_id)
-            .map(|r| r.value().clone())
+            .get_mut(prepared_id)
+            .map(|mut r| {
+                r.last_used_at = Instant::now();
+                r.value().clone()
+            })
             .ok_or_else(|| {
                 AppError::Transfer(format!("PreparedTransfer not found: {prepared_id}"))
             })?;
@@ -320,19 +810,14 @@ impl TransferManager {
             .filter(|f| selected_file_ids.contains(&f.file_id))
             .collect();
 
-        if selected_prepared.is_empty() {
+        let directories = prepared.directories;
+        if selected_prepared.is_empty() && directories.is_empty() {
             return Err(AppError::Transfer("未选择任何文件".into()));
         }
 
         let selected_files: Vec<FileInfo> = selected_prepared
             .iter()
-            .map(|f| FileInfo {
-                file_id: f.file_id,
-                name: f.name.clone(),
-                relative_path: f.relative_path.clone(),
-                size: f.size,
-                checksum: f.checksum.clone(),
-            })
+            .map(PreparedFile::to_file_info)
             .collect();
 
         let total_size: u64 = selected_files.iter().map(|f| f.size).sum();
@@ -345,6 +830,11 @@ impl TransferManager {
         let target_peer: PeerId = peer_id
             .parse()
             .map_err(|_| AppError::Transfer(format!("无效的 PeerId: {peer_id}")))?;
+        if target_peer == self.local_peer_id {
+            return Err(AppError::Transfer(format!(
+                "不能向本机发送文件（本机 PeerId: {target_peer}）"
+            )));
+        }
 
         info!(
             "Sending transfer offer to {}: session={}, files={}",
@@ -353,24 +843,48 @@ impl TransferManager {
             selected_files.len()
         );
 
-        // 后台任务：发送 Offer 请求并等待响应
+        // 后台任务：尽量切换到局域网直连后发送 Offer 请求并等待响应
         let client = self.client.clone();
         let this = Arc::clone(self);
-        let prepared_id = *prepared_id;
         let peer_id_str = peer_id.to_string();
         let peer_name = peer_name.to_string();
         tokio::spawn(async move {
             let emit_fail = |error: String| {
                 let _ = app.emit(
                     events::TRANSFER_FAILED,
-                    TransferFailedEvent {
-                        session_id,
-                        direction: TransferDirection::Send,
-                        error,
-                    },
+                    TransferFailedEvent::new(session_id, TransferDirection::Send, error),
                 );
             };
 
+            let mut connection = this.ensure_best_connection(target_peer).await;
+            if let Some(timeout) = prefer_direct_timeout {
+                connection = this
+                    .prefer_direct_connection(target_peer, total_size, timeout, connection)
+                    .await;
+            }
+            // 对端声明了能力时只请求其支持的特性；未声明（旧版本）时沿用原有协商方式
+            let caps = this.devices.capabilities(&target_peer);
+            let lan_plaintext = lan_plaintext && caps.is_none_or(|c| c.lan_plaintext);
+            let sign = caps.is_none_or(|c| c.offer_signature);
+            let key_exchange_required = caps.is_some_and(|c| c.key_exchange);
+
+            let encryption = !use_plaintext(lan_plaintext, connection.as_ref());
+            let key_exchange = KeyExchange::new();
+            let public_key = key_exchange.public_key();
+            let keypair = app.try_state::<Keypair>().filter(|_| sign);
+            let signature = keypair.and_then(|keypair| {
+                let payload = offer_payload(
+                    &session_id,
+                    &selected_files,
+                    &directories,
+                    total_size,
+                    Some(&public_key),
+                );
+                sign_offer(&keypair, &payload)
+                    .inspect_err(|e| warn!("Offer 签名失败，不签名发送: {}", e))
+                    .ok()
+            });
+
             let result = client
                 .send_request(
                     target_peer,
@@ -378,6 +892,10 @@ impl TransferManager {
                         session_id,
                         files: selected_files.clone(),
                         total_size,
+                        directories,
+                        encryption,
+                        public_key: Some(public_key),
+                        signature,
                     }),
                 )
                 .await;
@@ -385,10 +903,36 @@ impl TransferManager {
             match result {
                 Ok(AppResponse::Transfer(TransferResponse::OfferResult {
                     accepted: true,
-                    key: Some(key),
+                    key,
+                    encryption: negotiated,
+                    public_key,
                     ..
                 })) => {
-                    info!("Offer accepted for session {}, key received", session_id);
+                    let key = match initiator_session_key(
+                        key_exchange,
+                        public_key,
+                        key,
+                        key_exchange_required,
+                        &session_id,
+                        &this.local_peer_id,
+                        &target_peer,
+                    ) {
+                        Ok(key) => key,
+                        Err(e) => {
+                            warn!(
+                                "Offer accepted 但无法建立会话密钥: session={}, {}",
+                                session_id, e
+                            );
+                            emit_fail(e.to_string());
+                            return;
+                        }
+                    };
+                    info!("Offer accepted for session {}, key established", session_id);
+                    // 只有本方请求了明文且对方同意时才启用
+                    let plaintext_lan = !encryption && !negotiated;
+                    if plaintext_lan {
+                        info!("已协商局域网明文传输: session={}", session_id);
+                    }
 
                     if let Some(db) = app.try_state::<DatabaseConnection>() {
                         if let Err(e) = crate::database::ops::create_session(
@@ -415,15 +959,18 @@ impl TransferManager {
                         }
                     }
 
-                    let send_session = Arc::new(SendSession::new(
-                        session_id,
-                        target_peer,
-                        selected_prepared,
-                        &key,
-                        app.clone(),
-                    ));
-                    this.send_sessions.insert(session_id, send_session);
-                    this.prepared.remove(&prepared_id);
+                    let send_session = Arc::new(
+                        SendSession::new(
+                            session_id,
+                            target_peer,
+                            selected_prepared,
+                            &key,
+                            this.session_context(&app),
+                        )
+                        .with_connection(connection)
+                        .with_plaintext_lan(plaintext_lan),
+                    );
+                    this.sessions.insert_send(session_id, send_session);
 
                     let _ = app.emit(
                         events::TRANSFER_ACCEPTED,
@@ -441,14 +988,6 @@ impl TransferManager {
                         TransferRejectedEvent { session_id, reason },
                     );
                 }
-                Ok(AppResponse::Transfer(TransferResponse::OfferResult {
-                    accepted: true,
-                    key: None,
-                    ..
-                })) => {
-                    warn!("Offer accepted 但未收到密钥: session={}", session_id);
-                    emit_fail("对方接受但未提供加密密钥".into());
-                }
                 Ok(other) => {
                     warn!("意外的响应类型: {:?}", other);
                     emit_fail(format!("意外的响应类型: {other:?}"));
@@ -463,28 +1002,141 @@ impl TransferManager {
         Ok(StartSendResult { session_id })
     }
 
+    /// 将同一批文件同时发送给多个 peer
+    ///
+    /// 每个 peer 独立发送 Offer、独立会话：各自协商密钥，某个 peer 拒绝或失败不影响其他 peer。
+    /// 文件的扫描和校验和只在 prepare 阶段计算一次，所有会话共享同一份 `FileSource`。
+    /// peer 名称取自已配对设备的主机名。
+    pub fn send_offer_multi(
+        self: &Arc<Self>,
+        prepared_id: &Uuid,
+        peer_ids: &[String],
+        selected_file_ids: &[u32],
+        lan_plaintext: bool,
+        prefer_direct_timeout: Option<Duration>,
+        app: AppHandle,
+    ) -> AppResult<Vec<PeerSendResult>> {
+        if !self.prepared.contains_key(prepared_id) {
+            return Err(AppError::Transfer(format!(
+                "PreparedTransfer not found: {prepared_id}"
+            )));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        let results = peer_ids
+            .iter()
+            .filter(|peer_id| seen.insert(peer_id.as_str()))
+            .map(|peer_id| {
+                let peer_name = peer_id
+                    .parse::<PeerId>()
+                    .ok()
+                    .and_then(|p| self.devices.paired_name(&p))
+                    .unwrap_or_else(|| peer_id.clone());
+                let result = self.send_offer(
+                    prepared_id,
+                    peer_id,
+                    &peer_name,
+                    selected_file_ids,
+                    lan_plaintext,
+                    prefer_direct_timeout,
+                    app.clone(),
+                );
+                match result {
+                    Ok(StartSendResult { session_id }) => PeerSendResult {
+                        peer_id: peer_id.clone(),
+                        session_id: Some(session_id),
+                        error: None,
+                    },
+                    Err(e) => {
+                        warn!("向 {} 发送 Offer 失败: {}", peer_id, e);
+                        PeerSendResult {
+                            peer_id: peer_id.clone(),
+                            session_id: None,
+                            error: Some(e.to_string()),
+                        }
+                    }
+                }
+            })
+            .collect();
+        Ok(results)
+    }
+
+    /// 估算把已准备的文件发送给指定 peer 的耗时（不传输任何数据）
+    pub fn estimate(&self, prepared_id: &Uuid, peer_id: &str) -> AppResult<TransferEstimate> {
+        let prepared = self.prepared.get(prepared_id).ok_or_else(|| {
+            AppError::Transfer(format!("PreparedTransfer not found: {prepared_id}"))
+        })?;
+        let peer_id: PeerId = peer_id
+            .parse()
+            .map_err(|_| AppError::Transfer(format!("无效的 PeerId: {peer_id}")))?;
+        Ok(TransferEstimate::new(
+            prepared.total_size,
+            prepared.files.len(),
+            self.devices.connection_type(&peer_id),
+            self.devices.rtt_ms(&peer_id),
+        ))
+    }
+
     // ============ 发送方：响应 ChunkRequest ============
 
+    /// 对端连接类型变化时同步给该 peer 的所有活跃会话（事件循环调用）
+    pub async fn update_peer_connection(&self, peer_id: &PeerId, connection: ConnectionType) {
+        for session in self.sessions.send_sessions() {
+            if session.peer_id == *peer_id {
+                session.update_connection(Some(connection.clone()));
+            }
+        }
+        // 接收会话更新需要 await，先收集再处理，避免跨 await 持有 DashMap 引用
+        let receivers = self.sessions.receive_sessions();
+        for session in receivers.into_iter().filter(|s| s.peer_id == *peer_id) {
+            session.update_connection(Some(connection.clone())).await;
+        }
+    }
+
+    /// 是否有与指定 peer 进行中的发送或接收会话
+    pub fn has_session_with(&self, peer_id: &PeerId) -> bool {
+        self.sessions.has_session_with(peer_id)
+    }
+
     /// 获取发送会话（事件循环调用）
     pub fn get_send_session(&self, session_id: &Uuid) -> Option<Arc<SendSession>> {
-        self.send_sessions
-            .get(session_id)
-            .map(|r| Arc::clone(r.value()))
+        self.sessions.get_send(session_id)
     }
 
     /// 注册外部创建的发送会话（断点续传时由 event_loop 创建后注册）
     pub fn insert_send_session(&self, session_id: Uuid, session: Arc<SendSession>) {
-        self.send_sessions.insert(session_id, session);
+        self.sessions.insert_send(session_id, session);
     }
 
-    /// 移除发送会话
+    /// 移除发送会话（可重复调用），未发射终态事件时补发失败事件
     pub fn remove_send_session(&self, session_id: &Uuid) {
-        self.send_sessions.remove(session_id);
+        self.sessions.remove_send(session_id);
+    }
+
+    /// 接收方确认完成：发送会话发射完成事件后移除（事件循环在 DB 标记完成后调用）
+    pub fn complete_send_session(&self, session_id: &Uuid) {
+        self.sessions.complete_send(session_id);
+    }
+
+    /// 对端取消传输：结束并移除本方对应的会话，返回本方是否为发送方（事件循环调用）
+    pub fn cancel_by_peer(&self, session_id: &Uuid, reason: &str) -> bool {
+        self.sessions.cancel_by_peer(session_id, reason)
     }
 
     // ============ 接收方：缓存 + 响应 + 启动传输 ============
 
-    /// 缓存入站 Offer（事件循环调用）
+    /// 校验入站 Offer（事件循环在缓存前调用），违规时返回原因
+    pub fn validate_inbound_offer(
+        &self,
+        files: &[FileInfo],
+        directories: &[String],
+        total_size: u64,
+    ) -> Result<(), String> {
+        self.offer_limits.validate(files, directories, total_size)
+    }
+
+    /// 缓存入站 Offer（事件循环调用），返回推送给前端的事件 payload
+    #[expect(clippy::too_many_arguments, reason = "Offer 字段需要完整缓存")]
     pub fn cache_inbound_offer(
         &self,
         pending_id: u64,
@@ -492,42 +1144,118 @@ impl TransferManager {
         peer_name: String,
         session_id: Uuid,
         files: Vec<FileInfo>,
+        directories: Vec<String>,
         total_size: u64,
-    ) {
-        self.pending.insert(
+        encryption: bool,
+        public_key: Option<[u8; 32]>,
+    ) -> TransferOfferEvent {
+        let offer = PendingOffer {
+            pending_id,
+            peer_id,
+            peer_name,
             session_id,
-            PendingOffer {
-                pending_id,
-                peer_id,
-                peer_name,
-                session_id,
-                files,
-                total_size,
-                created_at: Instant::now(),
-            },
-        );
+            files,
+            directories,
+            total_size,
+            encryption,
+            public_key,
+            created_at: Instant::now(),
+        };
+        let event = offer.to_event();
+        self.pending.insert(session_id, offer);
+        event
     }
 
-    /// 接受传输并启动接收：生成密钥、回复 OfferResult、创建 ReceiveSession 并开始拉取
+    /// 获取所有未处理且未过期的入站 Offer（按到达顺序），供前端重载后恢复
+    pub fn get_pending_offers(&self) -> Vec<TransferOfferEvent> {
+        let mut offers: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|r| r.value().expires_in_secs() > 0)
+            .map(|r| (r.value().created_at, r.value().to_event()))
+            .collect();
+        offers.sort_by_key(|(created_at, _)| *created_at);
+        offers.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// 接受传输并启动接收：协商会话密钥、回复 OfferResult、创建 ReceiveSession 并开始拉取
+    ///
+    /// 发送方请求明文、`lan_plaintext` 为 true 且当前为局域网直连时同意明文传输。
+    /// `prefer_direct_timeout` 含义同 [`Self::send_offer`]，在回复 OfferResult 之后、开始拉取之前等待。
+    /// `accepted_file_ids` 不为 None 时只接收其中的文件，其余文件不会被拉取（发送方无需感知）。
+    #[expect(clippy::too_many_arguments, reason = "接收选项由前端逐项传入")]
     pub async fn accept_and_start_receive(
         &self,
         session_id: &Uuid,
+        accepted_file_ids: Option<&[u32]>,
         save_location: entity::SaveLocation,
+        save_path_template: Option<SavePathTemplate>,
+        lan_plaintext: bool,
+        prefer_direct_timeout: Option<Duration>,
         app: AppHandle,
     ) -> AppResult<()> {
-        let (_, offer) = self
+        let (_, mut offer) = self
             .pending
             .remove(session_id)
             .ok_or_else(|| AppError::Transfer(format!("pending offer not found: {session_id}")))?;
 
-        let key = generate_key();
+        if let Some(ids) = accepted_file_ids {
+            let files = select_files(&offer.files, ids);
+            if files.is_empty() && offer.directories.is_empty() {
+                // 保留 Offer，用户可以重新选择或拒绝
+                self.pending.insert(*session_id, offer);
+                return Err(AppError::Transfer("未选择任何文件".into()));
+            }
+            offer.total_size = files.iter().map(|f| f.size).sum();
+            offer.files = files;
+        }
+
+        // 发送方携带公钥时双方以 ECDH 派生密钥；旧版本发送方不携带，由本方生成并直接返回
+        // （对端声明支持密钥交换时不允许缺少公钥）
+        let key_exchange_required = self
+            .devices
+            .capabilities(&offer.peer_id)
+            .is_some_and(|c| c.key_exchange);
+        let derived = responder_session_key(
+            offer.public_key,
+            key_exchange_required,
+            session_id,
+            &offer.peer_id,
+            &self.local_peer_id,
+        );
+        let (key, legacy_key, public_key) = match derived {
+            Ok(keys) => keys,
+            Err(e) => {
+                let response = AppResponse::Transfer(TransferResponse::OfferResult {
+                    accepted: false,
+                    key: None,
+                    reason: Some(OfferRejectReason::InvalidOffer {
+                        message: e.to_string(),
+                    }),
+                    encryption: true,
+                    public_key: None,
+                });
+                let _ = self.client.send_response(offer.pending_id, response).await;
+                return Err(e);
+            }
+        };
+        let encryption = negotiate_encryption(
+            offer.encryption,
+            lan_plaintext,
+            self.devices.connection_type(&offer.peer_id).as_ref(),
+        );
 
-        info!("Accepting transfer offer: session={}", session_id);
+        info!(
+            "Accepting transfer offer: session={}, encryption={}",
+            session_id, encryption
+        );
 
         let response = AppResponse::Transfer(TransferResponse::OfferResult {
             accepted: true,
-            key: Some(key),
+            key: legacy_key,
             reason: None,
+            encryption,
+            public_key,
         });
 
         self.client
@@ -535,7 +1263,21 @@ impl TransferManager {
             .await
             .map_err(|e| AppError::Transfer(format!("回复 OfferResult 失败: {e}")))?;
 
-        // 持久化接收方会话记录到 DB
+        // 根据 SaveLocation 构造 FileSink（所选目录不可用时回退到公共目录）
+        let sink = resolve_file_sink(&save_location, offer.session_id, &app).await;
+
+        // 按模板改写保存的相对路径（DB 记录与断点续传都使用改写后的路径）；
+        // 未设置模板时等同于 `{filename}`，对端传来的路径同样经过清理
+        let template = save_path_template.unwrap_or_default();
+        let vars = SavePathVars::today(&offer.peer_name, offer.peer_id);
+        for file in &mut offer.files {
+            file.relative_path = template.render(&vars, &file.relative_path);
+        }
+        for dir in &mut offer.directories {
+            *dir = template.render(&vars, dir);
+        }
+
+        // 持久化接收方会话记录到 DB（记录实际使用的保存位置）
         let peer_id_str = offer.peer_id.to_string();
         if let Some(db) = app.try_state::<DatabaseConnection>() {
             if let Err(e) = crate::database::ops::create_session(
@@ -546,7 +1288,7 @@ impl TransferManager {
                 &offer.peer_name,
                 &offer.files,
                 offer.total_size,
-                Some(save_location.clone()),
+                Some(sink.to_save_location()),
                 None,
             )
             .await
@@ -562,17 +1304,25 @@ impl TransferManager {
             }
         }
 
-        // 根据 SaveLocation 构造 FileSink 并启动接收
-        let sink = build_file_sink(&save_location);
+        // 尽量切换到局域网直连后启动接收
+        let mut connection = self.ensure_best_connection(offer.peer_id).await;
+        if let Some(timeout) = prefer_direct_timeout {
+            connection = self
+                .prefer_direct_connection(offer.peer_id, offer.total_size, timeout, connection)
+                .await;
+        }
         self.start_receive_session(
             offer.session_id,
             offer.peer_id,
             offer.files,
+            offer.directories,
             offer.total_size,
             sink,
             &key,
             app,
             std::collections::HashMap::new(),
+            connection,
+            !encryption,
         );
 
         Ok(())
@@ -591,6 +1341,8 @@ impl TransferManager {
             accepted: false,
             key: None,
             reason: Some(OfferRejectReason::UserDeclined),
+            encryption: true,
+            public_key: None,
         });
 
         self.client
@@ -604,18 +1356,15 @@ impl TransferManager {
     /// 暂停发送：取消 → 通知对端 → 保存进度到 DB → 移除 SendSession
     pub async fn pause_send(&self, session_id: &Uuid, app: &AppHandle) -> AppResult<()> {
         // 1. 先取消 session（仍在 DashMap 中），立即拒绝新的 ChunkRequest，减少竞态窗口
-        {
-            let session = self
-                .send_sessions
-                .get(session_id)
-                .ok_or_else(|| AppError::Transfer(format!("发送会话不存在: {session_id}")))?;
-            session.cancel();
-        }
+        self.sessions
+            .get_send(session_id)
+            .ok_or_else(|| AppError::Transfer(format!("发送会话不存在: {session_id}")))?
+            .cancel();
 
         // 2. 移除 session 获取所有权
-        let (_, session) = self
-            .send_sessions
-            .remove(session_id)
+        let session = self
+            .sessions
+            .take_send(session_id)
             .ok_or_else(|| AppError::Transfer(format!("发送会话不存在: {session_id}")))?;
 
         // 3. 通知对端（接收方）暂停
@@ -642,16 +1391,15 @@ impl TransferManager {
     /// 暂停接收：取消本地 ReceiveSession → 通知对端
     pub async fn pause_receive(&self, session_id: &Uuid) -> AppResult<()> {
         let session = self
-            .receive_sessions
-            .get(session_id)
-            .map(|r| Arc::clone(r.value()))
+            .sessions
+            .get_receive(session_id)
             .ok_or_else(|| AppError::Transfer(format!("接收会话不存在: {session_id}")))?;
 
         // 先停止本地接收（确保 bitmap 刷写完成）
         session.cancel_and_wait().await;
 
         // 从 DashMap 中移除（on_finish 回调可能已移除，这里确保清理）
-        self.receive_sessions.remove(session_id);
+        self.sessions.remove_receive(session_id);
 
         // 通知对端（发送方）暂停
         let _ = self
@@ -670,42 +1418,22 @@ impl TransferManager {
 
     /// 取消发送
     pub async fn cancel_send(&self, session_id: &Uuid) -> AppResult<()> {
-        let (_, session) = self
-            .send_sessions
-            .remove(session_id)
-            .ok_or_else(|| AppError::Transfer(format!("发送会话不存在: {session_id}")))?;
-
-        session.cancel();
-        info!("Send session cancelled: session={}", session_id);
-        Ok(())
+        self.sessions.cancel_send(session_id).await
     }
 
     /// 取消接收
     pub async fn cancel_receive(&self, session_id: &Uuid) -> AppResult<()> {
-        let session = self
-            .receive_sessions
-            .get(session_id)
-            .map(|r| Arc::clone(r.value()))
-            .ok_or_else(|| AppError::Transfer(format!("接收会话不存在: {session_id}")))?;
-
-        // 取消并等待后台任务完成（含 bitmap 刷写），on_finish 回调会自动从 DashMap 移除
-        session.cancel_and_wait().await;
-        session.send_cancel().await;
-        session.cleanup_part_files().await;
-        info!("Receive session cancelled: session={}", session_id);
-        Ok(())
+        self.sessions.cancel_receive(session_id).await
     }
 
     /// 获取接收会话（事件循环调用）
     pub fn get_receive_session(&self, session_id: &Uuid) -> Option<Arc<ReceiveSession>> {
-        self.receive_sessions
-            .get(session_id)
-            .map(|r| Arc::clone(r.value()))
+        self.sessions.get_receive(session_id)
     }
 
     /// 移除接收会话
     pub fn remove_receive_session(&self, session_id: &Uuid) {
-        self.receive_sessions.remove(session_id);
+        self.sessions.remove_receive(session_id);
     }
 
     // ============ 断点续传 ============
@@ -727,6 +1455,16 @@ impl TransferManager {
             file_checksums.len()
         );
 
+        let connection = self.ensure_best_connection(target_peer).await;
+
+        // 每次恢复都重新协商密钥（旧版本发送方忽略公钥，直接返回新生成的密钥）
+        let key_exchange = KeyExchange::new();
+        let public_key = key_exchange.public_key();
+        let key_exchange_required = self
+            .devices
+            .capabilities(&target_peer)
+            .is_some_and(|c| c.resume_key_exchange);
+
         // 发送 ResumeRequest
         let response = self
             .client
@@ -735,6 +1473,7 @@ impl TransferManager {
                 AppRequest::Transfer(TransferRequest::ResumeRequest {
                     session_id,
                     file_checksums,
+                    public_key: Some(public_key),
                 }),
             )
             .await
@@ -743,11 +1482,22 @@ impl TransferManager {
         match response {
             AppResponse::Transfer(TransferResponse::ResumeResult {
                 accepted: true,
-                key: Some(key),
+                key,
+                public_key,
                 ..
             }) => {
                 info!("Resume accepted for session {}", session_id);
 
+                let key = initiator_session_key(
+                    key_exchange,
+                    public_key,
+                    key,
+                    key_exchange_required,
+                    &session_id,
+                    &target_peer,
+                    &self.local_peer_id,
+                )?;
+
                 crate::database::ops::mark_session_transferring(db, session_id).await?;
 
                 let total_size = session.total_size;
@@ -759,16 +1509,20 @@ impl TransferManager {
 
                 let (file_infos, initial_bitmaps) = build_file_infos_and_bitmaps(&files);
                 let (resume_file_infos, transferred_bytes) = build_resume_file_infos(&files);
+                let sink = resolve_file_sink(&save_location, session_id, &app).await;
 
                 self.start_receive_session(
                     session_id,
                     target_peer,
                     file_infos,
+                    Vec::new(),
                     total_size as u64,
-                    build_file_sink(&save_location),
+                    sink,
                     &key,
                     app,
                     initial_bitmaps,
+                    connection,
+                    false, // 断点续传不重新协商明文，始终加密
                 );
 
                 Ok(ResumeInfo {
@@ -779,11 +1533,6 @@ impl TransferManager {
                     transferred_bytes,
                 })
             }
-            AppResponse::Transfer(TransferResponse::ResumeResult {
-                accepted: true,
-                key: None,
-                ..
-            }) => Err(AppError::Transfer("Resume accepted 但未收到密钥".into())),
             AppResponse::Transfer(TransferResponse::ResumeResult {
                 accepted: false,
                 reason: Some(ResumeRejectReason::SenderCancelled),
@@ -801,6 +1550,7 @@ impl TransferManager {
                 let reason_str = match reason {
                     Some(ResumeRejectReason::FileModified) => "源文件已被修改，无法恢复传输",
                     Some(ResumeRejectReason::SessionNotFound) => "发送方找不到对应会话",
+                    Some(ResumeRejectReason::KeyExchangeFailed) => "密钥协商失败",
                     _ => "未知原因",
                 };
                 info!("Resume rejected for session {}: {}", session_id, reason_str);
@@ -826,8 +1576,14 @@ impl TransferManager {
         let file_checksums = build_file_checksums(&files);
         let (resume_file_infos, _) = build_resume_file_infos(&files);
 
-        // 生成密钥
-        let key = generate_key();
+        // 接收方声明支持续传密钥交换时重新协商密钥；否则（旧版本）生成密钥随 ResumeOffer 直接发送
+        let key_exchange = self
+            .devices
+            .capabilities(&target_peer)
+            .is_some_and(|c| c.resume_key_exchange)
+            .then(KeyExchange::new);
+        let public_key = key_exchange.as_ref().map(KeyExchange::public_key);
+        let legacy_key = key_exchange.is_none().then(generate_key);
 
         info!(
             "发送方发起断点续传: session={}, files={}",
@@ -838,16 +1594,20 @@ impl TransferManager {
         // 从 DB 构建 resume_state
         let resume_state = build_sender_resume_state(&files);
 
-        // 先创建 SendSession 并插入 DashMap（接收方开始 pulling 前必须就绪）
-        let send_session = Arc::new(SendSession::new_with_resume(
-            session_id,
-            target_peer,
-            prepared_files,
-            &key,
-            app,
-            &resume_state,
-        ));
-        self.send_sessions.insert(session_id, send_session);
+        // 旧版本接收方收到 ResumeOffer 即开始拉取，须先创建 SendSession；
+        // 密钥交换时接收方回复公钥后才开始拉取，待派生出密钥再创建
+        let mut prepared_files = Some(prepared_files);
+        if let Some(key) = &legacy_key {
+            let send_session = Arc::new(SendSession::new_with_resume(
+                session_id,
+                target_peer,
+                prepared_files.take().unwrap_or_default(),
+                key,
+                self.session_context(&app),
+                &resume_state,
+            ));
+            self.insert_send_session(session_id, send_session);
+        }
 
         // 发送 ResumeOffer 给接收方
         let response = self
@@ -856,22 +1616,46 @@ impl TransferManager {
                 target_peer,
                 AppRequest::Transfer(TransferRequest::ResumeOffer {
                     session_id,
-                    key,
+                    key: legacy_key,
                     file_checksums,
+                    public_key,
                 }),
             )
             .await
             .map_err(|e| {
-                self.send_sessions.remove(&session_id);
+                self.sessions.take_send(&session_id);
                 AppError::Transfer(format!("ResumeOffer 发送失败: {e}"))
             })?;
 
         match response {
             AppResponse::Transfer(TransferResponse::ResumeOfferResult {
-                accepted: true, ..
+                accepted: true,
+                public_key: peer_public,
+                ..
             }) => {
                 info!("ResumeOffer accepted for session {}", session_id);
 
+                if let (Some(exchange), Some(prepared_files)) = (key_exchange, prepared_files) {
+                    let key = initiator_session_key(
+                        exchange,
+                        peer_public,
+                        None,
+                        true,
+                        &session_id,
+                        &self.local_peer_id,
+                        &target_peer,
+                    )?;
+                    let send_session = Arc::new(SendSession::new_with_resume(
+                        session_id,
+                        target_peer,
+                        prepared_files,
+                        &key,
+                        self.session_context(&app),
+                        &resume_state,
+                    ));
+                    self.insert_send_session(session_id, send_session);
+                }
+
                 crate::database::ops::mark_session_transferring(db, session_id).await?;
 
                 let transferred_bytes: i64 = files.iter().map(|f| f.transferred_bytes).sum();
@@ -889,12 +1673,13 @@ impl TransferManager {
                 reason,
                 ..
             }) => {
-                self.send_sessions.remove(&session_id);
+                self.sessions.take_send(&session_id);
 
                 let reason_str = match reason {
                     Some(ResumeRejectReason::FileModified) => "接收方文件校验不匹配",
                     Some(ResumeRejectReason::SessionNotFound) => "接收方找不到对应会话",
                     Some(ResumeRejectReason::SenderCancelled) => "接收方已取消传输",
+                    Some(ResumeRejectReason::KeyExchangeFailed) => "密钥协商失败",
                     None => "未知原因",
                 };
                 info!(
@@ -905,7 +1690,7 @@ impl TransferManager {
                 Err(AppError::Transfer(reason_str.into()))
             }
             other => {
-                self.send_sessions.remove(&session_id);
+                self.sessions.take_send(&session_id);
                 Err(AppError::Transfer(format!("意外的响应类型: {other:?}")))
             }
         }
@@ -929,40 +1714,79 @@ impl TransferManager {
         app: AppHandle,
         initial_bitmaps: std::collections::HashMap<u32, Vec<u8>>,
     ) {
-        self.start_receive_session(session_id, peer_id, files, total_size, sink, key, app, initial_bitmaps);
+        self.start_receive_session(
+            session_id,
+            peer_id,
+            files,
+            Vec::new(),
+            total_size,
+            sink,
+            key,
+            app,
+            initial_bitmaps,
+            None,
+            false, // 断点续传不重新协商明文，始终加密
+        );
     }
 
     // ============ 内部方法 ============
 
+    /// 优先使用局域网地址连接对端，返回最终的连接类型
+    async fn ensure_best_connection(&self, peer_id: PeerId) -> Option<ConnectionType> {
+        crate::network::ensure_best_connection(&self.client, &self.devices, peer_id).await
+    }
+
+    /// 大文件且当前仅有中继连接时，等待打洞直连（超时则沿用中继）
+    async fn prefer_direct_connection(
+        &self,
+        peer_id: PeerId,
+        total_size: u64,
+        timeout: Duration,
+        connection: Option<ConnectionType>,
+    ) -> Option<ConnectionType> {
+        if total_size < crate::network::PREFER_DIRECT_MIN_SIZE
+            || connection != Some(ConnectionType::Relay)
+        {
+            return connection;
+        }
+        crate::network::wait_for_direct_connection(&self.devices, peer_id, timeout).await
+    }
+
     #[expect(clippy::too_many_arguments, reason = "传输会话初始化需要完整上下文")]
     fn start_receive_session(
         &self,
         session_id: Uuid,
         peer_id: PeerId,
         files: Vec<FileInfo>,
+        directories: Vec<String>,
         total_size: u64,
         sink: FileSink,
         key: &[u8; 32],
         app: AppHandle,
         initial_bitmaps: std::collections::HashMap<u32, Vec<u8>>,
+        connection: Option<ConnectionType>,
+        plaintext_lan: bool,
     ) {
-        let receive_session = Arc::new(ReceiveSession::new(
-            session_id,
-            peer_id,
-            files,
-            total_size,
-            sink,
-            key,
-            self.client.clone(),
-            app,
-            initial_bitmaps,
-        ));
-        self.receive_sessions
-            .insert(session_id, receive_session.clone());
-        let sessions_map = self.receive_sessions.clone();
-        receive_session.start_pulling(move |sid| {
-            sessions_map.remove(sid);
-        });
+        let preallocate = !app
+            .try_state::<crate::settings::SettingsStore>()
+            .is_some_and(|s| s.get().skip_preallocation);
+        let receive_session = Arc::new(
+            ReceiveSession::new(
+                session_id,
+                peer_id,
+                files,
+                directories,
+                total_size,
+                sink,
+                key,
+                self.session_context(&app),
+                initial_bitmaps,
+            )
+            .with_connection(connection)
+            .with_plaintext_lan(plaintext_lan)
+            .with_preallocation(preallocate),
+        );
+        self.sessions.start_receive(receive_session);
     }
 }
 
@@ -972,14 +1796,33 @@ pub fn generate_id() -> Uuid {
 }
 
 /// 将 `FileSource` 转换为可持久化的路径字符串
+///
+/// 内存数据无法持久化，只记录名称，断点续传时找不到对应文件。
 fn source_path_string(source: &FileSource) -> String {
     match source {
         FileSource::Path { path } => path.to_string_lossy().into_owned(),
         #[cfg(target_os = "android")]
         FileSource::AndroidUri(uri) => serde_json::to_string(uri).unwrap_or_default(),
+        FileSource::Memory { name, .. } => format!("memory:{name}"),
     }
 }
 
+/// 按 file_id 筛选接收方选中的文件（保持 Offer 中的顺序，未知 id 忽略）
+pub(crate) fn select_files(files: &[FileInfo], accepted_file_ids: &[u32]) -> Vec<FileInfo> {
+    files
+        .iter()
+        .filter(|f| accepted_file_ids.contains(&f.file_id))
+        .cloned()
+        .collect()
+}
+
+/// 按文件扩展名推断 MIME 类型（无法识别时返回 None）
+pub(crate) fn guess_mime(name: &str) -> Option<String> {
+    mime_guess::from_path(name)
+        .first()
+        .map(|m| m.essence_str().to_owned())
+}
+
 /// 根据 SaveLocation 构造 FileSink
 pub(crate) fn build_file_sink(save_location: &entity::SaveLocation) -> FileSink {
     match save_location {
@@ -987,16 +1830,60 @@ pub(crate) fn build_file_sink(save_location: &entity::SaveLocation) -> FileSink
             save_dir: std::path::PathBuf::from(path),
         },
         #[cfg(target_os = "android")]
-        entity::SaveLocation::AndroidPublicDir { subdir } => FileSink::AndroidPublicDir {
+        entity::SaveLocation::AndroidPublicDir {
+            base_dir,
+            subdir,
+            by_media_type,
+        } => FileSink::AndroidPublicDir {
+            base_dir: *base_dir,
             subdir: subdir.clone(),
+            by_media_type: *by_media_type,
         },
+        #[cfg(target_os = "android")]
+        entity::SaveLocation::AndroidSafDir { dir_uri } => {
+            match serde_json::from_value(dir_uri.clone()) {
+                Ok(dir_uri) => FileSink::AndroidSafDir { dir_uri },
+                Err(e) => {
+                    warn!("保存目录 URI 无效，改用公共目录: {}", e);
+                    FileSink::AndroidPublicDir {
+                        base_dir: entity::PublicBaseDir::Download,
+                        subdir: crate::file_sink::DEFAULT_PUBLIC_SUBDIR.into(),
+                        by_media_type: false,
+                    }
+                }
+            }
+        }
         #[cfg(not(target_os = "android"))]
-        entity::SaveLocation::AndroidPublicDir { .. } => {
-            unreachable!("AndroidPublicDir 不应出现在非 Android 平台")
+        entity::SaveLocation::AndroidPublicDir { .. }
+        | entity::SaveLocation::AndroidSafDir { .. } => {
+            unreachable!("Android 保存位置不应出现在非 Android 平台")
         }
     }
 }
 
+/// 构造 FileSink 并确认保存目录可访问
+///
+/// 用户所选目录的权限失效时回退到公共目录，并发射 `save-location-fallback` 事件提示前端。
+pub(crate) async fn resolve_file_sink(
+    save_location: &entity::SaveLocation,
+    session_id: Uuid,
+    app: &AppHandle,
+) -> FileSink {
+    let (sink, fallback_reason) = build_file_sink(save_location).resolve_access(app).await;
+    if let Some(reason) = fallback_reason {
+        warn!("会话 {} 保存位置回退: {}", session_id, reason);
+        let _ = app.emit(
+            events::SAVE_LOCATION_FALLBACK,
+            SaveLocationFallbackEvent {
+                session_id,
+                save_location: sink.to_save_location(),
+                reason,
+            },
+        );
+    }
+    sink
+}
+
 /// 从 DB 文件记录构建发送方的 resume_state（file_id → (chunks_done, transferred_bytes)）
 ///
 /// 利用 transferred_bytes 和 CHUNK_SIZE 反推 chunks_done。
@@ -1105,6 +1992,8 @@ pub(crate) fn build_file_infos_and_bitmaps(
             relative_path: f.relative_path.clone(),
             size: f.size as u64,
             checksum: f.checksum.clone(),
+            preview: None,
+            mime: guess_mime(&f.name),
         });
         bitmaps.insert(fid, f.completed_chunks.clone());
     }
@@ -1137,11 +2026,75 @@ pub(crate) async fn build_prepared_files_from_db(
             source: FileSource::Path { path },
             size: f.size as u64,
             checksum: f.checksum.clone(),
+            preview: None,
+            mime: guess_mime(&f.name),
+            modified: None,
         });
     }
     Ok(prepared)
 }
 
+/// 发起方（Offer / 断点续传请求的发出方）确定会话密钥：对方返回公钥时以 ECDH 派生，
+/// 否则使用旧版本对方直接返回的密钥
+///
+/// `key_exchange_required` 为对方是否声明支持密钥交换，此时不接受直接返回的密钥（防止降级）。
+fn initiator_session_key(
+    exchange: KeyExchange,
+    peer_public: Option<[u8; 32]>,
+    legacy_key: Option<SessionKey>,
+    key_exchange_required: bool,
+    session_id: &Uuid,
+    sender: &PeerId,
+    receiver: &PeerId,
+) -> AppResult<SessionKey> {
+    match (peer_public, legacy_key) {
+        (Some(peer_public), _) => exchange.derive(&peer_public, session_id, sender, receiver),
+        (None, Some(_)) if key_exchange_required => Err(AppError::KeyExchange(
+            "对方支持密钥交换，但返回了明文密钥".into(),
+        )),
+        (None, Some(key)) => Ok(key),
+        (None, None) => Err(AppError::KeyExchange("对方接受但未提供加密密钥".into())),
+    }
+}
+
+/// 应答方确定会话密钥：对方携带公钥时以 ECDH 派生，否则（旧版本对方）由本方生成
+///
+/// 返回 `(会话密钥, 需直接回复给旧版本对方的密钥, 需回复的本方公钥)`。
+/// 对方声明支持密钥交换（`key_exchange_required`）却未携带公钥时拒绝，不下发明文密钥。
+pub(crate) fn responder_session_key(
+    peer_public: Option<[u8; 32]>,
+    key_exchange_required: bool,
+    session_id: &Uuid,
+    sender: &PeerId,
+    receiver: &PeerId,
+) -> AppResult<(SessionKey, Option<SessionKey>, Option<[u8; 32]>)> {
+    match peer_public {
+        Some(peer_public) => {
+            let exchange = KeyExchange::new();
+            let public_key = exchange.public_key();
+            let key = exchange.derive(&peer_public, session_id, sender, receiver)?;
+            Ok((key, None, Some(public_key)))
+        }
+        None if key_exchange_required => Err(AppError::KeyExchange(
+            "对方支持密钥交换，但未携带公钥".into(),
+        )),
+        None => {
+            let key = generate_key();
+            Ok((key.clone(), Some(key), None))
+        }
+    }
+}
+
+/// 计算 hash 的并发文件数
+fn hash_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// 文件自上次准备以来是否可能已变化（大小或修改时间不同，修改时间未知时视为已变化）
+fn file_changed(file: &PreparedFile, meta: &FileSourceMetadata) -> bool {
+    file.size != meta.size || file.modified.is_none() || file.modified != meta.modified
+}
+
 /// 从 DashMap 中移除满足条件的条目并记录日志
 fn remove_expired<V>(map: &DashMap<Uuid, V>, is_expired: impl Fn(&V) -> bool, label: &str) {
     let expired: Vec<Uuid> = map
@@ -1156,3 +2109,364 @@ fn remove_expired<V>(map: &DashMap<Uuid, V>, is_expired: impl Fn(&V) -> bool, la
         info!("清理 {} 个过期的 {}", expired.len(), label);
     }
 }
+
+/// prepare 阶段的 hash 进度汇总（多个文件并发上报，加锁保证推送的累计值单调递增）
+struct HashProgress {
+    state: std::sync::Mutex<(u64, u32)>,
+    total_files: u32,
+    total_bytes: u64,
+    emit: Box<dyn Fn(PrepareProgress) + Send + Sync>,
+}
+
+impl HashProgress {
+    /// 累加已 hash 字节数（`done_file` 为 true 时同时累加已完成文件数）并推送
+    fn report(&self, current_file: &str, delta_bytes: u64, done_file: bool) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.0 += delta_bytes;
+        state.1 += u32::from(done_file);
+        (self.emit)(PrepareProgress {
+            current_file: current_file.to_string(),
+            completed_files: state.1,
+            total_files: self.total_files,
+            bytes_hashed: state.0,
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+/// 并发计算文件 hash，最多 `concurrency` 个文件同时进行，返回与 `entries` 顺序一致的校验和
+///
+/// 每个文件仍是流式读取，内存占用与文件大小无关。任一文件失败时取消其余任务并返回错误。
+/// `cancel` 在文件之间及每个 chunk 之间检查，取消后返回 [`AppError::Cancelled`]。
+async fn hash_files(
+    entries: &[EnumeratedFile],
+    app: Option<&AppHandle>,
+    concurrency: usize,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(PrepareProgress) + Send + Sync + 'static,
+) -> AppResult<Vec<String>> {
+    let progress = Arc::new(HashProgress {
+        state: std::sync::Mutex::new((0, 0)),
+        total_files: entries.len() as u32,
+        total_bytes: entries.iter().map(|e| e.size).sum(),
+        emit: Box::new(on_progress),
+    });
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let source = entry.source.clone();
+        let name: Arc<str> = entry.name.clone().into();
+        let app = app.cloned();
+        let progress = progress.clone();
+        let semaphore = semaphore.clone();
+        let cancel = cancel.clone();
+        tasks.spawn(async move {
+            let _permit = tokio::select! {
+                _ = cancel.cancelled() => return Err(AppError::Cancelled),
+                permit = semaphore.acquire_owned() => {
+                    permit.map_err(|e| AppError::Transfer(e.to_string()))?
+                }
+            };
+
+            // 上报的是当前文件已读字节数，换算为增量后再累加
+            let on_chunk = {
+                let (name, progress) = (name.clone(), progress.clone());
+                let hashed = std::sync::atomic::AtomicU64::new(0);
+                move |bytes_in_file: u64| {
+                    let last = hashed.swap(bytes_in_file, Ordering::Relaxed);
+                    progress.report(&name, bytes_in_file.saturating_sub(last), false);
+                }
+            };
+            let checksum = source
+                .compute_hash_with_progress(app.as_ref(), cancel, on_chunk)
+                .await?;
+            progress.report(&name, 0, true);
+            Ok::<_, AppError>((index, checksum))
+        });
+    }
+
+    let mut checksums = vec![String::new(); entries.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let (index, checksum) = joined??;
+        checksums[index] = checksum;
+    }
+    Ok(checksums)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn pending_offer(age_secs: u64) -> PendingOffer {
+        PendingOffer {
+            pending_id: 1,
+            peer_id: PeerId::random(),
+            peer_name: "laptop".into(),
+            session_id: Uuid::new_v4(),
+            files: vec![FileInfo {
+                file_id: 0,
+                name: "a.txt".into(),
+                relative_path: "dir/a.txt".into(),
+                size: 42,
+                checksum: String::new(),
+                preview: Some(vec![0xff, 0xd8]),
+                mime: Some("image/jpeg".into()),
+            }],
+            directories: vec!["dir/empty".into()],
+            total_size: 42,
+            encryption: true,
+            public_key: None,
+            created_at: Instant::now() - Duration::from_secs(age_secs),
+        }
+    }
+
+    #[test]
+    fn test_pending_offer_to_event() {
+        let offer = pending_offer(40);
+        let event = offer.to_event();
+        assert_eq!(event.session_id, offer.session_id);
+        assert_eq!(event.device_name, "laptop");
+        assert_eq!(event.files.len(), 1);
+        assert_eq!(event.files[0].relative_path, "dir/a.txt");
+        assert_eq!(event.files[0].preview.as_deref(), Some("/9g="));
+        assert_eq!(event.directories, vec!["dir/empty".to_string()]);
+        assert_eq!(event.total_size, 42);
+        assert!(event.expires_in_secs <= PENDING_OFFER_TIMEOUT_SECS - 40);
+    }
+
+    #[test]
+    fn test_expired_offer_has_zero_ttl() {
+        let offer = pending_offer(PENDING_OFFER_TIMEOUT_SECS + 10);
+        assert_eq!(offer.expires_in_secs(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_hash_files() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_parallel_hash");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut entries = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            let data = format!("file {i} ").repeat(i * 37 + 1).into_bytes();
+            let path = dir.join(format!("{i}.txt"));
+            std::fs::write(&path, &data).unwrap();
+            expected.push(blake3::hash(&data).to_hex().to_string());
+            entries.push(EnumeratedFile {
+                name: format!("{i}.txt"),
+                relative_path: format!("{i}.txt"),
+                source: FileSource::Path { path },
+                size: data.len() as u64,
+                is_directory: false,
+            });
+        }
+        let total_bytes: u64 = entries.iter().map(|e| e.size).sum();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::<PrepareProgress>::new()));
+        let recorder = events.clone();
+        let cancel = CancellationToken::new();
+        let checksums = hash_files(&entries, None, 8, &cancel, move |p| {
+            recorder.lock().unwrap().push(p);
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(checksums, expected);
+
+        let events = events.lock().unwrap();
+        assert!(events.windows(2).all(|w| {
+            w[0].bytes_hashed <= w[1].bytes_hashed && w[0].completed_files <= w[1].completed_files
+        }));
+        let last = events.last().unwrap();
+        assert_eq!(last.completed_files, 100);
+        assert_eq!(last.bytes_hashed, total_bytes);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_hash_files_cancelled() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_cancel_hash");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let entries: Vec<EnumeratedFile> = (0..4)
+            .map(|i| {
+                let path = dir.join(format!("{i}.bin"));
+                std::fs::write(&path, vec![i as u8; 1024]).unwrap();
+                EnumeratedFile {
+                    name: format!("{i}.bin"),
+                    relative_path: format!("{i}.bin"),
+                    source: FileSource::Path { path },
+                    size: 1024,
+                    is_directory: false,
+                }
+            })
+            .collect();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = hash_files(&entries, None, 2, &cancel, |_| {}).await;
+        assert!(matches!(result, Err(AppError::Cancelled)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_guess_mime() {
+        assert_eq!(guess_mime("photo.JPG").as_deref(), Some("image/jpeg"));
+        assert_eq!(guess_mime("clip.mp4").as_deref(), Some("video/mp4"));
+        assert_eq!(guess_mime("song.mp3").as_deref(), Some("audio/mpeg"));
+        assert_eq!(guess_mime("Makefile"), None);
+    }
+
+    #[test]
+    fn test_initiator_session_key() {
+        let session_id = Uuid::new_v4();
+        let sender = PeerId::random();
+        let receiver = PeerId::random();
+
+        // 新版本接收方：返回公钥，双方派生出同一把密钥
+        let receiver_kx = KeyExchange::new();
+        let receiver_public = receiver_kx.public_key();
+        let sender_kx = KeyExchange::new();
+        let sender_public = sender_kx.public_key();
+        let key = initiator_session_key(
+            sender_kx,
+            Some(receiver_public),
+            None,
+            true,
+            &session_id,
+            &sender,
+            &receiver,
+        )
+        .unwrap();
+        let expected = receiver_kx
+            .derive(&sender_public, &session_id, &sender, &receiver)
+            .unwrap();
+        assert_eq!(*key, *expected);
+
+        // 旧版本接收方：直接使用返回的密钥
+        let legacy = generate_key();
+        let key = initiator_session_key(
+            KeyExchange::new(),
+            None,
+            Some(legacy.clone()),
+            false,
+            &session_id,
+            &sender,
+            &receiver,
+        )
+        .unwrap();
+        assert_eq!(*key, *legacy);
+
+        // 对方声明支持密钥交换却返回明文密钥：视为降级攻击，拒绝
+        let downgraded = initiator_session_key(
+            KeyExchange::new(),
+            None,
+            Some(generate_key()),
+            true,
+            &session_id,
+            &sender,
+            &receiver,
+        );
+        assert!(matches!(downgraded, Err(AppError::KeyExchange(_))));
+
+        // 两者都没有：无法建立会话
+        let missing = initiator_session_key(
+            KeyExchange::new(),
+            None,
+            None,
+            false,
+            &session_id,
+            &sender,
+            &receiver,
+        );
+        assert!(matches!(missing, Err(AppError::KeyExchange(_))));
+    }
+
+    #[test]
+    fn test_responder_session_key() {
+        let session_id = Uuid::new_v4();
+        let sender = PeerId::random();
+        let receiver = PeerId::random();
+
+        // 发起方携带公钥（断点续传时由接收方发起）：回复公钥，不回复密钥
+        let receiver_kx = KeyExchange::new();
+        let receiver_public = receiver_kx.public_key();
+        let (key, legacy, sender_public) =
+            responder_session_key(Some(receiver_public), true, &session_id, &sender, &receiver)
+                .unwrap();
+        assert!(legacy.is_none());
+        let expected = initiator_session_key(
+            receiver_kx,
+            sender_public,
+            legacy,
+            true,
+            &session_id,
+            &sender,
+            &receiver,
+        )
+        .unwrap();
+        assert_eq!(*key, *expected);
+
+        // 旧版本发起方：生成密钥直接回复
+        let (key, legacy, public_key) =
+            responder_session_key(None, false, &session_id, &sender, &receiver).unwrap();
+        assert_eq!(legacy.as_deref(), Some(&*key));
+        assert!(public_key.is_none());
+
+        // 对方声明支持密钥交换却未携带公钥：拒绝，不下发明文密钥
+        let downgraded = responder_session_key(None, true, &session_id, &sender, &receiver);
+        assert!(matches!(downgraded, Err(AppError::KeyExchange(_))));
+
+        // 低阶点公钥：协商失败
+        let invalid =
+            responder_session_key(Some([0u8; 32]), false, &session_id, &sender, &receiver);
+        assert!(matches!(invalid, Err(AppError::KeyExchange(_))));
+    }
+
+    #[test]
+    fn test_file_changed() {
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let file = PreparedFile {
+            file_id: 0,
+            name: "a.txt".into(),
+            relative_path: "a.txt".into(),
+            source: FileSource::Path {
+                path: "a.txt".into(),
+            },
+            size: 10,
+            checksum: String::new(),
+            preview: None,
+            mime: None,
+            modified: Some(mtime),
+        };
+        let meta = |size, modified| FileSourceMetadata {
+            name: "a.txt".into(),
+            size,
+            is_dir: false,
+            modified,
+        };
+
+        assert!(!file_changed(&file, &meta(10, Some(mtime))));
+        assert!(file_changed(&file, &meta(11, Some(mtime))));
+        assert!(file_changed(
+            &file,
+            &meta(10, Some(mtime + Duration::from_secs(1)))
+        ));
+        assert!(file_changed(&file, &meta(10, None)));
+        // 准备时未取得修改时间：无法判断，一律重新计算
+        let unknown = PreparedFile {
+            modified: None,
+            ..file
+        };
+        assert!(file_changed(&unknown, &meta(10, None)));
+    }
+}
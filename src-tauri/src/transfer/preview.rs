@@ -0,0 +1,138 @@
+//! Offer 缩略图预览
+//!
+//! 发送方在准备阶段为图片文件生成一张小尺寸 JPEG，随 Offer 发给接收方，
+//! 便于用户在接受前确认内容。预览是尽力而为的：格式不支持、文件过大或解码失败时
+//! 直接跳过，不影响传输本身。
+//!
+//! 视频文件能被识别，但 `image` crate 无法解码视频帧，目前不生成预览。
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use tauri::AppHandle;
+use tracing::debug;
+
+use crate::file_source::{calc_total_chunks, FileSource};
+
+/// 预览图大小上限（编码后字节数）
+pub const PREVIEW_MAX_BYTES: usize = 32 * 1024;
+
+/// 参与预览生成的源文件大小上限，超过则跳过（避免读取 / 解码超大图片）
+const PREVIEW_SOURCE_MAX_BYTES: u64 = 20 * 1024 * 1024;
+
+/// 依次尝试的（最长边像素, JPEG 质量），直到编码结果不超过上限
+const PREVIEW_ATTEMPTS: [(u32, u8); 4] = [(256, 75), (192, 60), (128, 50), (96, 40)];
+
+/// 可生成预览的图片扩展名
+const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+
+/// 媒体文件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MediaKind {
+    Image,
+    Video,
+}
+
+/// 按扩展名识别媒体文件类型
+fn media_kind(name: &str) -> Option<MediaKind> {
+    const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mov", "mkv", "webm", "avi", "m4v"];
+
+    let ext = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        Some(MediaKind::Image)
+    } else if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        Some(MediaKind::Video)
+    } else {
+        None
+    }
+}
+
+/// 为文件生成预览图（JPEG），不支持或失败时返回 None
+pub async fn generate_preview(
+    source: &FileSource,
+    name: &str,
+    size: u64,
+    app: &AppHandle,
+) -> Option<Vec<u8>> {
+    if media_kind(name) != Some(MediaKind::Image) || size == 0 || size > PREVIEW_SOURCE_MAX_BYTES {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(size as usize);
+    for chunk_index in 0..calc_total_chunks(size) {
+        match source.read_chunk(size, chunk_index, Some(app)).await {
+            Ok(chunk) => data.extend_from_slice(&chunk),
+            Err(e) => {
+                debug!("读取预览源文件失败: {}: {}", name, e);
+                return None;
+            }
+        }
+    }
+
+    let preview = tokio::task::spawn_blocking(move || encode_preview(&data))
+        .await
+        .ok()
+        .flatten();
+    if preview.is_none() {
+        debug!("无法为 {} 生成预览", name);
+    }
+    preview
+}
+
+/// 解码图片并缩放编码为不超过 [`PREVIEW_MAX_BYTES`] 的 JPEG
+fn encode_preview(data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(data).ok()?;
+
+    PREVIEW_ATTEMPTS.iter().find_map(|&(max_side, quality)| {
+        let thumb = DynamicImage::ImageRgb8(image.thumbnail(max_side, max_side).to_rgb8());
+        let mut out = Cursor::new(Vec::new());
+        thumb
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality))
+            .ok()?;
+        let out = out.into_inner();
+        (out.len() <= PREVIEW_MAX_BYTES).then_some(out)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{ImageFormat, RgbImage};
+
+    /// 生成噪声图片（难以压缩，用于验证大小上限）
+    fn noisy_png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbImage::from_fn(width, height, |x, y| {
+            let v = x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503);
+            image::Rgb([v as u8, (v >> 8) as u8, (v >> 16) as u8])
+        });
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_media_kind() {
+        assert_eq!(media_kind("photo.JPG"), Some(MediaKind::Image));
+        assert_eq!(media_kind("a.b.webp"), Some(MediaKind::Image));
+        assert_eq!(media_kind("clip.mp4"), Some(MediaKind::Video));
+        assert_eq!(media_kind("notes.txt"), None);
+        assert_eq!(media_kind("png"), None);
+    }
+
+    #[test]
+    fn test_preview_is_small_jpeg() {
+        let preview = encode_preview(&noisy_png(2000, 1500)).unwrap();
+
+        assert!(preview.len() <= PREVIEW_MAX_BYTES, "{}", preview.len());
+        assert_eq!(image::guess_format(&preview).unwrap(), ImageFormat::Jpeg);
+        let decoded = image::load_from_memory(&preview).unwrap();
+        assert!(decoded.width() <= 256 && decoded.height() <= 256);
+    }
+
+    #[test]
+    fn test_invalid_image_has_no_preview() {
+        assert!(encode_preview(b"not an image").is_none());
+    }
+}
@@ -1,13 +1,14 @@
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use entity::SaveLocation;
 use serde::Serialize;
-use tauri::{AppHandle, Emitter};
 use uuid::Uuid;
 
-use crate::events;
+use crate::device::ConnectionType;
 use crate::file_source::calc_total_chunks;
+use crate::transfer::context::EventSink;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -51,6 +52,8 @@ pub struct TransferProgressEvent {
     pub speed: f64,
     pub eta: Option<f64>,
     pub files: Vec<FileProgressInfo>,
+    /// 与对端的连接类型（未知时为 None）
+    pub connection: Option<ConnectionType>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -61,6 +64,12 @@ pub struct TransferCompleteEvent {
     pub total_bytes: u64,
     pub elapsed_ms: u64,
     pub save_location: Option<SaveLocation>,
+    /// 接收方已保存文件的实际 URI（仅 Android 端，按媒体类型分流后文件可能分散在多个目录）
+    pub file_uris: Vec<serde_json::Value>,
+    /// 接收方已保存文件的最终绝对路径（仅桌面端，即 .part 重命名后的实际文件名）
+    pub file_paths: Vec<String>,
+    /// 传输结束时的连接类型（写入历史展示用）
+    pub final_connection: Option<ConnectionType>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -69,6 +78,94 @@ pub struct TransferFailedEvent {
     pub session_id: Uuid,
     pub direction: TransferDirection,
     pub error: String,
+    /// 失败前已完整传输的文件（接收方为已校验保存的文件，失败不会删除它们）
+    pub completed_files: Vec<FileProgressInfo>,
+    /// 出错的文件（会话级错误时为 None）
+    pub failed_file: Option<FileProgressInfo>,
+    /// 接收方的保存位置（已保存的文件在此处）
+    pub save_location: Option<SaveLocation>,
+}
+
+impl TransferFailedEvent {
+    /// 不含文件明细的失败事件（会话尚未开始传输或不持有进度时使用）
+    pub fn new(session_id: Uuid, direction: TransferDirection, error: String) -> Self {
+        Self {
+            session_id,
+            direction,
+            error,
+            completed_files: Vec::new(),
+            failed_file: None,
+            save_location: None,
+        }
+    }
+}
+
+/// 连接中断，会话正在重连对端（重连成功后恢复推送进度事件）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferReconnectingEvent {
+    pub session_id: Uuid,
+    pub direction: TransferDirection,
+    /// 重连窗口（秒），超时仍未恢复则会话失败
+    pub window_secs: u64,
+}
+
+/// 接收方全部分块到达后校验文件 BLAKE3 的进度（大文件校验耗时较长，供界面提示"正在校验"）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferVerifyingEvent {
+    pub session_id: Uuid,
+    pub file_id: u32,
+    pub name: String,
+    /// 已校验的字节数
+    pub bytes_hashed: u64,
+    pub total_bytes: u64,
+}
+
+/// 校验进度推送器：同一会话的所有文件共用一个节流窗口，大量小文件不会逐个推送
+#[derive(Clone)]
+pub struct VerifyProgress {
+    sink: Arc<dyn EventSink>,
+    session_id: Uuid,
+    last_emit: Arc<std::sync::Mutex<Option<Instant>>>,
+}
+
+impl VerifyProgress {
+    pub fn new(sink: Arc<dyn EventSink>, session_id: Uuid) -> Self {
+        Self {
+            sink,
+            session_id,
+            last_emit: Arc::default(),
+        }
+    }
+
+    /// 单个文件的校验进度回调（参数为已校验字节数），按 [`THROTTLE_INTERVAL`] 节流
+    pub fn for_file(
+        &self,
+        file_id: u32,
+        name: &str,
+        total_bytes: u64,
+    ) -> impl Fn(u64) + Send + 'static {
+        let this = self.clone();
+        let name = name.to_owned();
+        move |bytes_hashed| {
+            let now = Instant::now();
+            let Ok(mut last_emit) = this.last_emit.lock() else {
+                return;
+            };
+            if last_emit.is_some_and(|last| now.duration_since(last) < THROTTLE_INTERVAL) {
+                return;
+            }
+            *last_emit = Some(now);
+            this.sink.emit_verifying(&TransferVerifyingEvent {
+                session_id: this.session_id,
+                file_id,
+                name: name.clone(),
+                bytes_hashed,
+                total_bytes,
+            });
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -101,6 +198,55 @@ pub struct TransferResumedFileInfo {
     pub is_directory: bool,
 }
 
+/// 多个并发传输的总体进度（TransferManager 定时推送，与各会话的进度事件并存）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverallProgressEvent {
+    /// 活跃会话数（发送 + 接收）
+    pub active_sessions: usize,
+    /// 本次启动以来已完成的会话数
+    pub completed_sessions: u32,
+    /// 本次启动以来失败的会话数
+    pub failed_sessions: u32,
+    pub total_bytes: u64,
+    pub transferred_bytes: u64,
+    /// 合并速度（各会话速度之和，字节/秒）
+    pub speed: f64,
+    pub eta: Option<f64>,
+}
+
+impl OverallProgressEvent {
+    /// 汇总各活跃会话的进度快照
+    pub fn aggregate(
+        snapshots: &[ProgressSnapshot],
+        completed_sessions: u32,
+        failed_sessions: u32,
+    ) -> Self {
+        let total_bytes = snapshots.iter().map(|s| s.total_bytes).sum::<u64>();
+        let transferred_bytes = snapshots.iter().map(|s| s.transferred_bytes).sum::<u64>();
+        let speed = snapshots.iter().map(|s| s.speed).sum::<f64>();
+        let eta = (speed >= 1.0)
+            .then(|| total_bytes.saturating_sub(transferred_bytes) as f64 / speed);
+        Self {
+            active_sessions: snapshots.len(),
+            completed_sessions,
+            failed_sessions,
+            total_bytes,
+            transferred_bytes,
+            speed,
+            eta,
+        }
+    }
+}
+
+/// 单个会话的进度快照（汇总总体进度用）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressSnapshot {
+    pub total_bytes: u64,
+    pub transferred_bytes: u64,
+    pub speed: f64,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransferDbErrorEvent {
@@ -108,6 +254,16 @@ pub struct TransferDbErrorEvent {
     pub message: String,
 }
 
+/// 所选保存目录不可用、已回退到默认位置
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveLocationFallbackEvent {
+    pub session_id: Uuid,
+    /// 实际使用的保存位置
+    pub save_location: SaveLocation,
+    pub reason: String,
+}
+
 pub struct FileDesc {
     pub file_id: u32,
     pub name: String,
@@ -125,6 +281,7 @@ pub struct ProgressTracker {
     started_at: Instant,
     samples: VecDeque<(Instant, u64)>,
     last_emit: Option<Instant>,
+    connection: Option<ConnectionType>,
 }
 
 /// 节流间隔
@@ -150,7 +307,22 @@ impl ProgressTracker {
             started_at: Instant::now(),
             samples: VecDeque::new(),
             last_emit: None,
+            connection: None,
+        }
+    }
+
+    /// 当前连接类型
+    pub fn connection(&self) -> Option<&ConnectionType> {
+        self.connection.as_ref()
+    }
+
+    /// 设置与对端的连接类型（随进度事件推送），返回是否发生变化
+    pub fn set_connection(&mut self, connection: Option<ConnectionType>) -> bool {
+        if self.connection == connection {
+            return false;
         }
+        self.connection = connection;
+        true
     }
 
     /// 初始化 per-file 进度，支持断点续传恢复状态。
@@ -194,22 +366,29 @@ impl ProgressTracker {
     }
 
     /// 累加分块进度。首次调用时将文件标记为 Transferring，完成时标记为 Completed。
-    pub fn update_file_chunk(&mut self, file_id: u32, chunk_bytes: u64) {
-        if let Some(f) = self.files.iter_mut().find(|f| f.file_id == file_id) {
-            if f.status == FileTransferStatus::Completed {
-                return;
-            }
-            if f.status == FileTransferStatus::Pending {
-                f.status = FileTransferStatus::Transferring;
-            }
-            f.transferred += chunk_bytes;
-            f.chunks_done += 1;
-            if f.chunks_done >= f.total_chunks {
-                f.status = FileTransferStatus::Completed;
-                f.transferred = f.size;
-                self.completed_files += 1;
-            }
+    ///
+    /// 同一 chunk 只能计入一次，由调用方以 [`AtomicBitmap`](crate::transfer::bitmap::AtomicBitmap)
+    /// 去重后再调用。返回本次是否计入了进度（文件已完成 / 越界的 chunk 返回 false，
+    /// 调用方不应再累加字节数）。
+    pub fn update_file_chunk(&mut self, file_id: u32, chunk_index: u32, chunk_bytes: u64) -> bool {
+        let Some(f) = self.files.iter_mut().find(|f| f.file_id == file_id) else {
+            return false;
+        };
+        if f.status == FileTransferStatus::Completed || chunk_index >= f.total_chunks {
+            return false;
         }
+
+        if f.status == FileTransferStatus::Pending {
+            f.status = FileTransferStatus::Transferring;
+        }
+        f.transferred += chunk_bytes;
+        f.chunks_done += 1;
+        if f.chunks_done >= f.total_chunks {
+            f.status = FileTransferStatus::Completed;
+            f.transferred = f.size;
+            self.completed_files += 1;
+        }
+        true
     }
 
     pub fn set_file_transferring(&mut self, file_id: u32) {
@@ -220,10 +399,6 @@ impl ProgressTracker {
         }
     }
 
-    pub fn transferred_bytes(&self) -> u64 {
-        self.transferred_bytes
-    }
-
     /// 获取每个文件的已传输进度
     ///
     /// 返回 `Vec<(file_id, chunks_done, transferred_bytes)>`
@@ -271,12 +446,31 @@ impl ProgressTracker {
         self.started_at.elapsed().as_millis() as u64
     }
 
-    pub fn emit_progress(&mut self, app: &AppHandle) {
+    /// 当前进度快照（总体进度汇总用）
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            total_bytes: self.total_bytes,
+            transferred_bytes: self.transferred_bytes,
+            speed: self.speed(),
+        }
+    }
+
+    pub fn emit_progress(&mut self, sink: &dyn EventSink) {
         let now = Instant::now();
         if self.last_emit.is_some_and(|last| now.duration_since(last) < THROTTLE_INTERVAL) {
             return;
         }
-        self.last_emit = Some(now);
+        self.emit_progress_now(sink);
+    }
+
+    /// 所有文件均已完成
+    pub fn is_complete(&self) -> bool {
+        self.completed_files >= self.total_files
+    }
+
+    /// 跳过节流立即推送进度（连接类型变化等需要即时反馈的场景）
+    pub fn emit_progress_now(&mut self, sink: &dyn EventSink) {
+        self.last_emit = Some(Instant::now());
 
         let event = TransferProgressEvent {
             session_id: self.session_id,
@@ -288,14 +482,17 @@ impl ProgressTracker {
             speed: self.speed(),
             eta: self.eta(),
             files: self.files.clone(),
+            connection: self.connection.clone(),
         };
-        let _ = app.emit(events::TRANSFER_PROGRESS, &event);
+        sink.emit_progress(&event);
     }
 
     pub fn emit_complete(
         &self,
-        app: &AppHandle,
+        sink: &dyn EventSink,
         save_location: Option<SaveLocation>,
+        file_uris: Vec<serde_json::Value>,
+        file_paths: Vec<String>,
     ) {
         let event = TransferCompleteEvent {
             session_id: self.session_id,
@@ -303,16 +500,137 @@ impl ProgressTracker {
             total_bytes: self.transferred_bytes,
             elapsed_ms: self.elapsed_ms(),
             save_location,
+            file_uris,
+            file_paths,
+            final_connection: self.connection.clone(),
         };
-        let _ = app.emit(events::TRANSFER_COMPLETE, &event);
+        sink.emit_complete(&event);
     }
 
-    pub fn emit_failed(&self, app: &AppHandle, error: String) {
-        let event = TransferFailedEvent {
-            session_id: self.session_id,
-            direction: self.direction,
-            error,
-        };
-        let _ = app.emit(events::TRANSFER_FAILED, &event);
+    pub fn emit_failed(&self, sink: &dyn EventSink, error: String) {
+        self.emit_failed_with(sink, error, None, None);
+    }
+
+    /// 发射失败事件，附带已完成的文件、出错的文件和保存位置
+    pub fn emit_failed_with(
+        &self,
+        sink: &dyn EventSink,
+        error: String,
+        failed_file_id: Option<u32>,
+        save_location: Option<SaveLocation>,
+    ) {
+        let completed_files = self
+            .files
+            .iter()
+            .filter(|f| {
+                f.status == FileTransferStatus::Completed && Some(f.file_id) != failed_file_id
+            })
+            .cloned()
+            .collect();
+        let failed_file = failed_file_id
+            .and_then(|id| self.files.iter().find(|f| f.file_id == id))
+            .cloned();
+        sink.emit_failed(&TransferFailedEvent {
+            completed_files,
+            failed_file,
+            save_location,
+            ..TransferFailedEvent::new(self.session_id, self.direction, error)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::context::testing::RecordingSink;
+
+    #[test]
+    fn test_verify_progress_throttled_per_session() {
+        let sink = Arc::new(RecordingSink::default());
+        let session_id = Uuid::new_v4();
+        let verify = VerifyProgress::new(sink.clone(), session_id);
+
+        let first = verify.for_file(0, "a.bin", 300);
+        first(100);
+        first(300);
+        // 同一会话的下一个文件共用节流窗口
+        verify.for_file(1, "b.bin", 10)(10);
+
+        let events = sink.verifying.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].session_id, session_id);
+        assert_eq!((events[0].file_id, events[0].bytes_hashed), (0, 100));
+        drop(events);
+
+        std::thread::sleep(THROTTLE_INTERVAL);
+        verify.for_file(1, "b.bin", 10)(10);
+        assert_eq!(sink.verifying.lock().unwrap()[1].name, "b.bin");
+    }
+
+    #[test]
+    fn test_aggregate_overall_progress() {
+        let snapshots = [
+            ProgressSnapshot {
+                total_bytes: 1000,
+                transferred_bytes: 400,
+                speed: 100.0,
+            },
+            ProgressSnapshot {
+                total_bytes: 2000,
+                transferred_bytes: 500,
+                speed: 200.0,
+            },
+            // 尚未开始拉取的会话：只计入总量
+            ProgressSnapshot {
+                total_bytes: 600,
+                ..Default::default()
+            },
+        ];
+        let event = OverallProgressEvent::aggregate(&snapshots, 2, 1);
+
+        assert_eq!(event.active_sessions, 3);
+        assert_eq!(event.completed_sessions, 2);
+        assert_eq!(event.failed_sessions, 1);
+        assert_eq!(event.total_bytes, 3600);
+        assert_eq!(event.transferred_bytes, 900);
+        assert_eq!(event.speed, 300.0);
+        assert_eq!(event.eta, Some(9.0));
+    }
+
+    #[test]
+    fn test_update_file_chunk() {
+        let size = crate::file_source::CHUNK_SIZE as u64 + 10;
+        let mut tracker = ProgressTracker::new(Uuid::new_v4(), TransferDirection::Send, size, 1);
+        tracker.init_files_with_resume(
+            &[FileDesc {
+                file_id: 0,
+                name: "a.bin".into(),
+                size,
+            }],
+            &std::collections::HashMap::new(),
+        );
+
+        // 乱序拉取
+        assert!(tracker.update_file_chunk(0, 1, 10));
+        assert_eq!(tracker.get_file_progress(), vec![(0, 1, 10)]);
+        assert_eq!(tracker.completed_files, 0);
+
+        // 越界 chunk 与未知文件不计入
+        assert!(!tracker.update_file_chunk(0, 7, 10));
+        assert!(!tracker.update_file_chunk(3, 0, 10));
+
+        assert!(tracker.update_file_chunk(0, 0, size - 10));
+        // 文件完成后不再计入
+        assert!(!tracker.update_file_chunk(0, 0, size - 10));
+        assert_eq!(tracker.get_file_progress(), vec![(0, 2, size)]);
+        assert_eq!(tracker.completed_files, 1);
+        assert_eq!(tracker.files[0].status, FileTransferStatus::Completed);
+    }
+
+    #[test]
+    fn test_aggregate_idle() {
+        let event = OverallProgressEvent::aggregate(&[], 0, 0);
+        assert_eq!(event.active_sessions, 0);
+        assert_eq!(event.eta, None);
     }
 }
@@ -0,0 +1,421 @@
+//! 发送方顺序预读缓存
+//!
+//! 接收方按文件顺序并发拉取分块（同一文件最多 `MAX_CONCURRENT_CHUNKS` 个请求同时在途），
+//! 若每个请求各自打开文件、seek、读取一块，磁盘看到的是近似随机的访问，机械硬盘上会很慢。
+//!
+//! 某个文件的第一个分块被请求时，为该文件启动一个后台读取任务，从下一块开始顺序读入缓存：
+//! - 请求命中缓存时直接取走，不再访问磁盘
+//! - 请求的分块正在预读窗口内时等待读取任务读到它
+//! - 其他情况（重试已取走的分块、窗口外的跳跃请求、总缓存已满）直接读取磁盘
+//!
+//! 每个文件最多领先 [`READ_AHEAD_WINDOW_CHUNKS`] 块，整个会话的缓存不超过
+//! [`READ_AHEAD_MAX_BYTES`]；分块被取走即释放，落后请求位置一个窗口以上的分块被淘汰。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::file_source::CHUNK_SIZE;
+use crate::transfer::context::BoxFuture;
+use crate::{AppError, AppResult};
+
+/// 每个文件预读窗口的分块数（8 MB）
+pub const READ_AHEAD_WINDOW_CHUNKS: u32 = 32;
+
+/// 单个发送会话的预读缓存上限（32 MB，约 4 个文件同时预读）
+pub const READ_AHEAD_MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// 读取指定分块（生产环境为 `FileSource::read_chunk`）
+pub type ReadChunkFn = Arc<dyn Fn(u32) -> BoxFuture<'static, AppResult<Vec<u8>>> + Send + Sync>;
+
+/// 单个文件的预读状态
+#[derive(Default)]
+struct FileWindow {
+    /// 已读入、尚未被请求取走的分块
+    chunks: BTreeMap<u32, Vec<u8>>,
+    /// 读取任务下一个要读的分块
+    next_index: u32,
+    /// 读取任务是否在运行
+    active: bool,
+}
+
+#[derive(Default)]
+struct CacheState {
+    files: HashMap<u32, FileWindow>,
+    /// 所有文件已缓存的字节数
+    cached_bytes: usize,
+}
+
+impl CacheState {
+    fn is_full(&self) -> bool {
+        self.cached_bytes + CHUNK_SIZE > READ_AHEAD_MAX_BYTES
+    }
+
+    /// 丢弃读取任务已结束的文件中剩余的分块，返回是否释放了空间
+    fn evict_inactive(&mut self) -> bool {
+        let mut freed = 0;
+        for window in self.files.values_mut().filter(|w| !w.active) {
+            freed += window.chunks.values().map(Vec::len).sum::<usize>();
+            window.chunks.clear();
+        }
+        self.cached_bytes -= freed;
+        freed > 0
+    }
+}
+
+impl FileWindow {
+    /// 淘汰落后请求位置一个窗口以上的分块（断点续传时不会被请求的块），返回释放的字节数
+    fn evict_stale(&mut self, chunk_index: u32) -> usize {
+        let mut freed = 0;
+        self.chunks.retain(|&index, chunk| {
+            let keep = index + READ_AHEAD_WINDOW_CHUNKS > chunk_index;
+            if !keep {
+                freed += chunk.len();
+            }
+            keep
+        });
+        freed
+    }
+}
+
+/// 预读任务与请求共享的状态
+#[derive(Default)]
+struct Shared {
+    state: Mutex<CacheState>,
+    /// 分块读入 / 取走 / 读取任务结束时通知
+    changed: Notify,
+}
+
+/// 请求到达时的处理方式
+enum Lookup {
+    Hit(Vec<u8>),
+    Wait,
+    ReadDirect { start_reader: bool },
+}
+
+/// 发送会话的预读缓存（随会话销毁，销毁时停止所有读取任务）
+pub struct ReadAheadCache {
+    shared: Arc<Shared>,
+    stop: CancellationToken,
+}
+
+impl ReadAheadCache {
+    /// `cancel` 为会话的取消令牌，会话取消时读取任务随之停止
+    pub fn new(cancel: &CancellationToken) -> Self {
+        Self {
+            shared: Arc::default(),
+            stop: cancel.child_token(),
+        }
+    }
+
+    /// 读取分块：优先从预读缓存取，必要时启动该文件的顺序读取任务
+    pub async fn read_chunk(
+        &self,
+        file_id: u32,
+        total_chunks: u32,
+        chunk_index: u32,
+        read: ReadChunkFn,
+    ) -> AppResult<Vec<u8>> {
+        loop {
+            // 先注册通知再检查状态，避免检查后、等待前的通知丢失
+            let notified = self.shared.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            match self.lookup(file_id, chunk_index) {
+                Lookup::Hit(data) => {
+                    // 腾出了缓存空间，唤醒可能在等待的读取任务
+                    self.shared.changed.notify_waiters();
+                    return Ok(data);
+                }
+                Lookup::Wait => {
+                    tokio::select! {
+                        _ = notified => continue,
+                        _ = self.stop.cancelled() => return Err(AppError::Cancelled),
+                    }
+                }
+                Lookup::ReadDirect { start_reader } => {
+                    if start_reader {
+                        self.spawn_reader(file_id, total_chunks, read.clone());
+                    }
+                    return read(chunk_index).await;
+                }
+            }
+        }
+    }
+
+    /// 当前缓存的字节数
+    pub fn cached_bytes(&self) -> usize {
+        self.shared.state.lock().map_or(0, |s| s.cached_bytes)
+    }
+
+    fn lookup(&self, file_id: u32, chunk_index: u32) -> Lookup {
+        let Ok(mut guard) = self.shared.state.lock() else {
+            return Lookup::ReadDirect {
+                start_reader: false,
+            };
+        };
+        let state = &mut *guard;
+        if state.is_full() && state.evict_inactive() {
+            self.shared.changed.notify_waiters();
+        }
+        let full = state.is_full();
+
+        let Some(window) = state.files.get_mut(&file_id) else {
+            // 该文件的第一个请求：从下一块开始预读
+            state.files.insert(
+                file_id,
+                FileWindow {
+                    next_index: chunk_index + 1,
+                    active: true,
+                    ..Default::default()
+                },
+            );
+            return Lookup::ReadDirect { start_reader: true };
+        };
+
+        let freed = window.evict_stale(chunk_index);
+        if freed > 0 {
+            // 窗口腾出空间，唤醒可能在等待的读取任务
+            state.cached_bytes -= freed;
+            self.shared.changed.notify_waiters();
+        }
+
+        if let Some(data) = window.chunks.remove(&chunk_index) {
+            state.cached_bytes -= data.len();
+            return Lookup::Hit(data);
+        }
+
+        // 已被取走（重试请求）或总缓存已满：直接读取
+        //
+        // 总缓存只会因读入分块而变满，读入后会通知所有等待者重新检查，不会一直等待
+        if chunk_index < window.next_index || full {
+            return Lookup::ReadDirect {
+                start_reader: false,
+            };
+        }
+        if window.active && chunk_index < window.next_index + READ_AHEAD_WINDOW_CHUNKS {
+            return Lookup::Wait;
+        }
+
+        // 请求跳到读取位置之后：从该位置之后继续预读
+        window.next_index = chunk_index + 1;
+        let start_reader = !window.active;
+        window.active = true;
+        Lookup::ReadDirect { start_reader }
+    }
+
+    fn spawn_reader(&self, file_id: u32, total_chunks: u32, read: ReadChunkFn) {
+        let shared = Arc::clone(&self.shared);
+        let stop = self.stop.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = run_reader(&shared, file_id, total_chunks, read) => {}
+                _ = stop.cancelled() => {}
+            }
+            if let Ok(mut state) = shared.state.lock() {
+                if let Some(window) = state.files.get_mut(&file_id) {
+                    window.active = false;
+                }
+            }
+            shared.changed.notify_waiters();
+        });
+    }
+}
+
+impl Drop for ReadAheadCache {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+/// 顺序读取一个文件的分块，窗口或总缓存已满时等待分块被取走
+async fn run_reader(shared: &Shared, file_id: u32, total_chunks: u32, read: ReadChunkFn) {
+    loop {
+        let notified = shared.changed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let index = {
+            let Ok(state) = shared.state.lock() else {
+                return;
+            };
+            let Some(window) = state.files.get(&file_id) else {
+                return;
+            };
+            if window.next_index >= total_chunks {
+                return;
+            }
+            let window_full = window.chunks.len() >= READ_AHEAD_WINDOW_CHUNKS as usize;
+            if window_full || state.is_full() {
+                None
+            } else {
+                Some(window.next_index)
+            }
+        };
+        let Some(index) = index else {
+            notified.await;
+            continue;
+        };
+
+        let data = match read(index).await {
+            Ok(data) => data,
+            Err(e) => {
+                // 读取失败交给请求方直接读取时再报错
+                debug!("预读失败: file_id={}, chunk={}: {}", file_id, index, e);
+                return;
+            }
+        };
+
+        let Ok(mut guard) = shared.state.lock() else {
+            return;
+        };
+        let state = &mut *guard;
+        if let Some(window) = state.files.get_mut(&file_id) {
+            // 读取期间请求跳到了别处时丢弃本块
+            if window.next_index == index {
+                state.cached_bytes += data.len();
+                window.chunks.insert(index, data);
+                window.next_index = index + 1;
+            }
+        }
+        drop(guard);
+        shared.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::file_source::{calc_total_chunks, FileSource};
+
+    /// 记录每次读取的分块序号
+    fn recording_reader(
+        read: impl Fn(u32) -> BoxFuture<'static, AppResult<Vec<u8>>> + Send + Sync + 'static,
+    ) -> (ReadChunkFn, Arc<Mutex<Vec<u32>>>) {
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let recorded = reads.clone();
+        let read_fn: ReadChunkFn = Arc::new(move |index| {
+            recorded.lock().unwrap().push(index);
+            read(index)
+        });
+        (read_fn, reads)
+    }
+
+    #[tokio::test]
+    async fn test_large_file_reads_are_sequential() {
+        const TOTAL_CHUNKS: u32 = 160;
+        const CONCURRENCY: usize = 8;
+
+        let dir = std::env::temp_dir().join("swarmdrop_test_read_ahead");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("large.bin");
+        let size = TOTAL_CHUNKS as u64 * CHUNK_SIZE as u64 - 123;
+        let content: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &content).unwrap();
+        assert_eq!(calc_total_chunks(size), TOTAL_CHUNKS);
+
+        let source = FileSource::Path { path: path.clone() };
+        let (read, reads) = recording_reader(move |index| {
+            let source = source.clone();
+            Box::pin(async move { source.read_chunk(size, index, None).await })
+        });
+
+        // 与接收方一样：多个 worker 按顺序领取分块并发请求
+        let cache = Arc::new(ReadAheadCache::new(&CancellationToken::new()));
+        let next = Arc::new(AtomicU32::new(0));
+        let mut workers = Vec::new();
+        for _ in 0..CONCURRENCY {
+            let (cache, next, read) = (cache.clone(), next.clone(), read.clone());
+            workers.push(tokio::spawn(async move {
+                let mut received = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= TOTAL_CHUNKS {
+                        return received;
+                    }
+                    let data = cache
+                        .read_chunk(0, TOTAL_CHUNKS, index, read.clone())
+                        .await
+                        .unwrap();
+                    received.push((index, data));
+                }
+            }));
+        }
+        let mut received = Vec::new();
+        for worker in workers {
+            received.extend(worker.await.unwrap());
+        }
+        received.sort_by_key(|(index, _)| *index);
+        let data: Vec<u8> = received.into_iter().flat_map(|(_, d)| d).collect();
+        assert!(data == content, "数据不一致");
+
+        // 每块只读一次；除首块由请求直接读取外，其余由预读任务按顺序读取
+        let reads = reads.lock().unwrap().clone();
+        assert_eq!(reads.len(), TOTAL_CHUNKS as usize);
+        let sequential: Vec<u32> = reads.iter().copied().filter(|&i| i != 0).collect();
+        assert_eq!(sequential, (1..TOTAL_CHUNKS).collect::<Vec<_>>());
+        assert_eq!(cache.cached_bytes(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_window_limit_and_eviction() {
+        let (read, reads) =
+            recording_reader(|index| Box::pin(async move { Ok(vec![index as u8; CHUNK_SIZE]) }));
+        let cache = ReadAheadCache::new(&CancellationToken::new());
+        let read_count = || reads.lock().unwrap().len();
+
+        // 首块请求后预读任务最多领先一个窗口
+        cache.read_chunk(0, 1000, 0, read.clone()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(read_count(), 1 + READ_AHEAD_WINDOW_CHUNKS as usize);
+        assert_eq!(
+            cache.cached_bytes(),
+            READ_AHEAD_WINDOW_CHUNKS as usize * CHUNK_SIZE
+        );
+
+        // 跳过的块（如断点续传已完成的块）落后请求位置一个窗口后被淘汰，预读继续推进
+        let hit = cache.read_chunk(0, 1000, 40, read.clone()).await.unwrap();
+        assert_eq!(hit[0], 40);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(read_count(), 1 + READ_AHEAD_WINDOW_CHUNKS as usize + 9);
+        assert!(cache.cached_bytes() <= READ_AHEAD_WINDOW_CHUNKS as usize * CHUNK_SIZE);
+
+        // 命中缓存不读磁盘
+        let count = read_count();
+        let hit = cache.read_chunk(0, 1000, 20, read.clone()).await.unwrap();
+        assert_eq!(hit[0], 20);
+        // 已淘汰的块重新请求时直接读取
+        let reread = cache.read_chunk(0, 1000, 1, read.clone()).await.unwrap();
+        assert_eq!(reread[0], 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // 取走 20 后预读任务补读 1 块，加上直接读取的第 1 块
+        assert_eq!(read_count(), count + 2);
+
+        // 多个文件同时预读不超过总上限
+        for file_id in 1..8 {
+            cache
+                .read_chunk(file_id, 1000, 0, read.clone())
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(cache.cached_bytes() <= READ_AHEAD_MAX_BYTES);
+
+        // 缓存销毁后读取任务停止
+        drop(cache);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let count = read_count();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(read_count(), count);
+    }
+}
@@ -4,30 +4,34 @@
 //! 文件写入通过 [`PartFile`](crate::file_sink::PartFile) 的 OOP 方法完成，
 //! 加密使用 [`TransferCrypto`]。
 //! 使用 Semaphore 控制并发度（8 并发），CancellationToken 支持取消。
+//! 事件输出、网络请求和数据库通过 [`SessionContext`] 注入，测试中可替换为内存实现。
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use sea_orm::DatabaseConnection;
 use swarm_p2p_core::libp2p::PeerId;
-use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::{watch, Mutex, Semaphore};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use crate::file_sink::{FileSink, PartFile};
+use crate::device::ConnectionType;
+use crate::file_sink::{FileSink, PartFile, ResumeState};
 use crate::file_source::calc_total_chunks;
-use crate::protocol::{
-    AppNetClient, AppRequest, AppResponse, FileInfo, TransferRequest, TransferResponse,
+use crate::protocol::{AppRequest, AppResponse, FileInfo, TransferRequest, TransferResponse};
+use crate::transfer::bitmap::{bytes_from_bitmap, count_completed_in_bitmap, AtomicBitmap};
+use crate::transfer::context::SessionContext;
+use crate::transfer::crypto::{use_plaintext, TransferCrypto};
+use crate::transfer::progress::{
+    FileDesc, ProgressSnapshot, ProgressTracker, TransferDbErrorEvent, TransferDirection,
+    TransferReconnectingEvent, VerifyProgress,
 };
-use crate::transfer::crypto::TransferCrypto;
-use crate::transfer::progress::{FileDesc, ProgressTracker, TransferDbErrorEvent, TransferDirection};
 use crate::{AppError, AppResult};
 
 /// 最大并发拉取数
-const MAX_CONCURRENT_CHUNKS: usize = 8;
+pub(crate) const MAX_CONCURRENT_CHUNKS: usize = 8;
 
 /// 单个分块最大重试次数
 const MAX_CHUNK_RETRIES: u32 = 3;
@@ -35,6 +39,17 @@ const MAX_CHUNK_RETRIES: u32 = 3;
 /// 重试基础延迟
 const RETRY_DELAY_BASE_MS: u64 = 500;
 
+/// 连接中断后的重连窗口（秒），窗口内重连失败才判定传输失败
+const RECONNECT_WINDOW_SECS: u64 = 30;
+
+/// 重连失败后再次拨号的间隔
+const RECONNECT_RETRY_INTERVAL_MS: u64 = 2000;
+
+/// 连续失败多少次分块请求（跨所有并发任务，任一成功即清零）判定连接已断开
+///
+/// 为并发数的两倍：一次网络抖动会让所有进行中的请求同时失败，不应立即判定断开。
+const MAX_CONSECUTIVE_CHUNK_FAILURES: u32 = MAX_CONCURRENT_CHUNKS as u32 * 2;
+
 /// 每完成多少个 chunk 刷写一次 bitmap checkpoint 到 DB
 const CHECKPOINT_INTERVAL: u32 = 10;
 
@@ -46,16 +61,20 @@ pub struct ReceiveSession {
     pub peer_id: PeerId,
     /// 文件列表
     files: Vec<FileInfo>,
+    /// 需要重建的空目录（相对路径）
+    directories: Vec<String>,
     /// 总大小
     total_size: u64,
     /// 文件写入目标（工厂：创建 PartFile + 权限检查）
     sink: FileSink,
-    /// Tauri AppHandle（用于事件发射和 Android 操作）
-    app: AppHandle,
+    /// 外部依赖（事件输出、网络请求、数据库）
+    ctx: SessionContext,
     /// 加密器
     crypto: Arc<TransferCrypto>,
-    /// 网络客户端
-    client: AppNetClient,
+    /// 双方已协商局域网明文（仅在当前为局域网直连时接受明文分块）
+    plaintext_lan: bool,
+    /// 创建 .part 文件时按文件大小预分配
+    preallocate: bool,
     /// 取消令牌
     cancel_token: CancellationToken,
     /// 已创建的临时文件（用于取消时清理）
@@ -64,6 +83,20 @@ pub struct ReceiveSession {
     initial_bitmaps: HashMap<u32, Vec<u8>>,
     /// 传输完成信号（start_pulling 结束后发送 true）
     finished_tx: watch::Sender<bool>,
+    /// 重连互斥锁：并发分块任务同一时刻只由一个任务负责拨号
+    reconnect_lock: Mutex<()>,
+    /// 连接代次：每次重连成功 +1，用于判断失败的请求是否已被他人重连覆盖
+    connection_epoch: AtomicU64,
+    /// 重连窗口内未能恢复连接（之后的请求失败不再重连）
+    reconnect_failed: AtomicBool,
+    /// 重连窗口
+    reconnect_window: Duration,
+    /// 连续失败的分块请求数（任一请求成功或重连成功时清零）
+    consecutive_failures: AtomicU32,
+    /// 与对端的连接类型（随进度事件推送，事件循环在连接变化时更新）
+    connection: std::sync::Mutex<Option<ConnectionType>>,
+    /// 进度追踪器（run_transfer 创建后写入，供连接变化时强制推送进度）
+    progress: OnceLock<Arc<Mutex<ProgressTracker>>>,
 }
 
 impl ReceiveSession {
@@ -72,11 +105,11 @@ impl ReceiveSession {
         session_id: Uuid,
         peer_id: PeerId,
         files: Vec<FileInfo>,
+        directories: Vec<String>,
         total_size: u64,
         sink: FileSink,
         key: &[u8; 32],
-        client: AppNetClient,
-        app: AppHandle,
+        ctx: SessionContext,
         initial_bitmaps: HashMap<u32, Vec<u8>>,
     ) -> Self {
         let (finished_tx, _) = watch::channel(false);
@@ -84,18 +117,87 @@ impl ReceiveSession {
             session_id,
             peer_id,
             files,
+            directories,
             total_size,
             sink,
-            app,
+            ctx,
             crypto: Arc::new(TransferCrypto::new(key)),
-            client,
+            plaintext_lan: false,
+            preallocate: true,
             cancel_token: CancellationToken::new(),
             created_parts: Mutex::new(Vec::new()),
             initial_bitmaps,
             finished_tx,
+            reconnect_lock: Mutex::new(()),
+            connection_epoch: AtomicU64::new(0),
+            reconnect_failed: AtomicBool::new(false),
+            reconnect_window: Duration::from_secs(RECONNECT_WINDOW_SECS),
+            consecutive_failures: AtomicU32::new(0),
+            connection: std::sync::Mutex::new(None),
+            progress: OnceLock::new(),
+        }
+    }
+
+    /// 记录与对端的连接类型（随进度事件推送）
+    pub fn with_connection(mut self, connection: Option<ConnectionType>) -> Self {
+        if let Ok(c) = self.connection.get_mut() {
+            *c = connection;
+        }
+        self
+    }
+
+    /// 双方已在 Offer 中协商局域网明文
+    pub fn with_plaintext_lan(mut self, plaintext_lan: bool) -> Self {
+        self.plaintext_lan = plaintext_lan;
+        self
+    }
+
+    /// 是否预分配 .part 文件大小（默认开启，保存位置在 FAT32 / exFAT 等文件系统上时可关闭）
+    pub fn with_preallocation(mut self, preallocate: bool) -> Self {
+        self.preallocate = preallocate;
+        self
+    }
+
+    /// 当前连接类型
+    pub fn connection(&self) -> Option<ConnectionType> {
+        self.connection.lock().ok().and_then(|c| c.clone())
+    }
+
+    /// 当前进度快照（总体进度汇总用），尚未开始拉取时只计入总大小
+    pub async fn progress_snapshot(&self) -> ProgressSnapshot {
+        match self.progress.get() {
+            Some(progress) => progress.lock().await.snapshot(),
+            None => ProgressSnapshot {
+                total_bytes: self.total_size,
+                ..Default::default()
+            },
         }
     }
 
+    /// 传输中连接类型变化（打洞成功 / 回落中继）：更新并立即推送一次进度
+    ///
+    /// 会话已结束或所有文件已完成时只记录连接类型，不再推送进度。
+    pub async fn update_connection(&self, connection: Option<ConnectionType>) {
+        match self.connection.lock() {
+            Ok(mut c) if *c != connection => *c = connection.clone(),
+            _ => return,
+        }
+        if let Some(progress) = self.progress.get() {
+            let mut p = progress.lock().await;
+            let finished = *self.finished_tx.borrow() || p.is_complete();
+            if p.set_connection(connection) && !finished {
+                p.emit_progress_now(self.ctx.events.as_ref());
+            }
+        }
+    }
+
+    /// 覆盖重连窗口（测试中缩短等待）
+    #[cfg(test)]
+    fn with_reconnect_window(mut self, window: Duration) -> Self {
+        self.reconnect_window = window;
+        self
+    }
+
     /// 启动后台拉取任务
     ///
     /// 逐文件、并发分块拉取 → 解密 → 写入 → 校验 → 最终化。
@@ -139,7 +241,7 @@ impl ReceiveSession {
     /// 主传输逻辑，返回 true 表示正常完成，false 表示被取消
     async fn run_transfer(self: &Arc<Self>) -> AppResult<bool> {
         // Android 端在首次写入前请求存储权限
-        self.sink.ensure_permission(&self.app).await?;
+        self.sink.ensure_permission(self.ctx.app()).await?;
 
         let is_resume = !self.initial_bitmaps.is_empty();
 
@@ -171,25 +273,50 @@ impl ReceiveSession {
         tracker.init_files_with_resume(&file_descs, &resume_state);
 
         let progress = Arc::new(Mutex::new(tracker));
+        // 先登记 tracker 再读取连接类型，避免与 update_connection 竞争时丢失更新
+        let _ = self.progress.set(progress.clone());
+        progress.lock().await.set_connection(self.connection());
+
+        // 重建空目录（create_dir_all 幂等，断点续传时重复创建无副作用）
+        for dir in &self.directories {
+            if let Err(e) = self.sink.create_dir(dir).await {
+                self.fail_session(&progress, format!("创建目录失败: {dir}, {e}"), None)
+                    .await;
+                return Err(e);
+            }
+        }
 
+        let verify_progress = VerifyProgress::new(self.ctx.events.clone(), self.session_id);
+        let mut file_uris = Vec::new();
+        let mut file_paths = Vec::new();
         for file_info in &self.files {
             if self.cancel_token.is_cancelled() {
-                progress.lock().await.emit_failed(&self.app, "用户取消".into());
+                progress.lock().await.emit_failed_with(
+                    self.ctx.events.as_ref(),
+                    "用户取消".into(),
+                    None,
+                    Some(self.sink.to_save_location()),
+                );
                 return Ok(false);
             }
 
             let total_chunks = calc_total_chunks(file_info.size);
 
             // 断点续传：检查文件是否已被最终化（.part 已重命名为最终文件）
-            if is_resume {
-                let probe = self.sink.build_part_file(&file_info.relative_path, file_info.size);
-                if !probe.final_path.as_os_str().is_empty() && probe.final_path.exists() {
-                    info!(
-                        "文件已最终化，跳过: {} (file_id={})",
-                        file_info.name, file_info.file_id
-                    );
-                    continue;
-                }
+            let part_state = if is_resume {
+                self.sink
+                    .resume_state(&file_info.relative_path, file_info.size)
+                    .await
+            } else {
+                ResumeState::Missing
+            };
+            if let ResumeState::Finalized(final_path) = &part_state {
+                info!(
+                    "文件已最终化，跳过: {} (file_id={})",
+                    file_info.name, file_info.file_id
+                );
+                file_paths.push(final_path.to_string_lossy().into_owned());
+                continue;
             }
 
             let initial_bitmap = self.initial_bitmaps.get(&file_info.file_id);
@@ -198,12 +325,7 @@ impl ReceiveSession {
             // （.part 可能被校验失败删除或磁盘损坏，但 DB bitmap 仍保留）
             let effective_bitmap = if is_resume {
                 if let Some(bm) = initial_bitmap {
-                    let probe = self.sink.build_part_file(&file_info.relative_path, file_info.size);
-                    let part_ok = tokio::fs::metadata(&probe.part_path)
-                        .await
-                        .map(|m| m.len() == file_info.size)
-                        .unwrap_or(false);
-                    if part_ok {
+                    if part_state == ResumeState::PartValid {
                         Some(bm)
                     } else {
                         warn!(
@@ -211,9 +333,9 @@ impl ReceiveSession {
                             file_info.name, file_info.file_id
                         );
                         // 同步清除 DB 中的过期 bitmap
-                        if let Some(db) = self.app.try_state::<DatabaseConnection>() {
+                        if let Some(db) = &self.ctx.db {
                             let _ = crate::database::ops::reset_file_checkpoint(
-                                &db, self.session_id, file_info.file_id as i32,
+                                db, self.session_id, file_info.file_id as i32,
                             ).await;
                         }
                         None
@@ -232,16 +354,29 @@ impl ReceiveSession {
             if !is_fully_complete {
                 let mut p = progress.lock().await;
                 p.set_file_transferring(file_info.file_id);
-                p.emit_progress(&self.app);
+                p.emit_progress(self.ctx.events.as_ref());
             }
 
+            let app = self.ctx.app();
             let part_file = Arc::new(if is_resume {
                 self.sink
-                    .open_or_create_part_file(&file_info.relative_path, file_info.size, &self.app)
+                    .open_or_create_part_file(
+                        &file_info.relative_path,
+                        file_info.size,
+                        self.preallocate,
+                        file_info.mime.as_deref(),
+                        app,
+                    )
                     .await?
             } else {
                 self.sink
-                    .create_part_file(&file_info.relative_path, file_info.size, &self.app)
+                    .create_part_file(
+                        &file_info.relative_path,
+                        file_info.size,
+                        self.preallocate,
+                        file_info.mime.as_deref(),
+                        app,
+                    )
                     .await?
             });
 
@@ -260,24 +395,32 @@ impl ReceiveSession {
                 // 不删除 .part 文件——bitmap 已刷写到 DB，保留 .part 以支持断点续传。
                 // .part 文件仅在用户主动取消（cancel_receive）时才清理。
                 self.remove_created_part(&part_file).await;
-                self.fail_session(&progress, e.to_string()).await;
+                self.fail_session(&progress, e.to_string(), Some(file_info.file_id))
+                    .await;
                 return Err(e);
             }
 
+            // 全部分块已到达，进度停在 100%；大文件校验耗时较长，单独推送校验进度
+            let on_verify =
+                verify_progress.for_file(file_info.file_id, &file_info.name, file_info.size);
             match part_file
-                .verify_and_finalize(&file_info.checksum, &self.app)
+                .verify_and_finalize(&file_info.checksum, on_verify, self.ctx.app())
                 .await
             {
-                Ok(_final_path) => {
+                Ok(final_path) => {
+                    match part_file.saved_uri() {
+                        Some(uri) => file_uris.push(uri),
+                        None => file_paths.push(final_path.to_string_lossy().into_owned()),
+                    }
                     self.remove_created_part(&part_file).await;
                 }
                 Err(e) => {
                     self.remove_created_part(&part_file).await;
                     // 校验失败意味着 .part 已被删除，必须清除 DB 中的 bitmap，
                     // 否则下次恢复时跳过"已完成"的 chunk 导致数据全零→再次校验失败
-                    if let Some(db) = self.app.try_state::<DatabaseConnection>() {
+                    if let Some(db) = &self.ctx.db {
                         if let Err(e2) = crate::database::ops::reset_file_checkpoint(
-                            &db,
+                            db,
                             self.session_id,
                             file_info.file_id as i32,
                         )
@@ -290,7 +433,14 @@ impl ReceiveSession {
                         "文件校验失败: {} (file_id={})",
                         file_info.name, file_info.file_id
                     );
-                    self.fail_session(&progress, msg).await;
+                    self.fail_session(&progress, msg, Some(file_info.file_id))
+                        .await;
+                    // 通知发送方及时清理 SendSession，而不是等空闲超时
+                    let reason = format!(
+                        "校验失败: {} (file_id={})",
+                        file_info.name, file_info.file_id
+                    );
+                    self.send_cancel(&reason).await;
                     return Err(e);
                 }
             }
@@ -302,7 +452,8 @@ impl ReceiveSession {
         }
 
         let complete_result = self
-            .client
+            .ctx
+            .transport
             .send_request(
                 self.peer_id,
                 AppRequest::Transfer(TransferRequest::Complete {
@@ -323,24 +474,23 @@ impl ReceiveSession {
             }
         }
 
-        if let Some(db) = self.app.try_state::<DatabaseConnection>() {
+        if let Some(db) = &self.ctx.db {
             if let Err(e) =
-                crate::database::ops::mark_session_completed(&db, self.session_id).await
+                crate::database::ops::mark_session_completed(db, self.session_id).await
             {
                 warn!("DB 标记接收完成失败: {}", e);
-                let _ = self.app.emit(
-                    crate::events::TRANSFER_DB_ERROR,
-                    TransferDbErrorEvent {
-                        session_id: self.session_id,
-                        message: format!("保存完成状态失败: {e}"),
-                    },
-                );
+                self.ctx.events.emit_db_error(&TransferDbErrorEvent {
+                    session_id: self.session_id,
+                    message: format!("保存完成状态失败: {e}"),
+                });
             }
         }
 
         progress.lock().await.emit_complete(
-            &self.app,
+            self.ctx.events.as_ref(),
             Some(self.sink.to_save_location()),
+            file_uris,
+            file_paths,
         );
 
         Ok(true)
@@ -379,10 +529,10 @@ impl ReceiveSession {
                 )
             })
             .unwrap_or((0, 0));
-        let initial_bm = valid_bitmap
-            .cloned()
-            .unwrap_or_else(|| vec![0u8; bitmap_len]);
-        let bitmap = Arc::new(tokio::sync::Mutex::new(initial_bm));
+        let bitmap = Arc::new(match valid_bitmap {
+            Some(bm) => AtomicBitmap::from_bytes(bm),
+            None => AtomicBitmap::new(total_chunks),
+        });
         let completed_count = Arc::new(AtomicU32::new(initial_completed));
         let file_transferred = Arc::new(AtomicU64::new(initial_bytes));
 
@@ -390,10 +540,8 @@ impl ReceiveSession {
 
         for chunk_index in 0..total_chunks {
             // 跳过已完成的 chunk（断点续传）
-            if let Some(bm) = valid_bitmap {
-                if is_chunk_completed(bm, chunk_index) {
-                    continue;
-                }
+            if bitmap.is_set(chunk_index) {
+                continue;
             }
             // 等待 permit 时同时监听取消，避免取消后仍阻塞在 acquire
             let permit = tokio::select! {
@@ -429,31 +577,29 @@ impl ReceiveSession {
 
                 match result {
                     Ok(chunk_size) => {
+                        // 以 bitmap 为准：同一 chunk 重复完成（晚到的重试）只计入一次
+                        if !bitmap.set(chunk_index) {
+                            return;
+                        }
                         {
                             let mut p = progress.lock().await;
-                            p.add_bytes(chunk_size as u64);
-                            p.update_file_chunk(file_id, chunk_size as u64);
-                            p.emit_progress(&session.app);
+                            if p.update_file_chunk(file_id, chunk_index, chunk_size as u64) {
+                                p.add_bytes(chunk_size as u64);
+                            }
+                            p.emit_progress(session.ctx.events.as_ref());
                         }
 
-                        // 单次锁获取：标记 bitmap + 可选 checkpoint 克隆
-                        let checkpoint_bm = {
-                            let mut bm = bitmap.lock().await;
-                            mark_chunk_completed(&mut bm, chunk_index);
-                            file_transferred.fetch_add(chunk_size as u64, Ordering::Relaxed);
-                            let count = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
-                            if count.is_multiple_of(CHECKPOINT_INTERVAL) {
-                                Some(bm.clone())
-                            } else {
-                                None
-                            }
-                        };
+                        file_transferred.fetch_add(chunk_size as u64, Ordering::Relaxed);
+                        let count = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
+                        let checkpoint_bm = count
+                            .is_multiple_of(CHECKPOINT_INTERVAL)
+                            .then(|| bitmap.to_bytes());
 
                         if let Some(bm) = checkpoint_bm {
-                            if let Some(db) = session.app.try_state::<DatabaseConnection>() {
+                            if let Some(db) = &session.ctx.db {
                                 let bytes = file_transferred.load(Ordering::Relaxed);
                                 if let Err(e) = crate::database::ops::update_file_checkpoint(
-                                    &db,
+                                    db,
                                     session.session_id,
                                     file_id as i32,
                                     bm,
@@ -487,11 +633,11 @@ impl ReceiveSession {
         // 无论是取消、错误还是正常完成，都刷写最终 bitmap，确保已完成的 chunk 不丢失
         let has_error = first_error.lock().await.is_some();
         if self.cancel_token.is_cancelled() || has_error {
-            if let Some(db) = self.app.try_state::<DatabaseConnection>() {
-                let bm = bitmap.lock().await.clone();
+            if let Some(db) = &self.ctx.db {
+                let bm = bitmap.to_bytes();
                 let bytes = file_transferred.load(Ordering::Relaxed);
                 if let Err(e) = crate::database::ops::update_file_checkpoint(
-                    &db,
+                    db,
                     self.session_id,
                     file_info.file_id as i32,
                     bm,
@@ -537,8 +683,10 @@ impl ReceiveSession {
                 tokio::time::sleep(delay).await;
             }
 
+            let epoch = self.connection_epoch.load(Ordering::Acquire);
             let result = self
-                .client
+                .ctx
+                .transport
                 .send_request(
                     self.peer_id,
                     AppRequest::Transfer(TransferRequest::ChunkRequest {
@@ -551,44 +699,66 @@ impl ReceiveSession {
 
             match result {
                 Ok(AppResponse::Transfer(TransferResponse::Chunk {
-                    data, ..
+                    data, encrypted, ..
                 })) => {
-                    // 解密——失败时纳入重试（数据可能在传输中损坏）
-                    let plaintext = match self
-                        .crypto
-                        .decrypt_chunk(&self.session_id, file_id, chunk_index, &data)
-                    {
-                        Ok(p) => p,
-                        Err(e) => {
+                    let plaintext = if encrypted {
+                        // 解密失败意味着密钥不一致或数据被篡改（传输层已保证完整性），
+                        // 重试无法恢复，直接失败
+                        let decrypted = self.crypto.decrypt_chunk(
+                            &self.session_id,
+                            file_id,
+                            chunk_index,
+                            &data,
+                        );
+                        decrypted.map_err(|e| {
                             warn!(
-                                "解密失败，将重试: file_id={}, chunk={}, {}",
+                                "解密失败: file_id={}, chunk={}, {}",
                                 file_id, chunk_index, e
                             );
-                            last_error = Some(AppError::Transfer(format!(
-                                "解密失败: file_id={file_id}, chunk={chunk_index}, {e}"
-                            )));
-                            continue;
-                        }
+                            AppError::Crypto(format!("file_id={file_id}, chunk={chunk_index}"))
+                        })?
+                    } else if use_plaintext(self.plaintext_lan, self.connection().as_ref()) {
+                        data
+                    } else {
+                        // 未协商明文或当前不是局域网直连：拒收，重试时发送方会按新连接重新加密
+                        warn!(
+                            "拒收未加密的分块，将重试: file_id={}, chunk={}",
+                            file_id, chunk_index
+                        );
+                        last_error = Some(AppError::Transfer(format!(
+                            "拒收未加密的分块: file_id={file_id}, chunk={chunk_index}"
+                        )));
+                        self.record_chunk_failure()?;
+                        continue;
                     };
 
                     let chunk_size = plaintext.len();
 
                     // 通过 PartFile 写入分块（pwrite，并发安全）
-                    part_file.write_chunk(chunk_index, &plaintext).await?;
+                    part_file.write_chunk(chunk_index, plaintext).await?;
 
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
                     return Ok(chunk_size);
                 }
                 Ok(AppResponse::Transfer(TransferResponse::ChunkError { error, .. })) => {
                     last_error = Some(AppError::Transfer(format!(
                         "发送方报告错误: {error}"
                     )));
+                    self.record_chunk_failure()?;
                 }
                 Ok(other) => {
                     last_error = Some(AppError::Transfer(format!(
                         "意外的响应类型: {other:?}"
                     )));
+                    self.record_chunk_failure()?;
                 }
                 Err(e) => {
+                    warn!(
+                        "ChunkRequest 失败，等待重连: file_id={}, chunk={}, {}",
+                        file_id, chunk_index, e
+                    );
+                    self.record_chunk_failure()?;
+                    self.await_reconnect(epoch).await?;
                     last_error = Some(AppError::Transfer(format!(
                         "ChunkRequest 失败: {e}"
                     )));
@@ -603,15 +773,92 @@ impl ReceiveSession {
         }))
     }
 
+    /// 记录一次分块请求失败，连续失败达到上限时判定连接已断开
+    ///
+    /// 对端离开信号范围时连接可能静默中断（收不到 PeerDisconnected），
+    /// 此时直接结束传输，而不是让剩余分块逐个耗尽重试。
+    fn record_chunk_failure(&self) -> AppResult<()> {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= MAX_CONSECUTIVE_CHUNK_FAILURES {
+            warn!(
+                "连续 {} 次分块请求失败，判定连接已断开: session={}",
+                failures, self.session_id
+            );
+            return Err(AppError::Transfer(format!(
+                "连接已断开：连续 {failures} 次分块请求失败"
+            )));
+        }
+        Ok(())
+    }
+
+    /// 请求失败后等待与发送方的连接恢复
+    ///
+    /// 并发分块任务共享同一次重连：持锁的任务负责拨号，其余任务拿到锁后发现
+    /// `connection_epoch` 已变化即直接重试。窗口内始终无法重连时返回错误。
+    async fn await_reconnect(&self, seen_epoch: u64) -> AppResult<()> {
+        let _guard = self.reconnect_lock.lock().await;
+        if self.connection_epoch.load(Ordering::Acquire) != seen_epoch {
+            return Ok(());
+        }
+
+        let window_secs = self.reconnect_window.as_secs();
+        let lost = || AppError::Transfer(format!("连接中断，{window_secs} 秒内未能重连发送方"));
+        if self.reconnect_failed.load(Ordering::Acquire) {
+            return Err(lost());
+        }
+
+        self.ctx.events.emit_reconnecting(&TransferReconnectingEvent {
+            session_id: self.session_id,
+            direction: TransferDirection::Receive,
+            window_secs,
+        });
+
+        let deadline = tokio::time::Instant::now() + self.reconnect_window;
+        let retry_interval = Duration::from_millis(RECONNECT_RETRY_INTERVAL_MS);
+        loop {
+            let dial = tokio::time::timeout_at(deadline, self.ctx.transport.reconnect(self.peer_id));
+            let result = tokio::select! {
+                r = dial => r,
+                _ = self.cancel_token.cancelled() => {
+                    return Err(AppError::Transfer("传输已取消".into()));
+                }
+            };
+            match result {
+                Ok(Ok(())) => {
+                    info!("已重连发送方: session={}, peer={}", self.session_id, self.peer_id);
+                    self.connection_epoch.fetch_add(1, Ordering::AcqRel);
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Ok(Err(e)) => warn!("重连发送方失败: session={}, {}", self.session_id, e),
+                Err(_) => break,
+            }
+
+            if tokio::time::Instant::now() + retry_interval >= deadline {
+                break;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(retry_interval) => {}
+                _ = self.cancel_token.cancelled() => {
+                    return Err(AppError::Transfer("传输已取消".into()));
+                }
+            }
+        }
+
+        self.reconnect_failed.store(true, Ordering::Release);
+        Err(lost())
+    }
+
     /// 发送 Cancel 消息给发送方
-    pub async fn send_cancel(&self) {
+    pub async fn send_cancel(&self, reason: &str) {
         let _ = self
-            .client
+            .ctx
+            .transport
             .send_request(
                 self.peer_id,
                 AppRequest::Transfer(TransferRequest::Cancel {
                     session_id: self.session_id,
-                    reason: "用户取消".into(),
+                    reason: reason.into(),
                 }),
             )
             .await;
@@ -641,18 +888,31 @@ impl ReceiveSession {
     pub async fn cleanup_part_files(&self) {
         let parts = self.created_parts.lock().await;
         for part_file in parts.iter() {
-            part_file.cleanup(&self.app).await;
+            part_file.cleanup(self.ctx.app()).await;
         }
     }
 
     /// 标记会话失败：写入 DB 失败记录 + 发射失败事件
-    async fn fail_session(&self, progress: &Arc<Mutex<ProgressTracker>>, msg: String) {
-        if let Some(db) = self.app.try_state::<DatabaseConnection>() {
+    ///
+    /// 事件中附带已保存的文件和出错的文件；已最终化的文件不在 `created_parts` 中，
+    /// 后续清理不会删除它们。
+    async fn fail_session(
+        &self,
+        progress: &Arc<Mutex<ProgressTracker>>,
+        msg: String,
+        failed_file_id: Option<u32>,
+    ) {
+        if let Some(db) = &self.ctx.db {
             let _ =
-                crate::database::ops::mark_session_failed(&db, self.session_id, &msg).await;
+                crate::database::ops::mark_session_failed(db, self.session_id, &msg).await;
         }
         let p = progress.lock().await;
-        p.emit_failed(&self.app, msg);
+        p.emit_failed_with(
+            self.ctx.events.as_ref(),
+            msg,
+            failed_file_id,
+            Some(self.sink.to_save_location()),
+        );
     }
 
     /// 从跟踪列表中移除指定的 PartFile（通过 Arc 指针比较）
@@ -662,60 +922,934 @@ impl ReceiveSession {
     }
 }
 
-// ============ Bitmap 辅助函数 ============
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::AtomicUsize;
 
-/// 检查指定 chunk 是否已完成
-fn is_chunk_completed(bitmap: &[u8], chunk_index: u32) -> bool {
-    let byte_idx = (chunk_index / 8) as usize;
-    let bit_idx = chunk_index % 8;
-    byte_idx < bitmap.len() && (bitmap[byte_idx] & (1 << bit_idx)) != 0
-}
+    use entity::SaveLocation;
+
+    use super::*;
+    use crate::file_source::{path_ops, ScanContext, SymlinkPolicy, CHUNK_SIZE};
+    use crate::transfer::context::testing::{
+        test_context, LoopbackTransport, MockTransport, RecordingSink,
+    };
+    use crate::transfer::context::ChunkTransport;
+    use crate::transfer::offer::PreparedFile;
+    use crate::transfer::sender::SendSession;
+
+    const KEY: [u8; 32] = [7u8; 32];
 
-/// 标记指定 chunk 为已完成
-fn mark_chunk_completed(bitmap: &mut [u8], chunk_index: u32) {
-    let byte_idx = (chunk_index / 8) as usize;
-    let bit_idx = chunk_index % 8;
-    if byte_idx < bitmap.len() {
-        bitmap[byte_idx] |= 1 << bit_idx;
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("swarmdrop_test_recv_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
     }
-}
 
-/// 统计 bitmap 中已完成的 chunk 数（利用 popcount 加速）
-fn count_completed_in_bitmap(bitmap: &[u8], total_chunks: u32) -> u32 {
-    let full_bytes = (total_chunks / 8) as usize;
-    let remainder_bits = total_chunks % 8;
-
-    let mut count: u32 = bitmap
-        .iter()
-        .take(full_bytes)
-        .map(|b| b.count_ones())
-        .sum();
-
-    // 尾部不完整字节：仅统计有效位
-    if remainder_bits > 0 {
-        if let Some(&last_byte) = bitmap.get(full_bytes) {
-            let mask = (1u8 << remainder_bits) - 1;
-            count += (last_byte & mask).count_ones();
+    fn file_info(file_id: u32, relative_path: &str, data: &[u8]) -> FileInfo {
+        FileInfo {
+            file_id,
+            name: relative_path.rsplit('/').next().unwrap().to_string(),
+            relative_path: relative_path.to_string(),
+            size: data.len() as u64,
+            checksum: blake3::hash(data).to_hex().to_string(),
+            preview: None,
+            mime: None,
         }
     }
 
-    count
-}
+    /// 模拟发送方：ChunkRequest 返回加密分块，其余请求返回 Ack
+    fn serve(
+        session_id: Uuid,
+        contents: &HashMap<u32, Vec<u8>>,
+        request: &AppRequest,
+    ) -> AppResult<AppResponse> {
+        serve_with(session_id, contents, request, true)
+    }
 
-/// 根据 bitmap 计算已传输字节数
-fn bytes_from_bitmap(bitmap: &[u8], file_size: u64, total_chunks: u32) -> u64 {
-    if file_size == 0 || total_chunks == 0 {
-        return 0;
+    /// 模拟发送方，`encrypt` 为 false 时返回明文分块（局域网明文）
+    fn serve_with(
+        session_id: Uuid,
+        contents: &HashMap<u32, Vec<u8>>,
+        request: &AppRequest,
+        encrypt: bool,
+    ) -> AppResult<AppResponse> {
+        match request {
+            AppRequest::Transfer(TransferRequest::ChunkRequest {
+                file_id,
+                chunk_index,
+                ..
+            }) => {
+                let data = &contents[file_id];
+                let start = *chunk_index as usize * CHUNK_SIZE;
+                let end = (start + CHUNK_SIZE).min(data.len());
+                let chunk = &data[start..end];
+                let payload = if encrypt {
+                    TransferCrypto::new(&KEY)
+                        .encrypt_chunk(&session_id, *file_id, *chunk_index, chunk)
+                        .unwrap()
+                } else {
+                    chunk.to_vec()
+                };
+                Ok(AppResponse::Transfer(TransferResponse::Chunk {
+                    session_id,
+                    file_id: *file_id,
+                    chunk_index: *chunk_index,
+                    data: payload,
+                    is_last: end == data.len(),
+                    encrypted: encrypt,
+                }))
+            }
+            _ => Ok(AppResponse::Transfer(TransferResponse::Ack { session_id })),
+        }
     }
-    let chunk_size = crate::file_source::CHUNK_SIZE as u64;
-    let last_chunk_size = match file_size % chunk_size {
-        0 => chunk_size,
-        r => r,
-    };
 
-    let full_chunk_count = count_completed_in_bitmap(bitmap, total_chunks.saturating_sub(1));
-    let last_chunk_done = is_chunk_completed(bitmap, total_chunks - 1);
+    fn new_session(
+        session_id: Uuid,
+        files: Vec<FileInfo>,
+        directories: Vec<String>,
+        save_dir: PathBuf,
+        transport: Arc<dyn ChunkTransport>,
+        events: Arc<RecordingSink>,
+    ) -> Arc<ReceiveSession> {
+        let total_size = files.iter().map(|f| f.size).sum();
+        Arc::new(ReceiveSession::new(
+            session_id,
+            PeerId::random(),
+            files,
+            directories,
+            total_size,
+            FileSink::Path { save_dir },
+            &KEY,
+            test_context(transport, events),
+            HashMap::new(),
+        ))
+    }
+
+    fn sent_complete(transport: &MockTransport) -> bool {
+        transport
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| matches!(r, AppRequest::Transfer(TransferRequest::Complete { .. })))
+    }
+
+    #[tokio::test]
+    async fn test_receive_all_files() {
+        let dir = test_dir("all_files");
+        let session_id = Uuid::new_v4();
+        let big: Vec<u8> = (0..CHUNK_SIZE * 2 + 100).map(|i| (i % 251) as u8).collect();
+        let small = b"hello swarmdrop".to_vec();
+        let files = vec![
+            file_info(0, "big.bin", &big),
+            file_info(1, "sub/small.txt", &small),
+        ];
+        let contents = HashMap::from([(0, big.clone()), (1, small.clone())]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+
+        assert_eq!(std::fs::read(dir.join("big.bin")).unwrap(), big);
+        assert_eq!(std::fs::read(dir.join("sub/small.txt")).unwrap(), small);
+        assert!(!dir.join("big.bin.part").exists());
+        assert!(sent_complete(&transport));
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+        assert!(events.failed.lock().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_receive_accepted_subset() {
+        let dir = test_dir("accepted_subset");
+        let session_id = Uuid::new_v4();
+        let keep = b"keep me".to_vec();
+        let video: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| (i % 17) as u8).collect();
+        let also = b"also keep".to_vec();
+        let offered = vec![
+            file_info(0, "keep.txt", &keep),
+            file_info(1, "huge.mp4", &video),
+            file_info(2, "sub/also.txt", &also),
+        ];
+        let contents = HashMap::from([(0, keep.clone()), (1, video), (2, also.clone())]);
+
+        let files = crate::transfer::offer::select_files(&offered, &[2, 0, 99]);
+        assert_eq!(
+            files.iter().map(|f| f.file_id).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+        assert_eq!(session.total_size, (keep.len() + also.len()) as u64);
+
+        assert!(session.run_transfer().await.unwrap());
+
+        // 未选中的文件从未被请求
+        assert!(transport.requests.lock().unwrap().iter().all(|r| !matches!(
+            r,
+            AppRequest::Transfer(TransferRequest::ChunkRequest { file_id: 1, .. })
+        )));
+        assert_eq!(std::fs::read(dir.join("keep.txt")).unwrap(), keep);
+        assert_eq!(std::fs::read(dir.join("sub/also.txt")).unwrap(), also);
+        assert!(!dir.join("huge.mp4").exists());
+        assert!(!dir.join("huge.mp4.part").exists());
+        assert!(sent_complete(&transport));
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// 与 prepare 相同：遍历来源目录、流式计算 BLAKE3，返回准备好的文件与空目录
+    async fn prepare_dir(source: &Path, name: &str) -> (Vec<PreparedFile>, Vec<String>) {
+        let scan = Arc::new(ScanContext::default());
+        let entries = path_ops::enumerate_dir(source, name, SymlinkPolicy::Skip, scan)
+            .await
+            .unwrap();
+        let (dirs, entries): (Vec<_>, Vec<_>) = entries.into_iter().partition(|e| e.is_directory);
+        let mut files = Vec::new();
+        for (file_id, entry) in entries.into_iter().enumerate() {
+            let checksum = entry
+                .source
+                .compute_hash_with_progress(None, CancellationToken::new(), |_| {})
+                .await
+                .unwrap();
+            files.push(PreparedFile {
+                file_id: file_id as u32,
+                name: entry.name,
+                relative_path: entry.relative_path,
+                source: entry.source,
+                size: entry.size,
+                checksum,
+                preview: None,
+                mime: None,
+                modified: None,
+            });
+        }
+        (files, dirs.into_iter().map(|e| e.relative_path).collect())
+    }
+
+    /// 端到端：真实的 SendSession 经回环 transport 读取、加密分块，ReceiveSession 写入并校验
+    #[tokio::test]
+    async fn test_loopback_transfer() {
+        let source = test_dir("loopback_src");
+        let dir = test_dir("loopback_dst");
+        let big: Vec<u8> = (0..CHUNK_SIZE * 3 + 7).map(|i| (i % 239) as u8).collect();
+        std::fs::create_dir_all(source.join("docs/nested")).unwrap();
+        std::fs::create_dir_all(source.join("empty")).unwrap();
+        std::fs::write(source.join("big.bin"), &big).unwrap();
+        std::fs::write(source.join("docs/readme.md"), "# SwarmDrop").unwrap();
+        std::fs::write(source.join("docs/nested/zero.txt"), "").unwrap();
+
+        let (prepared, directories) = prepare_dir(&source, "album").await;
+        assert_eq!(prepared.len(), 3);
+        assert_eq!(directories, vec!["album/empty"]);
+        let files: Vec<FileInfo> = prepared.iter().map(PreparedFile::to_file_info).collect();
+
+        let session_id = Uuid::new_v4();
+        let receiver_peer_id = PeerId::random();
+        let send_events = Arc::new(RecordingSink::default());
+        let sender = Arc::new(SendSession::new(
+            session_id,
+            receiver_peer_id,
+            prepared,
+            &KEY,
+            test_context(
+                Arc::new(MockTransport::new(move |_| {
+                    Ok(AppResponse::Transfer(TransferResponse::Ack { session_id }))
+                })),
+                send_events.clone(),
+            ),
+        ));
+        let recv_events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files.clone(),
+            directories,
+            dir.clone(),
+            Arc::new(LoopbackTransport::new(sender, receiver_peer_id)),
+            recv_events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+
+        for file in &files {
+            let received = std::fs::read(dir.join(&file.relative_path)).unwrap();
+            assert_eq!(
+                blake3::hash(&received).to_hex().to_string(),
+                file.checksum,
+                "{}",
+                file.relative_path
+            );
+            assert!(!dir.join(format!("{}.part", file.relative_path)).exists());
+        }
+        assert!(dir.join("album/empty").is_dir());
+        // 接收方发送 Complete 后双方各发射一次完成事件
+        assert_eq!(send_events.complete.lock().unwrap().len(), 1);
+        assert_eq!(recv_events.complete.lock().unwrap().len(), 1);
+        assert!(send_events.failed.lock().unwrap().is_empty());
+        assert!(recv_events.failed.lock().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// 不预分配时 .part 文件随分块写入扩展，传输照常完成
+    #[tokio::test]
+    async fn test_receive_without_preallocation() {
+        let dir = test_dir("no_prealloc");
+        let session_id = Uuid::new_v4();
+        let big: Vec<u8> = (0..CHUNK_SIZE * 3 + 5).map(|i| (i % 241) as u8).collect();
+        let files = vec![file_info(0, "big.bin", &big)];
+        let contents = HashMap::from([(0, big.clone())]);
+
+        // 首次请求分块时记录 .part 文件大小（尚未写入任何数据）
+        let part_path = dir.join("big.bin.part");
+        let initial_len = Arc::new(std::sync::Mutex::new(None));
+        let transport = Arc::new(MockTransport::new({
+            let initial_len = initial_len.clone();
+            move |req| {
+                initial_len
+                    .lock()
+                    .unwrap()
+                    .get_or_insert_with(|| std::fs::metadata(&part_path).unwrap().len());
+                serve(session_id, &contents, req)
+            }
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = Arc::new(
+            ReceiveSession::new(
+                session_id,
+                PeerId::random(),
+                files,
+                Vec::new(),
+                big.len() as u64,
+                FileSink::Path {
+                    save_dir: dir.clone(),
+                },
+                &KEY,
+                test_context(transport.clone(), events.clone()),
+                HashMap::new(),
+            )
+            .with_preallocation(false),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+
+        assert_eq!(*initial_len.lock().unwrap(), Some(0));
+        assert_eq!(std::fs::read(dir.join("big.bin")).unwrap(), big);
+        assert!(sent_complete(&transport));
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+        assert!(events.failed.lock().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// 协商了局域网明文的会话
+    fn plaintext_session(
+        session_id: Uuid,
+        files: Vec<FileInfo>,
+        save_dir: PathBuf,
+        transport: Arc<MockTransport>,
+        connection: ConnectionType,
+    ) -> ReceiveSession {
+        let total_size = files.iter().map(|f| f.size).sum();
+        ReceiveSession::new(
+            session_id,
+            PeerId::random(),
+            files,
+            Vec::new(),
+            total_size,
+            FileSink::Path { save_dir },
+            &KEY,
+            test_context(transport, Arc::new(RecordingSink::default())),
+            HashMap::new(),
+        )
+        .with_plaintext_lan(true)
+        .with_connection(Some(connection))
+    }
+
+    #[tokio::test]
+    async fn test_receive_plaintext_on_lan() {
+        let dir = test_dir("plaintext_lan");
+        let session_id = Uuid::new_v4();
+        let data: Vec<u8> = (0..CHUNK_SIZE + 10).map(|i| (i % 13) as u8).collect();
+        let files = vec![file_info(0, "lan.bin", &data)];
+        let contents = HashMap::from([(0, data.clone())]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve_with(session_id, &contents, req, false)
+        }));
+        let session = plaintext_session(
+            session_id,
+            files,
+            dir.clone(),
+            transport.clone(),
+            ConnectionType::Lan,
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+        assert_eq!(std::fs::read(dir.join("lan.bin")).unwrap(), data);
+        assert!(sent_complete(&transport));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reject_plaintext_off_lan() {
+        let dir = test_dir("plaintext_relay");
+        let session_id = Uuid::new_v4();
+        let data = b"must stay encrypted".to_vec();
+        let files = vec![file_info(0, "relay.txt", &data)];
+        let contents = HashMap::from([(0, data)]);
+
+        // 已协商明文，但连接为中继：明文分块一律拒收
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve_with(session_id, &contents, req, false)
+        }));
+        let session = plaintext_session(
+            session_id,
+            files,
+            dir.clone(),
+            transport,
+            ConnectionType::Relay,
+        );
+
+        let err = session.run_transfer().await.unwrap_err();
+        assert!(err.to_string().contains("拒收未加密的分块"), "{err}");
+        assert!(!dir.join("relay.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_connection_change_tracking() {
+        let dir = test_dir("connection_change");
+        let session_id = Uuid::new_v4();
+        let data = b"connection".to_vec();
+        let contents = HashMap::from([(0, data.clone())]);
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            vec![file_info(0, "a.txt", &data)],
+            Vec::new(),
+            dir.clone(),
+            transport,
+            events.clone(),
+        );
+
+        // 传输开始前更新：仅记录，不推送
+        session.update_connection(Some(ConnectionType::Relay)).await;
+        assert!(events.progress.lock().unwrap().is_empty());
+
+        assert!(session.run_transfer().await.unwrap());
+        // 校验阶段推送了校验进度
+        let verifying = events.verifying.lock().unwrap().clone();
+        assert_eq!(verifying.len(), 1);
+        assert_eq!(verifying[0].bytes_hashed, data.len() as u64);
+        assert_eq!(verifying[0].total_bytes, data.len() as u64);
+        assert_eq!(
+            events.complete.lock().unwrap()[0].final_connection,
+            Some(ConnectionType::Relay)
+        );
+
+        // 传输完成后连接类型变化不再推送进度
+        let before = events.progress.lock().unwrap().len();
+        session.update_connection(Some(ConnectionType::Dcutr)).await;
+        assert_eq!(events.progress.lock().unwrap().len(), before);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_retry_recovers() {
+        let dir = test_dir("retry_ok");
+        let session_id = Uuid::new_v4();
+        let data = vec![0x5Au8; 1024];
+        let files = vec![file_info(0, "a.bin", &data)];
+        let contents = HashMap::from([(0, data.clone())]);
+
+        // 前两次 ChunkRequest 失败，第三次成功
+        let attempts = AtomicUsize::new(0);
+        let transport = Arc::new(MockTransport::new(move |req| {
+            if matches!(
+                req,
+                AppRequest::Transfer(TransferRequest::ChunkRequest { .. })
+            ) && attempts.fetch_add(1, Ordering::SeqCst) < 2
+            {
+                return Err(AppError::Network("模拟网络抖动".into()));
+            }
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport,
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+        assert_eq!(std::fs::read(dir.join("a.bin")).unwrap(), data);
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_retry_exhausted_fails() {
+        let dir = test_dir("retry_exhausted");
+        let session_id = Uuid::new_v4();
+        let data = vec![1u8; 64];
+        let files = vec![file_info(0, "a.bin", &data)];
+
+        let transport = Arc::new(MockTransport::new(|_| {
+            Err(AppError::Network("对端不可达".into()))
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
 
-    full_chunk_count as u64 * chunk_size
-        + if last_chunk_done { last_chunk_size } else { 0 }
+        assert!(session.run_transfer().await.is_err());
+        assert!(!dir.join("a.bin").exists());
+        assert!(!sent_complete(&transport));
+        assert!(events.complete.lock().unwrap().is_empty());
+        assert_eq!(events.failed.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_connection_lost() {
+        let dir = test_dir("reconnect_ok");
+        let session_id = Uuid::new_v4();
+        let data: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| (i % 199) as u8).collect();
+        let files = vec![file_info(0, "a.bin", &data)];
+        let contents = HashMap::from([(0, data.clone())]);
+
+        // 连接断开期间所有 ChunkRequest 失败，重连后恢复
+        let online = Arc::new(AtomicBool::new(false));
+        let online_for_requests = online.clone();
+        let transport = Arc::new(
+            MockTransport::new(move |req| {
+                if matches!(
+                    req,
+                    AppRequest::Transfer(TransferRequest::ChunkRequest { .. })
+                ) && !online_for_requests.load(Ordering::SeqCst)
+                {
+                    return Err(AppError::Network("connection closed".into()));
+                }
+                serve(session_id, &contents, req)
+            })
+            .with_reconnect(move || {
+                online.store(true, Ordering::SeqCst);
+                Ok(())
+            }),
+        );
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+        assert_eq!(std::fs::read(dir.join("a.bin")).unwrap(), data);
+        // 并发失败的分块共享同一次重连
+        assert_eq!(transport.reconnects.load(Ordering::SeqCst), 1);
+        assert_eq!(events.reconnecting.lock().unwrap().len(), 1);
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_failures_fail_fast() {
+        let dir = test_dir("fail_fast");
+        let session_id = Uuid::new_v4();
+        let data = vec![9u8; CHUNK_SIZE * 16];
+        let files = vec![file_info(0, "a.bin", &data)];
+
+        // 发送方对所有分块都报错：达到连续失败上限即结束，不等每个分块耗尽重试
+        let transport = Arc::new(MockTransport::new(move |req| match req {
+            AppRequest::Transfer(TransferRequest::ChunkRequest {
+                file_id,
+                chunk_index,
+                ..
+            }) => Ok(AppResponse::Transfer(TransferResponse::ChunkError {
+                session_id,
+                file_id: *file_id,
+                chunk_index: *chunk_index,
+                error: "read failed".into(),
+            })),
+            _ => Ok(AppResponse::Transfer(TransferResponse::Ack { session_id })),
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        let err = session.run_transfer().await.unwrap_err();
+        assert!(err.to_string().contains("连接已断开"), "{err}");
+        assert_eq!(err.code(), "transfer/connection-lost");
+        let chunk_requests = transport
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r,
+                    AppRequest::Transfer(TransferRequest::ChunkRequest { .. })
+                )
+            })
+            .count();
+        assert!(
+            chunk_requests < MAX_CONSECUTIVE_CHUNK_FAILURES as usize + MAX_CONCURRENT_CHUNKS,
+            "{chunk_requests}"
+        );
+        assert_eq!(events.failed.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_failure_fails_without_retry() {
+        let dir = test_dir("decrypt_failed");
+        let session_id = Uuid::new_v4();
+        let data = b"tampered".to_vec();
+        let files = vec![file_info(0, "a.txt", &data)];
+
+        // 发送方使用了不同的密钥
+        let transport = Arc::new(MockTransport::new(move |req| match req {
+            AppRequest::Transfer(TransferRequest::ChunkRequest {
+                file_id,
+                chunk_index,
+                ..
+            }) => Ok(AppResponse::Transfer(TransferResponse::Chunk {
+                session_id,
+                file_id: *file_id,
+                chunk_index: *chunk_index,
+                data: TransferCrypto::new(&[8u8; 32])
+                    .encrypt_chunk(&session_id, *file_id, *chunk_index, &data)
+                    .unwrap(),
+                is_last: true,
+                encrypted: true,
+            })),
+            _ => Ok(AppResponse::Transfer(TransferResponse::Ack { session_id })),
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        let err = session.run_transfer().await.unwrap_err();
+        assert!(matches!(err, AppError::Crypto(_)), "{err}");
+        let chunk_requests = transport
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r,
+                    AppRequest::Transfer(TransferRequest::ChunkRequest { .. })
+                )
+            })
+            .count();
+        assert_eq!(chunk_requests, 1);
+        let failed = events.failed.lock().unwrap();
+        assert!(failed[0].error.contains("密钥不匹配或数据被篡改"));
+        drop(failed);
+        assert!(!dir.join("a.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_window_elapsed_fails() {
+        let dir = test_dir("reconnect_timeout");
+        let session_id = Uuid::new_v4();
+        let data = vec![3u8; 64];
+        let files = vec![file_info(0, "a.bin", &data)];
+
+        let transport = Arc::new(
+            MockTransport::new(|_| Err(AppError::Network("connection closed".into())))
+                .with_reconnect(|| Err(AppError::Network("dial failed".into()))),
+        );
+        let events = Arc::new(RecordingSink::default());
+        let session = Arc::new(
+            ReceiveSession::new(
+                session_id,
+                PeerId::random(),
+                files,
+                Vec::new(),
+                data.len() as u64,
+                FileSink::Path {
+                    save_dir: dir.clone(),
+                },
+                &KEY,
+                test_context(transport.clone(), events.clone()),
+                HashMap::new(),
+            )
+            .with_reconnect_window(Duration::from_millis(100)),
+        );
+
+        assert!(session.run_transfer().await.is_err());
+        assert_eq!(transport.reconnects.load(Ordering::SeqCst), 1);
+        assert_eq!(events.reconnecting.lock().unwrap().len(), 1);
+        assert_eq!(events.failed.lock().unwrap().len(), 1);
+        assert!(!sent_complete(&transport));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_start() {
+        let dir = test_dir("cancel");
+        let session_id = Uuid::new_v4();
+        let data = vec![2u8; 64];
+        let files = vec![file_info(0, "a.bin", &data)];
+
+        let transport = Arc::new(MockTransport::new(|_| {
+            Err(AppError::Network("不应发出请求".into()))
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        session.cancel();
+        assert!(!session.run_transfer().await.unwrap());
+        assert!(transport.requests.lock().unwrap().is_empty());
+        let failed = events.failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error, "用户取消");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch_fails() {
+        let dir = test_dir("checksum_mismatch");
+        let session_id = Uuid::new_v4();
+        let data = vec![3u8; 128];
+        let mut info = file_info(0, "a.bin", &data);
+        info.checksum = blake3::hash(b"something else").to_hex().to_string();
+        let contents = HashMap::from([(0, data)]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            vec![info],
+            Vec::new(),
+            dir.clone(),
+            transport,
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.is_err());
+        assert!(!dir.join("a.bin").exists());
+        assert!(!dir.join("a.bin.part").exists());
+        let failed = events.failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].error.contains("文件校验失败"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_failure_reports_saved_files() {
+        let dir = test_dir("failure_saved_files");
+        let session_id = Uuid::new_v4();
+        let good = vec![1u8; 100];
+        let bad = vec![2u8; 50];
+        let mut bad_info = file_info(1, "b.bin", &bad);
+        bad_info.checksum = blake3::hash(b"tampered").to_hex().to_string();
+        let contents = HashMap::from([(0, good.clone()), (1, bad)]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            vec![file_info(0, "a.bin", &good), bad_info],
+            Vec::new(),
+            dir.clone(),
+            transport,
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.is_err());
+        session.cleanup_part_files().await;
+        // 先前已校验保存的文件保留
+        assert_eq!(std::fs::read(dir.join("a.bin")).unwrap(), good);
+
+        let failed = events.failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        let names: Vec<_> = failed[0]
+            .completed_files
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, ["a.bin"]);
+        assert_eq!(failed[0].failed_file.as_ref().unwrap().file_id, 1);
+        assert!(matches!(
+            &failed[0].save_location,
+            Some(SaveLocation::Path { path }) if *path == dir.to_string_lossy()
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_failure_notifies_sender() {
+        let dir = test_dir("verify_cancel");
+        let session_id = Uuid::new_v4();
+        let data = vec![5u8; 64];
+        let mut info = file_info(0, "report.pdf", &data);
+        info.checksum = blake3::hash(b"tampered").to_hex().to_string();
+        let contents = HashMap::from([(0, data)]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let session = new_session(
+            session_id,
+            vec![info],
+            Vec::new(),
+            dir.clone(),
+            transport.clone(),
+            Arc::new(RecordingSink::default()),
+        );
+
+        assert!(session.run_transfer().await.is_err());
+
+        let requests = transport.requests.lock().unwrap();
+        let reason = requests.iter().find_map(|r| match r {
+            AppRequest::Transfer(TransferRequest::Cancel { reason, .. }) => Some(reason.clone()),
+            _ => None,
+        });
+        assert!(reason.is_some_and(|r| r.contains("校验失败") && r.contains("report.pdf")));
+        drop(requests);
+        assert!(!sent_complete(&transport));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_empty_file_and_empty_dir() {
+        let dir = test_dir("empty_entries");
+        let session_id = Uuid::new_v4();
+        let files = vec![file_info(0, "folder/empty.txt", &[])];
+        let directories = vec!["folder/nested/empty_dir".to_string()];
+        let contents = HashMap::from([(0, Vec::new())]);
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &contents, req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            files,
+            directories,
+            dir.clone(),
+            transport.clone(),
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+
+        let empty = dir.join("folder/empty.txt");
+        assert!(empty.is_file());
+        assert_eq!(std::fs::metadata(&empty).unwrap().len(), 0);
+        assert!(dir.join("folder/nested/empty_dir").is_dir());
+        assert!(sent_complete(&transport));
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_directories_only() {
+        let dir = test_dir("dirs_only");
+        let session_id = Uuid::new_v4();
+
+        let transport = Arc::new(MockTransport::new(move |req| {
+            serve(session_id, &HashMap::new(), req)
+        }));
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(
+            session_id,
+            Vec::new(),
+            vec!["a".into(), "b/c".into()],
+            dir.clone(),
+            transport,
+            events.clone(),
+        );
+
+        assert!(session.run_transfer().await.unwrap());
+        assert!(dir.join("a").is_dir());
+        assert!(dir.join("b/c").is_dir());
+        assert_eq!(events.complete.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
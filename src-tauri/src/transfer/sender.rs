@@ -1,46 +1,66 @@
 //! 发送方会话
 //!
 //! 管理单个发送传输的生命周期：响应 ChunkRequest、处理 Complete/Cancel。
-//! 文件读取通过 [`file_source`](crate::file_source) 模块完成，加密使用 [`TransferCrypto`]。
+//! 会话结束时（完成 / 对方取消 / 空闲超时 / 被移除）恰好发射一次终态事件，
+//! 并立即释放文件来源（Android 上的 content URI 授权）和加密密钥，不等会话从管理器中移除。
+//! 文件读取通过 [`file_source`](crate::file_source) 模块完成，并经 [`ReadAheadCache`]
+//! 顺序预读以减少并发请求造成的随机读；加密使用 [`TransferCrypto`]。
 //! 使用 `Arc<std::sync::Mutex<ProgressTracker>>` 实现并发安全的进度追踪。
 
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use swarm_p2p_core::libp2p::PeerId;
-use tauri::AppHandle;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::file_source::calc_total_chunks;
-use crate::protocol::TransferResponse;
-use crate::transfer::crypto::TransferCrypto;
+use crate::device::ConnectionType;
+use crate::file_source::{calc_total_chunks, FileSource};
+use crate::protocol::{AppRequest, TransferRequest, TransferResponse};
+use crate::transfer::bitmap::AtomicBitmap;
+use crate::transfer::context::SessionContext;
+use crate::transfer::crypto::{use_plaintext, TransferCrypto};
 use crate::transfer::offer::PreparedFile;
-use crate::transfer::progress::{FileDesc, ProgressTracker, TransferDirection};
+use crate::transfer::progress::{FileDesc, ProgressSnapshot, ProgressTracker, TransferDirection};
+use crate::transfer::read_ahead::{ReadAheadCache, ReadChunkFn};
 use crate::{AppError, AppResult};
 
+/// 会话持有的文件来源与密钥（进入终态时释放）
+struct SessionResources {
+    /// 准备好的文件列表（含文件来源）
+    files: Vec<PreparedFile>,
+    /// 加密器（释放时清零密钥）
+    crypto: TransferCrypto,
+}
+
 /// 发送方会话
 pub struct SendSession {
     /// 传输会话 ID
     pub session_id: Uuid,
-    /// 对端 PeerId（暂停时需要通知对端）
+    /// 对端 PeerId（暂停 / 取消时需要通知对端）
     pub peer_id: PeerId,
-    /// 准备好的文件列表（含文件来源）
-    files: Vec<PreparedFile>,
-    /// 加密器
-    crypto: TransferCrypto,
-    /// Tauri 应用句柄（文件读取时传递给 FileSource + 进度事件发射）
-    app: AppHandle,
+    /// 文件与加密器，会话结束后为 None
+    resources: RwLock<Option<SessionResources>>,
+    /// 双方已协商局域网明文（仍需逐块确认当前为局域网直连）
+    plaintext_lan: bool,
+    /// 外部依赖（进度事件发射 + Android 文件读取所需的 AppHandle）
+    ctx: SessionContext,
     /// 进度追踪器（Arc<Mutex> 供并发 ChunkRequest 任务共享）
     progress: Arc<Mutex<ProgressTracker>>,
+    /// 每个文件已计入进度的 chunk（file_id → bitmap），接收方重试同一 chunk 时不重复计数
+    sent_chunks: std::collections::HashMap<u32, AtomicBitmap>,
     /// 取消令牌
     cancel_token: CancellationToken,
+    /// 分块预读缓存（会话取消时停止预读）
+    read_ahead: ReadAheadCache,
     /// 会话创建时间（用于统计传输耗时）
     created_at: Instant,
     /// 最后活动时间戳（毫秒，从 created_at 起算，用于空闲超时清理）
     last_activity_ms: Arc<AtomicU64>,
+    /// 是否已发射终态事件（complete / failed 只发射一次）
+    finished: AtomicBool,
 }
 
 impl SendSession {
@@ -49,9 +69,9 @@ impl SendSession {
         peer_id: PeerId,
         files: Vec<PreparedFile>,
         key: &[u8; 32],
-        app: AppHandle,
+        ctx: SessionContext,
     ) -> Self {
-        Self::new_inner(session_id, peer_id, files, key, app, &std::collections::HashMap::new())
+        Self::new_inner(session_id, peer_id, files, key, ctx, &std::collections::HashMap::new())
     }
 
     /// 断点续传专用构造函数
@@ -63,10 +83,10 @@ impl SendSession {
         peer_id: PeerId,
         files: Vec<PreparedFile>,
         key: &[u8; 32],
-        app: AppHandle,
+        ctx: SessionContext,
         resume_state: &std::collections::HashMap<u32, (u32, u64)>,
     ) -> Self {
-        Self::new_inner(session_id, peer_id, files, key, app, resume_state)
+        Self::new_inner(session_id, peer_id, files, key, ctx, resume_state)
     }
 
     fn new_inner(
@@ -74,7 +94,7 @@ impl SendSession {
         peer_id: PeerId,
         files: Vec<PreparedFile>,
         key: &[u8; 32],
-        app: AppHandle,
+        ctx: SessionContext,
         resume_state: &std::collections::HashMap<u32, (u32, u64)>,
     ) -> Self {
         let total_bytes: u64 = files.iter().map(|f| f.size).sum();
@@ -92,28 +112,57 @@ impl SendSession {
             })
             .collect();
         tracker.init_files_with_resume(&file_descs, resume_state);
+        let sent_chunks = files
+            .iter()
+            .map(|f| (f.file_id, AtomicBitmap::new(calc_total_chunks(f.size))))
+            .collect();
 
+        let cancel_token = CancellationToken::new();
         Self {
             session_id,
             peer_id,
-            files,
-            crypto: TransferCrypto::new(key),
-            app,
+            resources: RwLock::new(Some(SessionResources {
+                files,
+                crypto: TransferCrypto::new(key),
+            })),
+            plaintext_lan: false,
+            ctx,
             progress: Arc::new(Mutex::new(tracker)),
-            cancel_token: CancellationToken::new(),
+            sent_chunks,
+            read_ahead: ReadAheadCache::new(&cancel_token),
+            cancel_token,
             created_at: Instant::now(),
             last_activity_ms: Arc::new(AtomicU64::new(0)),
+            finished: AtomicBool::new(false),
         }
     }
 
-    /// 获取传输耗时（毫秒）
-    pub fn elapsed_ms(&self) -> u64 {
-        self.created_at.elapsed().as_millis() as u64
+    /// 记录与对端的连接类型（随进度事件推送）
+    pub fn with_connection(self, connection: Option<ConnectionType>) -> Self {
+        if let Ok(mut p) = self.progress.lock() {
+            p.set_connection(connection);
+        }
+        self
     }
 
-    /// 获取已发送总字节数（从 ProgressTracker 读取）
-    pub fn total_bytes_sent(&self) -> u64 {
-        self.progress.lock().map_or(0, |p| p.transferred_bytes())
+    /// 双方已在 Offer 中协商局域网明文
+    pub fn with_plaintext_lan(mut self, plaintext_lan: bool) -> Self {
+        self.plaintext_lan = plaintext_lan;
+        self
+    }
+
+    /// 传输中连接类型变化（打洞成功 / 回落中继）：更新并立即推送一次进度
+    pub fn update_connection(&self, connection: Option<ConnectionType>) {
+        if let Ok(mut p) = self.progress.lock() {
+            if p.set_connection(connection) {
+                p.emit_progress_now(self.ctx.events.as_ref());
+            }
+        }
+    }
+
+    /// 当前进度快照（总体进度汇总用）
+    pub fn progress_snapshot(&self) -> ProgressSnapshot {
+        self.progress.lock().map_or_else(|_| ProgressSnapshot::default(), |p| p.snapshot())
     }
 
     /// 获取每个文件的已传输进度（用于暂停时持久化到 DB）
@@ -126,9 +175,13 @@ impl SendSession {
             .unwrap_or_default()
     }
 
-    /// 处理 ChunkRequest：读取文件分块 → 加密 → 上报进度 → 返回 Chunk 响应
+    /// 处理 ChunkRequest：校验请求 → 等待内存预算 → 读取文件分块 → 加密 → 上报进度 → 返回 Chunk 响应
+    ///
+    /// 已协商明文且当前为局域网直连时跳过加密。
+    /// 所有会话的在途请求超出内存预算时在读取前排队，会话取消时排队的请求立即返回。
     pub async fn handle_chunk_request(
         &self,
+        from: &PeerId,
         file_id: u32,
         chunk_index: u32,
     ) -> AppResult<TransferResponse> {
@@ -136,39 +189,55 @@ impl SendSession {
             return Err(AppError::Transfer("传输已取消".into()));
         }
 
-        let file = self
-            .files
-            .iter()
-            .find(|f| f.file_id == file_id)
-            .ok_or_else(|| {
-                AppError::Transfer(format!("文件不存在: file_id={file_id}"))
-            })?;
+        let (source, size) = self.validate_chunk_request(from, file_id, chunk_index)?;
 
-        // 通过 FileSource 异步读取分块（内部已处理 spawn_blocking）
-        let plaintext = file.source.read_chunk(file.size, chunk_index, &self.app).await?;
+        // 明文与密文缓冲区在返回前一直占用名额
+        let _permit = match &self.ctx.chunk_budget {
+            Some(budget) => Some(budget.acquire(&self.cancel_token).await?),
+            None => None,
+        };
+
+        // 优先从预读缓存读取，未命中时通过 FileSource 读取（内部已处理 spawn_blocking）
+        let plaintext = self
+            .read_ahead
+            .read_chunk(
+                file_id,
+                calc_total_chunks(size),
+                chunk_index,
+                self.chunk_reader(source, size),
+            )
+            .await?;
 
         let plaintext_len = plaintext.len() as u64;
 
-        // 加密
-        let data = self
-            .crypto
-            .encrypt_chunk(&self.session_id, file_id, chunk_index, &plaintext)
-            .map_err(|e| AppError::Transfer(format!("加密失败: {e}")))?;
+        // 加密（连接类型按当前值判断，回落中继后自动恢复加密）
+        let encrypted = !self
+            .progress
+            .lock()
+            .is_ok_and(|p| use_plaintext(self.plaintext_lan, p.connection()));
+        let data = if encrypted {
+            self.encrypt_chunk(file_id, chunk_index, &plaintext)?
+        } else {
+            plaintext
+        };
 
         // 更新最后活动时间戳
         self.last_activity_ms
             .store(self.created_at.elapsed().as_millis() as u64, Ordering::Relaxed);
 
         // 上报进度（锁内操作极短：VecDeque push + 200ms 节流检查）
+        let first_sent = self
+            .sent_chunks
+            .get(&file_id)
+            .is_some_and(|bitmap| bitmap.set(chunk_index));
         if let Ok(mut p) = self.progress.lock() {
-            p.add_bytes(plaintext_len);
-            p.update_file_chunk(file_id, plaintext_len);
-            p.emit_progress(&self.app);
+            if first_sent && p.update_file_chunk(file_id, chunk_index, plaintext_len) {
+                p.add_bytes(plaintext_len);
+            }
+            p.emit_progress(self.ctx.events.as_ref());
         }
 
-        // 计算 is_last
-        let total_chunks = calc_total_chunks(file.size);
-        let is_last = chunk_index + 1 >= total_chunks;
+        let is_last = chunk_index + 1 >= calc_total_chunks(size);
 
         Ok(TransferResponse::Chunk {
             session_id: self.session_id,
@@ -176,24 +245,117 @@ impl SendSession {
             chunk_index,
             data,
             is_last,
+            encrypted,
+        })
+    }
+
+    /// 加密分块（会话已结束、密钥已释放时返回错误）
+    fn encrypt_chunk(
+        &self,
+        file_id: u32,
+        chunk_index: u32,
+        plaintext: &[u8],
+    ) -> AppResult<Vec<u8>> {
+        let resources = self.resources.read().map_err(|_| session_ended())?;
+        let resources = resources.as_ref().ok_or_else(session_ended)?;
+        resources
+            .crypto
+            .encrypt_chunk(&self.session_id, file_id, chunk_index, plaintext)
+            .map_err(|e| AppError::Transfer(format!("加密失败: {e}")))
+    }
+
+    /// 文件的分块读取函数（预读任务在后台使用，需持有来源的所有权）
+    fn chunk_reader(&self, source: FileSource, size: u64) -> ReadChunkFn {
+        let app = self.ctx.app().cloned();
+        Arc::new(move |chunk_index| {
+            let source = source.clone();
+            let app = app.clone();
+            Box::pin(async move { source.read_chunk(size, chunk_index, app.as_ref()).await })
         })
     }
 
-    /// 处理 Complete：记录日志，会话将由 TransferManager 清理
+    /// 校验分块请求：只接受会话对端、本会话内的文件、范围内的分块
+    ///
+    /// 返回文件来源与大小（读取期间不持有锁，会话可随时结束并释放文件列表）。
+    fn validate_chunk_request(
+        &self,
+        from: &PeerId,
+        file_id: u32,
+        chunk_index: u32,
+    ) -> AppResult<(FileSource, u64)> {
+        if *from != self.peer_id {
+            return Err(AppError::InvalidChunkRequest(format!(
+                "会话不属于请求方: session={}",
+                self.session_id
+            )));
+        }
+
+        let resources = self.resources.read().map_err(|_| session_ended())?;
+        let resources = resources.as_ref().ok_or_else(session_ended)?;
+        let file = resources
+            .files
+            .iter()
+            .find(|f| f.file_id == file_id)
+            .ok_or_else(|| {
+                AppError::InvalidChunkRequest(format!("文件不存在: file_id={file_id}"))
+            })?;
+
+        let total_chunks = calc_total_chunks(file.size);
+        if chunk_index >= total_chunks {
+            return Err(AppError::InvalidChunkRequest(format!(
+                "分块越界: file_id={file_id}, chunk_index={chunk_index}, total_chunks={total_chunks}"
+            )));
+        }
+        Ok((file.source.clone(), file.size))
+    }
+
+    /// 处理 Complete：推送最终进度并发射完成事件，会话将由 TransferManager 清理
     pub fn handle_complete(&self) {
         info!(
             "Transfer complete acknowledged: session={}",
             self.session_id
         );
+        self.release();
+        if !self.mark_finished() {
+            return;
+        }
+        if let Ok(mut p) = self.progress.lock() {
+            p.emit_progress_now(self.ctx.events.as_ref());
+            p.emit_complete(self.ctx.events.as_ref(), None, Vec::new(), Vec::new());
+        }
     }
 
-    /// 处理 Cancel：取消所有进行中的操作
-    pub fn handle_cancel(&self) {
+    /// 处理 Cancel：取消所有进行中的操作并发射失败事件
+    pub fn handle_cancel(&self, reason: &str) {
         warn!(
-            "Transfer cancelled by peer: session={}",
-            self.session_id
+            "Transfer cancelled by peer: session={}, reason={}",
+            self.session_id, reason
         );
+        self.fail(format!("对方取消: {reason}"));
+    }
+
+    /// 以失败结束会话（空闲超时、被移除等），已发射过终态事件时只取消不再发射
+    pub fn fail(&self, error: String) {
+        self.release();
+        if !self.mark_finished() {
+            return;
+        }
+        if let Ok(p) = self.progress.lock() {
+            p.emit_failed(self.ctx.events.as_ref(), error);
+        }
+    }
+
+    /// 进入终态：停止预读，释放文件来源并清零密钥（可重复调用）
+    fn release(&self) {
         self.cancel_token.cancel();
+        if let Ok(mut resources) = self.resources.write() {
+            resources.take();
+        }
+    }
+
+    /// 标记已结束，返回本次调用是否为首次（需要发射终态事件）
+    fn mark_finished(&self) -> bool {
+        !self.finished.swap(true, Ordering::AcqRel)
     }
 
     /// 获取取消令牌（供外部检查是否已取消）
@@ -201,9 +363,27 @@ impl SendSession {
         &self.cancel_token
     }
 
-    /// 主动取消
+    /// 主动取消（暂停等场景，由调用方自行通知前端，不发射终态事件）
     pub fn cancel(&self) {
-        self.cancel_token.cancel();
+        self.mark_finished();
+        self.release();
+    }
+
+    /// 发送 Cancel 消息给接收方
+    ///
+    /// 发送方是被动的（只响应 ChunkRequest），不主动通知的话接收方会一直重试到预算耗尽。
+    pub async fn send_cancel(&self) {
+        let _ = self
+            .ctx
+            .transport
+            .send_request(
+                self.peer_id,
+                AppRequest::Transfer(TransferRequest::Cancel {
+                    session_id: self.session_id,
+                    reason: "用户取消".into(),
+                }),
+            )
+            .await;
     }
 
     /// 返回自上次活动以来的空闲时间（毫秒）
@@ -213,3 +393,366 @@ impl SendSession {
         elapsed.saturating_sub(last)
     }
 }
+
+fn session_ended() -> AppError {
+    AppError::Transfer("发送会话已结束".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::file_source::{FileSource, CHUNK_SIZE};
+    use crate::protocol::AppResponse;
+    use crate::transfer::chunk_budget::ChunkBudget;
+    use crate::transfer::context::testing::{test_context, MockTransport, RecordingSink};
+
+    fn new_session(
+        session_id: Uuid,
+        transport: Arc<MockTransport>,
+        events: Arc<RecordingSink>,
+    ) -> SendSession {
+        SendSession::new(
+            session_id,
+            PeerId::random(),
+            Vec::new(),
+            &[7u8; 32],
+            test_context(transport, events),
+        )
+    }
+
+    fn ack_transport(session_id: Uuid) -> Arc<MockTransport> {
+        Arc::new(MockTransport::new(move |_| {
+            Ok(AppResponse::Transfer(TransferResponse::Ack { session_id }))
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_cancel_notifies_receiver() {
+        let session_id = Uuid::new_v4();
+        let transport = ack_transport(session_id);
+        let session = new_session(
+            session_id,
+            transport.clone(),
+            Arc::new(RecordingSink::default()),
+        );
+
+        session.cancel();
+        session.send_cancel().await;
+
+        assert!(session.cancel_token().is_cancelled());
+        let requests = transport.requests.lock().unwrap();
+        assert!(matches!(
+            requests.as_slice(),
+            [AppRequest::Transfer(TransferRequest::Cancel { session_id: id, .. })] if *id == session_id
+        ));
+    }
+
+    #[test]
+    fn test_complete_emits_once() {
+        let session_id = Uuid::new_v4();
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(session_id, ack_transport(session_id), events.clone());
+
+        session.handle_complete();
+        session.handle_complete();
+        session.fail("发送会话已结束".into());
+
+        let complete = events.complete.lock().unwrap();
+        assert_eq!(complete.len(), 1);
+        assert_eq!(complete[0].direction, TransferDirection::Send);
+        assert!(events.failed.lock().unwrap().is_empty());
+        // 完成前强制推送一次最终进度
+        assert_eq!(events.progress.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_peer_cancel_emits_failed_once() {
+        let session_id = Uuid::new_v4();
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(session_id, ack_transport(session_id), events.clone());
+
+        session.handle_cancel("用户取消");
+        session.fail("发送会话已结束".into());
+        session.handle_complete();
+
+        assert!(session.cancel_token().is_cancelled());
+        let failed = events.failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].error.contains("用户取消"));
+        assert!(events.complete.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_local_cancel_is_silent() {
+        let session_id = Uuid::new_v4();
+        let events = Arc::new(RecordingSink::default());
+        let session = new_session(session_id, ack_transport(session_id), events.clone());
+
+        // 暂停等主动取消场景由调用方通知前端，之后移除会话不再补发失败事件
+        session.cancel();
+        session.fail("发送会话已结束".into());
+
+        assert!(events.failed.lock().unwrap().is_empty());
+        assert!(events.complete.lock().unwrap().is_empty());
+    }
+
+    fn prepared_file(file_id: u32, size: u64) -> PreparedFile {
+        PreparedFile {
+            file_id,
+            name: format!("file-{file_id}.bin"),
+            relative_path: format!("file-{file_id}.bin"),
+            source: FileSource::Path {
+                path: std::env::temp_dir().join("swarmdrop_test_missing.bin"),
+            },
+            size,
+            checksum: String::new(),
+            preview: None,
+            mime: None,
+            modified: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunk_request_validation() {
+        let session_id = Uuid::new_v4();
+        let peer_id = PeerId::random();
+        let session = SendSession::new(
+            session_id,
+            peer_id,
+            vec![
+                prepared_file(0, CHUNK_SIZE as u64 * 2 + 1),
+                prepared_file(1, 0),
+            ],
+            &[7u8; 32],
+            test_context(
+                ack_transport(session_id),
+                Arc::new(RecordingSink::default()),
+            ),
+        );
+
+        // 越界：3 个分块的文件请求第 4 块，空文件只有第 0 块
+        for (file_id, chunk_index) in [(0, 3), (0, u32::MAX), (1, 1)] {
+            let err = session
+                .handle_chunk_request(&peer_id, file_id, chunk_index)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, AppError::InvalidChunkRequest(msg) if msg.contains("分块越界")),
+                "{err}"
+            );
+        }
+
+        // 本会话中不存在的文件
+        let err = session
+            .handle_chunk_request(&peer_id, 9, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, AppError::InvalidChunkRequest(msg) if msg.contains("文件不存在")));
+
+        // 其他节点不能读取本会话的数据
+        let err = session
+            .handle_chunk_request(&PeerId::random(), 0, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, AppError::InvalidChunkRequest(msg) if msg.contains("会话不属于")));
+
+        // 范围内的请求通过校验，进入读取阶段（测试文件不存在，报 IO 错误）
+        let err = session
+            .handle_chunk_request(&peer_id, 0, 2)
+            .await
+            .unwrap_err();
+        assert!(!matches!(err, AppError::InvalidChunkRequest(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_chunk_budget_parks_until_cancelled() {
+        let session_id = Uuid::new_v4();
+        let peer_id = PeerId::random();
+        let budget = ChunkBudget::new(1);
+        let session = Arc::new(SendSession::new(
+            session_id,
+            peer_id,
+            vec![prepared_file(0, CHUNK_SIZE as u64)],
+            &[7u8; 32],
+            test_context(
+                ack_transport(session_id),
+                Arc::new(RecordingSink::default()),
+            )
+            .with_chunk_budget(budget.clone()),
+        ));
+
+        // 其他会话占满预算：本会话的请求排队，不读取文件
+        let held = budget.acquire(&CancellationToken::new()).await.unwrap();
+        let parked = {
+            let session = session.clone();
+            tokio::spawn(async move { session.handle_chunk_request(&peer_id, 0, 0).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!parked.is_finished());
+
+        session.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(1), parked)
+            .await
+            .expect("取消后排队的请求应立即返回")
+            .unwrap();
+        assert!(matches!(result, Err(AppError::Transfer(msg)) if msg.contains("取消")));
+        drop(held);
+        assert_eq!(budget.available(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_only_on_negotiated_lan() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_send_plaintext");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.txt");
+        let data = b"plaintext on lan".to_vec();
+        std::fs::write(&path, &data).unwrap();
+
+        let session_id = Uuid::new_v4();
+        let peer_id = PeerId::random();
+        let key = [7u8; 32];
+        let file = PreparedFile {
+            source: FileSource::Path { path },
+            ..prepared_file(0, data.len() as u64)
+        };
+        let session = SendSession::new(
+            session_id,
+            peer_id,
+            vec![file],
+            &key,
+            test_context(
+                ack_transport(session_id),
+                Arc::new(RecordingSink::default()),
+            ),
+        )
+        .with_plaintext_lan(true);
+
+        let chunk = |response| match response {
+            TransferResponse::Chunk {
+                data, encrypted, ..
+            } => (data, encrypted),
+            other => panic!("unexpected response: {other:?}"),
+        };
+
+        // 局域网直连：明文
+        session.update_connection(Some(ConnectionType::Lan));
+        let (plain, encrypted) = chunk(session.handle_chunk_request(&peer_id, 0, 0).await.unwrap());
+        assert!(!encrypted);
+        assert_eq!(plain, data);
+
+        // 回落中继：恢复加密
+        session.update_connection(Some(ConnectionType::Relay));
+        let (cipher, encrypted) =
+            chunk(session.handle_chunk_request(&peer_id, 0, 0).await.unwrap());
+        assert!(encrypted);
+        assert_ne!(cipher, data);
+        let decrypted = TransferCrypto::new(&key)
+            .decrypt_chunk(&session_id, 0, 0, &cipher)
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_chunk_counted_once() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_send_repeated");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        let data = vec![0x3Cu8; CHUNK_SIZE + 10];
+        std::fs::write(&path, &data).unwrap();
+
+        let session_id = Uuid::new_v4();
+        let peer_id = PeerId::random();
+        let file = PreparedFile {
+            source: FileSource::Path { path },
+            ..prepared_file(0, data.len() as u64)
+        };
+        let session = SendSession::new(
+            session_id,
+            peer_id,
+            vec![file],
+            &[7u8; 32],
+            test_context(
+                ack_transport(session_id),
+                Arc::new(RecordingSink::default()),
+            ),
+        );
+
+        // 接收方重试最后一块：只计入一次
+        for _ in 0..2 {
+            session.handle_chunk_request(&peer_id, 0, 1).await.unwrap();
+        }
+        assert_eq!(session.get_file_progress(), vec![(0, 1, 10)]);
+        assert_eq!(session.progress_snapshot().transferred_bytes, 10);
+
+        session.handle_chunk_request(&peer_id, 0, 0).await.unwrap();
+        assert_eq!(
+            session.progress_snapshot().transferred_bytes,
+            data.len() as u64
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_terminal_state_releases_resources() {
+        let dir = std::env::temp_dir().join("swarmdrop_test_send_release");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        std::fs::write(&path, vec![1u8; CHUNK_SIZE * 4]).unwrap();
+
+        const SESSIONS: usize = 8;
+        let events = Arc::new(RecordingSink::default());
+        let peer_id = PeerId::random();
+        let mut handles = Vec::new();
+        for i in 0..SESSIONS {
+            let session_id = Uuid::new_v4();
+            let file = PreparedFile {
+                source: FileSource::Path { path: path.clone() },
+                ..prepared_file(0, CHUNK_SIZE as u64 * 4)
+            };
+            let session = Arc::new(SendSession::new(
+                session_id,
+                peer_id,
+                vec![file],
+                &[7u8; 32],
+                test_context(ack_transport(session_id), events.clone()),
+            ));
+            session.handle_chunk_request(&peer_id, 0, 0).await.unwrap();
+
+            // Complete 与 Cancel / 空闲超时同时到达
+            handles.push(tokio::spawn(async move {
+                let racer = {
+                    let session = session.clone();
+                    std::thread::spawn(move || match i % 2 {
+                        0 => session.handle_cancel("用户取消"),
+                        _ => session.fail("接收方长时间无响应".into()),
+                    })
+                };
+                session.handle_complete();
+                racer.join().unwrap();
+                session
+            }));
+        }
+
+        for handle in handles {
+            let session = handle.await.unwrap();
+            assert!(session.cancel_token().is_cancelled());
+            // 文件来源与密钥已释放，不再响应分块请求
+            assert!(session.resources.read().unwrap().is_none());
+            assert!(session.handle_chunk_request(&peer_id, 0, 1).await.is_err());
+        }
+
+        // 每个会话恰好一个终态事件
+        let terminal = events.complete.lock().unwrap().len() + events.failed.lock().unwrap().len();
+        assert_eq!(terminal, SESSIONS);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
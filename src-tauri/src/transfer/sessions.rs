@@ -0,0 +1,392 @@
+//! 活跃传输会话表
+//!
+//! [`TransferManager`](super::offer::TransferManager) 的发送 / 接收会话表及其插入、移除规则。
+//! 会话只通过这里的方法进入终态后移除，不依赖网络客户端，测试中可驱动会话到各终态并核对表已清空。
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use swarm_p2p_core::libp2p::PeerId;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::transfer::receiver::ReceiveSession;
+use crate::transfer::sender::SendSession;
+use crate::{AppError, AppResult};
+
+/// 活跃的发送 / 接收会话（key = session_id）
+#[derive(Default)]
+pub struct SessionRegistry {
+    send: DashMap<Uuid, Arc<SendSession>>,
+    /// Arc 包装以便拉取任务结束时在回调中清理
+    receive: Arc<DashMap<Uuid, Arc<ReceiveSession>>>,
+    /// 会话插入 / 移除时唤醒总体进度任务（及时启停 Android 前台服务）
+    changed: Arc<Notify>,
+}
+
+impl SessionRegistry {
+    /// 会话插入 / 移除通知
+    pub fn changed(&self) -> &Notify {
+        &self.changed
+    }
+
+    /// 当前的（发送会话数, 接收会话数）
+    pub fn count(&self) -> (usize, usize) {
+        (self.send.len(), self.receive.len())
+    }
+
+    /// 所有发送会话的快照
+    pub fn send_sessions(&self) -> Vec<Arc<SendSession>> {
+        self.send.iter().map(|r| r.value().clone()).collect()
+    }
+
+    /// 所有接收会话的快照（调用方 await 时不持有 DashMap 引用）
+    pub fn receive_sessions(&self) -> Vec<Arc<ReceiveSession>> {
+        self.receive.iter().map(|r| r.value().clone()).collect()
+    }
+
+    /// 是否有与指定 peer 进行中的发送或接收会话
+    pub fn has_session_with(&self, peer_id: &PeerId) -> bool {
+        self.send.iter().any(|s| s.peer_id == *peer_id)
+            || self.receive.iter().any(|s| s.peer_id == *peer_id)
+    }
+
+    // ============ 发送会话 ============
+
+    pub fn get_send(&self, session_id: &Uuid) -> Option<Arc<SendSession>> {
+        self.send.get(session_id).map(|r| Arc::clone(r.value()))
+    }
+
+    pub fn insert_send(&self, session_id: Uuid, session: Arc<SendSession>) {
+        self.send.insert(session_id, session);
+        self.changed.notify_one();
+    }
+
+    /// 移出发送会话但不结束它（暂停、续传失败等由调用方处理）
+    pub fn take_send(&self, session_id: &Uuid) -> Option<Arc<SendSession>> {
+        let (_, session) = self.send.remove(session_id)?;
+        self.changed.notify_one();
+        Some(session)
+    }
+
+    /// 移除发送会话（可重复调用）
+    ///
+    /// 若会话尚未发射终态事件（未完成 / 未取消 / 未暂停），补发一次失败事件，
+    /// 保证前端不会残留一个永远"传输中"的发送任务。
+    /// Complete 与 Cancel 同时到达时两边都会调用，只有先移除的一方生效，
+    /// 终态事件由会话自身保证只发射一次。
+    pub fn remove_send(&self, session_id: &Uuid) {
+        if let Some(session) = self.take_send(session_id) {
+            session.fail("发送会话已结束".into());
+        }
+    }
+
+    /// 接收方确认完成：发射完成事件并移除
+    pub fn complete_send(&self, session_id: &Uuid) {
+        if let Some(session) = self.get_send(session_id) {
+            session.handle_complete();
+        }
+        self.remove_send(session_id);
+    }
+
+    /// 本方取消发送，并通知接收方停止拉取
+    pub async fn cancel_send(&self, session_id: &Uuid) -> AppResult<()> {
+        let session = self
+            .take_send(session_id)
+            .ok_or_else(|| AppError::Transfer(format!("发送会话不存在: {session_id}")))?;
+
+        // 发送方是被动方，需主动通知接收方停止拉取
+        session.fail("用户取消".into());
+        session.send_cancel().await;
+        info!("Send session cancelled: session={}", session_id);
+        Ok(())
+    }
+
+    /// 清理空闲超过 `timeout_ms` 的发送会话（接收方长时间无响应）
+    pub fn remove_idle_sends(&self, timeout_ms: u64) {
+        let idle_ids: Vec<Uuid> = self
+            .send
+            .iter()
+            .filter(|r| r.value().idle_ms() > timeout_ms)
+            .map(|r| *r.key())
+            .collect();
+        for id in &idle_ids {
+            if let Some(session) = self.take_send(id) {
+                session.fail("接收方长时间无响应".into());
+                warn!("清理空闲超时的 send session: {}", id);
+            }
+        }
+    }
+
+    // ============ 接收会话 ============
+
+    pub fn get_receive(&self, session_id: &Uuid) -> Option<Arc<ReceiveSession>> {
+        self.receive.get(session_id).map(|r| Arc::clone(r.value()))
+    }
+
+    /// 注册接收会话并开始拉取，拉取任务结束（成功或失败）后自动移除
+    pub fn start_receive(&self, session: Arc<ReceiveSession>) {
+        self.receive.insert(session.session_id, session.clone());
+        self.changed.notify_one();
+        let receive = self.receive.clone();
+        let changed = self.changed.clone();
+        session.start_pulling(move |sid| {
+            receive.remove(sid);
+            changed.notify_one();
+        });
+    }
+
+    /// 移除接收会话（可重复调用）
+    pub fn remove_receive(&self, session_id: &Uuid) {
+        if self.receive.remove(session_id).is_some() {
+            self.changed.notify_one();
+        }
+    }
+
+    /// 本方取消接收：等待后台任务结束（拉取回调随即移除会话），通知发送方并清理临时文件
+    pub async fn cancel_receive(&self, session_id: &Uuid) -> AppResult<()> {
+        let session = self
+            .get_receive(session_id)
+            .ok_or_else(|| AppError::Transfer(format!("接收会话不存在: {session_id}")))?;
+
+        // 取消并等待后台任务完成（含 bitmap 刷写）
+        session.cancel_and_wait().await;
+        session.send_cancel("用户取消").await;
+        session.cleanup_part_files().await;
+        info!("Receive session cancelled: session={}", session_id);
+        Ok(())
+    }
+
+    // ============ 对端取消 ============
+
+    /// 对端取消传输：结束并移除本方对应的会话，返回本方是否为发送方
+    ///
+    /// 发送会话自行发射失败事件；接收会话在后台等待 bitmap 刷写完成后清理 `.part` 文件。
+    pub fn cancel_by_peer(&self, session_id: &Uuid, reason: &str) -> bool {
+        let is_sender = match self.get_send(session_id) {
+            Some(s) => {
+                s.handle_cancel(reason);
+                self.remove_send(session_id);
+                true
+            }
+            None => false,
+        };
+
+        if let Some(s) = self.get_receive(session_id) {
+            self.remove_receive(session_id);
+            tokio::spawn(async move {
+                s.cancel_and_wait().await;
+                s.cleanup_part_files().await;
+            });
+        }
+        is_sender
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::file_sink::FileSink;
+    use crate::file_source::{FileSource, CHUNK_SIZE};
+    use crate::protocol::{AppRequest, AppResponse, FileInfo, TransferRequest, TransferResponse};
+    use crate::transfer::context::testing::{test_context, LoopbackTransport, RecordingSink};
+    use crate::transfer::context::{BoxFuture, ChunkTransport};
+    use crate::transfer::offer::PreparedFile;
+
+    const KEY: [u8; 32] = [9u8; 32];
+    const SESSIONS: usize = 3;
+
+    /// 模拟事件循环：把发往对端的请求分发到对端的会话表（ChunkRequest 交给回环 transport）
+    struct PeerDispatch {
+        peer: Arc<SessionRegistry>,
+        loopback: Option<LoopbackTransport>,
+    }
+
+    impl ChunkTransport for PeerDispatch {
+        fn send_request(
+            &self,
+            peer_id: PeerId,
+            request: AppRequest,
+        ) -> BoxFuture<'_, AppResult<AppResponse>> {
+            Box::pin(async move {
+                match request {
+                    AppRequest::Transfer(TransferRequest::Complete { session_id }) => {
+                        self.peer.complete_send(&session_id);
+                        Ok(AppResponse::Transfer(TransferResponse::Ack { session_id }))
+                    }
+                    AppRequest::Transfer(TransferRequest::Cancel { session_id, reason }) => {
+                        self.peer.cancel_by_peer(&session_id, &reason);
+                        Ok(AppResponse::Transfer(TransferResponse::Ack { session_id }))
+                    }
+                    request => match &self.loopback {
+                        Some(loopback) => loopback.send_request(peer_id, request).await,
+                        None => Err(AppError::Transfer(format!("不支持的请求: {request:?}"))),
+                    },
+                }
+            })
+        }
+
+        fn reconnect(&self, _peer_id: PeerId) -> BoxFuture<'_, AppResult<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("swarmdrop_test_sessions_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn source_file(dir: &Path) -> PreparedFile {
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 17).map(|i| (i % 251) as u8).collect();
+        let path = dir.join("data.bin");
+        std::fs::write(&path, &data).unwrap();
+        PreparedFile {
+            file_id: 0,
+            name: "data.bin".into(),
+            relative_path: "data.bin".into(),
+            source: FileSource::Path { path },
+            size: data.len() as u64,
+            checksum: blake3::hash(&data).to_hex().to_string(),
+            preview: None,
+            mime: None,
+            modified: None,
+        }
+    }
+
+    /// 双方各自的会话表
+    struct Pair {
+        sender: Arc<SessionRegistry>,
+        receiver: Arc<SessionRegistry>,
+    }
+
+    impl Pair {
+        fn new() -> Self {
+            Self {
+                sender: Arc::new(SessionRegistry::default()),
+                receiver: Arc::new(SessionRegistry::default()),
+            }
+        }
+
+        /// 在双方会话表中建立一个会话，`pull` 为 true 时接收方立即开始拉取
+        fn open(&self, prepared: &PreparedFile, save_dir: PathBuf, pull: bool) -> Uuid {
+            let session_id = Uuid::new_v4();
+            let sender_peer = PeerId::random();
+            let receiver_peer = PeerId::random();
+            let file: FileInfo = prepared.to_file_info();
+
+            let to_receiver = PeerDispatch {
+                peer: self.receiver.clone(),
+                loopback: None,
+            };
+            let send_session = Arc::new(SendSession::new(
+                session_id,
+                receiver_peer,
+                vec![prepared.clone()],
+                &KEY,
+                test_context(Arc::new(to_receiver), Arc::new(RecordingSink::default())),
+            ));
+            self.sender.insert_send(session_id, send_session.clone());
+            if !pull {
+                return session_id;
+            }
+
+            let to_sender = PeerDispatch {
+                peer: self.sender.clone(),
+                loopback: Some(LoopbackTransport::new(send_session, receiver_peer)),
+            };
+            let receive_session = Arc::new(ReceiveSession::new(
+                session_id,
+                sender_peer,
+                vec![file.clone()],
+                Vec::new(),
+                file.size,
+                FileSink::Path { save_dir },
+                &KEY,
+                test_context(Arc::new(to_sender), Arc::new(RecordingSink::default())),
+                HashMap::new(),
+            ));
+            self.receiver.start_receive(receive_session);
+            session_id
+        }
+
+        /// 等待双方会话表都清空
+        async fn assert_drained(&self) {
+            let drained = tokio::time::timeout(Duration::from_secs(10), async {
+                while self.sender.count() != (0, 0) || self.receiver.count() != (0, 0) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await;
+            assert!(
+                drained.is_ok(),
+                "会话表未清空: sender={:?}, receiver={:?}",
+                self.sender.count(),
+                self.receiver.count()
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sessions_drained_after_terminal_states() {
+        let source = test_dir("source");
+        let prepared = source_file(&source);
+
+        // 完成：接收方拉取结束后移除，发送方收到 Complete 后移除
+        let pair = Pair::new();
+        for i in 0..SESSIONS {
+            pair.open(&prepared, test_dir(&format!("complete_{i}")), true);
+        }
+        pair.assert_drained().await;
+
+        // 取消：接收方、发送方各自取消，对端收到 Cancel 后移除
+        let pair = Pair::new();
+        for i in 0..SESSIONS {
+            let session_id = pair.open(&prepared, test_dir(&format!("cancel_recv_{i}")), true);
+            let _ = pair.receiver.cancel_receive(&session_id).await;
+            let session_id = pair.open(&prepared, test_dir(&format!("cancel_send_{i}")), true);
+            let _ = pair.sender.cancel_send(&session_id).await;
+        }
+        pair.assert_drained().await;
+
+        // Complete 与 Cancel 同时到达发送方：只有先移除的一方生效，不残留
+        let pair = Pair::new();
+        for i in 0..SESSIONS {
+            let session_id = pair.open(&prepared, test_dir(&format!("race_{i}")), false);
+            let sender = pair.sender.clone();
+            let complete = tokio::spawn(async move { sender.complete_send(&session_id) });
+            let sender = pair.sender.clone();
+            let cancel =
+                tokio::spawn(async move { sender.cancel_by_peer(&session_id, "用户取消") });
+            let (complete, cancel) = tokio::join!(complete, cancel);
+            complete.unwrap();
+            cancel.unwrap();
+        }
+        pair.assert_drained().await;
+
+        // 空闲超时：接收方一直未拉取，清理任务移除发送会话
+        let pair = Pair::new();
+        for i in 0..SESSIONS {
+            pair.open(&prepared, test_dir(&format!("idle_{i}")), false);
+        }
+        assert_eq!(pair.sender.count(), (SESSIONS, 0));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        pair.sender.remove_idle_sends(0);
+        pair.assert_drained().await;
+
+        let _ = std::fs::remove_dir_all(&source);
+        for name in ["complete", "cancel_recv", "cancel_send", "race", "idle"] {
+            for i in 0..SESSIONS {
+                let _ = std::fs::remove_dir_all(
+                    std::env::temp_dir().join(format!("swarmdrop_test_sessions_{name}_{i}")),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Offer 签名
+//!
+//! 发送方用节点身份密钥（libp2p identity）对 Offer 内容签名，接收方在缓存 Offer 前
+//! 用发送方的公钥验证，确保界面展示的文件列表与之后拉取校验的内容一致，
+//! 不会被转发消息的中间节点篡改。
+//!
+//! 签名覆盖 [`offer_payload`] 的规范编码：session_id、文件列表（含校验和与预览）、
+//! 空目录、总大小，以及密钥交换公钥（防止替换公钥做中间人）。
+//! 旧版本发送方不携带签名，接收方仍按原逻辑处理；但对端在能力声明中表明支持签名时，
+//! 缺少签名的 Offer 一律拒绝，防止中间节点剥离签名降级（见 [`check_offer_signature`]）。
+
+use swarm_p2p_core::libp2p::identity::{Keypair, PublicKey};
+use swarm_p2p_core::libp2p::PeerId;
+use uuid::Uuid;
+
+use crate::protocol::{FileInfo, OfferSignature};
+use crate::{AppError, AppResult};
+
+/// 规范编码的域分离前缀（编码方式变化时递增版本号）
+const OFFER_SIGNING_DOMAIN: &[u8] = b"swarmdrop-offer-v1";
+
+/// Offer 的规范编码（定长整数大端序，变长字段带 u32 长度前缀）
+pub fn offer_payload(
+    session_id: &Uuid,
+    files: &[FileInfo],
+    directories: &[String],
+    total_size: u64,
+    public_key: Option<&[u8; 32]>,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(256 + files.len() * 128);
+    put_bytes(&mut buf, OFFER_SIGNING_DOMAIN);
+    buf.extend_from_slice(session_id.as_bytes());
+    buf.extend_from_slice(&total_size.to_be_bytes());
+
+    buf.extend_from_slice(&(files.len() as u32).to_be_bytes());
+    for file in files {
+        buf.extend_from_slice(&file.file_id.to_be_bytes());
+        put_bytes(&mut buf, file.name.as_bytes());
+        put_bytes(&mut buf, file.relative_path.as_bytes());
+        buf.extend_from_slice(&file.size.to_be_bytes());
+        put_bytes(&mut buf, file.checksum.as_bytes());
+        put_optional(&mut buf, file.preview.as_deref());
+        put_optional(&mut buf, file.mime.as_ref().map(|m| m.as_bytes()));
+    }
+
+    buf.extend_from_slice(&(directories.len() as u32).to_be_bytes());
+    for dir in directories {
+        put_bytes(&mut buf, dir.as_bytes());
+    }
+
+    put_optional(&mut buf, public_key.map(|k| &k[..]));
+    buf
+}
+
+/// 用节点身份密钥签名
+pub fn sign_offer(keypair: &Keypair, payload: &[u8]) -> AppResult<OfferSignature> {
+    let signature = keypair
+        .sign(payload)
+        .map_err(|e| AppError::Crypto(format!("Offer 签名失败: {e}")))?;
+    Ok(OfferSignature {
+        public_key: keypair.public().encode_protobuf(),
+        signature,
+    })
+}
+
+/// 验证签名：公钥必须属于发送方 PeerId，签名必须覆盖收到的 Offer 内容
+pub fn verify_offer(
+    signature: &OfferSignature,
+    sender: &PeerId,
+    payload: &[u8],
+) -> Result<(), String> {
+    let public_key = PublicKey::try_decode_protobuf(&signature.public_key)
+        .map_err(|e| format!("无法解析签名公钥: {e}"))?;
+    if public_key.to_peer_id() != *sender {
+        return Err("签名公钥与发送方不符".into());
+    }
+    if !public_key.verify(payload, &signature.signature) {
+        return Err("签名与 Offer 内容不符".into());
+    }
+    Ok(())
+}
+
+/// 校验入站 Offer 的签名
+///
+/// `signature_required` 为 true（对端声明支持 `offer-sig`）时必须携带签名；
+/// 否则视为旧版本发送方，签名可选，携带了就必须有效。
+pub fn check_offer_signature(
+    signature: Option<&OfferSignature>,
+    signature_required: bool,
+    sender: &PeerId,
+    payload: &[u8],
+) -> Result<(), String> {
+    match signature {
+        Some(signature) => verify_offer(signature, sender, payload),
+        None if signature_required => Err("对端支持 Offer 签名，但 Offer 未携带签名".into()),
+        None => Ok(()),
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn put_optional(buf: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        Some(bytes) => {
+            buf.push(1);
+            put_bytes(buf, bytes);
+        }
+        None => buf.push(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64) -> FileInfo {
+        FileInfo {
+            file_id: 0,
+            name: name.into(),
+            relative_path: name.into(),
+            size,
+            checksum: "ab".repeat(32),
+            preview: None,
+            mime: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::generate_ed25519();
+        let sender = keypair.public().to_peer_id();
+        let session_id = Uuid::new_v4();
+        let files = vec![file("a.txt", 10)];
+        let public_key = [3u8; 32];
+
+        let payload = offer_payload(&session_id, &files, &[], 10, Some(&public_key));
+        let signature = sign_offer(&keypair, &payload).unwrap();
+        assert!(verify_offer(&signature, &sender, &payload).is_ok());
+
+        // 篡改文件大小、替换密钥交换公钥都会使签名失效
+        let tampered = offer_payload(
+            &session_id,
+            &[file("a.txt", 11)],
+            &[],
+            10,
+            Some(&public_key),
+        );
+        assert!(verify_offer(&signature, &sender, &tampered).is_err());
+        let swapped = offer_payload(&session_id, &files, &[], 10, Some(&[4u8; 32]));
+        assert!(verify_offer(&signature, &sender, &swapped).is_err());
+
+        // 其他节点的签名（即使本身有效）不能冒充发送方
+        let other = Keypair::generate_ed25519();
+        let forged = sign_offer(&other, &payload).unwrap();
+        assert!(verify_offer(&forged, &sender, &payload).is_err());
+    }
+
+    #[test]
+    fn test_stripped_signature_rejected() {
+        let keypair = Keypair::generate_ed25519();
+        let sender = keypair.public().to_peer_id();
+        let payload = offer_payload(&Uuid::new_v4(), &[file("a.txt", 10)], &[], 10, None);
+        let signature = sign_offer(&keypair, &payload).unwrap();
+
+        // 声明支持签名的对端：签名被剥离时拒绝，不能降级为旧版本
+        assert!(check_offer_signature(None, true, &sender, &payload).is_err());
+        assert!(check_offer_signature(Some(&signature), true, &sender, &payload).is_ok());
+
+        // 旧版本（未声明能力）：不带签名仍可接受，带了无效签名照样拒绝
+        assert!(check_offer_signature(None, false, &sender, &payload).is_ok());
+        let forged = sign_offer(&Keypair::generate_ed25519(), &payload).unwrap();
+        assert!(check_offer_signature(Some(&forged), false, &sender, &payload).is_err());
+    }
+
+    #[test]
+    fn test_payload_is_unambiguous() {
+        let session_id = Uuid::new_v4();
+        // 字段边界不同的两组文件名不会编码成相同的字节
+        let mut a = file("ab", 1);
+        a.relative_path = "c".into();
+        let mut b = file("a", 1);
+        b.relative_path = "bc".into();
+        assert_ne!(
+            offer_payload(&session_id, &[a], &[], 1, None),
+            offer_payload(&session_id, &[b], &[], 1, None)
+        );
+    }
+}
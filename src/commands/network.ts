@@ -25,6 +25,8 @@ export type NatStatus = "public" | "unknown";
 
 export type DeviceStatus = "online" | "offline";
 export type ConnectionType = "lan" | "dcutr" | "relay";
+/** 连接质量（由 RTT 与连接类型推导） */
+export type ConnectionQuality = "excellent" | "good" | "fair" | "poor";
 export type NodeStatus = "running" | "stopped";
 
 export interface Device {
@@ -33,17 +35,75 @@ export interface Device {
   os: string;
   platform: string;
   arch: string;
+  /** 展示名：昵称优先，重名时带 PeerId 短后缀（前端离线回退数据可能缺省） */
+  displayName?: string;
+  /** 已配对设备的昵称 */
+  nickname?: string | null;
   status: DeviceStatus;
   connection?: ConnectionType;
   latency?: number;
+  quality?: ConnectionQuality;
+  /** 最近一次建立连接的时间戳（毫秒） */
+  lastConnectedAt?: number;
+  /** 已配对设备最近一次在线的时间戳（毫秒，跨重启保留） */
+  lastSeenAt?: number | null;
   isPaired: boolean;
 }
 
+/** 单个设备的详细信息（get_device 返回） */
+export interface DeviceDetail extends Device {
+  /** 已知的全部地址（按发现顺序） */
+  addrs: string[];
+  /** identify 上报的原始 agent_version */
+  agentVersion: string | null;
+  /** 当前连接建立的时间戳（毫秒，未连接时为 null） */
+  connectedAt: number | null;
+  /** DCUtR 打洞是否成功 */
+  holePunched: boolean;
+  /** 最近的 ping RTT（毫秒，从旧到新） */
+  rttHistory: number[];
+  /** 是否有与该设备进行中的传输 */
+  hasActiveTransfer: boolean;
+}
+
 export interface DeviceListResult {
   devices: Device[];
   total: number;
 }
 
+/** 已配对设备上线（paired-device-online） */
+export interface PairedDeviceOnlineEvent {
+  peerId: PeerId;
+  connection: ConnectionType | null;
+  /** 完整设备信息 */
+  device: Device | null;
+  /** 刚下线又重连（连接抖动），不应再次提醒 */
+  flapping: boolean;
+}
+
+/** 已配对设备下线（paired-device-offline） */
+export interface PairedDeviceOfflineEvent {
+  peerId: PeerId;
+  device: Device | null;
+}
+
+/** 打洞失败，连接仍走中继（hole-punch-failed） */
+export interface HolePunchFailedEvent {
+  peerId: PeerId;
+  error: string;
+}
+
+/** 单个节点的打洞统计（排查传输慢的原因） */
+export interface PeerDiagnostics {
+  /** 当前连接类型，未连接时为 null */
+  connection: ConnectionType | null;
+  /** 打洞尝试次数（成功 + 失败） */
+  holePunchAttempts: number;
+  holePunchFailures: number;
+  /** 最近一次打洞失败的错误信息 */
+  lastHolePunchError: string | null;
+}
+
 export interface NetworkStatus {
   status: NodeStatus;
   peerId: string | null;
@@ -58,6 +118,79 @@ export interface NetworkStatus {
   relayPeers: string[];
   /** 是否至少有一个引导节点已连接 */
   bootstrapConnected: boolean;
+  /** 当前连接的引导节点 PeerId */
+  bootstrapPeer: string | null;
+  /** 生效中的网络功能开关（节点未运行时为 null） */
+  options: NetworkOptions | null;
+  /** 各中继节点的预约状态（配置的中继在前） */
+  relays: RelayStatus[];
+}
+
+/** 单个中继节点的预约状态 */
+export interface RelayStatus {
+  peerId: string;
+  /** 是否已预约成功 */
+  reserved: boolean;
+}
+
+/**
+ * 网络功能开关（省略的字段默认开启，修改后重启节点生效）
+ */
+export interface NetworkOptions {
+  /** 局域网 mDNS 发现 */
+  mdns?: boolean;
+  /** 中继客户端（关闭即为仅局域网模式） */
+  relayClient?: boolean;
+  /** DCUtR 打洞 */
+  dcutr?: boolean;
+  /** AutoNAT 公网可达性探测 */
+  autonat?: boolean;
+}
+
+/**
+ * 网络超时配置（秒，省略的字段使用后端默认值，必须大于 0）
+ */
+export interface NetworkTimeouts {
+  /** 请求等待响应的超时，作用于所有请求类型（默认 180） */
+  reqRespTimeoutSecs?: number;
+  /** 主动拨号的超时（默认 15） */
+  dialTimeoutSecs?: number;
+  /** 启动时 DHT bootstrap 的超时（默认 60） */
+  bootstrapTimeoutSecs?: number;
+  /** 定期检查离线的已配对设备是否上线的间隔（默认 180） */
+  pairedCheckIntervalSecs?: number;
+  /** 未连接、未配对的设备超过该时长未见即从设备列表中清理（默认 600） */
+  stalePeerWindowSecs?: number;
+}
+
+/**
+ * 监听配置（省略的字段使用随机端口 / 所有网卡）
+ */
+export interface ListenConfig {
+  /** TCP 监听端口（0 为随机端口） */
+  tcpPort?: number;
+  /** QUIC（UDP）监听端口（0 为随机端口） */
+  quicPort?: number;
+  /** 监听的网卡 IP 地址（为空时监听 0.0.0.0） */
+  interfaces?: string[];
+}
+
+/** 指定端口不可用、已回退到随机端口（listen-port-fallback 事件 payload） */
+export interface ListenFallbackEvent {
+  transport: "tcp" | "quic";
+  interface: string;
+  requestedPort: number;
+  reason: string;
+}
+
+/**
+ * 引导 / 中继节点配置（自建部署时使用）
+ */
+export interface PeerConfig {
+  /** 是否连接内置的引导+中继节点（默认 true） */
+  useDefaultBootstrap?: boolean;
+  /** 自定义中继节点地址（Multiaddr，必须包含 /p2p/<PeerId>） */
+  customRelays?: string[];
 }
 
 /**
@@ -65,12 +198,52 @@ export interface NetworkStatus {
  * 注意：调用前必须确保 keypair 已通过 register_keypair 注册到后端
  *
  * @param pairedDevices - 已配对设备列表（从 Stronghold 读取）
+ * @param timeouts - 可选的超时配置
+ * @param listenConfig - 可选的监听端口 / 网卡配置（修改后重启节点生效）
+ * @param options - 可选的网络功能开关
+ * @param peerConfig - 可选的引导 / 中继节点配置（地址无效时返回 Config 错误）
  */
 export async function start(
   pairedDevices: PairedDevice[],
   customBootstrapNodes?: string[],
+  timeouts?: NetworkTimeouts,
+  listenConfig?: ListenConfig,
+  options?: NetworkOptions,
+  peerConfig?: PeerConfig,
 ): Promise<void> {
-  await invoke("start", { pairedDevices, customBootstrapNodes });
+  await invoke("start", {
+    pairedDevices,
+    customBootstrapNodes,
+    timeouts,
+    listenConfig,
+    options,
+    ...peerConfig,
+  });
+}
+
+/** 节点地址无效的原因（与 Rust InvalidAddrReason 对应） */
+export type InvalidAddrReason =
+  | { type: "malformed"; message: string }
+  | { type: "missing_peer_id" };
+
+/** 节点地址列表的校验结果 */
+export interface AddrValidation {
+  /** 有效地址数 */
+  validCount: number;
+  /** 有效地址的规范形式（去除空白、重新编码） */
+  normalized: string[];
+  invalid: { addr: string; reason: InvalidAddrReason }[];
+}
+
+/**
+ * 校验自定义引导 / 中继节点地址（空行忽略）
+ *
+ * start 遇到无效地址会直接失败，保存设置前应先调用本函数提示用户。
+ */
+export async function validateBootstrapNodes(
+  addrs: string[],
+): Promise<AddrValidation> {
+  return invoke<AddrValidation>("validate_bootstrap_nodes", { addrs });
 }
 
 /**
@@ -80,14 +253,50 @@ export async function shutdown(): Promise<void> {
   await invoke("shutdown");
 }
 
+/** 组合过滤条件，省略的字段不参与过滤 */
+export interface DeviceFilterConditions {
+  paired?: boolean;
+  connected?: boolean;
+  /** 平台，如 "android"、"windows"（不区分大小写） */
+  platform?: string;
+  /** 主机名或昵称子串匹配（不区分大小写） */
+  query?: string;
+}
+
+export type DeviceFilter =
+  | "all"
+  | "connected"
+  | "paired"
+  | { filtered: DeviceFilterConditions };
+export type DeviceSort = "latency" | "name" | "lastConnected";
+
+/** 设备查询选项（对应 Rust DeviceQuery，filter 之外的条件） */
+export interface DeviceQueryOptions {
+  /** 只返回在线设备，可与任意 filter 组合 */
+  onlineOnly?: boolean;
+  sort?: DeviceSort;
+  /** 主机名子串匹配（不区分大小写） */
+  search?: string;
+}
+
 /**
  * 获取设备列表
- * @param filter - 过滤器: "all" | "connected" | "paired"，默认 "connected"
+ * @param filter - 过滤器: "all" | "connected" | "paired" | { filtered }，默认 "all"
+ * @param options - 在线过滤、排序与搜索条件
  */
 export async function listDevices(
-  filter?: "all" | "connected" | "paired",
+  filter?: DeviceFilter,
+  options?: DeviceQueryOptions,
 ): Promise<DeviceListResult> {
-  return invoke("list_devices", { filter });
+  return invoke("list_devices", { query: { filter, ...options } });
+}
+
+/**
+ * 获取单个设备的详细信息
+ * 未知设备抛出 `DeviceNotFound` 错误（code: `device/not-found`）
+ */
+export async function getDevice(peerId: string): Promise<DeviceDetail> {
+  return invoke("get_device", { peerId });
 }
 
 /**
@@ -96,3 +305,175 @@ export async function listDevices(
 export async function getNetworkStatus(): Promise<NetworkStatus> {
   return invoke("get_network_status");
 }
+
+/** 单一连接类型的累计流量 */
+export interface TrafficCounts {
+  bytesSent: number;
+  bytesReceived: number;
+  /** 完成的传输会话数 */
+  sessions: number;
+  /** 累计连接时长（秒） */
+  connectedSecs: number;
+}
+
+/** 按连接类型累计的流量统计（跨重启保留） */
+export interface TrafficStats {
+  lan: TrafficCounts;
+  dcutr: TrafficCounts;
+  relay: TrafficCounts;
+  /** 连接类型未知的传输 */
+  unknown: TrafficCounts;
+  /** 统计起始时间（毫秒时间戳） */
+  since: number;
+}
+
+/**
+ * 获取流量统计
+ */
+export async function getTrafficStats(): Promise<TrafficStats> {
+  return invoke("get_traffic_stats");
+}
+
+/**
+ * 清零流量统计
+ */
+export async function resetTrafficStats(): Promise<void> {
+  await invoke("reset_traffic_stats");
+}
+
+/**
+ * 导出诊断包（zip）：网络状态、设备列表、活跃传输、版本信息与最近日志，PeerId 已脱敏
+ * @param path - 用户选择的保存路径
+ * @returns 写入的路径（可用 opener 插件打开所在目录）
+ */
+export async function exportDiagnostics(path: string): Promise<string> {
+  return invoke("export_diagnostics", { path });
+}
+
+/**
+ * 获取诊断快照（JSON，PeerId 已脱敏）：网络状态、设备列表、引导节点连接状态、
+ * 配对概况、活跃传输与最近日志，内容与诊断包一致
+ */
+export async function getDiagnosticsSnapshot(): Promise<Record<string, unknown>> {
+  return invoke("get_diagnostics_snapshot");
+}
+
+/** 日志级别（过滤时包含该级别及更严重的级别） */
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+/**
+ * 获取最近的日志行（从旧到新，最多保留 2000 行）
+ * @param limit - 最多返回的行数（取最新的），不传返回全部
+ * @param levelFilter - 最详细的级别，如 "warn" 只返回 WARN 和 ERROR
+ */
+export async function getRecentLogs(
+  limit?: number,
+  levelFilter?: LogLevel,
+): Promise<string[]> {
+  return invoke("get_recent_logs", { limit, levelFilter });
+}
+
+/** 清空日志缓冲区 */
+export async function clearLogs(): Promise<void> {
+  await invoke("clear_logs");
+}
+
+/** 获取当前的日志过滤规则（语法同 RUST_LOG） */
+export async function getLogLevel(): Promise<string> {
+  return invoke("get_log_level");
+}
+
+/**
+ * 运行时切换日志过滤规则，无需重启应用
+ * @param filter - 如 "debug" 或 "swarmdrop=trace,libp2p=info"，无效规则会抛出 Config 错误
+ */
+export async function setLogLevel(filter: string): Promise<void> {
+  await invoke("set_log_level", { filter });
+}
+
+/** `log-line` 事件：实时推送的日志行 */
+export interface LogLineEvent {
+  level: LogLevel;
+  target: string;
+  message: string;
+  /** 自上一条推送以来因限流丢弃的行数 */
+  dropped: number;
+}
+
+/** 获取推送到前端的最低日志级别 */
+export async function getLogStreamLevel(): Promise<LogLevel | "off"> {
+  return invoke("get_log_stream_level");
+}
+
+/**
+ * 设置推送到前端（`log-line` 事件）的最低日志级别，默认 "warn"
+ * @param level - "off" 关闭推送；只能在 setLogLevel 的过滤规则放行的范围内生效
+ */
+export async function setLogStreamLevel(
+  level: LogLevel | "off",
+): Promise<void> {
+  await invoke("set_log_stream_level", { level });
+}
+
+/** 省电模式是否开启 */
+export async function getPowerSaveMode(): Promise<boolean> {
+  return invoke("get_power_save_mode");
+}
+
+/**
+ * 开启或关闭省电模式：暂停 DHT 在线记录刷新与已配对设备在线检查
+ *
+ * 开启期间之后才上线的设备可能找不到本机，发来的传输请求会丢失；开始传输时后端自动关闭。
+ * 移动端切到后台 / 回到前台时由后端自动切换。
+ */
+export async function setPowerSaveMode(enabled: boolean): Promise<void> {
+  await invoke("set_power_save_mode", { enabled });
+}
+
+/**
+ * 手动拨号指定节点（排查连通性用），未配对设备同样适用
+ * @param addrs - 可选的 multiaddr 列表，拨号前注册到地址簿
+ * @returns 拨通后的连接类型，连接事件尚未处理时为 null
+ */
+export async function dialPeer(
+  peerId: string,
+  addrs?: string[],
+): Promise<ConnectionType | null> {
+  return invoke("dial_peer", { peerId, addrs });
+}
+
+/** 手动重连已配对设备的结果 */
+export interface PairedReconnectResult {
+  /** 已连接、无需重连的设备 */
+  connected: PeerId[];
+  /** 查到 DHT 在线记录的设备 */
+  found: PeerId[];
+  /** 拨号成功的设备 */
+  dialed: PeerId[];
+}
+
+/**
+ * 立即重新检查所有离线的已配对设备（查询 DHT 在线记录并拨号）
+ */
+export async function reconnectPairedDevices(): Promise<PairedReconnectResult> {
+  return invoke<PairedReconnectResult>("reconnect_paired_devices");
+}
+
+/**
+ * 立即重新检查指定的已配对设备，未配对时抛出错误
+ */
+export async function reconnectDevice(
+  peerId: PeerId,
+): Promise<PairedReconnectResult> {
+  return invoke<PairedReconnectResult>("reconnect_device", { peerId });
+}
+
+/**
+ * 获取指定节点的打洞统计与当前连接类型
+ * 打洞失败时传输走中继，速度受中继带宽限制
+ */
+export async function getPeerDiagnostics(
+  peerId: string,
+): Promise<PeerDiagnostics> {
+  return invoke("get_peer_diagnostics", { peerId });
+}
@@ -5,6 +5,12 @@
 
 import { invoke } from "@tauri-apps/api/core";
 import type { PeerId } from "./network";
+import type { PairedDevice } from "@/stores/secret-store";
+
+/**
+ * 配对码格式：6 位数字，或两个单词加两位数字（如 `maple-otter-42`）
+ */
+export type CodeFormat = "digits6" | "words3";
 
 /**
  * 配对码信息
@@ -61,16 +67,48 @@ export type PairingResponse =
  * 生成配对码，发布到 DHT 供对端查询
  *
  * @param expiresInSecs - 配对码有效期（秒），默认 300
+ * @param format - 配对码格式，默认 6 位数字
  */
 export async function generatePairingCode(
   expiresInSecs?: number,
+  format?: CodeFormat,
 ): Promise<PairingCodeInfo> {
-  return invoke<PairingCodeInfo>("generate_pairing_code", { expiresInSecs });
+  return invoke<PairingCodeInfo>("generate_pairing_code", {
+    expiresInSecs,
+    format,
+  });
+}
+
+/**
+ * 当前配对码的二维码载荷（`swarmdrop://pair?...`），需先生成配对码
+ */
+export async function generatePairingPayload(): Promise<string> {
+  return invoke<string>("generate_pairing_payload");
+}
+
+/**
+ * 扫码配对：解析二维码载荷并直接发起配对，地址失效时回退到 DHT 查询
+ *
+ * 载荷格式错误时抛出 `InvalidPairingPayload` 错误（code: `pairing/invalid-payload`）。
+ */
+export async function pairFromPayload(
+  payload: string,
+): Promise<PairingResponse> {
+  return invoke<PairingResponse>("pair_from_payload", { payload });
+}
+
+/**
+ * 撤销当前配对码，同时移除其 DHT 记录（用户取消配对码页面时调用）
+ */
+export async function revokePairingCode(): Promise<void> {
+  return invoke("revoke_pairing_code");
 }
 
 /**
  * 通过配对码查询对端设备信息
  *
+ * 配对码属于本机时抛出 `SelfPairing` 错误（code: `pairing/self`）。
+ *
  * @param code - 6 位配对码
  */
 export async function getDeviceInfo(code: string): Promise<DeviceInfo> {
@@ -80,6 +118,8 @@ export async function getDeviceInfo(code: string): Promise<DeviceInfo> {
 /**
  * 向对端发起配对请求
  *
+ * 目标为本机时抛出 `SelfPairing` 错误（code: `pairing/self`）。
+ *
  * @param peerId - 对端 Peer ID
  * @param method - 配对方式
  * @param addrs - 对端可达地址（可选），用于跨网络场景下注册地址后直接 dial
@@ -92,6 +132,19 @@ export async function requestPairing(
   return invoke<PairingResponse>("request_pairing", { peerId, method, addrs });
 }
 
+/**
+ * 向局域网内已发现的设备直接发起配对（不经过 DHT，无互联网时可用）
+ *
+ * 对端当前未通过 mDNS 在局域网中发现时会返回错误。
+ *
+ * @param peerId - 对端 Peer ID
+ */
+export async function requestPairingLan(
+  peerId: PeerId,
+): Promise<PairingResponse> {
+  return invoke<PairingResponse>("request_pairing_lan", { peerId });
+}
+
 /**
  * 取消与指定设备的配对（同步更新后端运行时状态）
  *
@@ -101,6 +154,92 @@ export async function removePairedDevice(peerId: PeerId): Promise<void> {
   return invoke("remove_paired_device", { peerId });
 }
 
+/**
+ * 设置已配对设备的昵称（后端去除首尾空白并截断，为空时清除昵称）
+ *
+ * 成功后后端推送 paired-device-updated，由 network-store 同步到 Stronghold。
+ * 节点未运行时返回 NodeNotStarted 错误。
+ */
+export async function setDeviceNickname(
+  peerId: PeerId,
+  nickname: string | null,
+): Promise<PairedDevice> {
+  return invoke<PairedDevice>("set_device_nickname", { peerId, nickname });
+}
+
+/**
+ * 屏蔽节点：之后其配对请求与传输请求都被静默拒绝（重启后仍生效）
+ *
+ * @returns 是否同时取消了配对（为 true 时前端应同步移除 Stronghold 中的设备）
+ */
+export async function blockPeer(peerId: PeerId): Promise<boolean> {
+  return invoke<boolean>("block_peer", { peerId });
+}
+
+/** 解除屏蔽 */
+export async function unblockPeer(peerId: PeerId): Promise<void> {
+  await invoke("unblock_peer", { peerId });
+}
+
+/** 被屏蔽的节点列表 */
+export async function listBlocked(): Promise<PeerId[]> {
+  return invoke<PeerId[]>("list_blocked");
+}
+
+/**
+ * 配对验证码核对状态
+ */
+export interface PairingVerification {
+  /** 用户是否已核对 */
+  verified: boolean;
+  /** 验证码（已核对或此前配对的设备为 null） */
+  code: string | null;
+}
+
+/**
+ * 对端发来的配对核对结果（pairing-confirmation-received 事件）
+ */
+export interface PairingConfirmationEvent {
+  peerId: PeerId;
+  action: "confirm" | "abort";
+}
+
+/** `pairing-bruteforce-detected` 事件：某节点多次猜错配对码，已被临时锁定 */
+export interface PairingBruteforceEvent {
+  peerId: PeerId;
+  /** 配对码累计猜错次数过多，已作废（需重新生成） */
+  codeRevoked: boolean;
+}
+
+/** `paired-device-removed` 事件：对端取消了与本机的配对（后端已移除运行时状态） */
+export interface PairedDeviceRemovedEvent {
+  peerId: PeerId;
+  hostname: string;
+}
+
+/**
+ * 查询已配对设备的验证码，供两端用户比对
+ */
+export async function getPairingVerification(
+  peerId: PeerId,
+): Promise<PairingVerification> {
+  return invoke<PairingVerification>("get_pairing_verification", { peerId });
+}
+
+/**
+ * 确认两端验证码一致（通知对端），前端应同时标记 Stronghold 中的设备为已核对
+ */
+export async function confirmPairing(peerId: PeerId): Promise<void> {
+  return invoke("confirm_pairing", { peerId });
+}
+
+/**
+ * 验证码不一致，中止配对（对端同样移除），前端应同时从 Stronghold 移除
+ */
+export async function abortPairing(peerId: PeerId): Promise<void> {
+  return invoke("abort_pairing", { peerId });
+}
+
 /**
  * 响应收到的配对请求（接受/拒绝）
  *
@@ -0,0 +1,43 @@
+/**
+ * Settings commands
+ * 后端持久化的设置（接收时未指定保存位置等场景由后端直接使用）
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { SaveLocation } from "@/commands/transfer";
+
+/** 后端设置 */
+export interface AppSettings {
+  /** 默认保存位置，null 时使用平台默认（下载目录下的 SwarmDrop） */
+  defaultSaveDir: SaveLocation | null;
+  /**
+   * 保存路径模板，如 `Received/{deviceName}/{date}/{filename}`，null 时直接保存到所选目录
+   *
+   * 占位符：`{deviceName}`、`{peerId}`、`{date}`（YYYY-MM-DD），`{filename}` 必须单独作为最后一段
+   */
+  savePathTemplate?: string | null;
+  /** 接收时不预分配文件大小（保存位置在 FAT32 / exFAT 等文件系统上时开启） */
+  skipPreallocation?: boolean;
+  /** 入站 Offer 的文件数上限（含空目录），null 时使用默认值 50000（修改后重启节点生效） */
+  maxOfferFiles?: number | null;
+  /** 入站 Offer 的总大小上限（字节），null 时使用默认值 1 TiB（修改后重启节点生效） */
+  maxOfferTotalSize?: number | null;
+  /** 发送方同时读取 / 加密的分块数上限，null 时按可用内存确定（修改后重启节点生效） */
+  maxInFlightChunks?: number | null;
+}
+
+/** 读取后端设置 */
+export function getSettings(): Promise<AppSettings> {
+  return invoke("get_settings");
+}
+
+/** 保存后端设置（默认保存目录不存在时创建，不可写时报错） */
+export function setSettings(settings: AppSettings): Promise<void> {
+  return invoke("set_settings", { settings });
+}
+
+/** 只修改部分设置，其余字段保持不变 */
+export async function updateSettings(patch: Partial<AppSettings>): Promise<void> {
+  const current = await getSettings();
+  return setSettings({ ...current, ...patch });
+}
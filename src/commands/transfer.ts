@@ -5,13 +5,23 @@
 
 import { Channel, invoke } from "@tauri-apps/api/core";
 import type { AndroidFsUri } from "tauri-plugin-android-fs-api";
+import type { ConnectionType } from "@/commands/network";
 
 // === 类型定义 ===
 
+/** Android 公共目录（缺省为 Download） */
+export type AndroidPublicBaseDir = "download" | "documents";
+
 /** 保存位置（跨平台） */
 export type SaveLocation =
   | { type: "path"; path: string }
-  | { type: "androidPublicDir"; subdir: string };
+  | {
+      type: "androidPublicDir";
+      subdir: string;
+      baseDir?: AndroidPublicBaseDir;
+      byMediaType?: boolean;
+    }
+  | { type: "androidSafDir"; dirUri: AndroidFsUri };
 
 /** 传输方向 */
 export type TransferDirection = "send" | "receive";
@@ -21,6 +31,7 @@ export type TransferStatus =
   | "pending"
   | "waiting_accept"
   | "transferring"
+  | "reconnecting"
   | "completed"
   | "failed"
   | "cancelled";
@@ -32,6 +43,8 @@ export interface TransferFileInfo {
   relativePath: string;
   size: number;
   isDirectory: boolean;
+  /** 缩略图预览（base64 编码的 JPEG，仅收到的 Offer 中的图片文件） */
+  preview?: string | null;
 }
 
 /** 准备发送的结果 */
@@ -51,6 +64,8 @@ export interface TransferSession {
   totalSize: number;
   status: TransferStatus;
   progress: TransferProgressEvent | null;
+  /** 接收方正在校验的文件（收到下一次进度事件时清除） */
+  verifying?: TransferVerifyingEvent | null;
   error: string | null;
   startedAt: number;
   completedAt: number | null;
@@ -65,7 +80,15 @@ export interface TransferOfferEvent {
   peerId: string;
   deviceName: string;
   files: TransferFileInfo[];
+  /** 需要在接收方重建的空目录（相对路径） */
+  directories: string[];
   totalSize: number;
+  /** 剩余有效时间（秒），超时后后端自动丢弃该 Offer */
+  expiresInSecs: number;
+  /** 发送方是否要求加密（false 表示请求局域网明文） */
+  encryption: boolean;
+  /** 未指定保存位置时使用的默认保存位置（用于预填） */
+  defaultSaveLocation: SaveLocation | null;
 }
 
 /** 单个文件的进度信息 */
@@ -89,6 +112,18 @@ export interface TransferProgressEvent {
   eta: number | null;
   /** 每个文件的独立进度 */
   files: FileProgressInfo[];
+  /** 与对端的连接类型（未知时为 null） */
+  connection: ConnectionType | null;
+}
+
+/** 接收方全部分块到达后校验文件 BLAKE3 的进度 */
+export interface TransferVerifyingEvent {
+  sessionId: string;
+  fileId: number;
+  name: string;
+  /** 已校验的字节数 */
+  bytesHashed: number;
+  totalBytes: number;
 }
 
 /** 传输完成 */
@@ -98,6 +133,24 @@ export interface TransferCompleteEvent {
   totalBytes: number;
   elapsedMs: number;
   saveLocation?: SaveLocation;
+  /** 接收方已保存文件的实际 URI（仅 Android） */
+  fileUris: AndroidFsUri[];
+  /** 接收方已保存文件的最终绝对路径（仅桌面端） */
+  filePaths: string[];
+  /** 传输结束时的连接类型 */
+  finalConnection: ConnectionType | null;
+}
+
+/** 所有活跃传输的总体进度（定时推送） */
+export interface OverallProgressEvent {
+  activeSessions: number;
+  completedSessions: number;
+  failedSessions: number;
+  totalBytes: number;
+  transferredBytes: number;
+  /** 合并速度（字节/秒） */
+  speed: number;
+  eta: number | null;
 }
 
 /** 传输失败 */
@@ -105,6 +158,20 @@ export interface TransferFailedEvent {
   sessionId: string;
   direction: TransferDirection;
   error: string;
+  /** 失败前已完整传输的文件（接收方为已保存的文件，不会因失败被删除） */
+  completedFiles: FileProgressInfo[];
+  /** 出错的文件（会话级错误时为 null） */
+  failedFile: FileProgressInfo | null;
+  /** 接收方的保存位置 */
+  saveLocation: SaveLocation | null;
+}
+
+/** 连接中断，正在重连对端（重连成功后恢复推送进度） */
+export interface TransferReconnectingEvent {
+  sessionId: string;
+  direction: TransferDirection;
+  /** 重连窗口（秒），超时仍未恢复则传输失败 */
+  windowSecs: number;
 }
 
 /** 对端暂停传输 */
@@ -141,6 +208,8 @@ export interface ScannedSourceResult {
   isDirectory: boolean;
   files: ScannedFile[];
   totalSize: number;
+  /** 默认保存目录位于此目录内（本机收发时接收的文件会写入来源目录） */
+  containsSaveDir: boolean;
 }
 
 /** 扫描到的单个文件（同时用于 scanSources 返回和 prepareSend 输入） */
@@ -149,6 +218,8 @@ export interface ScannedFile {
   name: string;
   relativePath: string;
   size: number;
+  /** 是否为空目录条目（接收方会重建该目录） */
+  isDirectory?: boolean;
 }
 
 /** prepare_send 进度事件 */
@@ -170,13 +241,26 @@ export interface PrepareProgress {
 /** Offer 被拒绝的原因（与 Rust OfferRejectReason 对应） */
 export type OfferRejectReason =
   | { type: "not_paired" }
-  | { type: "user_declined" };
+  | { type: "no_longer_paired" }
+  | { type: "user_declined" }
+  | { type: "invalid_offer"; message: string }
+  | { type: "invalid_signature" }
+  | { type: "shutting_down" };
 
 /** 开始发送的结果（立即返回 session_id，后续通过事件通知） */
 export interface StartSendResult {
   sessionId: string;
 }
 
+/** 多目标发送中单个 peer 的结果 */
+export interface PeerSendResult {
+  peerId: string;
+  /** 已发出 Offer 时的会话 ID */
+  sessionId: string | null;
+  /** 发出 Offer 失败的原因（不影响其他 peer） */
+  error: string | null;
+}
+
 /** 对方接受 Offer 的事件 */
 export interface TransferAcceptedEvent {
   sessionId: string;
@@ -194,6 +278,14 @@ export interface TransferDbErrorEvent {
   message: string;
 }
 
+/** 所选保存目录不可用、已回退到默认位置 */
+export interface SaveLocationFallbackEvent {
+  sessionId: string;
+  /** 实际使用的保存位置 */
+  saveLocation: SaveLocation;
+  reason: string;
+}
+
 /** 恢复传输的结果（返回给前端以创建运行时 session） */
 export interface ResumeTransferResult {
   sessionId: string;
@@ -205,40 +297,185 @@ export interface ResumeTransferResult {
   transferredBytes: number;
 }
 
+/**
+ * 目录中符号链接的处理方式
+ * - skip: 忽略（默认）
+ * - follow: 跟随链接（循环链接会被跳过）
+ * - includeAsFile: 指向文件的链接按普通文件发送
+ */
+export type SymlinkPolicy = "skip" | "follow" | "includeAsFile";
+
+/**
+ * 目录遍历的 glob 过滤规则，按相对路径（含所选目录名）或条目名匹配
+ * - include: 非空时只保留匹配的文件
+ * - exclude: 匹配的文件跳过，匹配的目录不再进入，如 `**\/node_modules/**`、`.git`
+ */
+export interface ScanFilter {
+  include?: string[];
+  exclude?: string[];
+}
+
+/** scan_sources 进度事件（累计值） */
+export interface ScanProgress {
+  /** 已发现的文件数 */
+  filesFound: number;
+  /** 已统计的字节数 */
+  bytesFound: number;
+}
+
 /**
  * 扫描文件来源：遍历目录、收集元数据，不计算 hash
  * 用于用户选择文件后在 UI 上展示文件树
+ * @param symlinkPolicy 符号链接处理方式，默认 skip
+ * @param onProgress 可选的进度回调，大目录扫描时定期推送
+ * @param scanId 可选的扫描 ID，扫描期间可用于 cancelPrepare
+ * @param filter 可选的 include/exclude 过滤规则，默认不过滤
  */
 export async function scanSources(
   sources: FileSource[],
+  symlinkPolicy?: SymlinkPolicy,
+  onProgress?: (progress: ScanProgress) => void,
+  scanId?: string,
+  filter?: ScanFilter,
 ): Promise<ScannedSourceResult[]> {
-  return invoke("scan_sources", { sources });
+  const channel = new Channel<ScanProgress>();
+  if (onProgress) {
+    channel.onmessage = onProgress;
+  }
+  return invoke("scan_sources", {
+    sources,
+    symlinkPolicy,
+    include: filter?.include,
+    exclude: filter?.exclude,
+    scanId,
+    onProgress: channel,
+  });
 }
 
 /**
  * 准备发送：对预扫描的文件列表计算 BLAKE3 校验和
  * 接收 scanSources 返回的 ScannedFile 列表（前端可能已移除部分文件）
+ * @param preparedId 前端预先生成的 ID，准备期间可用于 cancelPrepare
  * @param onProgress 可选的进度回调，实时接收 hash 计算进度
  */
 export async function prepareSend(
   files: ScannedFile[],
+  preparedId: string,
   onProgress?: (progress: PrepareProgress) => void,
 ): Promise<PreparedTransfer> {
   const channel = new Channel<PrepareProgress>();
   if (onProgress) {
     channel.onmessage = onProgress;
   }
-  return invoke("prepare_send", { files, onProgress: channel });
+  return invoke("prepare_send", { preparedId, files, onProgress: channel });
+}
+
+/** verify_file 返回的校验结果 */
+export interface HashVerification {
+  /** 是否与期望的 checksum 一致 */
+  matches: boolean;
+  /** 实际计算出的 BLAKE3 hash（hex） */
+  checksum: string;
 }
 
-/** 开始发送到指定设备，等待对方响应 */
+/**
+ * 重新校验已接收文件的完整性，无需重新下载
+ * @param source 文件来源（桌面路径或 Android content URI）
+ * @param expectedChecksum 传输记录中的 BLAKE3 checksum
+ */
+export async function verifyFile(
+  source: FileSource,
+  expectedChecksum: string,
+): Promise<HashVerification> {
+  return invoke("verify_file", { source, expectedChecksum });
+}
+
+/**
+ * 取消进行中的 prepareSend / scanSources，被取消的调用以 Cancelled 错误结束
+ * @returns 是否找到对应的准备任务
+ */
+export async function cancelPrepare(preparedId: string): Promise<boolean> {
+  return invoke("cancel_prepare", { preparedId });
+}
+
+/**
+ * 开始发送到指定设备，等待对方响应
+ *
+ * preferDirectTimeoutMs：大文件仅有中继连接时，等待打洞直连的毫秒数（省略时不等待）
+ */
 export async function startSend(
   preparedId: string,
   peerId: string,
   peerName: string,
   selectedFileIds: number[],
+  lanPlaintext?: boolean,
+  preferDirectTimeoutMs?: number,
 ): Promise<StartSendResult> {
-  return invoke("start_send", { preparedId, peerId, peerName, selectedFileIds });
+  return invoke("start_send", {
+    preparedId,
+    peerId,
+    peerName,
+    selectedFileIds,
+    lanPlaintext,
+    preferDirectTimeoutMs,
+  });
+}
+
+/**
+ * 重新准备：只对上次准备之后大小或修改时间变化的文件重新计算校验和
+ * 已不存在的文件会从结果中移除，其余文件的 fileId 不变
+ */
+export async function refreshPrepared(
+  preparedId: string,
+  onProgress?: (progress: PrepareProgress) => void,
+): Promise<PreparedTransfer> {
+  const channel = new Channel<PrepareProgress>();
+  if (onProgress) {
+    channel.onmessage = onProgress;
+  }
+  return invoke("refresh_prepared", { preparedId, onProgress: channel });
+}
+
+/** 丢弃已准备的传输（发送后仍会保留以便再次发送），返回是否存在 */
+export async function discardPrepared(preparedId: string): Promise<boolean> {
+  return invoke("discard_prepared", { preparedId });
+}
+
+/** 同时发送给多个设备（每个设备独立会话） */
+export async function startSendMulti(
+  preparedId: string,
+  peerIds: string[],
+  selectedFileIds: number[],
+  lanPlaintext?: boolean,
+  preferDirectTimeoutMs?: number,
+): Promise<PeerSendResult[]> {
+  return invoke("start_send_multi", {
+    preparedId,
+    peerIds,
+    selectedFileIds,
+    lanPlaintext,
+    preferDirectTimeoutMs,
+  });
+}
+
+/** 发送耗时估算（未连接时 connection / etaSecs 为 null） */
+export interface TransferEstimate {
+  totalBytes: number;
+  fileCount: number;
+  connection: ConnectionType | null;
+  rttMs: number | null;
+  /** 估算吞吐（字节/秒） */
+  throughput: number | null;
+  /** 预计耗时（秒） */
+  etaSecs: number | null;
+}
+
+/** 估算发送给指定设备的耗时（不传输数据） */
+export async function estimateTransfer(
+  preparedId: string,
+  peerId: string,
+): Promise<TransferEstimate> {
+  return invoke("estimate_transfer", { preparedId, peerId });
 }
 
 /** 取消发送 */
@@ -246,12 +483,28 @@ export async function cancelSend(sessionId: string): Promise<void> {
   return invoke("cancel_send", { sessionId });
 }
 
-/** 确认接收 */
+/**
+ * 确认接收（saveLocation 为 null 时使用设置中的默认保存位置）
+ *
+ * savePathTemplate 省略时使用设置中的保存路径模板，传空字符串表示本次不使用模板；
+ * acceptedFileIds 省略时接收全部文件，否则只接收其中的文件（传空数组会报错「未选择任何文件」）
+ */
 export async function acceptReceive(
   sessionId: string,
-  saveLocation: SaveLocation,
+  saveLocation: SaveLocation | null,
+  lanPlaintext?: boolean,
+  preferDirectTimeoutMs?: number,
+  savePathTemplate?: string,
+  acceptedFileIds?: number[],
 ): Promise<void> {
-  return invoke("accept_receive", { sessionId, saveLocation });
+  return invoke("accept_receive", {
+    sessionId,
+    acceptedFileIds,
+    saveLocation,
+    savePathTemplate,
+    lanPlaintext,
+    preferDirectTimeoutMs,
+  });
 }
 
 /** 拒绝接收 */
@@ -259,6 +512,11 @@ export async function rejectReceive(sessionId: string): Promise<void> {
   return invoke("reject_receive", { sessionId });
 }
 
+/** 获取尚未处理的入站 Offer（前端重载后恢复） */
+export async function getPendingOffers(): Promise<TransferOfferEvent[]> {
+  return invoke("get_pending_offers");
+}
+
 /** 取消接收 */
 export async function cancelReceive(sessionId: string): Promise<void> {
   return invoke("cancel_receive", { sessionId });
@@ -349,6 +607,12 @@ export async function resumeTransfer(
 /** 解析 Android 公共目录的 content:// URI（用于 showViewDirDialog） */
 export async function resolveAndroidDirUri(
   subdir: string,
+  baseDir?: AndroidPublicBaseDir,
 ): Promise<AndroidFsUri | null> {
-  return invoke("resolve_android_dir_uri", { subdir });
+  return invoke("resolve_android_dir_uri", { subdir, baseDir });
+}
+
+/** 在系统文件管理器中显示并选中文件（仅桌面端） */
+export async function revealPath(path: string): Promise<void> {
+  return invoke("reveal_path", { path });
 }
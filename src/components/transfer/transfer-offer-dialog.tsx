@@ -12,14 +12,22 @@ import {
 } from "@/components/responsive-dialog";
 import { Trans } from "@lingui/react/macro";
 import { useTransferStore } from "@/stores/transfer-store";
+import { usePreferencesStore } from "@/stores/preferences-store";
 import {
   acceptReceive,
   rejectReceive,
   type SaveLocation,
+  type TransferFileInfo,
 } from "@/commands/transfer";
 import { FileTree } from "@/routes/_app/send/-components/file-tree";
 import { buildTreeDataFromOffer } from "@/routes/_app/send/-file-tree";
-import { pickFolder, getDefaultSavePath, isAndroid } from "@/lib/file-picker";
+import {
+  pickFolder,
+  pickAndroidSaveDir,
+  getDefaultSavePath,
+  isAndroid,
+} from "@/lib/file-picker";
+import type { AndroidFsUri } from "tauri-plugin-android-fs-api";
 import { useNavigate } from "@tanstack/react-router";
 import { toast } from "sonner";
 import { getErrorMessage } from "@/lib/errors";
@@ -27,6 +35,13 @@ import { getErrorMessage } from "@/lib/errors";
 export function TransferOfferDialog() {
   const navigate = useNavigate();
   const [savePath, setSavePath] = useState("");
+  // Android：用户选择的保存目录，未选择时保存到设置中的公共目录（默认 Download/SwarmDrop）
+  const [androidSaveDir, setAndroidSaveDir] = useState<AndroidFsUri | null>(
+    null,
+  );
+  const androidBaseDir = usePreferencesStore(
+    (s) => s.transfer.androidBaseDir ?? "download",
+  );
   const [processing, setProcessing] = useState(false);
   const [dismissedSessionId, setDismissedSessionId] = useState<string | null>(
     null,
@@ -51,13 +66,22 @@ export function TransferOfferDialog() {
   useEffect(() => {
     let cancelled = false;
     getDefaultSavePath().then((path) => {
-      if (!cancelled) setSavePath(path);
+      if (!cancelled) setSavePath((current) => current || path);
     });
     return () => {
       cancelled = true;
     };
   }, []);
 
+  // 预填后端设置中的默认保存目录
+  const defaultSavePath =
+    currentOffer?.defaultSaveLocation?.type === "path"
+      ? currentOffer.defaultSaveLocation.path
+      : null;
+  useEffect(() => {
+    if (defaultSavePath) setSavePath(defaultSavePath);
+  }, [defaultSavePath]);
+
   // 当 dismissedSessionId 对应的 offer 被移除后，清除 dismissedSessionId
   useEffect(() => {
     if (
@@ -74,6 +98,11 @@ export function TransferOfferDialog() {
   }, [currentOffer]);
 
   const handleChangePath = useCallback(async () => {
+    if (isAndroid()) {
+      const dir = await pickAndroidSaveDir();
+      if (dir) setAndroidSaveDir(dir);
+      return;
+    }
     const selected = await pickFolder();
     if (selected) {
       setSavePath(selected);
@@ -84,11 +113,23 @@ export function TransferOfferDialog() {
     if (!currentOffer) return;
     setProcessing(true);
     try {
-      const saveLocation: SaveLocation = isAndroid()
-        ? { type: "androidPublicDir", subdir: "SwarmDrop" }
-        : { type: "path", path: savePath };
+      const saveLocation: SaveLocation = !isAndroid()
+        ? { type: "path", path: savePath }
+        : androidSaveDir
+          ? { type: "androidSafDir", dirUri: androidSaveDir }
+          : {
+              type: "androidPublicDir",
+              subdir: "SwarmDrop",
+              baseDir: androidBaseDir,
+              byMediaType:
+                usePreferencesStore.getState().transfer.mediaByType ?? false,
+            };
 
-      await acceptReceive(currentOffer.sessionId, saveLocation);
+      await acceptReceive(
+        currentOffer.sessionId,
+        saveLocation,
+        usePreferencesStore.getState().transfer.lanPlaintext ?? false,
+      );
 
       addSession({
         sessionId: currentOffer.sessionId,
@@ -116,7 +157,15 @@ export function TransferOfferDialog() {
       setProcessing(false);
       shiftOffer();
     }
-  }, [currentOffer, savePath, addSession, navigate, shiftOffer]);
+  }, [
+    currentOffer,
+    savePath,
+    androidSaveDir,
+    androidBaseDir,
+    addSession,
+    navigate,
+    shiftOffer,
+  ]);
 
   const handleReject = useCallback(async () => {
     if (!currentOffer) return;
@@ -160,9 +209,16 @@ export function TransferOfferDialog() {
           <ResponsiveDialogDescription className="text-center">
             <Trans>来自 {currentOffer.deviceName}</Trans>
           </ResponsiveDialogDescription>
+          {!currentOffer.encryption && (
+            <p className="text-center text-xs text-muted-foreground">
+              <Trans>对方请求在局域网内不加密传输，需本机也开启该选项才生效</Trans>
+            </p>
+          )}
         </ResponsiveDialogHeader>
 
         <div className="flex-1 overflow-y-auto px-4 sm:px-0">
+          <PreviewStrip files={currentOffer.files} />
+
           <div className="max-h-[40vh] min-h-30">
             <FileTree
               mode="select"
@@ -174,15 +230,18 @@ export function TransferOfferDialog() {
             />
           </div>
 
-          {!isAndroid() && (
-            <div className="mt-4">
-              <SavePathSelector
-                savePath={savePath}
-                onChangePath={handleChangePath}
-                disabled={processing}
-              />
-            </div>
-          )}
+          <div className="mt-4">
+            <SavePathSelector
+              savePath={
+                isAndroid()
+                  ? (androidSaveDir?.uri ??
+                    `${androidBaseDir === "documents" ? "Documents" : "Download"}/SwarmDrop`)
+                  : savePath
+              }
+              onChangePath={handleChangePath}
+              disabled={processing}
+            />
+          </div>
         </div>
 
         <ResponsiveDialogFooter className="flex-row justify-center gap-3 sm:justify-center">
@@ -207,6 +266,37 @@ export function TransferOfferDialog() {
   );
 }
 
+/** 最多展示的缩略图数量 */
+const MAX_PREVIEWS = 6;
+
+/** Offer 中图片文件的缩略图（发送方生成，可能缺失） */
+const PreviewStrip = memo(function PreviewStrip({
+  files,
+}: {
+  files: TransferFileInfo[];
+}) {
+  const previews = useMemo(
+    () => files.filter((f) => f.preview).slice(0, MAX_PREVIEWS),
+    [files],
+  );
+
+  if (previews.length === 0) return null;
+
+  return (
+    <div className="mb-3 flex gap-2 overflow-x-auto">
+      {previews.map((f) => (
+        <img
+          key={f.fileId}
+          src={`data:image/jpeg;base64,${f.preview}`}
+          alt={f.name}
+          title={f.name}
+          className="size-16 shrink-0 rounded-md border border-border object-cover"
+        />
+      ))}
+    </div>
+  );
+});
+
 const SavePathSelector = memo(function SavePathSelector({
   savePath,
   onChangePath,
@@ -3,14 +3,25 @@
 // === 网络状态 ===
 export const NETWORK_STATUS_CHANGED = "network-status-changed";
 export const DEVICES_CHANGED = "devices-changed";
+export const LISTEN_PORT_FALLBACK = "listen-port-fallback";
+export const HOLE_PUNCH_FAILED = "hole-punch-failed";
 
 // === 配对 ===
 export const PAIRING_REQUEST_RECEIVED = "pairing-request-received";
 export const PAIRED_DEVICE_ADDED = "paired-device-added";
+export const PAIRED_DEVICE_ONLINE = "paired-device-online";
+export const PAIRED_DEVICE_OFFLINE = "paired-device-offline";
+export const PAIRED_DEVICE_REMOVED = "paired-device-removed";
+export const PAIRED_DEVICE_UPDATED = "paired-device-updated";
+export const PAIRING_CONFIRMATION_RECEIVED = "pairing-confirmation-received";
+export const PAIRING_BRUTEFORCE_DETECTED = "pairing-bruteforce-detected";
 
 // === 传输 ===
 export const TRANSFER_OFFER = "transfer-offer";
 export const TRANSFER_PROGRESS = "transfer-progress";
+export const TRANSFER_VERIFYING = "transfer-verifying";
+export const OVERALL_PROGRESS = "overall-progress";
+export const TRANSFER_RECONNECTING = "transfer-reconnecting";
 export const TRANSFER_COMPLETE = "transfer-complete";
 export const TRANSFER_FAILED = "transfer-failed";
 export const TRANSFER_ACCEPTED = "transfer-accepted";
@@ -18,3 +29,7 @@ export const TRANSFER_REJECTED = "transfer-rejected";
 export const TRANSFER_PAUSED = "transfer-paused";
 export const TRANSFER_RESUMED = "transfer-resumed";
 export const TRANSFER_DB_ERROR = "transfer-db-error";
+export const SAVE_LOCATION_FALLBACK = "save-location-fallback";
+
+// === 诊断 ===
+export const LOG_LINE = "log-line";
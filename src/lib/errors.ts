@@ -2,13 +2,19 @@
  * 后端错误处理工具
  *
  * Tauri invoke 失败时抛出的是后端 AppError 序列化后的对象：
- * `{ kind: "NodeNotStarted", message: "Node not started" }`
+ * `{ kind: "NodeNotStarted", code: "node/not-started", message: "Node not started", retryable: false }`
+ *
+ * `message` 仅用于展示，判断错误类型请使用 `code`（稳定，不随文案变化）。
  */
 
 /** 后端 AppError 序列化格式 */
 export interface AppError {
   kind: string;
+  /** 稳定的机器可读错误码，如 "transfer/checksum-mismatch" */
+  code: string;
   message: string;
+  /** 相同操作重试是否可能成功 */
+  retryable: boolean;
 }
 
 /** 判断错误是否为后端 AppError */
@@ -26,6 +32,16 @@ export function isErrorKind(err: unknown, kind: string): boolean {
   return isAppError(err) && err.kind === kind;
 }
 
+/** 判断错误是否为特定错误码 */
+export function isErrorCode(err: unknown, code: string): boolean {
+  return isAppError(err) && err.code === code;
+}
+
+/** 判断错误是否值得重试（非 AppError 按不可重试处理） */
+export function isRetryable(err: unknown): boolean {
+  return isAppError(err) && err.retryable === true;
+}
+
 /** 从错误中提取人类可读的消息 */
 export function getErrorMessage(err: unknown): string {
   if (isAppError(err)) return err.message;
@@ -104,6 +104,15 @@ export async function pickFolder(
   return await open({ directory: true, defaultPath });
 }
 
+/**
+ * Android 端选择接收文件的保存目录（SAF tree URI，可选 SD 卡或任意文件夹）
+ * 访问权限由后端在接受传输时持久化
+ */
+export async function pickAndroidSaveDir(): Promise<AndroidFsUri | null> {
+  const AndroidFs = await getAndroidFs();
+  return await AndroidFs.showOpenDirPicker({});
+}
+
 /**
  * 打开文件夹（在系统文件管理器中显示）
  * Android：使用 showViewDirDialog 打开目录（需要 readable content:// URI）
@@ -170,10 +179,13 @@ export async function revealFile(
 /**
  * 打开传输完成后的文件/文件夹
  * 根据 SaveLocation 类型分支处理：
- * - Path：桌面端，使用文件路径打开
- * - AndroidPublicDir：Android 端，通过 Rust 解析 content:// URI，调用 showViewDirDialog
+ * - Path：桌面端，有完成事件记录的最终路径时在文件管理器中选中该文件，否则按保存目录拼接
+ * - AndroidPublicDir：Android 端，有完成事件记录的文件 URI 时直接打开文件，
+ *   否则通过 Rust 解析 content:// URI，调用 showViewDirDialog
+ * - AndroidSafDir：Android 端，用户选择的目录，直接用其 URI 打开
  */
 export async function openTransferResult(session: {
+  sessionId?: string;
   saveLocation?: SaveLocation;
   files: { relativePath: string }[];
 }): Promise<void> {
@@ -182,6 +194,17 @@ export async function openTransferResult(session: {
   const loc = session.saveLocation;
 
   if (loc.type === "path") {
+    // 桌面端：优先使用完成事件中的最终路径（与保存时的实际文件名一致）
+    const { useTransferStore } = await import("@/stores/transfer-store");
+    const filePaths = session.sessionId
+      ? useTransferStore.getState().savedFilePaths[session.sessionId]
+      : undefined;
+    if (filePaths?.length === 1) {
+      const { revealPath } = await import("@/commands/transfer");
+      await revealPath(filePaths[0]);
+      return;
+    }
+
     // 桌面端：使用文件系统路径
     if (session.files.length === 1) {
       const filePath = await join(loc.path, session.files[0].relativePath);
@@ -192,11 +215,26 @@ export async function openTransferResult(session: {
     return;
   }
 
+  if (loc.type === "androidSafDir") {
+    await openFolder(loc.dirUri);
+    return;
+  }
+
   if (loc.type === "androidPublicDir") {
+    // 按媒体类型分流时文件可能不在 Download 下，优先使用完成事件中的实际 URI
+    const { useTransferStore } = await import("@/stores/transfer-store");
+    const fileUris = session.sessionId
+      ? useTransferStore.getState().savedFileUris[session.sessionId]
+      : undefined;
+    if (fileUris && (loc.byMediaType || fileUris.length === 1)) {
+      await openFile(fileUris[0]);
+      return;
+    }
+
     // Android 端：通过 Rust resolve_initial_location 获取 content:// URI
     try {
       const { resolveAndroidDirUri } = await import("@/commands/transfer");
-      const uri = await resolveAndroidDirUri(loc.subdir);
+      const uri = await resolveAndroidDirUri(loc.subdir, loc.baseDir);
       if (uri) {
         const opened = await openFolder(uri);
         if (opened) return;
@@ -100,7 +100,7 @@ export function DeviceCard({ device, variant = "card", onSend, onConnect, onUnpa
           {/* Info */}
           <div className="flex flex-1 flex-col gap-1">
             <span className="text-[15px] font-medium text-foreground">
-              {device.hostname}
+              {device.displayName ?? device.hostname}
             </span>
             {device.isPaired ? (
               <div className="flex items-center gap-1.5">
@@ -202,7 +202,7 @@ export function DeviceCard({ device, variant = "card", onSend, onConnect, onUnpa
           </div>
           <div className="flex flex-1 flex-col gap-1">
             <span className="text-sm font-medium text-foreground">
-              {device.hostname}
+              {device.displayName ?? device.hostname}
             </span>
             <div className="flex items-center gap-1">
               {device.isPaired ? (
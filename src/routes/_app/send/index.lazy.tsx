@@ -14,10 +14,11 @@ import { toast } from "sonner";
 import { Trans } from "@lingui/react/macro";
 import type { Device } from "@/commands/network";
 import type { FileSource, PrepareProgress } from "@/commands/transfer";
-import { prepareSend, startSend } from "@/commands/transfer";
+import { discardPrepared, prepareSend, startSend } from "@/commands/transfer";
 import { useTransferStore } from "@/stores/transfer-store";
 import { useNetworkStore } from "@/stores/network-store";
 import { useSecretStore } from "@/stores/secret-store";
+import { usePreferencesStore } from "@/stores/preferences-store";
 import { useBreakpoint } from "@/hooks/use-breakpoint";
 import { useFileSelection } from "./-use-file-selection";
 import { getErrorMessage } from "@/lib/errors";
@@ -79,14 +80,21 @@ function SendPage() {
     try {
       // 将扫描到的文件列表传给后端计算 hash
       const scannedFiles = fileSelection.getScannedFiles();
-      const prepared = await prepareSend(scannedFiles, setPrepareProgress);
+      const prepared = await prepareSend(
+        scannedFiles,
+        crypto.randomUUID(),
+        setPrepareProgress,
+      );
       const fileIds = prepared.files.map((f) => f.fileId);
       const result = await startSend(
         prepared.preparedId,
         device.peerId,
         device.hostname,
         fileIds,
+        usePreferencesStore.getState().transfer.lanPlaintext ?? false,
       );
+      // 本页每次发送都重新准备，不会复用这次的准备结果
+      void discardPrepared(prepared.preparedId);
 
       // startSend 立即返回 session_id，后续通过事件通知结果
       useTransferStore.getState().addSession({
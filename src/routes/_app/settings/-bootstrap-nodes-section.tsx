@@ -13,6 +13,7 @@ import { Input } from "@/components/ui/input";
 import { Badge } from "@/components/ui/badge";
 import { usePreferencesStore } from "@/stores/preferences-store";
 import { useNetworkStore } from "@/stores/network-store";
+import { validateBootstrapNodes } from "@/commands/network";
 import { toast } from "sonner";
 
 /** 默认引导节点（与后端 BOOTSTRAP_NODES 对应，只读展示） */
@@ -21,11 +22,6 @@ const DEFAULT_BOOTSTRAP_NODES = [
   "/ip4/47.115.172.218/udp/4001/quic-v1/p2p/12D3KooWCq8xgrSap7VZZHpW7EYXw8zFmNEgru9D7cGHGW3bMASX",
 ];
 
-/** 简单的 Multiaddr 格式校验：必须包含 /p2p/ 且以 / 开头 */
-function isValidMultiaddr(addr: string): boolean {
-  return addr.startsWith("/") && addr.includes("/p2p/");
-}
-
 /** 截断 Multiaddr 用于显示 */
 function truncateAddr(addr: string): string {
   if (addr.length <= 60) return addr;
@@ -52,16 +48,22 @@ export function BootstrapNodesSection() {
   const [needsRestart, setNeedsRestart] = useState(false);
   const [restarting, setRestarting] = useState(false);
 
-  function handleAdd() {
-    const addr = inputValue.trim();
-    if (!addr) return;
+  async function handleAdd() {
+    if (!inputValue.trim()) return;
 
-    if (!isValidMultiaddr(addr)) {
+    // 由后端按实际解析规则校验，并使用规范化后的地址
+    const { normalized, invalid } = await validateBootstrapNodes([inputValue]);
+    if (invalid.length > 0) {
+      const { reason } = invalid[0];
       toast.error(t(msg`无效的 Multiaddr 地址`), {
-        description: t(msg`地址需以 / 开头且包含 /p2p/ 部分`),
+        description:
+          reason.type === "missing_peer_id"
+            ? t(msg`地址需包含 /p2p/<PeerId> 部分`)
+            : reason.message,
       });
       return;
     }
+    const addr = normalized[0];
 
     if (
       customBootstrapNodes.includes(addr) ||
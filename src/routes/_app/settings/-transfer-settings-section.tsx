@@ -7,22 +7,48 @@ import { useCallback, useEffect, useState } from "react";
 import { Trans } from "@lingui/react/macro";
 import { FolderOpen } from "lucide-react";
 import { Switch } from "@/components/ui/switch";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
 import { useShallow } from "zustand/react/shallow";
 import { usePreferencesStore } from "@/stores/preferences-store";
 import { homeDir } from "@tauri-apps/api/path";
 import { pickFolder, getDefaultSavePath, isAndroid } from "@/lib/file-picker";
 import { toast } from "sonner";
+import type { AndroidPublicBaseDir } from "@/commands/transfer";
+import { updateSettings } from "@/commands/settings";
+import { getErrorMessage } from "@/lib/errors";
 
 export function TransferSettingsSection() {
-  const { savePath, autoAccept, setTransferSavePath, setTransferAutoAccept } =
-    usePreferencesStore(
-      useShallow((state) => ({
-        savePath: state.transfer.savePath,
-        autoAccept: state.transfer.autoAccept,
-        setTransferSavePath: state.setTransferSavePath,
-        setTransferAutoAccept: state.setTransferAutoAccept,
-      })),
-    );
+  const {
+    savePath,
+    autoAccept,
+    mediaByType,
+    androidBaseDir,
+    lanPlaintext,
+    setTransferSavePath,
+    setTransferAutoAccept,
+    setTransferMediaByType,
+    setTransferAndroidBaseDir,
+    setTransferLanPlaintext,
+  } = usePreferencesStore(
+    useShallow((state) => ({
+      savePath: state.transfer.savePath,
+      autoAccept: state.transfer.autoAccept,
+      mediaByType: state.transfer.mediaByType ?? false,
+      androidBaseDir: state.transfer.androidBaseDir ?? "download",
+      lanPlaintext: state.transfer.lanPlaintext ?? false,
+      setTransferSavePath: state.setTransferSavePath,
+      setTransferAutoAccept: state.setTransferAutoAccept,
+      setTransferMediaByType: state.setTransferMediaByType,
+      setTransferAndroidBaseDir: state.setTransferAndroidBaseDir,
+      setTransferLanPlaintext: state.setTransferLanPlaintext,
+    })),
+  );
 
   const [displayPath, setDisplayPath] = useState("<未设置>");
 
@@ -49,17 +75,43 @@ export function TransferSettingsSection() {
   }, [savePath]);
 
   const handleChangePath = useCallback(async () => {
+    let selected: string | null;
     try {
-      const selected = await pickFolder(savePath);
-      if (selected) {
-        setTransferSavePath(selected);
-      }
+      selected = await pickFolder(savePath);
     } catch (err) {
       console.error("Failed to pick folder:", err);
       toast.error("无法打开文件夹选择器，请检查存储权限");
+      return;
+    }
+    if (!selected) return;
+    try {
+      // 同步到后端设置：接收时未指定目录则保存到这里
+      await updateSettings({ defaultSaveDir: { type: "path", path: selected } });
+      setTransferSavePath(selected);
+    } catch (err) {
+      toast.error(getErrorMessage(err));
     }
   }, [savePath, setTransferSavePath]);
 
+  const handleAndroidBaseDirChange = useCallback(
+    async (value: string) => {
+      const baseDir = value as AndroidPublicBaseDir;
+      try {
+        await updateSettings({
+          defaultSaveDir: {
+            type: "androidPublicDir",
+            subdir: "SwarmDrop",
+            baseDir,
+          },
+        });
+        setTransferAndroidBaseDir(baseDir);
+      } catch (err) {
+        toast.error(getErrorMessage(err));
+      }
+    },
+    [setTransferAndroidBaseDir],
+  );
+
   const handleAutoAcceptChange = useCallback(
     (checked: boolean) => {
       setTransferAutoAccept(checked);
@@ -97,6 +149,68 @@ export function TransferSettingsSection() {
           </button>
         )}
 
+        {/* 默认公共目录（Android） */}
+        {isAndroid() && (
+          <div className="flex items-center justify-between border-b border-border p-4">
+            <div className="flex flex-col gap-0.5">
+              <span className="text-sm font-medium text-foreground">
+                <Trans>默认保存目录</Trans>
+              </span>
+              <span className="text-xs text-muted-foreground">
+                <Trans>接收时未选择目录则保存到该目录下的 SwarmDrop 文件夹</Trans>
+              </span>
+            </div>
+            <Select
+              value={androidBaseDir}
+              onValueChange={handleAndroidBaseDirChange}
+            >
+              <SelectTrigger className="w-30 sm:w-35">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="download">Download</SelectItem>
+                <SelectItem value="documents">Documents</SelectItem>
+              </SelectContent>
+            </Select>
+          </div>
+        )}
+
+        {/* 按媒体类型分目录（Android） */}
+        {isAndroid() && (
+          <div className="flex items-center justify-between border-b border-border p-4">
+            <div className="flex flex-col gap-0.5">
+              <span className="text-sm font-medium text-foreground">
+                <Trans>媒体文件分类保存</Trans>
+              </span>
+              <span className="text-xs text-muted-foreground">
+                <Trans>图片、视频、音频分别保存到相册、影片、音乐目录</Trans>
+              </span>
+            </div>
+            <Switch
+              checked={mediaByType}
+              onCheckedChange={setTransferMediaByType}
+            />
+          </div>
+        )}
+
+        {/* 局域网明文传输 */}
+        <div className="flex items-center justify-between border-b border-border p-4">
+          <div className="flex flex-col gap-0.5">
+            <span className="text-sm font-medium text-foreground">
+              <Trans>局域网不加密传输</Trans>
+            </span>
+            <span className="text-xs text-muted-foreground">
+              <Trans>
+                仅在双方都开启且为局域网直连时生效，可提升速度；中继连接始终加密
+              </Trans>
+            </span>
+          </div>
+          <Switch
+            checked={lanPlaintext}
+            onCheckedChange={setTransferLanPlaintext}
+          />
+        </div>
+
         {/* 自动接收 */}
         <div className="flex items-center justify-between p-4">
           <div className="flex flex-col gap-0.5">
@@ -32,6 +32,7 @@ import { getDeviceIcon } from "@/components/pairing/device-icon";
 import { FileTree } from "../send/-components/file-tree";
 import { buildTreeDataFromSession } from "../send/-file-tree";
 import type { TransferSession, TransferHistoryItem } from "@/commands/transfer";
+import type { ConnectionType } from "@/commands/network";
 import {
   calcPercent,
   isActiveStatus,
@@ -165,6 +166,7 @@ const StatusBadge = memo(function StatusBadge({
     pending: t`等待中`,
     waiting_accept: t`等待确认`,
     transferring: t`传输中`,
+    reconnecting: t`重连中`,
     completed: t`已完成`,
     failed: t`失败`,
     cancelled: t`已取消`,
@@ -182,6 +184,16 @@ const StatusBadge = memo(function StatusBadge({
   );
 });
 
+/** 连接类型标签（进度区展示，便于确认是否走了局域网） */
+function connectionLabel(connection: ConnectionType): string {
+  const labels: Record<ConnectionType, string> = {
+    lan: t`局域网`,
+    dcutr: t`打洞`,
+    relay: t`中继`,
+  };
+  return labels[connection];
+}
+
 const TransferProgress = memo(function TransferProgress({
   session,
   historyItem,
@@ -231,6 +243,9 @@ const TransferProgress = memo(function TransferProgress({
             {progressPercent}%
           </span>
           <span className="text-[11px] text-muted-foreground md:text-xs">
+            {session.progress.connection && (
+              <>{connectionLabel(session.progress.connection)} · </>
+            )}
             {formatSpeed(session.progress.speed)}
           </span>
         </div>
@@ -103,6 +103,7 @@ export function HistoryItem({ item }: HistoryItemProps) {
   const onOpenFolder = withAction(async () => {
     if (!item.savePath) return;
     await openTransferResult({
+      sessionId,
       saveLocation: item.savePath ?? undefined,
       files: files.map((f) => ({ relativePath: f.relativePath })),
     });
@@ -84,7 +84,8 @@ export function isActiveStatus(status: TransferStatus): boolean {
   return (
     status === "pending" ||
     status === "waiting_accept" ||
-    status === "transferring"
+    status === "transferring" ||
+    status === "reconnecting"
   );
 }
 
@@ -102,6 +103,8 @@ export const STATUS_CLASSNAMES: Record<TransferSession["status"], string> = {
     "bg-yellow-100 text-yellow-600 dark:bg-yellow-500/15 dark:text-yellow-400",
   transferring:
     "bg-blue-100 text-blue-600 dark:bg-blue-500/15 dark:text-blue-400",
+  reconnecting:
+    "bg-amber-100 text-amber-600 dark:bg-amber-500/15 dark:text-amber-400",
   completed:
     "bg-green-100 text-green-600 dark:bg-green-500/15 dark:text-green-400",
   failed: "bg-red-100 text-red-600 dark:bg-red-500/15 dark:text-red-400",
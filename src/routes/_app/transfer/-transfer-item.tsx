@@ -150,6 +150,13 @@ export const TransferItem = memo(function TransferItem({
             </div>
           )}
 
+          {session.status === "reconnecting" && (
+            <div className="flex items-center gap-1.5 text-[12px] text-amber-600 dark:text-amber-400 md:text-[13px]">
+              <Loader2 className="size-3 animate-spin md:size-3.5" />
+              <Trans>连接中断，正在重连...</Trans>
+            </div>
+          )}
+
           {session.status === "waiting_accept" && (
             <div className="flex items-center gap-1.5 text-[12px] text-amber-600 dark:text-amber-400 md:text-[13px]">
               <Loader2 className="size-3 animate-spin md:size-3.5" />
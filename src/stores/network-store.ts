@@ -5,7 +5,11 @@
 
 import { create } from "zustand";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
-import type { Device, NetworkStatus } from "@/commands/network";
+import type {
+  Device,
+  NetworkStatus,
+  PairedDeviceOnlineEvent,
+} from "@/commands/network";
 import {
   start,
   shutdown,
@@ -13,12 +17,24 @@ import {
   getNetworkStatus,
 } from "@/commands/network";
 import { startMcpServer } from "@/commands/mcp";
+import type {
+  PairedDeviceRemovedEvent,
+  PairingBruteforceEvent,
+  PairingConfirmationEvent,
+} from "@/commands/pairing";
 import {
   DEVICES_CHANGED,
   NETWORK_STATUS_CHANGED,
   PAIRING_REQUEST_RECEIVED,
   PAIRED_DEVICE_ADDED,
+  PAIRED_DEVICE_ONLINE,
+  PAIRED_DEVICE_REMOVED,
+  PAIRED_DEVICE_UPDATED,
+  PAIRING_CONFIRMATION_RECEIVED,
+  PAIRING_BRUTEFORCE_DETECTED,
 } from "@/constants/events";
+import { toast } from "sonner";
+import { t } from "@lingui/core/macro";
 import { getErrorMessage } from "@/lib/errors";
 import { useSecretStore, type PairedDevice } from "@/stores/secret-store";
 import { usePairingStore } from "@/stores/pairing-store";
@@ -91,6 +107,51 @@ async function setupEventListeners() {
     listen<PairedDevice>(PAIRED_DEVICE_ADDED, (event) => {
       useSecretStore.getState().addPairedDevice(event.payload);
     }),
+
+    // 对端核对配对验证码（中止时后端已移除，同步移除 Stronghold 中的设备）
+    listen<PairingConfirmationEvent>(PAIRING_CONFIRMATION_RECEIVED, (event) => {
+      const { peerId, action } = event.payload;
+      if (action === "abort") {
+        useSecretStore.getState().removePairedDevice(peerId);
+        toast.error(t`对方发现配对验证码不一致，已取消配对`);
+      } else {
+        toast.info(t`对方已确认配对验证码`);
+      }
+    }),
+
+    // 已配对设备的主机名等信息变化（后端推送完整记录，覆盖 Stronghold 中的旧值）
+    listen<PairedDevice>(PAIRED_DEVICE_UPDATED, (event) => {
+      useSecretStore.getState().updatePairedDevice(event.payload);
+    }),
+
+    // 对端取消了配对（后端已移除，同步移除 Stronghold 中的设备）
+    listen<PairedDeviceRemovedEvent>(PAIRED_DEVICE_REMOVED, (event) => {
+      const { peerId, hostname } = event.payload;
+      useSecretStore.getState().removePairedDevice(peerId);
+      toast.info(t`${hostname} 已取消与本机的配对`);
+    }),
+
+    // 有节点反复猜测配对码（已临时锁定该节点；配对码作废时需重新生成）
+    listen<PairingBruteforceEvent>(PAIRING_BRUTEFORCE_DETECTED, (event) => {
+      const { codeRevoked } = event.payload;
+      if (codeRevoked) {
+        // 后端已撤销配对码，退出配对码页面（保留待处理的入站请求）
+        if (usePairingStore.getState().current.phase === "generating") {
+          usePairingStore.setState({ current: { phase: "idle" } });
+        }
+        toast.error(t`配对码被多次猜错，已作废，请重新生成`);
+      } else {
+        toast.warning(t`有设备多次输错配对码，已暂时阻止其配对请求`);
+      }
+    }),
+
+    // 已配对设备上线（连接抖动导致的重连不提示）
+    listen<PairedDeviceOnlineEvent>(PAIRED_DEVICE_ONLINE, (event) => {
+      const { device, flapping } = event.payload;
+      if (flapping || !device) return;
+      const name = device.displayName ?? device.hostname;
+      toast.info(t`${name} 已上线`);
+    }),
   ]);
 
   unlistenFns = fns;
@@ -133,8 +194,21 @@ export const useNetworkStore = create<NetworkState>()((set, get) => ({
       // 设置 Tauri Event 监听（在启动前设置，避免丢失早期事件）
       await setupEventListeners();
 
-      const { customBootstrapNodes, mcp } = usePreferencesStore.getState();
-      await start(pairedDevices, customBootstrapNodes);
+      const {
+        customBootstrapNodes,
+        useDefaultBootstrap,
+        customRelays,
+        networkOptions,
+        mcp,
+      } = usePreferencesStore.getState();
+      await start(
+        pairedDevices,
+        customBootstrapNodes,
+        undefined,
+        undefined,
+        networkOptions,
+        { useDefaultBootstrap, customRelays },
+      );
 
       // 如果启用了 MCP 自动启动，启动 MCP Server
       if (mcp.autoStart) {
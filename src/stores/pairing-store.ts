@@ -9,6 +9,7 @@ import { t } from "@lingui/core/macro";
 import type { PairingCodeInfo, DeviceInfo, PairingResponse, PairingMethod, PairingRefuseReason } from "@/commands/pairing";
 import {
   generatePairingCode,
+  revokePairingCode,
   getDeviceInfo,
   requestPairing,
   respondPairingRequest,
@@ -319,6 +320,10 @@ export const usePairingStore = create<PairingState>()(
     },
 
     reset() {
+      // 离开配对码页面时撤销配对码，避免 DHT 记录在过期前仍可被查询
+      if (get().current.phase === "generating") {
+        revokePairingCode().catch(() => {});
+      }
       // 递增搜索版本以取消进行中的搜索
       searchVersion++;
       set({
@@ -8,6 +8,8 @@ import { create } from "zustand";
 import { createJSONStorage, persist } from "zustand/middleware";
 import { createTauriStorage } from "@/lib/tauri-store";
 import { dynamicActivate, defaultLocale, type LocaleKey } from "@/lib/i18n";
+import type { NetworkOptions } from "@/commands/network";
+import type { AndroidPublicBaseDir } from "@/commands/transfer";
 
 interface PreferencesState {
   /** 语言 */
@@ -18,12 +20,24 @@ interface PreferencesState {
   autoStart: boolean;
   /** 自定义引导节点地址列表（Multiaddr 格式） */
   customBootstrapNodes: string[];
+  /** 是否连接内置引导节点（自建部署时可关闭） */
+  useDefaultBootstrap: boolean;
+  /** 自定义中继节点地址列表（Multiaddr 格式） */
+  customRelays: string[];
+  /** 网络功能开关（修改后重启节点生效） */
+  networkOptions: Required<NetworkOptions>;
   /** 文件传输设置 */
   transfer: {
     /** 接收文件的默认保存路径 */
     savePath: string;
     /** 是否自动接受已配对设备的文件 */
     autoAccept: boolean;
+    /** Android：图片 / 视频 / 音频分别保存到 Pictures / Movies / Music */
+    mediaByType: boolean;
+    /** Android：未选择保存目录时使用的公共目录（Download / Documents） */
+    androidBaseDir: AndroidPublicBaseDir;
+    /** 局域网内不加密传输（双方都开启且为局域网直连时生效） */
+    lanPlaintext: boolean;
   };
   /** MCP Server 设置 */
   mcp: {
@@ -45,10 +59,24 @@ interface PreferencesState {
   addBootstrapNode: (addr: string) => void;
   /** 删除自定义引导节点 */
   removeBootstrapNode: (addr: string) => void;
+  /** 设置是否连接内置引导节点 */
+  setUseDefaultBootstrap: (useDefault: boolean) => void;
+  /** 添加自定义中继节点 */
+  addRelay: (addr: string) => void;
+  /** 删除自定义中继节点 */
+  removeRelay: (addr: string) => void;
+  /** 修改网络功能开关 */
+  setNetworkOptions: (options: NetworkOptions) => void;
   /** 设置传输保存路径 */
   setTransferSavePath: (path: string) => void;
   /** 设置自动接收 */
   setTransferAutoAccept: (autoAccept: boolean) => void;
+  /** 设置按媒体类型分目录保存（Android） */
+  setTransferMediaByType: (mediaByType: boolean) => void;
+  /** 设置默认公共目录（Android） */
+  setTransferAndroidBaseDir: (androidBaseDir: AndroidPublicBaseDir) => void;
+  /** 设置局域网不加密传输 */
+  setTransferLanPlaintext: (lanPlaintext: boolean) => void;
   /** 设置 MCP 端口 */
   setMcpPort: (port: number) => void;
   /** 设置 MCP 自动启动 */
@@ -76,9 +104,20 @@ export const usePreferencesStore = create<PreferencesState>()(
       deviceName: "",
       autoStart: false,
       customBootstrapNodes: [],
+      useDefaultBootstrap: true,
+      customRelays: [],
+      networkOptions: {
+        mdns: true,
+        relayClient: true,
+        dcutr: true,
+        autonat: true,
+      },
       transfer: {
         savePath: "",
         autoAccept: false,
+        mediaByType: false,
+        androidBaseDir: "download",
+        lanPlaintext: false,
       },
       mcp: {
         port: 19527,
@@ -110,6 +149,28 @@ export const usePreferencesStore = create<PreferencesState>()(
         }));
       },
 
+      setUseDefaultBootstrap(useDefault: boolean) {
+        set({ useDefaultBootstrap: useDefault });
+      },
+
+      addRelay(addr: string) {
+        set((state) => ({
+          customRelays: [...state.customRelays, addr],
+        }));
+      },
+
+      removeRelay(addr: string) {
+        set((state) => ({
+          customRelays: state.customRelays.filter((n) => n !== addr),
+        }));
+      },
+
+      setNetworkOptions(options: NetworkOptions) {
+        set((state) => ({
+          networkOptions: { ...state.networkOptions, ...options },
+        }));
+      },
+
       setTransferSavePath(path: string) {
         set((state) => ({
           transfer: { ...state.transfer, savePath: path },
@@ -122,6 +183,24 @@ export const usePreferencesStore = create<PreferencesState>()(
         }));
       },
 
+      setTransferMediaByType(mediaByType: boolean) {
+        set((state) => ({
+          transfer: { ...state.transfer, mediaByType },
+        }));
+      },
+
+      setTransferAndroidBaseDir(androidBaseDir: AndroidPublicBaseDir) {
+        set((state) => ({
+          transfer: { ...state.transfer, androidBaseDir },
+        }));
+      },
+
+      setTransferLanPlaintext(lanPlaintext: boolean) {
+        set((state) => ({
+          transfer: { ...state.transfer, lanPlaintext },
+        }));
+      },
+
       setMcpPort(port: number) {
         set((state) => ({
           mcp: { ...state.mcp, port },
@@ -142,6 +221,9 @@ export const usePreferencesStore = create<PreferencesState>()(
         deviceName: state.deviceName,
         autoStart: state.autoStart,
         customBootstrapNodes: state.customBootstrapNodes,
+        useDefaultBootstrap: state.useDefaultBootstrap,
+        customRelays: state.customRelays,
+        networkOptions: state.networkOptions,
         transfer: state.transfer,
         mcp: state.mcp,
       }),
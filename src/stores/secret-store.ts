@@ -22,6 +22,14 @@ export interface PairedDevice {
   arch: string;
   /** 配对时间戳 */
   pairedAt: number;
+  /** 是否已核对配对验证码（缺省视为已核对） */
+  verified?: boolean;
+  /** 配对验证码（未核对时保留） */
+  verificationCode?: string;
+  /** 用户设置的昵称 */
+  nickname?: string;
+  /** 最近一次在线的时间戳（毫秒，分钟精度） */
+  lastSeenAt?: number;
 }
 
 interface SecretState {
@@ -44,8 +52,12 @@ interface SecretState {
   addPairedDevice: (device: Omit<PairedDevice, "pairedAt">) => void;
   /** 移除已配对设备 */
   removePairedDevice: (peerId: string) => void;
+  /** 用后端推送的完整信息覆盖已配对设备（不存在时忽略） */
+  updatePairedDevice: (device: PairedDevice) => void;
   /** 更新已配对设备主机名 */
   updatePairedDeviceHostname: (peerId: string, hostname: string) => void;
+  /** 标记已配对设备的验证码已核对 */
+  markPairedDeviceVerified: (peerId: string) => void;
 }
 
 /**
@@ -123,6 +135,14 @@ export const useSecretStore = create<SecretState>()(
         });
       },
 
+      updatePairedDevice(device) {
+        set({
+          pairedDevices: get().pairedDevices.map((d) =>
+            d.peerId === device.peerId ? device : d
+          ),
+        });
+      },
+
       updatePairedDeviceHostname(peerId: string, hostname: string) {
         set({
           pairedDevices: get().pairedDevices.map((d) =>
@@ -130,6 +150,16 @@ export const useSecretStore = create<SecretState>()(
           ),
         });
       },
+
+      markPairedDeviceVerified(peerId: string) {
+        set({
+          pairedDevices: get().pairedDevices.map((d) =>
+            d.peerId === peerId
+              ? { ...d, verified: true, verificationCode: undefined }
+              : d
+          ),
+        });
+      },
     }),
     {
       name: "secret-store",
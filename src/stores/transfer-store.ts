@@ -1,8 +1,11 @@
 import { create } from "zustand";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { addPluginListener } from "@tauri-apps/api/core";
 import {
   TRANSFER_OFFER,
   TRANSFER_PROGRESS,
+  TRANSFER_VERIFYING,
+  TRANSFER_RECONNECTING,
   TRANSFER_COMPLETE,
   TRANSFER_FAILED,
   TRANSFER_ACCEPTED,
@@ -10,11 +13,14 @@ import {
   TRANSFER_PAUSED,
   TRANSFER_RESUMED,
   TRANSFER_DB_ERROR,
+  SAVE_LOCATION_FALLBACK,
 } from "@/constants/events";
 import type {
   TransferSession,
   TransferOfferEvent,
   TransferProgressEvent,
+  TransferVerifyingEvent,
+  TransferReconnectingEvent,
   TransferCompleteEvent,
   TransferFailedEvent,
   TransferAcceptedEvent,
@@ -22,9 +28,19 @@ import type {
   TransferPausedEvent,
   TransferResumedEvent,
   TransferDbErrorEvent,
+  SaveLocationFallbackEvent,
   TransferHistoryItem,
 } from "@/commands/transfer";
-import { getTransferHistory } from "@/commands/transfer";
+import {
+  cancelReceive,
+  cancelSend,
+  getPendingOffers,
+  getTransferHistory,
+} from "@/commands/transfer";
+import { removePairedDevice } from "@/commands/pairing";
+import { isAndroid } from "@/lib/file-picker";
+import { useSecretStore } from "@/stores/secret-store";
+import type { AndroidFsUri } from "tauri-plugin-android-fs-api";
 import { toast } from "sonner";
 import { t } from "@lingui/core/macro";
 
@@ -32,6 +48,10 @@ interface TransferState {
   sessions: Record<string, TransferSession>;
   dbHistory: TransferHistoryItem[];
   pendingOffers: TransferOfferEvent[];
+  /** 本次运行中接收完成的文件 URI（Android，供"打开"按钮定位分散在各媒体目录的文件） */
+  savedFileUris: Record<string, AndroidFsUri[]>;
+  /** 本次运行中接收完成的文件最终路径（桌面端，供"在文件夹中显示"定位实际文件） */
+  savedFilePaths: Record<string, string[]>;
 
   addSession: (session: TransferSession) => void;
   updateProgress: (event: TransferProgressEvent) => void;
@@ -61,6 +81,35 @@ export async function setupTransferListeners() {
       useTransferStore.getState().updateProgress(event.payload);
     }),
 
+    listen<TransferVerifyingEvent>(TRANSFER_VERIFYING, (event) => {
+      const { sessionId } = event.payload;
+      useTransferStore.setState((state) => {
+        const session = state.sessions[sessionId];
+        if (!session) return state;
+        return {
+          sessions: {
+            ...state.sessions,
+            [sessionId]: { ...session, verifying: event.payload },
+          },
+        };
+      });
+    }),
+
+    listen<TransferReconnectingEvent>(TRANSFER_RECONNECTING, (event) => {
+      // 连接中断：标记为重连中，下一次进度事件到达时自动恢复为 transferring
+      const { sessionId } = event.payload;
+      useTransferStore.setState((state) => {
+        const session = state.sessions[sessionId];
+        if (!session) return state;
+        return {
+          sessions: {
+            ...state.sessions,
+            [sessionId]: { ...session, status: "reconnecting" },
+          },
+        };
+      });
+    }),
+
     listen<TransferCompleteEvent>(TRANSFER_COMPLETE, (event) => {
       useTransferStore.getState().completeSession(event.payload);
     }),
@@ -111,12 +160,26 @@ export async function setupTransferListeners() {
 
     listen<TransferRejectedEvent>(TRANSFER_REJECTED, (event) => {
       const { sessionId, reason } = event.payload;
+      const peerId = useTransferStore.getState().sessions[sessionId]?.peerId;
       useTransferStore.setState((state) => {
         const { [sessionId]: _, ...rest } = state.sessions;
         return { sessions: rest };
       });
       if (reason?.type === "not_paired") {
         toast.error(t`设备已取消配对`);
+      } else if (reason?.type === "no_longer_paired") {
+        // 对方离线时取消了配对，本机同样移除该设备
+        if (peerId) {
+          removePairedDevice(peerId).catch(() => {});
+          useSecretStore.getState().removePairedDevice(peerId);
+        }
+        toast.error(t`对方已取消与本机的配对，请重新配对`);
+      } else if (reason?.type === "invalid_offer") {
+        toast.error(t`对方拒绝了不合法的传输请求：${reason.message}`);
+      } else if (reason?.type === "invalid_signature") {
+        toast.error(t`对方无法验证传输请求的签名，请求可能被篡改`);
+      } else if (reason?.type === "shutting_down") {
+        toast.error(t`对方正在关闭，请稍后重试`);
       } else {
         toast.error(t`对方拒绝了请求`);
       }
@@ -126,9 +189,63 @@ export async function setupTransferListeners() {
       const { message } = event.payload;
       toast.error(message);
     }),
+
+    listen<SaveLocationFallbackEvent>(SAVE_LOCATION_FALLBACK, (event) => {
+      const { sessionId, saveLocation, reason } = event.payload;
+      useTransferStore.setState((state) => {
+        const session = state.sessions[sessionId];
+        if (!session) return state;
+        return {
+          sessions: {
+            ...state.sessions,
+            [sessionId]: { ...session, saveLocation },
+          },
+        };
+      });
+      toast.warning(reason);
+    }),
   ]);
 
+  if (isAndroid()) {
+    // 前台服务通知上的"取消"按钮：取消全部进行中的传输
+    const listener = await addPluginListener(
+      "transfer-service",
+      "cancel-transfers",
+      cancelAllSessions,
+    );
+    fns.push(() => listener.unregister());
+  }
+
   unlistenFns = fns;
+
+  // 先注册监听再拉取缓存，避免两者之间到达的 Offer 丢失
+  await restorePendingOffers();
+}
+
+/** 恢复后端缓存的待确认 Offer（前端重载后 transfer-offer 事件不会重发） */
+async function restorePendingOffers() {
+  try {
+    const offers = await getPendingOffers();
+    const { pushOffer } = useTransferStore.getState();
+    for (const offer of offers) {
+      pushOffer(offer);
+    }
+  } catch (e) {
+    console.error("恢复待确认的传输请求失败:", e);
+  }
+}
+
+/** 取消全部活跃传输 */
+async function cancelAllSessions() {
+  const { sessions, cancelSession } = useTransferStore.getState();
+  await Promise.allSettled(
+    Object.values(sessions).map((session) => {
+      cancelSession(session.sessionId);
+      return session.direction === "send"
+        ? cancelSend(session.sessionId)
+        : cancelReceive(session.sessionId);
+    }),
+  );
 }
 
 export async function cleanupTransferListeners() {
@@ -156,6 +273,8 @@ export const useTransferStore = create<TransferState>()((set, get) => ({
   sessions: {},
   dbHistory: [],
   pendingOffers: [],
+  savedFileUris: {},
+  savedFilePaths: {},
 
   addSession(session) {
     set((state) => ({
@@ -174,6 +293,7 @@ export const useTransferStore = create<TransferState>()((set, get) => ({
             ...session,
             status: "transferring",
             progress: event,
+            verifying: null,
           },
         },
       };
@@ -181,6 +301,22 @@ export const useTransferStore = create<TransferState>()((set, get) => ({
   },
 
   completeSession(event) {
+    if (event.fileUris.length > 0) {
+      set((state) => ({
+        savedFileUris: {
+          ...state.savedFileUris,
+          [event.sessionId]: event.fileUris,
+        },
+      }));
+    }
+    if (event.filePaths.length > 0) {
+      set((state) => ({
+        savedFilePaths: {
+          ...state.savedFilePaths,
+          [event.sessionId]: event.filePaths,
+        },
+      }));
+    }
     removeAndRefresh(event.sessionId);
   },
 
@@ -200,9 +336,12 @@ export const useTransferStore = create<TransferState>()((set, get) => ({
   },
 
   pushOffer(offer) {
-    set((state) => ({
-      pendingOffers: [...state.pendingOffers, offer],
-    }));
+    set((state) => {
+      if (state.pendingOffers.some((o) => o.sessionId === offer.sessionId)) {
+        return state;
+      }
+      return { pendingOffers: [...state.pendingOffers, offer] };
+    });
   },
 
   shiftOffer() {